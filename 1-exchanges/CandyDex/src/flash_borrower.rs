@@ -0,0 +1,42 @@
+use scrypto::prelude::*;
+
+// Example of external Component borrowing from CandyDex "flash_loan" method, whose methods receive 
+// borrowed resources and flash receipt and return repayment and flash receipt.
+blueprint! {
+    struct FlashBorrower {
+        // Resources topping up the loan to pay the flash loan fee.
+        funds: Vault,
+        // Flash receipts kept instead of being handed back.
+        receipts: Vec<Vault>
+    }
+
+    impl FlashBorrower {
+        pub fn new(funds: Bucket) -> Component {
+            Self {
+                funds: Vault::with_bucket(funds),
+                receipts: Vec::new()
+            }
+            .instantiate()
+        }
+
+            // Repay the loan adding all funds, exceeding resources are returned by CandyDex.
+        pub fn repay(&mut self, mut loan: Bucket, receipt: Bucket) -> (Bucket,Bucket) {
+            loan.put(self.funds.take_all());
+            (loan,receipt)
+        }
+
+            // Repay the loan without protocol fee.
+        pub fn underpay(&mut self, loan: Bucket, receipt: Bucket) -> (Bucket,Bucket) {
+            (loan,receipt)
+        }
+
+            // Repay the loan adding all funds but deposit the flash receipt in a Vault, handing 
+            // back an empty bucket of flash receipts instead.
+        pub fn keep_receipt(&mut self, mut loan: Bucket, receipt: Bucket) -> (Bucket,Bucket) {
+            let empty_receipt = Bucket::new(receipt.resource_address());
+            self.receipts.push(Vault::with_bucket(receipt));
+            loan.put(self.funds.take_all());
+            (loan,empty_receipt)
+        }
+    }
+}
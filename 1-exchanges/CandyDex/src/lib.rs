@@ -1,6 +1,8 @@
 use sbor::*;
 use scrypto::prelude::*;
 
+mod flash_borrower;
+
 blueprint! {
     struct CandyDex {        
         // XRD vault.
@@ -10,19 +12,21 @@ blueprint! {
         
         // Candy Hashmap with name, symbol, price.              
         candy_map: HashMap<Address, (String, String, Decimal)>,        
-        // metaBadge Hashmap with entry fee level, metaCandy amount & Candy address.  
-        badge_map: HashMap<Address, (Decimal, Decimal, Address)>,         
-        // Candy Hashmap with accrued fee, metaCandy amount & address.
+        // Candy Hashmap with candy amount owed to liquidity providers, metaCandy supply & address.
         meta_map: HashMap<Address, (Decimal, Decimal, Address)>,         
+        // Candy Hashmap with XRD fee accrued to candy liquidity providers.
+        lp_xrd_map: HashMap<Address, Decimal>,
         // metaCandy Hashmap with MetaToken resource adresses. 
         meta: HashMap<Address, MetaToken>,  
         
-        // Badge to mint and burn metaCandies.                      
+        // Badge to mint and burn metaCandies and flash loan receipts.                      
         minter_badge: Vault,         
+        // Transient flash loan receipt resource, burnt once loan is repaid.
+        flash_receipt: ResourceDef,
         // Owner badge to determine protocol fee and collect accrued XRD fee.                                     
         owner_badge: ResourceDef,      
         
-        // Protocol XRD fee variable.                           
+        // Protocol XRD fee variable, accrued by XRD flash loans.
         xrd_fee: Decimal,   
         // Amount of accrued XRD protocol fee withdrawed by protocol owner.                                      
         xrd_claimed: Decimal,                                     
@@ -40,14 +44,23 @@ blueprint! {
                 .metadata("name", " OwnerBadge ")
                 .initial_supply_fungible(1);
 
+            // Flash loan receipts are only minted and burnt within "flash_loan" method, which 
+            // checks the very receipt it minted is handed back along with the repayment.
+            let flash_receipt: ResourceDef = ResourceBuilder::new_non_fungible()
+                .metadata("name", " FlashReceipt ")
+                .flags(MINTABLE | BURNABLE | RESTRICTED_TRANSFER)
+                .badge(minter_badge.resource_def(), MAY_MINT | MAY_BURN)
+                .no_initial_supply();
+
             let component = Self {
                 collected_xrd: Vault::new(RADIX_TOKEN),
                 candy_vaults: HashMap::new(),
                 candy_map: HashMap::new(),
-                badge_map: HashMap::new(),
                 meta_map: HashMap::new(),
+                lp_xrd_map: HashMap::new(),
                 meta: HashMap::new(),
                 minter_badge: Vault::with_bucket(minter_badge),
+                flash_receipt,
                 owner_badge: badge_bucket.resource_def(),
                 xrd_fee: dec!(0),
                 xrd_claimed: dec!(0),
@@ -57,65 +70,6 @@ blueprint! {
             (component,badge_bucket)
         }
 
-            // Create a metaBadge and populate a hashmap to associate a determinated entry fee level
-            // to a candy stoke event.
-            fn add_meta_badge(
-                &mut self, 
-                symbol: String, 
-                candy_addr: Address, 
-                entry_fee: Decimal, 
-                meta_amnt: Decimal
-            ) -> Bucket {
-                let mut meta_badge_res_def: ResourceDef = 
-                    ResourceBuilder::new_fungible(DIVISIBILITY_MAXIMUM)
-                        .metadata("symbol", format!(" mBadge{}", symbol.clone()))
-                        .flags(MINTABLE | BURNABLE)
-                        .badge(self.minter_badge.resource_def(), MAY_MINT | MAY_BURN)
-                        .no_initial_supply();
-                
-                let badge_address = meta_badge_res_def.address();               
-                self.badge_map.insert(badge_address.clone(),(entry_fee, meta_amnt, candy_addr));
-
-                self.minter_badge.authorize(|auth| {meta_badge_res_def.mint(1, auth)})
-            }
-
-            // Populate a hashmap relating a determinated metaBadge address to a metaCandy amount
-            // with the purpose of guarantee a fair Protocol's accrued fee distribution.
-            fn meta_amounts(
-                &mut self, 
-                meta_badge: Bucket, 
-                meta_amount: Decimal, 
-                flag: Decimal
-            ) -> Bucket {
-                let meta_badge_addr: Address = meta_badge.resource_address().clone();
-                
-                let (a,mut m_candy_amnt,c) = *self.badge_map.get(&meta_badge_addr).unwrap();
-                let entry_fee = a;
-                let candy_addr = c;               
-                
-                // Determine if caller is a "stock/restock" method or an "unstock" one and udate
-                // consequentely metaCandy amount, burn metaBadge if all liquidity is removed. 
-                if flag == dec!(0) {
-                    m_candy_amnt = m_candy_amnt+meta_amount;
-                    self.badge_map.insert(meta_badge_addr,(entry_fee,m_candy_amnt,candy_addr));
-                    meta_badge
-                } else if flag == dec!(1) {
-                    assert!( meta_amount <= m_candy_amnt," Let's check passed amount ");
-                    m_candy_amnt = m_candy_amnt-meta_amount;
-                  
-                    if m_candy_amnt == dec!(0) {
-                        self.badge_map.remove(&meta_badge_addr);
-                        CandyDex::badge_burn(self, meta_badge);
-                        Bucket::new(RADIX_TOKEN)
-                    } else { 
-                        self.badge_map.insert(meta_badge_addr,(entry_fee,m_candy_amnt,candy_addr));
-                        meta_badge 
-                    }
-                } else { 
-                    std::process::abort() 
-                }
-            }
-
             // Create a metaCandy resource relative to a kind of candy provided to protocol by end 
             // users and populate related hashmaps.
             fn add_meta_candy(
@@ -146,17 +100,30 @@ blueprint! {
                 meta_address
             }
 
-            // Mint a metacandy amount relative to an amount of candy provided to protocol and 
-            // update total metaCandy minted amount.
+            // Mint a metaCandy amount pro rata to the share of candy pool value provided to protocol 
+            // and update candy amount owed to liquidity providers as well as total metaCandy supply.
             fn meta_mint(&mut self, candy_amnt: Decimal, candy_address: Address) -> Bucket {             
+                let lp_value = CandyDex::lp_value(self, candy_address);
+                let meta_amnt: Decimal;
+
                 match self.meta_map.get_mut(&candy_address.clone()) {
-                    Some((_a,minted_amnt,_c)) => *minted_amnt = *minted_amnt+candy_amnt,
+                    Some((lp_candy,meta_supply,_c)) => {
+                        // First provider gets a metaCandy for each candy, following ones a share 
+                        // of the candy pool value including accrued fees.
+                        if *meta_supply == dec!(0) {
+                            meta_amnt = candy_amnt;
+                        } else {
+                            meta_amnt = candy_amnt*(*meta_supply)/lp_value;
+                        }
+                        *lp_candy = *lp_candy+candy_amnt;
+                        *meta_supply = *meta_supply+meta_amnt;
+                    },
                     None => std::process::abort()                  
                 };
 
                 let m_candy = self.meta.get_mut(&candy_address).unwrap();
                
-                self.minter_badge.authorize(|auth| {m_candy.meta_res_def.mint(candy_amnt, auth)})
+                self.minter_badge.authorize(|auth| {m_candy.meta_res_def.mint(meta_amnt, auth)})
             }
 
             // Burn a metacandy amount relative to amount of candy claimed by end user 
@@ -165,10 +132,21 @@ blueprint! {
                 self.minter_badge.authorize(|auth| {meta_candy.burn_with_auth(auth)});
             }
 
-            // Burn metaBadge once all relative metaCandy has been claimed by end user 
-            // via "unstock_candy" function.
-            fn badge_burn(&mut self, meta_badge: Bucket) {
-                self.minter_badge.authorize(|auth| {meta_badge.burn_with_auth(auth)});
+            // Retrieve candy pool value owed to liquidity providers, expressed in candy, accrued 
+            // XRD fee included.
+            fn lp_value(&mut self, candy_addr: Address) -> Decimal {
+                let (lp_candy,_b,_c) = *self.meta_map.get(&candy_addr).unwrap();
+                let lp_xrd = *self.lp_xrd_map.get(&candy_addr).unwrap();
+
+                lp_candy+lp_xrd/CandyDex::candyprice(self, candy_addr)
+            }
+
+            // Accrue XRD fee to liquidity providers of the candy sold in exchange.
+            fn accrue_xrd_fee(&mut self, candy_addr: Address, xrd_amnt: Decimal) {
+                match self.lp_xrd_map.get_mut(&candy_addr) {
+                    Some(lp_xrd) => *lp_xrd = *lp_xrd+xrd_amnt,
+                    None => std::process::abort()                  
+                };
             }
 
             // Retrieve price of specific candy type.
@@ -248,10 +226,10 @@ blueprint! {
                 candy
             }
 
-            // Take buyed candy from candy vault and increment total accrued fee in relative hashmap.
+            // Take buyed candy from candy vault and accrue fee, left in vault, to liquidity providers.
             fn candytake(&mut self, candy_out_nbr: Decimal, candy_out_addr: Address) -> Bucket {
                 match self.meta_map.get_mut(&candy_out_addr.clone()) {
-                    Some((amnt_fee,_b,_c)) => *amnt_fee = *amnt_fee+candy_out_nbr*self.fee/100,
+                    Some((lp_candy,_b,_c)) => *lp_candy = *lp_candy+candy_out_nbr*self.fee/100,
                     None => std::process::abort()                  
                 };
 
@@ -361,7 +339,7 @@ blueprint! {
 
             // Stock candy function callable by an end user wishing to supply unpresent liquidity to 
             // protocol.
-        pub fn stock_candy(&mut self, candy: Bucket, new_price: Decimal) -> (Bucket,Bucket) {
+        pub fn stock_candy(&mut self, candy: Bucket, new_price: Decimal) -> Bucket {
             let candy_addr = candy.resource_address();
             let candy_amnt = candy.amount();
             let candy_res_def = candy.resource_def();
@@ -390,26 +368,17 @@ blueprint! {
             // Insert Candy address as well as other metadata in relative hashmaps.
             self.candy_map.insert(candy_addr,(name.clone(),symbol.clone(),new_price));
             self.meta_map.insert(candy_addr,(none,none,candy_addr));
+            self.lp_xrd_map.insert(candy_addr,none);
             
-            // Mint metaCandy, metaBadge and insert relative hashmaps values. 
+            // Create metaCandy resource and mint liquidity provider's share.
             CandyDex::add_meta_candy(self, name.clone(), symbol.clone(), candy_addr);
             
-            let meta_candy: Bucket = CandyDex::meta_mint(self, candy_amnt, candy_addr);           
-            
-            let meta_amount = meta_candy.amount();
-            let meta_badge: Bucket = 
-                CandyDex::add_meta_badge(self, symbol, candy_addr, none, meta_amount);
-            
-            self.badge_map.insert(meta_badge.resource_address().clone(),(none,none,candy_addr));
-            
-            let output_badge: Bucket = CandyDex::meta_amounts(self, meta_badge, meta_amount, none);
-            
-            (meta_candy,output_badge)
+            CandyDex::meta_mint(self, candy_amnt, candy_addr)
         }
 
             // Restock candy function callable by an end user wishing to supply present liquidity to 
             // protocol.
-        pub fn restock_candy(&mut self, candy: Bucket) -> (Bucket,Bucket) {
+        pub fn restock_candy(&mut self, candy: Bucket) -> Bucket {
             let candy_addr = candy.resource_address();            
             assert!( candy_addr != RADIX_TOKEN," Cannot stock XRD as candy ");
             
@@ -430,64 +399,36 @@ blueprint! {
 
             v.put(candy);
             
-            // Mint relative metaCandy tokens.    
-            let meta_candy: Bucket = CandyDex::meta_mint(self, amnt, candy_addr);
-
-            // Retrieve entry fee level.
-            let (accrued_fee,_b,_c) = self.meta_map.get(&candy_addr).unwrap();
-            info!(" entry_fee {} ",accrued_fee);
-            
-            // Mint a metaBadge and update relative hashmaps values.
-            let entry_fee = *accrued_fee;            
-            let (_name,symbol,_price) = self.candy_map.get(&candy_addr).unwrap();
-            let symbol_str = symbol.to_string();
-            let meta_amount = meta_candy.amount();
-            let meta_badge: Bucket = 
-                CandyDex::add_meta_badge(self, symbol_str, candy_addr, entry_fee, meta_amount);
-            
-            self.badge_map.insert(meta_badge.resource_address()
-                .clone(),(entry_fee,dec!(0),candy_addr));
-            
-            let output_badge: Bucket = 
-                CandyDex::meta_amounts(self, meta_badge, meta_amount, dec!(0));
-            
-            (meta_candy,output_badge)
+            // Mint relative metaCandy tokens pro rata to candy pool value.    
+            CandyDex::meta_mint(self, amnt, candy_addr)
         }
 
             // Unstock candy function callable by an end user wishing to withdraw owned candy 
             // liquidity amount from protocol.
-        pub fn unstock_candy(
-            &mut self, 
-            candy_addr: Address, 
-            meta_candy: Bucket, 
-            meta_badge: Bucket
-        ) -> (Bucket,Bucket,Bucket) {
+        pub fn unstock_candy(&mut self, candy_addr: Address, meta_candy: Bucket) -> (Bucket,Bucket) {
 
             // Verify supplied resources correspondances. 
-            let badge_amnt: Decimal = meta_badge.amount();
-            assert!( badge_amnt >= dec!(1), " Please supply meta badge ");
-            
-            let (accrued_fee,total_minted,meta_address) = self.meta_map.get(&candy_addr).unwrap();
-            assert!(meta_address == &meta_candy.resource_address()," Mismatch between Candy & metaCandy! ");
-            
-            let (entry_fee,_b,candy_address) = 
-                self.badge_map.get(&meta_badge.resource_address()).unwrap();
-            assert!(candy_address == &candy_addr," MetaBadge address unrecognized! ");
+            let (lp_candy,meta_supply,meta_address) = *self.meta_map.get(&candy_addr).unwrap();
+            assert!(meta_address == meta_candy.resource_address()," Mismatch between Candy & metaCandy! ");
             
             let meta_candy_amnt: Decimal = meta_candy.amount();
-            let candy_out_nbr: Decimal;
+            assert!( meta_candy_amnt > dec!(0), " Please supply metaCandy ");
+            
+            let lp_xrd = *self.lp_xrd_map.get(&candy_addr).unwrap();
             let candy_bucket: Bucket;
-            let xrd_out: Bucket;
-            let delta_fee: Decimal = *accrued_fee-(*entry_fee);
             
-            // Determine output Candy amount.
-            candy_out_nbr = meta_candy_amnt+delta_fee*meta_candy_amnt/(*total_minted);
+            // Determine output Candy and accrued XRD fee amounts pro rata to supplied metaCandy.
+            let candy_out_nbr = lp_candy*meta_candy_amnt/meta_supply;
+            let mut xrd_amnt = lp_xrd*meta_candy_amnt/meta_supply;
             
-            info!(" total_minted {} ",total_minted);
-            info!(" accrued_fee {} ",accrued_fee);
-            info!(" entry_fee {} ",entry_fee);
-            info!(" delta_fee {} ",delta_fee);
+            info!(" meta_supply {} ",meta_supply);
+            info!(" lp_candy {} ",lp_candy);
+            info!(" lp_xrd {} ",lp_xrd);
             info!(" candy_out_nbr {} ",candy_out_nbr);
+            info!(" xrd_fee_out {} ",xrd_amnt);
+            
+            self.meta_map.insert(candy_addr,(lp_candy-candy_out_nbr,meta_supply-meta_candy_amnt,meta_address));
+            self.lp_xrd_map.insert(candy_addr,lp_xrd-xrd_amnt);
             
             // Retrieve Candy reserve amount.
             let total_candy = self.candy_vaults.get(&candy_addr).unwrap().amount();
@@ -502,8 +443,6 @@ blueprint! {
                             std::process::abort()
                         }
                     };
-                    let zero: Decimal = dec!(0);
-                    xrd_out = self.collected_xrd.take(zero);
             }else{  let delta_candy = candy_out_nbr-total_candy;
                     candy_bucket = match self.candy_vaults.get_mut(&candy_addr) {
                         Some(vault) => vault.take(total_candy),
@@ -513,41 +452,35 @@ blueprint! {
                         }
                     };
                     let price_in: Decimal = CandyDex::candyprice(self, candy_addr);
-                    let xrd_amnt = delta_candy*price_in;
-                    assert!( xrd_amnt <= self.collected_xrd.amount(), " Not enough XRD in Vault ");
-                    xrd_out = self.collected_xrd.take(xrd_amnt);
+                    xrd_amnt = xrd_amnt+delta_candy*price_in;
             }
+            assert!( xrd_amnt <= self.collected_xrd.amount(), " Not enough XRD in Vault ");
+            let xrd_out: Bucket = self.collected_xrd.take(xrd_amnt);
 
-            // Burn relative metaCandy, verify user's remaining Candy liquidity is greater then zero
-            // otherwise burn metaBadge, update relative hashmaps values. 
-            let meta_candy_amnt: Decimal = meta_candy.amount().clone();
-            
+            // Burn relative metaCandy.
             CandyDex::meta_burn(self, meta_candy);
             
-            let output_badge: Bucket = 
-                CandyDex::meta_amounts(self, meta_badge, meta_candy_amnt, dec!(1));
-            
-            (candy_bucket,xrd_out,output_badge)
+            (candy_bucket,xrd_out)
         }
 
-            // Retrieve liquidity provider position providing a relative metaBadge as reference.
-        pub fn stock_position(&mut self, meta_badge: BucketRef) {
-            let badge_amnt: Decimal = meta_badge.amount();
-            assert!( badge_amnt >= dec!(1), " Please provide your own metaBadge as reference ");
-            
-            match self.badge_map.get(&meta_badge.resource_address()) {
-                Some((a,b,c)) => { 
-                    let (accrued_fee,total_minted,_meta_address) = self.meta_map.get(&c).unwrap();
-                    let delta_fee: Decimal = *accrued_fee-(*a);
-                    let candy_out_nbr = *b+delta_fee*(*b)/(*total_minted);
-                    info!(" accrued_fee {} ",accrued_fee);
-                    info!(" total_minted {} ",total_minted);
-                    info!(" entry_fee {} ",a);
-                    info!(" delta_fee {} ",delta_fee);
+            // Retrieve candy and XRD amounts owed to a metaCandy amount of a determinated candy pool.
+        pub fn get_lp_value(&self, candy_addr: Address, meta_amnt: Decimal) -> (Decimal,Decimal) {
+            match self.meta_map.get(&candy_addr) {
+                Some((lp_candy,meta_supply,_c)) => { 
+                    assert!( *meta_supply > dec!(0), " No liquidity in candy pool ");
+                    assert!( meta_amnt <= *meta_supply, " Let's check passed amount ");
+                    
+                    let lp_xrd = self.lp_xrd_map.get(&candy_addr).unwrap();
+                    let candy_out_nbr = *lp_candy*meta_amnt/(*meta_supply);
+                    let xrd_out_nbr = *lp_xrd*meta_amnt/(*meta_supply);
+                    info!(" meta_supply {} ",meta_supply);
                     info!(" candy_out_nbr {} ",candy_out_nbr);
+                    info!(" xrd_out_nbr {} ",xrd_out_nbr);
+                    
+                    (candy_out_nbr,xrd_out_nbr)
                 },
                 None => {
-                    info!(" No badge's correspondence! ");
+                    info!(" Could not find candy in stock !");
                     std::process::abort()
                 }
             }
@@ -730,7 +663,7 @@ blueprint! {
                 CandyDex::candyput_pri(self, new_price*new_price, new_price, addr, candy_out);
             assert!( nmbr >= xrd_min , "Not enough xrd output amount");
              
-            self.xrd_fee = self.xrd_fee+nmbr*self.fee/100;
+            CandyDex::accrue_xrd_fee(self, addr, nmbr*self.fee/100);
 
             self.collected_xrd.take(*&(nmbr-nmbr*self.fee/100))
         }
@@ -796,7 +729,7 @@ blueprint! {
                 None => std::process::abort()                  
             };
 
-            self.xrd_fee = self.xrd_fee+xrd_input*self.fee/100;
+            CandyDex::accrue_xrd_fee(self, addr, xrd_input*self.fee/100);
 
             (
                 CandyDex::candyput_sec(self, xrd_input, addr, dec!(1), new_price, candy_out),
@@ -804,83 +737,100 @@ blueprint! {
             )
         }
 
-            // Request a flashswap performing a call to an external Component address.
-        pub fn flashswap(
-            &mut self, 
-            amnt_in: Decimal, 
-            addr_in: Address, 
-            bckt_addr: Address, 
-            ext_addr: Address, 
-            method: String
-        ) -> Bucket {                
-            let amount_in = CandyDex::adjust_fee(self, amnt_in);
-            
-            let token_bucket: Bucket;
-            let token_output: Bucket;
+            // Lend an amount of a stocked candy or XRD. Returns borrowed resources along with a 
+            // receipt holding loan data, burnt by "flash_repay" method within "flash_loan" method.
+        fn flash_borrow(&mut self, amnt: Decimal, addr: Address) -> (Bucket,Bucket) {
+            assert!( amnt > dec!(0), " Let's borrow a positive amount ");
+
+            let loan: Bucket;
+            
+            // Verify nature of resources to borrow and their existance within Protocol's vaults.
+            if addr == RADIX_TOKEN {
+                assert!( amnt <= self.collected_xrd.amount(), " Not enough XRD in Vault ");
+                loan = self.collected_xrd.take(amnt);
+            } else {
+                loan = match self.candy_vaults.get_mut(&addr) {
+                    Some(vault) => {
+                        assert!( amnt <= vault.amount(), " Not enough candy in Vault ");
+                        vault.take(amnt)
+                    },
+                    None => {
+                        info!(" Candy not in stock! ");
+                        std::process::abort()
+                    }
+                };
+            }
 
-            let price_in: Decimal;
-            let price_out: Decimal;
-            
-            // Conditional code block to verify nature of resources to borrow, their existance 
-            // within Protocol's vaults, collect them and retrieve their prices in case of 
-            // affermative response.
-            if  addr_in == bckt_addr {    
-                price_in = dec!(1);
-                price_out = dec!(1);
+            let fee = amnt*self.fee/100;
+            info!(" Borrowed {} of {}, repay {} ", amnt, addr, amnt+fee);
 
-                if addr_in == RADIX_TOKEN {
-                    token_bucket = self.collected_xrd.take(*&(amount_in-amount_in*self.fee/100));
-                    self.xrd_fee = self.xrd_fee+amount_in*self.fee/100;
-                } else { 
-                    token_bucket = CandyDex::candytake(self, amount_in, addr_in); 
-                }
+            let receipt_data = FlashReceipt { resource_address: addr, amount: amnt, fee };
+            let flash_receipt = &mut self.flash_receipt;
+            let receipt: Bucket = self.minter_badge.authorize(|auth| {
+                flash_receipt.mint_non_fungible(&NonFungibleKey::from(Uuid::generate()), receipt_data, auth)
+            });
 
-            } else if addr_in == RADIX_TOKEN && addr_in != bckt_addr {
-                token_bucket = self.collected_xrd.take(*&(amount_in-amount_in*self.fee/100));
-                self.xrd_fee = self.xrd_fee+amount_in*self.fee/100;
-                price_in = dec!(1);
-                price_out = CandyDex::candyprice(self, bckt_addr);
-            } else if addr_in != bckt_addr && bckt_addr == RADIX_TOKEN {
-                token_bucket = CandyDex::candytake(self, amount_in, addr_in);
-                price_in = CandyDex::candyprice(self, addr_in);
-                price_out = dec!(1);
-            } else if bckt_addr != RADIX_TOKEN {
-                token_bucket = CandyDex::candytake(self, amount_in, addr_in);
-                price_in = CandyDex::candyprice(self, addr_in);
-                price_out = CandyDex::candyprice(self, bckt_addr);
-            } else { 
-                info!(" Check out addresses! "); 
-                std::process::abort(); 
-            }
-            
-            // Encode arguments to call external Component within borrowed resources and call him. 
-            let args = vec![scrypto_encode(&token_bucket),scrypto_encode(&bckt_addr)];
-            
-            let token_return = Component::from(ext_addr).call::<Bucket>(&method.to_string(), args);
+            (loan,receipt)
+        }
 
-            // Calculate Protocol's repay amount to refurbish.
-            let amount = token_return.amount();
-            let nmbr = (amnt_in+amnt_in*self.fee/100)*price_in/price_out;
-            
-            // Check nature of returned resources and collect them consequentely.
-            if bckt_addr != RADIX_TOKEN {   
-                let v = self.candy_vaults.get_mut(&bckt_addr).unwrap();
+            // Repay a flash loan providing borrowed resources plus protocol fee along with the loan 
+            // receipt minted with given key. Burns the receipt and returns exceeding resources.
+        fn flash_repay(&mut self, mut repayment: Bucket, receipt: Bucket, key: NonFungibleKey) -> Bucket {
+            assert!( receipt.resource_address() == self.flash_receipt.address(), " Flash receipt unrecognized! ");
+            assert!( 
+                receipt.amount() == dec!(1) && receipt.get_non_fungible_key() == key, 
+                " Flash receipt unrecognized! "
+            );
+
+            let data: FlashReceipt = 
+                self.flash_receipt.get_non_fungible_data(&receipt.get_non_fungible_key());
+            let owed = data.amount+data.fee;
+
+            assert!( repayment.resource_address() == data.resource_address, " Mismatch between loan & repayment! ");
+            assert!( repayment.amount() >= owed, " Not enough repayment amount ");
+            info!(" Repaid {} of {} ", owed, data.resource_address);
+
+            // Collect repayment, accrue fee to protocol for XRD loans, to candy liquidity 
+            // providers otherwise.
+            if data.resource_address == RADIX_TOKEN {
+                self.collected_xrd.put(repayment.take(owed));
+                self.xrd_fee = self.xrd_fee+data.fee;
+            } else {
+                let v = self.candy_vaults.get_mut(&data.resource_address).unwrap();
+                v.put(repayment.take(owed));
+
+                match self.meta_map.get_mut(&data.resource_address) {
+                    Some((lp_candy,_b,_c)) => *lp_candy = *lp_candy+data.fee,
+                    None => std::process::abort()                  
+                };
+            }
 
-                v.put(token_return);
+            self.minter_badge.authorize(|auth| {receipt.burn_with_auth(auth)});
 
-                token_output = v.take(amount-nmbr);    
-            } else { 
-                self.collected_xrd.put(token_return);
-                token_output = self.collected_xrd.take(*&(amount-nmbr)); 
-            }
+            repayment
+        }
 
-            // Check if transaction has been profitable or abort it.
-            if token_output.amount() < dec!(0) {
-               info!(" Sorry mate, ain't nothin' to scrape! ");
-               std::process::abort();
-            }
+            // Request a flash loan performing a call to an external Component address and method, 
+            // which receives borrowed resources and receipt and must return repayment and receipt.
+            // Returns exceeding repayment amount.
+        pub fn flash_loan(
+            &mut self, 
+            amnt: Decimal, 
+            addr: Address, 
+            ext_addr: Address, 
+            method: String
+        ) -> Bucket {                
+            let (loan,receipt) = CandyDex::flash_borrow(self, amnt, addr);
+            let key = receipt.get_non_fungible_key();
+            
+            // Encode arguments to call external Component within borrowed resources and call him. 
+            let args = vec![scrypto_encode(&loan),scrypto_encode(&receipt)];
+            
+            let (repayment,receipt_back) = 
+                Component::from(ext_addr).call::<(Bucket,Bucket)>(&method.to_string(), args);
 
-            token_output
+            // Check repayment, burn receipt and return profit.
+            CandyDex::flash_repay(self, repayment, receipt_back, key)
         }
     }
 }
@@ -910,3 +860,11 @@ impl MetaToken {
         }
     }
 }
+
+// Flash loan receipt data, borrowed resource address, amount and fee to repay.
+#[derive(NonFungibleData)]
+pub struct FlashReceipt {
+    resource_address: Address,
+    amount: Decimal,
+    fee: Decimal,
+}
@@ -0,0 +1,469 @@
+use radix_engine::engine::*;
+use radix_engine::ledger::*;
+use radix_engine::model::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+fn get_vault_info<'a, L: SubstateStore>(
+    ledger: &'a L,
+    component: &Address,
+    id: &Vid,
+) -> (Address, Decimal) {
+    let vault = ledger.get_vault(component, id).unwrap();
+    (vault.resource_address(), vault.amount())
+}
+
+fn get_lazymap_info<'a, L: SubstateStore>(
+    ledger: &'a L,
+    component: &Address,
+    id: &Mid,
+) -> Vec<(Address, Decimal)> {
+    let lazy_map = ledger.get_lazy_map(component, id).unwrap();
+    lazy_map
+        .map()
+        .iter()
+        .flat_map(|(_, data)| {
+            let validated_data = validate_data(data).unwrap();
+            validated_data
+                .vaults
+                .iter()
+                .map(|vid| get_vault_info(ledger, component, vid))
+                .collect::<Vec<(Address, Decimal)>>()
+        })
+        .collect()
+}
+
+fn balance<'a, L: SubstateStore>(ledger: &'a L, account: Address, token: Address) -> Decimal {
+    let component = ledger.get_component(account).unwrap();
+    let validated_data = validate_data(component.state()).unwrap();
+    validated_data
+        .lazy_maps
+        .iter()
+        .flat_map(|mid| get_lazymap_info(ledger, &account, &mid))
+        .filter(|(address, _)| *address == token)
+        .map(|(_, amount)| amount)
+        .next()
+        .unwrap_or(Decimal::zero())
+}
+
+fn assert_close(value: Decimal, expected: Decimal) {
+    let epsilon = dec!("0.000000001");
+    assert!(
+        value > expected - epsilon && value < expected + epsilon,
+        "expected about {} found {}",
+        expected,
+        value
+    );
+}
+
+fn create_candy<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+) -> Address {
+    let mut metadata = HashMap::new();
+    metadata.insert("name".to_owned(), "THETAGUM".to_owned());
+    metadata.insert("symbol".to_owned(), "THG".to_owned());
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .new_token_fixed(metadata, dec!("100000"))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    receipt.resource_def(0).unwrap()
+}
+
+fn transfer<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    from: Address,
+    to: Address,
+    amount: Decimal,
+    token: Address,
+) {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .withdraw_from_account(
+                    &Resource::Fungible {
+                        amount,
+                        resource_address: token,
+                    },
+                    from,
+                )
+                .call_method_with_all_resources(to, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+}
+
+/// Calls a CandyDex method from the account, returns the receipt whatever the result
+fn call<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    candy_dex: Address,
+    method: &str,
+    args: Vec<String>,
+) -> Receipt {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(candy_dex, method, args, Some(account))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    receipt
+}
+
+fn get_lp_value<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    candy_dex: Address,
+    candy: Address,
+    meta_amount: Decimal,
+) -> (Decimal, Decimal) {
+    let receipt = call(
+        executor,
+        key,
+        account,
+        candy_dex,
+        "get_lp_value",
+        vec![candy.to_string(), meta_amount.to_string()],
+    );
+    assert!(receipt.result.is_ok());
+    scrypto_decode(&receipt.outputs[receipt.outputs.len() - 2].raw).unwrap()
+}
+
+/// Instantiates a FlashBorrower funded with THG from the account
+fn new_borrower<'a, L: SubstateStore>(executor: &mut TransactionExecutor<'a, L>, env: &Env, funds: Decimal) -> Address {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_function(env.package, "FlashBorrower", "new", vec![format!("{},{}", funds, env.thg)], Some(env.account))
+                .call_method_with_all_resources(env.account, "deposit_batch")
+                .build(vec![env.key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    receipt.component(0).unwrap()
+}
+
+/// Borrows THG with "flash_loan", calling the given FlashBorrower method with the loan and its receipt
+fn flash_loan<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    env: &Env,
+    amount: Decimal,
+    borrower: Address,
+    method: &str,
+) -> Receipt {
+    call(
+        executor,
+        env.key,
+        env.account,
+        env.candy_dex,
+        "flash_loan",
+        vec![amount.to_string(), env.thg.to_string(), borrower.to_string(), method.to_owned()],
+    )
+}
+
+struct Env {
+    key: EcdsaPublicKey,
+    package: Address,
+    account: Address,
+    candy_dex: Address,
+    thg: Address,
+    mthg: Address,
+    flash_receipt: Address,
+}
+
+struct Pool {
+    vault: Decimal,
+    lp_candy: Decimal,
+    lp_xrd: Decimal,
+    meta_supply: Decimal,
+    price: Decimal,
+}
+
+/// Sets up a CandyDex with a 1% fee and 10000 THG stocked at 2 XRD, then trades 1000 XRD for THG and 100 THG for
+/// XRD so that fees accrue to liquidity providers in both THG and XRD.
+/// Returns the pool state replayed with the blueprint maths along with the environment.
+fn setup<'a, L: SubstateStore>(executor: &mut TransactionExecutor<'a, L>) -> (Env, Pool) {
+    let key = executor.new_public_key();
+    let account = executor.new_account(key);
+    let package = executor.publish_package(include_code!("candydex")).unwrap();
+
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_function(package, "CandyDex", "new", vec!["1".to_owned()], Some(account))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    let candy_dex = receipt.component(0).unwrap();
+    // the minter and owner badges are created before the flash receipt
+    let flash_receipt = receipt.resource_def(2).unwrap();
+    let thg = create_candy(executor, key, account);
+
+    let receipt = call(
+        executor,
+        key,
+        account,
+        candy_dex,
+        "stock_candy",
+        vec![format!("10000,{}", thg), "2".to_owned()],
+    );
+    assert!(receipt.result.is_ok());
+    let mthg = receipt.resource_def(0).unwrap();
+    assert_eq!(balance(executor.ledger(), account, mthg), dec!("10000"));
+
+    let fee = dec!("1");
+    let mut pool = Pool {
+        vault: dec!("10000"),
+        lp_candy: dec!("10000"),
+        lp_xrd: Decimal::zero(),
+        meta_supply: dec!("10000"),
+        price: dec!("2"),
+    };
+
+    // buying THG raises its price, the fee is left in the candy vault
+    let receipt = call(
+        executor,
+        key,
+        account,
+        candy_dex,
+        "buy_candy_sell_exact_xrd",
+        vec!["0".to_owned(), thg.to_string(), format!("1000,{}", RADIX_TOKEN)],
+    );
+    assert!(receipt.result.is_ok());
+    let amount = dec!("1000") / pool.price;
+    pool.price = (pool.vault * pool.price + amount * pool.price) / pool.vault;
+    let candy_out = dec!("1000") / pool.price;
+    pool.lp_candy = pool.lp_candy + candy_out * fee / 100;
+    pool.vault = pool.vault - (candy_out - candy_out * fee / 100);
+
+    // selling THG lowers its price, the fee is accrued in XRD
+    let receipt = call(
+        executor,
+        key,
+        account,
+        candy_dex,
+        "buy_xrd_sell_exact_candy",
+        vec!["0".to_owned(), format!("100,{}", thg)],
+    );
+    assert!(receipt.result.is_ok());
+    pool.price = pool.vault * pool.price / (pool.vault + dec!("100"));
+    let xrd_out = dec!("100") * (pool.price * pool.price) / pool.price;
+    pool.lp_xrd = pool.lp_xrd + xrd_out * fee / 100;
+    pool.vault = pool.vault + dec!("100");
+
+    assert!(pool.lp_candy > dec!("10000") && pool.lp_xrd > Decimal::zero());
+    let env = Env {
+        key,
+        package,
+        account,
+        candy_dex,
+        thg,
+        mthg,
+        flash_receipt,
+    };
+    (env, pool)
+}
+
+#[test]
+fn test_lp_value_includes_candy_and_xrd_fees() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (env, pool) = setup(&mut executor);
+    let (key, account, candy_dex, thg) = (env.key, env.account, env.candy_dex, env.thg);
+
+    let (candy, xrd) = get_lp_value(&mut executor, key, account, candy_dex, thg, dec!("10000"));
+    assert_eq!(candy, pool.lp_candy);
+    assert_eq!(xrd, pool.lp_xrd);
+
+    // shares are pro rata to the metaCandy amount
+    let (candy, xrd) = get_lp_value(&mut executor, key, account, candy_dex, thg, dec!("2500"));
+    assert_eq!(candy, pool.lp_candy * dec!("2500") / pool.meta_supply);
+    assert_eq!(xrd, pool.lp_xrd * dec!("2500") / pool.meta_supply);
+
+    // more than the supply is refused
+    let receipt = call(
+        &mut executor,
+        key,
+        account,
+        candy_dex,
+        "get_lp_value",
+        vec![thg.to_string(), "10001".to_owned()],
+    );
+    assert!(receipt.result.is_err());
+}
+
+#[test]
+fn test_second_lp_joining_after_fees_does_not_dilute_the_first() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (env, mut pool) = setup(&mut executor);
+    let (key, account, candy_dex, thg, mthg) = (env.key, env.account, env.candy_dex, env.thg, env.mthg);
+
+    let second_lp = executor.new_account(key);
+    transfer(&mut executor, key, account, second_lp, dec!("1000"), thg);
+
+    // the fees accrued so far are part of the pool value, so 1000 THG buy less than 1000 metaCandy
+    let lp_value = pool.lp_candy + pool.lp_xrd / pool.price;
+    let first_lp_value = lp_value;
+    let receipt = call(
+        &mut executor,
+        key,
+        second_lp,
+        candy_dex,
+        "restock_candy",
+        vec![format!("1000,{}", thg)],
+    );
+    assert!(receipt.result.is_ok());
+    let meta_minted = dec!("1000") * pool.meta_supply / lp_value;
+    pool.lp_candy = pool.lp_candy + dec!("1000");
+    pool.vault = pool.vault + dec!("1000");
+    pool.meta_supply = pool.meta_supply + meta_minted;
+    assert!(meta_minted < dec!("1000"));
+    assert_eq!(balance(executor.ledger(), second_lp, mthg), meta_minted);
+
+    // the second provider owns the 1000 THG it brought
+    let (candy, xrd) = get_lp_value(&mut executor, key, second_lp, candy_dex, thg, meta_minted);
+    assert_eq!(candy, pool.lp_candy * meta_minted / pool.meta_supply);
+    assert_eq!(xrd, pool.lp_xrd * meta_minted / pool.meta_supply);
+    assert_close(candy + xrd / pool.price, dec!("1000"));
+
+    // the first provider still owns the whole pool value before the second joined
+    let (candy, xrd) = get_lp_value(&mut executor, key, account, candy_dex, thg, dec!("10000"));
+    assert_eq!(candy, pool.lp_candy * dec!("10000") / pool.meta_supply);
+    assert_close(candy + xrd / pool.price, first_lp_value);
+}
+
+#[test]
+fn test_unstock_pays_the_share_of_candy_and_xrd() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (env, mut pool) = setup(&mut executor);
+    let (key, account, candy_dex, thg, mthg) = (env.key, env.account, env.candy_dex, env.thg, env.mthg);
+
+    // withdraw in two halves, each pays its share of the remaining pool and burns the metaCandy. Buyers took more
+    // THG out of the vault than the fees left in it, so the second half gets the candy left and the rest in XRD.
+    for _ in 0..2 {
+        let candy_before = balance(executor.ledger(), account, thg);
+        let xrd_before = balance(executor.ledger(), account, RADIX_TOKEN);
+        let receipt = call(
+            &mut executor,
+            key,
+            account,
+            candy_dex,
+            "unstock_candy",
+            vec![thg.to_string(), format!("5000,{}", mthg)],
+        );
+        assert!(receipt.result.is_ok());
+
+        let mut candy_out = pool.lp_candy * dec!("5000") / pool.meta_supply;
+        let mut xrd_out = pool.lp_xrd * dec!("5000") / pool.meta_supply;
+        pool.lp_candy = pool.lp_candy - candy_out;
+        pool.lp_xrd = pool.lp_xrd - xrd_out;
+        pool.meta_supply = pool.meta_supply - dec!("5000");
+        if candy_out > pool.vault {
+            xrd_out = xrd_out + (candy_out - pool.vault) * pool.price;
+            candy_out = pool.vault;
+        }
+        pool.vault = pool.vault - candy_out;
+        assert_eq!(balance(executor.ledger(), account, thg), candy_before + candy_out);
+        assert_eq!(balance(executor.ledger(), account, RADIX_TOKEN), xrd_before + xrd_out);
+        assert_eq!(balance(executor.ledger(), account, mthg), pool.meta_supply);
+    }
+
+    assert_eq!(pool.vault, Decimal::zero());
+
+    // the pool is empty
+    let receipt = call(
+        &mut executor,
+        key,
+        account,
+        candy_dex,
+        "get_lp_value",
+        vec![thg.to_string(), "1".to_owned()],
+    );
+    assert!(receipt.result.is_err());
+}
+
+#[test]
+fn test_flash_loan_repaid_in_full() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (env, pool) = setup(&mut executor);
+
+    // 100 THG plus the 1% fee, the fee goes to the liquidity providers and the funds left are returned
+    let borrower = new_borrower(&mut executor, &env, dec!("5"));
+    let before = balance(executor.ledger(), env.account, env.thg);
+    let receipt = flash_loan(&mut executor, &env, dec!("100"), borrower, "repay");
+    assert!(receipt.result.is_ok());
+    assert_eq!(balance(executor.ledger(), env.account, env.thg), before + dec!("4"));
+    assert_eq!(balance(executor.ledger(), env.account, env.flash_receipt), Decimal::zero());
+
+    let (candy, _) = get_lp_value(&mut executor, env.key, env.account, env.candy_dex, env.thg, pool.meta_supply);
+    assert_eq!(candy, pool.lp_candy + dec!("1"));
+}
+
+#[test]
+fn test_flash_loan_underpaid_is_reverted() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (env, pool) = setup(&mut executor);
+
+    // the loan is repaid but not the fee
+    let borrower = new_borrower(&mut executor, &env, dec!("5"));
+    let before = balance(executor.ledger(), env.account, env.thg);
+    let receipt = flash_loan(&mut executor, &env, dec!("100"), borrower, "underpay");
+    assert!(receipt.result.is_err());
+    assert_eq!(balance(executor.ledger(), env.account, env.thg), before);
+
+    let (candy, _) = get_lp_value(&mut executor, env.key, env.account, env.candy_dex, env.thg, pool.meta_supply);
+    assert_eq!(candy, pool.lp_candy);
+}
+
+#[test]
+fn test_flash_loan_without_repay_is_reverted() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (env, pool) = setup(&mut executor);
+
+    // loans can't be taken out of "flash_loan", the receipt would be deposited and the loan kept
+    let receipt = call(
+        &mut executor,
+        env.key,
+        env.account,
+        env.candy_dex,
+        "flash_borrow",
+        vec!["100".to_owned(), env.thg.to_string()],
+    );
+    assert!(receipt.result.is_err());
+    assert_eq!(balance(executor.ledger(), env.account, env.flash_receipt), Decimal::zero());
+
+    // the borrower deposits the receipt in its own vault and hands back an empty receipt bucket
+    let borrower = new_borrower(&mut executor, &env, dec!("5"));
+    let before = balance(executor.ledger(), env.account, env.thg);
+    let receipt = flash_loan(&mut executor, &env, dec!("100"), borrower, "keep_receipt");
+    assert!(receipt.result.is_err());
+    assert_eq!(balance(executor.ledger(), env.account, env.thg), before);
+
+    let (candy, _) = get_lp_value(&mut executor, env.key, env.account, env.candy_dex, env.thg, pool.meta_supply);
+    assert_eq!(candy, pool.lp_candy);
+}
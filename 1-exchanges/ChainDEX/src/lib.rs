@@ -18,14 +18,25 @@ use scrypto::prelude::*;
 pub struct Order {
     is_buy: bool,           // is b for a
     price: Decimal,         // a per b
+    size: Decimal,          // a to buy/sell when order was created
     #[scrypto(mutable)]
     amount: Decimal,        // a to buy/sell
     #[scrypto(mutable)]
-    filled: Decimal,        // amount of order filled
+    filled: Decimal,        // amount of order filled and not claimed yet
+    #[scrypto(mutable)]
+    total_filled: Decimal,  // amount of order filled since creation
     #[scrypto(mutable)]
     next: NonFungibleKey    // key of next order in buy/sell chain
 }
 
+#[derive(TypeId, Encode, Decode, Describe, Debug, Clone, PartialEq)]
+pub struct Fill {
+    counter_order: NonFungibleKey,  // key of order matched against
+    price: Decimal,                 // a per b, price of the resting order
+    amount: Decimal,                // a bought/sold
+    epoch: u64,                     // epoch of the match
+}
+
 blueprint! {
     struct ChainBook {
         name: String,
@@ -36,6 +47,7 @@ blueprint! {
         sell_head: NonFungibleKey,  // key of first in sell chain
         buy_head: NonFungibleKey,   // key of first in buy chain
         count: u128,                // used to create new order key
+        fill_history: LazyMap<NonFungibleKey, Vec<Fill>>,  // fills of each order, oldest first
     }
 
     impl ChainBook {
@@ -61,13 +73,15 @@ blueprint! {
                 sell_head: 0.into(),
                 buy_head: 0.into(),
                 count: 0,
+                fill_history: LazyMap::new(),
             }
             .instantiate()
         }
 
         // fills orders with a better price than asking then creates a limit order with the remaining tokens
-        // returns (tokens, ?order)
-        pub fn create_order(&mut self, mut tokens: Bucket, price: Decimal) -> (Bucket, Bucket) {
+        // the fills of the new order are kept in its history only if an order is minted
+        // returns (tokens, ?order, [fill])
+        pub fn create_order(&mut self, mut tokens: Bucket, price: Decimal) -> (Bucket, Bucket, Vec<Fill>) {
             assert!(
                 tokens.resource_def() == self.a_pool.resource_def() || tokens.resource_def() == self.b_pool.resource_def(),
                 "Tokens not in this pair"
@@ -76,10 +90,11 @@ blueprint! {
             self.count += 1;
             let order_key: NonFungibleKey = self.count.into();
             let is_buy: bool = tokens.resource_def() == self.b_pool.resource_def();
+            let mut taker_fills: Vec<Fill> = Vec::new();
             
             if is_buy { // b for a (buy)
                 let mut filled: Bucket = Bucket::new(self.a_pool.resource_def());
-                // fill colisions (market buy)
+                // fill colisions (market buy), best price first then oldest order first
                 {
                     let mut sell_pointer: NonFungibleKey = self.sell_head.clone();
 
@@ -99,7 +114,9 @@ blueprint! {
                         self.b_pool.put(tokens.take(to_fill * data.price));
                         data.amount -= to_fill;
                         data.filled += to_fill;
+                        data.total_filled += to_fill;
                         filled.put(self.a_pool.take(to_fill));
+                        taker_fills.push(self.record_fill(&order_key, &sell_pointer, data.price, to_fill));
                         
                         self.admin_badge.authorize(|auth| {
                             self.order_def.update_non_fungible_data(&sell_pointer, data, auth);
@@ -111,6 +128,7 @@ blueprint! {
                 }
 
                 // make order (limit buy)
+                let taker_filled: Decimal = filled.amount();
                 let to_order: Decimal = tokens.amount() / price;
                 self.b_pool.put(tokens);
 
@@ -144,7 +162,10 @@ blueprint! {
 
                         next
                     };
-                    // return (filled, order)
+                    if !taker_fills.is_empty() {
+                        self.fill_history.insert(order_key.clone(), taker_fills.clone());
+                    }
+                    // return (filled, order, fills)
                     (
                         filled,
                         self.admin_badge.authorize(|auth| {
@@ -152,20 +173,24 @@ blueprint! {
                                 Order {
                                     is_buy: is_buy,
                                     price: price,
+                                    size: taker_filled + to_order,
                                     amount: to_order,
                                     filled: 0.into(),
+                                    total_filled: taker_filled,
                                     next: next,
                                 },
                                 auth)
-                        })
+                        }),
+                        taker_fills
                     )
                 } else {
-                    // return (filled, empty bucket)
-                    (filled, Bucket::new(self.order_def.clone()))
+                    // return (filled, empty bucket, fills)
+                    (filled, Bucket::new(self.order_def.clone()), taker_fills)
                 }
             } else { // a for b (sell)
                 let mut filled: Bucket = Bucket::new(self.b_pool.resource_def());
-                // fill colisions (market sell)
+                let mut taker_filled: Decimal = 0.into();
+                // fill colisions (market sell), best price first then oldest order first
                 {
                     let mut buy_pointer: NonFungibleKey = self.buy_head.clone();
 
@@ -185,7 +210,10 @@ blueprint! {
                         self.a_pool.put(tokens.take(to_fill));
                         data.amount -= to_fill;
                         data.filled += to_fill;
+                        data.total_filled += to_fill;
+                        taker_filled += to_fill;
                         filled.put(self.b_pool.take(to_fill * data.price));
+                        taker_fills.push(self.record_fill(&order_key, &buy_pointer, data.price, to_fill));
 
                         self.admin_badge.authorize(|auth| {
                             self.order_def.update_non_fungible_data(&buy_pointer, data, auth);
@@ -231,7 +259,10 @@ blueprint! {
 
                         next
                     };
-                    // return (filled, order)
+                    if !taker_fills.is_empty() {
+                        self.fill_history.insert(order_key.clone(), taker_fills.clone());
+                    }
+                    // return (filled, order, fills)
                     (   
                         filled,
                         self.admin_badge.authorize(|auth| {
@@ -239,16 +270,19 @@ blueprint! {
                                 Order {
                                     is_buy: is_buy,
                                     price: price,
+                                    size: taker_filled + to_order,
                                     amount: to_order,
                                     filled: 0.into(),
+                                    total_filled: taker_filled,
                                     next: next,
                                 },
                                 auth)
-                        })
+                        }),
+                        taker_fills
                     )
                 } else {
-                    // return (filled, empty bucket)
-                    (filled, Bucket::new(self.order_def.clone()))
+                    // return (filled, empty bucket, fills)
+                    (filled, Bucket::new(self.order_def.clone()), taker_fills)
                 }
             }
         }
@@ -319,21 +353,31 @@ blueprint! {
                 } else {
                     self.sell_head.clone()
                 };
-                let mut next: NonFungibleKey = self.order_def.get_non_fungible_data::<Order>(&curr).next;
 
-                // find order that points to order to cancel
-                while next != order_key {
-                    curr = next;
-                    next = self.order_def.get_non_fungible_data::<Order>(&curr).next;
-                }
+                if curr == order_key {
+                    // if order to cancel is first point head to next order
+                    if data.is_buy {
+                        self.buy_head = data.next.clone();
+                    } else {
+                        self.sell_head = data.next.clone();
+                    }
+                } else {
+                    let mut next: NonFungibleKey = self.order_def.get_non_fungible_data::<Order>(&curr).next;
 
-                // update order that points to order to cancel
-                let mut curr_data: Order = self.order_def.get_non_fungible_data::<Order>(&curr);
-                curr_data.next = data.next;
+                    // find order that points to order to cancel
+                    while next != order_key {
+                        curr = next;
+                        next = self.order_def.get_non_fungible_data::<Order>(&curr).next;
+                    }
 
-                self.admin_badge.authorize(|auth| {
-                    self.order_def.update_non_fungible_data(&curr, curr_data, auth);
-                });
+                    // update order that points to order to cancel
+                    let mut curr_data: Order = self.order_def.get_non_fungible_data::<Order>(&curr);
+                    curr_data.next = data.next;
+
+                    self.admin_badge.authorize(|auth| {
+                        self.order_def.update_non_fungible_data(&curr, curr_data, auth);
+                    });
+                }
                 
                 // return remaining tokens
                 if data.is_buy {
@@ -357,5 +401,41 @@ blueprint! {
 
             (remaining_tokens, filled_tokens)
         }
+
+        // gives fills of order, oldest first
+        // returns [fill]
+        pub fn get_fill_history(&self, order: BucketRef) -> Vec<Fill> {
+            assert!(
+                order.resource_def() == self.order_def,
+                "Order not for this pair"
+            );
+
+            let order_key: NonFungibleKey = order.get_non_fungible_key();
+            order.drop();
+
+            self.fill_history.get(&order_key).unwrap_or(Vec::new())
+        }
+
+        // adds a match to the fill history of the resting order
+        // returns the fill seen from the new order
+        fn record_fill(&mut self, taker_key: &NonFungibleKey, maker_key: &NonFungibleKey, price: Decimal, amount: Decimal) -> Fill {
+            let epoch: u64 = Context::current_epoch();
+
+            let mut history: Vec<Fill> = self.fill_history.get(maker_key).unwrap_or(Vec::new());
+            history.push(Fill {
+                counter_order: taker_key.clone(),
+                price: price,
+                amount: amount,
+                epoch: epoch,
+            });
+            self.fill_history.insert(maker_key.clone(), history);
+
+            Fill {
+                counter_order: maker_key.clone(),
+                price: price,
+                amount: amount,
+                epoch: epoch,
+            }
+        }
     }
 }
@@ -45,6 +45,9 @@ resim call-method $chain_book claim_tokens `#00000000000000000000000000000001,$o
 # claim tokens from part filled order
 resim call-method $chain_book claim_tokens `#0000000000000000000000000000000f,$order
 
+# fill history of part filled order
+resim call-method $chain_book get_fill_history `#0000000000000000000000000000000f,$order
+
 # part market part limit sell
 resim call-method $chain_book create_order 1,$bitcoin 35000
 
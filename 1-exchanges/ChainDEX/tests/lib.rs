@@ -0,0 +1,264 @@
+use radix_engine::ledger::*;
+use radix_engine::model::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+// mirrors of the blueprint types, fields in the same order
+#[derive(TypeId, Decode, Debug, PartialEq)]
+struct Fill {
+    counter_order: NonFungibleKey,
+    price: Decimal,
+    amount: Decimal,
+    epoch: u64,
+}
+
+#[derive(TypeId, Decode, Debug)]
+struct OrderImmutable {
+    is_buy: bool,
+    price: Decimal,
+    size: Decimal,
+}
+
+#[derive(TypeId, Decode, Debug)]
+struct OrderMutable {
+    amount: Decimal,
+    filled: Decimal,
+    total_filled: Decimal,
+    next: NonFungibleKey,
+}
+
+fn dec(value: &str) -> Decimal {
+    Decimal::from_str(value).unwrap()
+}
+
+fn fill(counter_order: u128, price: &str, amount: &str) -> Fill {
+    Fill {
+        counter_order: counter_order.into(),
+        price: dec(price),
+        amount: dec(amount),
+        epoch: 0,
+    }
+}
+
+fn order_arg(key: u128, order_def: Address) -> String {
+    format!("#{:032x},{}", key, order_def)
+}
+
+fn create_token<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+) -> Address {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .new_token_fixed(HashMap::new(), Decimal::from(1000000))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    receipt.resource_def(0).unwrap()
+}
+
+/// Sets up a BTC/USDT ChainBook, returns (key, account, chain_book, order_def, btc, usdt)
+fn setup<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+) -> (EcdsaPublicKey, Address, Address, Address, Address, Address) {
+    let key = executor.new_public_key();
+    let account = executor.new_account(key);
+    let package = executor.publish_package(include_code!("chain_dex")).unwrap();
+    let btc = create_token(executor, key, account);
+    let usdt = create_token(executor, key, account);
+
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_function(
+                    package,
+                    "ChainBook",
+                    "instantiate_chain_book",
+                    vec!["BTC/USDT".to_owned(), btc.to_string(), usdt.to_string()],
+                    None,
+                )
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+    // the admin badge is created first, then the order resource
+    (key, account, receipt.component(0).unwrap(), receipt.resource_def(1).unwrap(), btc, usdt)
+}
+
+/// Places an order, returns the fills of the new order
+fn create_order<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    chain_book: Address,
+    tokens: (&str, Address),
+    price: &str,
+) -> Vec<Fill> {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(
+                    chain_book,
+                    "create_order",
+                    vec![format!("{},{}", tokens.0, tokens.1), price.to_owned()],
+                    Some(account),
+                )
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+    let (_, _, fills): (Bucket, Bucket, Vec<Fill>) =
+        scrypto_decode(&receipt.outputs[receipt.outputs.len() - 2].raw).unwrap();
+    fills
+}
+
+fn get_fill_history<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    chain_book: Address,
+    order: String,
+) -> Vec<Fill> {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(chain_book, "get_fill_history", vec![order], Some(account))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+    scrypto_decode(&receipt.outputs[receipt.outputs.len() - 2].raw).unwrap()
+}
+
+fn get_order<'a, L: SubstateStore>(
+    ledger: &'a L,
+    order_def: Address,
+    key: u128,
+) -> Option<(OrderImmutable, OrderMutable)> {
+    ledger.get_non_fungible(order_def, &key.into()).map(|order| {
+        (
+            scrypto_decode(&order.immutable_data()).unwrap(),
+            scrypto_decode(&order.mutable_data()).unwrap(),
+        )
+    })
+}
+
+#[test]
+fn test_fills_are_returned_and_kept_for_minted_orders() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (key, account, chain_book, order_def, btc, usdt) = setup(&mut executor);
+
+    // resting sells #1 at 40000 and #2 at 20000
+    assert!(create_order(&mut executor, key, account, chain_book, ("1", btc), "40000").is_empty());
+    assert!(create_order(&mut executor, key, account, chain_book, ("1", btc), "20000").is_empty());
+
+    // #3 buys at the best price first and completes instantly
+    let fills = create_order(&mut executor, key, account, chain_book, ("50000", usdt), "40000");
+    assert_eq!(fills, vec![fill(2, "20000", "1"), fill(1, "40000", "0.75")]);
+    assert!(get_order(executor.ledger(), order_def, 3).is_none());
+
+    // the resting orders keep the match in their history
+    let history = get_fill_history(&mut executor, key, account, chain_book, order_arg(2, order_def));
+    assert_eq!(history, vec![fill(3, "20000", "1")]);
+    let history = get_fill_history(&mut executor, key, account, chain_book, order_arg(1, order_def));
+    assert_eq!(history, vec![fill(3, "40000", "0.75")]);
+
+    // #4 fills the rest of #1 then rests, its history starts with the fill
+    let fills = create_order(&mut executor, key, account, chain_book, ("20000", usdt), "40000");
+    assert_eq!(fills, vec![fill(1, "40000", "0.25")]);
+    let history = get_fill_history(&mut executor, key, account, chain_book, order_arg(4, order_def));
+    assert_eq!(history, fills);
+
+    let (data, state) = get_order(executor.ledger(), order_def, 4).unwrap();
+    assert!(data.is_buy);
+    assert_eq!(data.price, dec("40000"));
+    assert_eq!(data.size, dec("0.5"));
+    assert_eq!(state.amount, dec("0.25"));
+    assert_eq!(state.filled, Decimal::zero());
+    assert_eq!(state.total_filled, dec("0.25"));
+
+    let (data, state) = get_order(executor.ledger(), order_def, 1).unwrap();
+    assert_eq!(data.size, dec("1"));
+    assert_eq!(state.amount, Decimal::zero());
+    assert_eq!(state.filled, dec("1"));
+    assert_eq!(state.total_filled, dec("1"));
+
+    // #5 sells into #4 at its price, then rests with the remaining tokens
+    let fills = create_order(&mut executor, key, account, chain_book, ("0.5", btc), "30000");
+    assert_eq!(fills, vec![fill(4, "40000", "0.25")]);
+
+    let (_, state) = get_order(executor.ledger(), order_def, 4).unwrap();
+    assert_eq!(state.amount, Decimal::zero());
+    assert_eq!(state.filled, dec("0.25"));
+    assert_eq!(state.total_filled, dec("0.5"));
+
+    let (data, state) = get_order(executor.ledger(), order_def, 5).unwrap();
+    assert!(!data.is_buy);
+    assert_eq!(data.size, dec("0.5"));
+    assert_eq!(state.amount, dec("0.25"));
+    assert_eq!(state.total_filled, dec("0.25"));
+
+    let history = get_fill_history(&mut executor, key, account, chain_book, order_arg(4, order_def));
+    assert_eq!(history, vec![fill(1, "40000", "0.25"), fill(5, "40000", "0.25")]);
+}
+
+#[test]
+fn test_same_price_orders_fill_oldest_first() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (key, account, chain_book, _, btc, usdt) = setup(&mut executor);
+
+    create_order(&mut executor, key, account, chain_book, ("1", btc), "40000");
+    create_order(&mut executor, key, account, chain_book, ("1", btc), "40000");
+    create_order(&mut executor, key, account, chain_book, ("1", btc), "39000");
+
+    // a buy under the asks rests without filling
+    assert!(create_order(&mut executor, key, account, chain_book, ("10000", usdt), "38000").is_empty());
+
+    let fills = create_order(&mut executor, key, account, chain_book, ("99000", usdt), "40000");
+    assert_eq!(
+        fills,
+        vec![fill(3, "39000", "1"), fill(1, "40000", "1"), fill(2, "40000", "0.5")]
+    );
+}
+
+#[test]
+fn test_cancel_head_order() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (key, account, chain_book, order_def, btc, usdt) = setup(&mut executor);
+
+    // #2 is the best sell and heads the chain
+    create_order(&mut executor, key, account, chain_book, ("1", btc), "40000");
+    create_order(&mut executor, key, account, chain_book, ("1", btc), "30000");
+
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(&executor)
+                .call_method(chain_book, "cancel_order", vec![order_arg(2, order_def)], Some(account))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+
+    // the head moved on to #1, which now fills at its own price
+    let fills = create_order(&mut executor, key, account, chain_book, ("40000", usdt), "40000");
+    assert_eq!(fills, vec![fill(1, "40000", "1")]);
+}
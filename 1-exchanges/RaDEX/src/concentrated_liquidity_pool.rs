@@ -0,0 +1,1247 @@
+use scrypto::prelude::*;
+use crate::utils::*;
+
+/// The lowest tick that a position may start at. The square root of the price at this tick is `1.0001^(-100000)`.
+pub const MIN_TICK: i32 = -200_000;
+
+/// The highest tick that a position may end at. The square root of the price at this tick is `1.0001^(100000)`.
+pub const MAX_TICK: i32 = 200_000;
+
+/// The data of the NFTs given to the liquidity providers of a concentrated liquidity pool. Each NFT represents a
+/// single deposit of liquidity which is only active while the price of the pool is within its tick range.
+#[derive(NonFungibleData, Describe, Encode, Decode, TypeId)]
+pub struct ConcentratedPosition {
+    /// The tick at which the price range of this position starts (inclusive).
+    pub lower_tick: i32,
+
+    /// The tick at which the price range of this position ends (exclusive).
+    pub upper_tick: i32,
+
+    /// The amount of liquidity that this position provides to the pool while the price is within its range.
+    pub liquidity: Decimal,
+
+    /// The fees earned per unit of liquidity within the range of this position at the time that it was created. This
+    /// is used to calculate the fees owed to the position when it is removed.
+    pub fee_growth_inside_last: HashMap<ResourceAddress, Decimal>,
+}
+
+/// The information that a concentrated liquidity pool keeps about each of the initialized ticks. A tick is initialized
+/// when at least one position starts or ends at it.
+#[derive(TypeId, Encode, Decode, Describe, Debug, Clone)]
+pub struct TickInfo {
+    /// The amount of liquidity that becomes active when the price crosses this tick going up. This is negated when the
+    /// price crosses the tick going down.
+    pub liquidity_net: Decimal,
+
+    /// The total amount of liquidity of all of the positions which start or end at this tick. When this reaches zero
+    /// the tick is no longer needed and is removed.
+    pub liquidity_gross: Decimal,
+
+    /// The fees earned per unit of liquidity on the other side of this tick (relative to the current price) for each
+    /// of the two tokens.
+    pub fee_growth_outside: HashMap<ResourceAddress, Decimal>,
+}
+
+/// The outcome of simulating a swap against the current state of a concentrated liquidity pool.
+struct SwapResult {
+    /// The amount of input tokens consumed by the swap including the pool fee.
+    amount_in: Decimal,
+
+    /// The amount of output tokens given out by the swap.
+    amount_out: Decimal,
+
+    /// The square root of the price after the swap.
+    sqrt_price: Decimal,
+
+    /// The tick of the price after the swap.
+    current_tick: i32,
+
+    /// The active liquidity after the swap.
+    liquidity: Decimal,
+
+    /// The fees earned per unit of liquidity in the input token after the swap.
+    fee_growth_global: Decimal,
+
+    /// The ticks crossed during the swap along with the fees earned per unit of liquidity in the input token at the
+    /// time of crossing.
+    crossed_ticks: Vec<(i32, Decimal)>,
+}
+
+/// Calculates the square root of the price at the given tick.
+///
+/// The price at a tick `i` is `1.0001^i` which makes the square root of the price `1.0001^(i/2)`. This is calculated
+/// through exponentiation by squaring of the square root of `1.0001`.
+///
+/// # Arguments:
+///
+/// * `tick` (i32) - The tick to calculate the square root of the price at.
+///
+/// # Returns:
+///
+/// `Decimal` - The square root of the price at the given tick.
+pub fn sqrt_price_at_tick(tick: i32) -> Decimal {
+    assert!(
+        (tick >= MIN_TICK) & (tick <= MAX_TICK),
+        "[Tick Math]: The tick must be between {} and {}.", MIN_TICK, MAX_TICK
+    );
+
+    let mut result: Decimal = Decimal::one();
+    let mut base: Decimal = dec!("1.000049998750062496");
+    let mut exponent: u32 = tick.unsigned_abs();
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result * base;
+        }
+        base = base * base;
+        exponent >>= 1;
+    }
+
+    return if tick < 0 { Decimal::one() / result } else { result };
+}
+
+/// Finds the tick that the given square root of a price falls in.
+///
+/// This function performs a binary search over the range of ticks for the largest tick whose square root of the
+/// price is less than or equal to the given one.
+///
+/// # Arguments:
+///
+/// * `sqrt_price` (Decimal) - The square root of the price to find the tick of.
+///
+/// # Returns:
+///
+/// `i32` - The tick that the given price falls in.
+pub fn tick_at_sqrt_price(sqrt_price: Decimal) -> i32 {
+    let mut low: i32 = MIN_TICK;
+    let mut high: i32 = MAX_TICK;
+    while low < high {
+        let middle: i32 = low + (high - low + 1) / 2;
+        if sqrt_price_at_tick(middle) <= sqrt_price {
+            low = middle;
+        } else {
+            high = middle - 1;
+        }
+    }
+    return low;
+}
+
+/// Calculates the largest amount of liquidity that the given amounts of the two tokens can provide over a range.
+///
+/// # Arguments:
+///
+/// * `sqrt_price` (Decimal) - The square root of the current price of the pool.
+/// * `sqrt_price_lower` (Decimal) - The square root of the price at the start of the range.
+/// * `sqrt_price_upper` (Decimal) - The square root of the price at the end of the range.
+/// * `amount0` (Decimal) - The amount of the first token.
+/// * `amount1` (Decimal) - The amount of the second token.
+///
+/// # Returns:
+///
+/// `Decimal` - The amount of liquidity.
+fn liquidity_for_amounts(
+    sqrt_price: Decimal,
+    sqrt_price_lower: Decimal,
+    sqrt_price_upper: Decimal,
+    amount0: Decimal,
+    amount1: Decimal
+) -> Decimal {
+    let one: Decimal = Decimal::one();
+    return if sqrt_price <= sqrt_price_lower {
+        // The price is below the range, so the position is made entirely out of the first token.
+        amount0 / (one / sqrt_price_lower - one / sqrt_price_upper)
+    } else if sqrt_price >= sqrt_price_upper {
+        // The price is above the range, so the position is made entirely out of the second token.
+        amount1 / (sqrt_price_upper - sqrt_price_lower)
+    } else {
+        let liquidity0: Decimal = amount0 / (one / sqrt_price - one / sqrt_price_upper);
+        let liquidity1: Decimal = amount1 / (sqrt_price - sqrt_price_lower);
+        if liquidity0 < liquidity1 { liquidity0 } else { liquidity1 }
+    };
+}
+
+/// Calculates the amounts of the two tokens that the given amount of liquidity is worth over a range.
+///
+/// # Arguments:
+///
+/// * `sqrt_price` (Decimal) - The square root of the current price of the pool.
+/// * `sqrt_price_lower` (Decimal) - The square root of the price at the start of the range.
+/// * `sqrt_price_upper` (Decimal) - The square root of the price at the end of the range.
+/// * `liquidity` (Decimal) - The amount of liquidity.
+///
+/// # Returns:
+///
+/// * `Decimal` - The amount of the first token.
+/// * `Decimal` - The amount of the second token.
+fn amounts_for_liquidity(
+    sqrt_price: Decimal,
+    sqrt_price_lower: Decimal,
+    sqrt_price_upper: Decimal,
+    liquidity: Decimal
+) -> (Decimal, Decimal) {
+    let one: Decimal = Decimal::one();
+    return if sqrt_price <= sqrt_price_lower {
+        (liquidity * (one / sqrt_price_lower - one / sqrt_price_upper), Decimal::zero())
+    } else if sqrt_price >= sqrt_price_upper {
+        (Decimal::zero(), liquidity * (sqrt_price_upper - sqrt_price_lower))
+    } else {
+        (liquidity * (one / sqrt_price - one / sqrt_price_upper), liquidity * (sqrt_price - sqrt_price_lower))
+    };
+}
+
+blueprint!{
+    /// This is a struct used to define a concentrated liquidity pool in the RaDEX decentralized exchange. Unlike the
+    /// constant product `LiquidityPool` which spreads its liquidity over every possible price, each deposit into a
+    /// concentrated liquidity pool only provides liquidity over a price range chosen by the liquidity provider. The
+    /// price ranges are defined in terms of ticks where the price at tick `i` is `1.0001^i` (in terms of the second
+    /// token per unit of the first token). Each deposit is represented by an NFT position instead of fungible tracking
+    /// tokens, since no two deposits are alike.
+    ///
+    /// Within the range of two neighbouring initialized ticks the pool behaves like a constant product pool with the
+    /// liquidity of all of the positions that cover that range. When a swap moves the price across an initialized tick
+    /// the liquidity of the positions starting or ending at that tick is added or removed.
+    struct ConcentratedLiquidityPool {
+        /// These are the vaults where the reserves of the two tokens are stored. As with the `LiquidityPool`, this
+        /// hashmap will always have exactly two vaults for the two assets being traded against one another.
+        vaults: HashMap<ResourceAddress, Vault>,
+
+        /// The resource addresses of the two tokens sorted through the `sort_addresses` function. The price of the
+        /// pool is the price of the first token in terms of the second token.
+        token_addresses: (ResourceAddress, ResourceAddress),
+
+        /// The resource address of the NFTs given to the liquidity providers to represent their positions.
+        position_address: ResourceAddress,
+
+        /// This badge has the authority to mint and burn the position NFTs.
+        position_admin_badge: Vault,
+
+        /// This is a decimal value between 0 and 100 which defines the amount of fees paid to the liquidity providers
+        /// whose positions are active when a swap is made through this liquidity pool.
+        fee_to_pool: Decimal,
+
+        /// The square root of the current price of the pool.
+        sqrt_price: Decimal,
+
+        /// The tick that the current price falls in. This is the largest tick whose price is less than or equal to
+        /// the current price.
+        current_tick: i32,
+
+        /// The sum of the liquidity of all of the positions whose range covers the current price.
+        liquidity: Decimal,
+
+        /// The initialized ticks of the pool sorted by tick, which allows for the next tick in either direction to be
+        /// found quickly during swaps.
+        ticks: BTreeMap<i32, TickInfo>,
+
+        /// The total fees earned per unit of liquidity since the creation of the pool for each of the two tokens.
+        fee_growth_globals: HashMap<ResourceAddress, Decimal>,
+
+        /// The number of position NFTs minted so far. This is used as the ID of the next position NFT.
+        positions_minted: u64,
+    }
+
+    impl ConcentratedLiquidityPool {
+        /// Creates a new concentrated liquidity pool of the two token types passed to this function.
+        ///
+        /// The initial price of the pool is set to the ratio of the two buckets passed and all of the tokens passed are
+        /// deposited into the pool as a position covering the full range of ticks. Since the full range is not quite
+        /// infinite, a tiny amount of one of the tokens may be more than what the position needs; this amount is left
+        /// in the pool.
+        ///
+        /// This function does a number of checks before a Liquidity Pool is created, these checks are:
+        ///
+        /// * **Check 1:** Checks that `token1` and `token2` are not of the same type.
+        /// * **Check 2:** Checks that both `token1` and `token2` are fungible tokens.
+        /// * **Check 3:** Checks that neither of the buckets are empty.
+        /// * **Check 4:** Checks that the fee is between 0 and 100.
+        /// * **Check 5:** Checks that the ratio of the two buckets is within the range of prices that ticks cover.
+        ///
+        /// # Arguments:
+        ///
+        /// * `token1` (Bucket) - A bucket containing the amount of the first token used to initialize the pool.
+        /// * `token2` (Bucket) - A bucket containing the amount of the second token used to initialize the pool.
+        /// * `fee_to_pool` (Decimal) - A decimal value of the fee imposed on all swaps from this liquidity pool. This
+        /// should be a value between 0 and 100.
+        ///
+        /// # Returns:
+        ///
+        /// * `ComponentAddress` - The address of the newly created concentrated liquidity pool.
+        /// * `Bucket` - A bucket containing the full range position NFT issued to the creator of the pool.
+        pub fn new(
+            token1: Bucket,
+            token2: Bucket,
+            fee_to_pool: Decimal
+        ) -> (ComponentAddress, Bucket) {
+            // Performing the checks to see if this liquidity pool may be created or not.
+            assert_ne!(
+                token1.resource_address(), token2.resource_address(),
+                "[CL Pool Creation]: Liquidity pools may only be created between two different tokens."
+            );
+
+            assert_ne!(
+                borrow_resource_manager!(token1.resource_address()).resource_type(), ResourceType::NonFungible,
+                "[CL Pool Creation]: Both assets must be fungible."
+            );
+            assert_ne!(
+                borrow_resource_manager!(token2.resource_address()).resource_type(), ResourceType::NonFungible,
+                "[CL Pool Creation]: Both assets must be fungible."
+            );
+
+            assert!(
+                !token1.is_empty() & !token2.is_empty(),
+                "[CL Pool Creation]: Can't create a pool from an empty bucket."
+            );
+
+            assert!(
+                (fee_to_pool >= Decimal::zero()) & (fee_to_pool <= dec!("100")),
+                "[CL Pool Creation]: Fee must be between 0 and 100"
+            );
+
+            // Sorting the buckets and finding the initial price of the pool from their ratio.
+            let (bucket1, bucket2): (Bucket, Bucket) = sort_buckets(token1, token2);
+            let addresses: (ResourceAddress, ResourceAddress) = (bucket1.resource_address(), bucket2.resource_address());
+            let sqrt_price: Decimal = sqrt(bucket2.amount() / bucket1.amount());
+            assert!(
+                (sqrt_price > sqrt_price_at_tick(MIN_TICK)) & (sqrt_price < sqrt_price_at_tick(MAX_TICK)),
+                "[CL Pool Creation]: The ratio of the two tokens is outside of the range of supported prices."
+            );
+            let current_tick: i32 = tick_at_sqrt_price(sqrt_price);
+
+            let lp_id: String = format!("{}-{}", addresses.0, addresses.1);
+            let pair_name: String = address_pair_symbol(addresses.0, addresses.1);
+            info!(
+                "[CL Pool Creation]: Creating new concentrated pool between tokens: {}, of name: {}, Ratio: {}:{}",
+                lp_id, pair_name, bucket1.amount(), bucket2.amount()
+            );
+
+            // The creator's position covers the full range of ticks.
+            let liquidity: Decimal = liquidity_for_amounts(
+                sqrt_price,
+                sqrt_price_at_tick(MIN_TICK),
+                sqrt_price_at_tick(MAX_TICK),
+                bucket1.amount(),
+                bucket2.amount()
+            );
+
+            // Creating the admin badge of the liquidity pool which will be given the authority to mint and burn the
+            // position NFTs issued to the liquidity providers.
+            let position_admin_badge: Bucket = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata("name", "Position Admin Badge")
+                .metadata("symbol", "PAB")
+                .metadata("description", "This is an admin badge that has the authority to mint and burn position NFTs")
+                .metadata("lp_id", format!("{}", lp_id))
+                .initial_supply(1);
+
+            let position_address: ResourceAddress = ResourceBuilder::new_non_fungible()
+                .metadata("name", format!("{} Concentrated Liquidity Position", pair_name))
+                .metadata("symbol", "CLP")
+                .metadata("description", "An NFT representing liquidity provided to a concentrated liquidity pool over a price range")
+                .metadata("lp_id", format!("{}", lp_id))
+                .mintable(rule!(require(position_admin_badge.resource_address())), LOCKED)
+                .burnable(rule!(require(position_admin_badge.resource_address())), LOCKED)
+                .no_initial_supply();
+
+            // Since no fees have been earned yet, all of the fee growth values start at zero.
+            let mut zero_fee_growth: HashMap<ResourceAddress, Decimal> = HashMap::new();
+            zero_fee_growth.insert(addresses.0, Decimal::zero());
+            zero_fee_growth.insert(addresses.1, Decimal::zero());
+
+            let position: Bucket = position_admin_badge.authorize(|| {
+                borrow_resource_manager!(position_address).mint_non_fungible(
+                    &NonFungibleId::from_u64(0),
+                    ConcentratedPosition {
+                        lower_tick: MIN_TICK,
+                        upper_tick: MAX_TICK,
+                        liquidity: liquidity,
+                        fee_growth_inside_last: zero_fee_growth.clone(),
+                    }
+                )
+            });
+
+            let mut ticks: BTreeMap<i32, TickInfo> = BTreeMap::new();
+            ticks.insert(MIN_TICK, TickInfo {
+                liquidity_net: liquidity,
+                liquidity_gross: liquidity,
+                fee_growth_outside: zero_fee_growth.clone()
+            });
+            ticks.insert(MAX_TICK, TickInfo {
+                liquidity_net: -liquidity,
+                liquidity_gross: liquidity,
+                fee_growth_outside: zero_fee_growth.clone()
+            });
+
+            let mut vaults: HashMap<ResourceAddress, Vault> = HashMap::new();
+            vaults.insert(bucket1.resource_address(), Vault::with_bucket(bucket1));
+            vaults.insert(bucket2.resource_address(), Vault::with_bucket(bucket2));
+
+            // Creating the liquidity pool component and instantiating it
+            let liquidity_pool: ComponentAddress = Self {
+                vaults: vaults,
+                token_addresses: addresses,
+                position_address: position_address,
+                position_admin_badge: Vault::with_bucket(position_admin_badge),
+                fee_to_pool: fee_to_pool,
+                sqrt_price: sqrt_price,
+                current_tick: current_tick,
+                liquidity: liquidity,
+                ticks: ticks,
+                fee_growth_globals: zero_fee_growth,
+                positions_minted: 1,
+            }
+            .instantiate()
+            .globalize();
+
+            return (liquidity_pool, position);
+        }
+
+        /// Checks if the given address belongs to this pool or not.
+        ///
+        /// # Arguments:
+        ///
+        /// * `address` (ResourceAddress) - The address of the resource that we wish to check if it belongs to the pool.
+        ///
+        /// # Returns:
+        ///
+        /// * `bool` - A boolean of whether the address belongs to this pool or not.
+        pub fn belongs_to_pool(
+            &self,
+            address: ResourceAddress
+        ) -> bool {
+            return self.vaults.contains_key(&address);
+        }
+
+        /// Asserts that the given address belongs to the pool.
+        ///
+        /// # Arguments:
+        ///
+        /// * `address` (ResourceAddress) - The address of the resource that we wish to check if it belongs to the pool.
+        /// * `label` (String) - The label of the method that called this assert method.
+        pub fn assert_belongs_to_pool(
+            &self,
+            address: ResourceAddress,
+            label: String
+        ) {
+            assert!(
+                self.belongs_to_pool(address),
+                "[{}]: The provided resource address does not belong to the pool.",
+                label
+            );
+        }
+
+        /// Gets the resource addresses of the tokens in this liquidity pool and returns them as a `Vec<ResourceAddress>`.
+        ///
+        /// # Returns:
+        ///
+        /// `Vec<ResourceAddress>` - A vector of the resource addresses of the tokens in this liquidity pool.
+        pub fn addresses(&self) -> Vec<ResourceAddress> {
+            return vec![self.token_addresses.0, self.token_addresses.1];
+        }
+
+        /// Gets the address of the other resource if the passed resource address belongs to the pool.
+        ///
+        /// # Arguments
+        ///
+        /// * `resource_address` (ResourceAddress) - The resource address for a token from the pool.
+        ///
+        /// # Returns:
+        ///
+        /// * `ResourceAddress` - The address of the other token in this pool.
+        pub fn other_resource_address(
+            &self,
+            resource_address: ResourceAddress
+        ) -> ResourceAddress {
+            // Checking if the passed resource address belongs to this pool.
+            self.assert_belongs_to_pool(resource_address, String::from("CL Other Resource Address"));
+
+            return if self.token_addresses.0 == resource_address {
+                self.token_addresses.1
+            } else {
+                self.token_addresses.0
+            };
+        }
+
+        /// Calculates the spot price of the given token in terms of the other token in the pool.
+        ///
+        /// # Arguments:
+        ///
+        /// * `resource_address` (ResourceAddress) - The resource address of the token to get the price of.
+        ///
+        /// # Returns:
+        ///
+        /// * `Decimal` - The amount of the other token that one unit of the given token is worth at the current price.
+        pub fn spot_price(
+            &self,
+            resource_address: ResourceAddress
+        ) -> Decimal {
+            // Checking if the passed resource address belongs to this pool.
+            self.assert_belongs_to_pool(resource_address, String::from("CL Spot Price"));
+
+            let price: Decimal = self.sqrt_price * self.sqrt_price;
+            return if resource_address == self.token_addresses.0 { price } else { Decimal::one() / price };
+        }
+
+        /// Calculates the largest amount of the given token that can be taken out of the pool through swaps.
+        ///
+        /// Unlike a constant product pool, not all of the tokens in the vaults of a concentrated liquidity pool may be
+        /// swapped out, only the tokens that belong to the active ranges along the way. This method walks across every
+        /// initialized tick in the direction of the swap and sums up the output of each range.
+        ///
+        /// # Arguments:
+        ///
+        /// * `resource_address` (ResourceAddress) - The resource address of the output token.
+        ///
+        /// # Returns:
+        ///
+        /// * `Decimal` - The amount of the given token that can be swapped out of the pool.
+        pub fn reserve(
+            &self,
+            resource_address: ResourceAddress
+        ) -> Decimal {
+            // Checking if the passed resource address belongs to this pool.
+            self.assert_belongs_to_pool(resource_address, String::from("CL Reserve"));
+
+            // Taking out the second token means selling the first token which pushes the price down.
+            let zero_for_one: bool = resource_address == self.token_addresses.1;
+            let one: Decimal = Decimal::one();
+
+            let mut sqrt_price: Decimal = self.sqrt_price;
+            let mut current_tick: i32 = self.current_tick;
+            let mut liquidity: Decimal = self.liquidity;
+            let mut available: Decimal = Decimal::zero();
+            loop {
+                let next_tick: Option<i32> = self.next_initialized_tick(current_tick, zero_for_one);
+                if next_tick.is_none() {
+                    break;
+                }
+                let tick: i32 = next_tick.unwrap();
+                let target_sqrt_price: Decimal = sqrt_price_at_tick(tick);
+
+                available = available + if zero_for_one {
+                    liquidity * (sqrt_price - target_sqrt_price)
+                } else {
+                    liquidity * (one / sqrt_price - one / target_sqrt_price)
+                };
+
+                sqrt_price = target_sqrt_price;
+                if zero_for_one {
+                    liquidity = liquidity - self.ticks[&tick].liquidity_net;
+                    current_tick = tick - 1;
+                } else {
+                    liquidity = liquidity + self.ticks[&tick].liquidity_net;
+                    current_tick = tick;
+                }
+            }
+            return available;
+        }
+
+        /// Calculates the amount of output that can be given for for a given amount of input.
+        ///
+        /// This method performs a number of checks before the calculation is done:
+        ///
+        /// * **Check 1:** Checks that the provided resource address belongs to this liquidity pool.
+        ///
+        /// # Arguments:
+        ///
+        /// * `input_resource_address` (ResourceAddress) - The resource address of the input token.
+        /// * `input_amount` (Decimal) - The amount of input tokens to calculate the output for.
+        ///
+        /// # Returns:
+        ///
+        /// * `Decimal` - The output amount for the given input.
+        pub fn calculate_output_amount(
+            &self,
+            input_resource_address: ResourceAddress,
+            input_amount: Decimal
+        ) -> Decimal {
+            // Checking if the passed resource address belongs to this pool.
+            self.assert_belongs_to_pool(input_resource_address, String::from("CL Calculate Output"));
+
+            let swap_result: Option<SwapResult> = self.compute_swap(input_resource_address, input_amount, true);
+            assert!(swap_result.is_some(), "[CL Calculate Output]: Not enough liquidity in the pool for this swap.");
+            return swap_result.unwrap().amount_out;
+        }
+
+        /// Calculates the amount of output that can be given for a given amount of input, if the pool has enough 
+        /// liquidity for it.
+        ///
+        /// Unlike `calculate_output_amount`, this method does not fail when the active ranges of the pool run out 
+        /// before the whole input is consumed. This allows the router to skip pools which are too thin for a route.
+        ///
+        /// This method performs a number of checks before the calculation is done:
+        ///
+        /// * **Check 1:** Checks that the provided resource address belongs to this liquidity pool.
+        ///
+        /// # Arguments:
+        ///
+        /// * `input_resource_address` (ResourceAddress) - The resource address of the input token.
+        /// * `input_amount` (Decimal) - The amount of input tokens to calculate the output for.
+        ///
+        /// # Returns:
+        ///
+        /// * `Option<Decimal>` - The output amount for the given input, `None` if the pool does not have enough
+        /// liquidity for the swap.
+        pub fn try_calculate_output_amount(
+            &self,
+            input_resource_address: ResourceAddress,
+            input_amount: Decimal
+        ) -> Option<Decimal> {
+            // Checking if the passed resource address belongs to this pool.
+            self.assert_belongs_to_pool(input_resource_address, String::from("CL Calculate Output"));
+
+            return self.compute_swap(input_resource_address, input_amount, true)
+                .map(|swap_result| swap_result.amount_out);
+        }
+
+        /// Calculates the amount of input required to receive the specified amount of output tokens.
+        ///
+        /// This method performs a number of checks before the calculation is done:
+        ///
+        /// * **Check 1:** Checks that the provided resource address belongs to this liquidity pool.
+        ///
+        /// # Arguments:
+        ///
+        /// * `output_resource_address` (ResourceAddress) - The resource address of the output token.
+        /// * `output_amount` (Decimal) - The amount of output tokens to calculate the input for.
+        ///
+        /// # Returns:
+        ///
+        /// * `Decimal` - The input amount for the given output.
+        pub fn calculate_input_amount(
+            &self,
+            output_resource_address: ResourceAddress,
+            output_amount: Decimal
+        ) -> Decimal {
+            // Checking if the passed resource address belongs to this pool.
+            self.assert_belongs_to_pool(output_resource_address, String::from("CL Calculate Input"));
+
+            let input_resource_address: ResourceAddress = self.other_resource_address(output_resource_address);
+            let swap_result: Option<SwapResult> = self.compute_swap(input_resource_address, output_amount, false);
+            assert!(swap_result.is_some(), "[CL Calculate Input]: Not enough liquidity in the pool for this swap.");
+            return swap_result.unwrap().amount_in;
+        }
+
+        /// Calculates the amount of input required to receive the specified amount of output tokens, if the pool has
+        /// enough liquidity for it.
+        ///
+        /// Unlike `calculate_input_amount`, this method does not fail when the active ranges of the pool run out 
+        /// before the whole output is given. This allows the router to skip pools which are too thin for a route.
+        ///
+        /// This method performs a number of checks before the calculation is done:
+        ///
+        /// * **Check 1:** Checks that the provided resource address belongs to this liquidity pool.
+        ///
+        /// # Arguments:
+        ///
+        /// * `output_resource_address` (ResourceAddress) - The resource address of the output token.
+        /// * `output_amount` (Decimal) - The amount of output tokens to calculate the input for.
+        ///
+        /// # Returns:
+        ///
+        /// * `Option<Decimal>` - The input amount for the given output, `None` if the pool does not have enough 
+        /// liquidity for the swap.
+        pub fn try_calculate_input_amount(
+            &self,
+            output_resource_address: ResourceAddress,
+            output_amount: Decimal
+        ) -> Option<Decimal> {
+            // Checking if the passed resource address belongs to this pool.
+            self.assert_belongs_to_pool(output_resource_address, String::from("CL Calculate Input"));
+
+            let input_resource_address: ResourceAddress = self.other_resource_address(output_resource_address);
+            return self.compute_swap(input_resource_address, output_amount, false)
+                .map(|swap_result| swap_result.amount_in);
+        }
+
+        /// Adds liquidity over the given tick range to this liquidity pool in exchange for a position NFT.
+        ///
+        /// This method calculates the largest amount of liquidity that the two buckets can provide over the given tick
+        /// range at the current price of the pool, deposits the amount of tokens that this liquidity requires, and
+        /// mints a position NFT for the liquidity provider. If the current price is outside of the range then only one
+        /// of the two tokens is needed and the other bucket may be empty.
+        ///
+        /// This method performs a number of checks before liquidity is added to the pool:
+        ///
+        /// * **Check 1:** Checks that the buckets passed are of tokens that belong to this liquidity pool.
+        /// * **Check 2:** Checks that the lower tick is below the upper tick and that both are within the valid range.
+        /// * **Check 3:** Checks that the buckets passed provide a non-zero amount of liquidity.
+        ///
+        /// # Arguments:
+        ///
+        /// * `token1` (Bucket) - A bucket containing the amount of the first token to add to the pool.
+        /// * `token2` (Bucket) - A bucket containing the amount of the second token to add to the pool.
+        /// * `lower_tick` (i32) - The tick at which the price range of the position starts.
+        /// * `upper_tick` (i32) - The tick at which the price range of the position ends.
+        ///
+        /// # Returns:
+        ///
+        /// * `Bucket` - A bucket of the remaining tokens of the first token type (sorted).
+        /// * `Bucket` - A bucket of the remaining tokens of the second token type (sorted).
+        /// * `Bucket` - A bucket of the position NFT issued to the liquidity provider.
+        pub fn add_liquidity(
+            &mut self,
+            token1: Bucket,
+            token2: Bucket,
+            lower_tick: i32,
+            upper_tick: i32
+        ) -> (Bucket, Bucket, Bucket) {
+            // Checking if the tokens belong to this liquidity pool and that the range is valid.
+            self.assert_belongs_to_pool(token1.resource_address(), String::from("CL Add Liquidity"));
+            self.assert_belongs_to_pool(token2.resource_address(), String::from("CL Add Liquidity"));
+            assert_ne!(
+                token1.resource_address(), token2.resource_address(),
+                "[CL Add Liquidity]: The two buckets must be of the two different tokens of the pool."
+            );
+            assert!(lower_tick < upper_tick, "[CL Add Liquidity]: The lower tick must be below the upper tick.");
+            assert!(
+                (lower_tick >= MIN_TICK) & (upper_tick <= MAX_TICK),
+                "[CL Add Liquidity]: The ticks must be between {} and {}.", MIN_TICK, MAX_TICK
+            );
+
+            // Calculating the amount of liquidity that the two buckets can provide over the given range.
+            let (mut bucket1, mut bucket2): (Bucket, Bucket) = sort_buckets(token1, token2);
+            let sqrt_price_lower: Decimal = sqrt_price_at_tick(lower_tick);
+            let sqrt_price_upper: Decimal = sqrt_price_at_tick(upper_tick);
+            let liquidity: Decimal = liquidity_for_amounts(
+                self.sqrt_price, sqrt_price_lower, sqrt_price_upper, bucket1.amount(), bucket2.amount()
+            );
+            assert!(liquidity > Decimal::zero(), "[CL Add Liquidity]: The buckets do not provide any liquidity.");
+            info!(
+                "[CL Add Liquidity]: Adding {} liquidity over the ticks [{}, {})", liquidity, lower_tick, upper_tick
+            );
+
+            // Updating the ticks of the position before finding the fees earned within its range so far, since a tick
+            // which was not initialized before needs to be initialized first.
+            self.update_position(lower_tick, upper_tick, liquidity);
+            let mut fee_growth_inside: HashMap<ResourceAddress, Decimal> = HashMap::new();
+            for address in self.addresses() {
+                fee_growth_inside.insert(address, self.fee_growth_inside(lower_tick, upper_tick, address));
+            }
+
+            // Depositing the amounts required by the liquidity. These amounts are capped by the amounts in the buckets
+            // to protect against rounding in the last decimal place.
+            let (amount1, amount2): (Decimal, Decimal) = amounts_for_liquidity(
+                self.sqrt_price, sqrt_price_lower, sqrt_price_upper, liquidity
+            );
+            let amount1: Decimal = if amount1 > bucket1.amount() { bucket1.amount() } else { amount1 };
+            let amount2: Decimal = if amount2 > bucket2.amount() { bucket2.amount() } else { amount2 };
+            self.deposit(bucket1.take(amount1));
+            self.deposit(bucket2.take(amount2));
+
+            // Minting the position NFT for the liquidity provider.
+            let position_id: NonFungibleId = NonFungibleId::from_u64(self.positions_minted);
+            self.positions_minted += 1;
+            let position_manager: &ResourceManager = borrow_resource_manager!(self.position_address);
+            let position: Bucket = self.position_admin_badge.authorize(|| {
+                position_manager.mint_non_fungible(
+                    &position_id,
+                    ConcentratedPosition {
+                        lower_tick: lower_tick,
+                        upper_tick: upper_tick,
+                        liquidity: liquidity,
+                        fee_growth_inside_last: fee_growth_inside,
+                    }
+                )
+            });
+
+            return (bucket1, bucket2, position);
+        }
+
+        /// Removes the liquidity of the given position from the pool along with the fees that it earned.
+        ///
+        /// This method calculates the amounts of the two tokens that the liquidity of the position is worth at the
+        /// current price as well as the fees earned within the range of the position since it was created. These are
+        /// withdrawn from the pool and the position NFT is burned.
+        ///
+        /// This method performs a number of checks before liquidity removed from the pool:
+        ///
+        /// * **Check 1:** Checks that the bucket contains a single position NFT of this liquidity pool.
+        ///
+        /// # Arguments:
+        ///
+        /// * `position` (Bucket) - A bucket of the position NFT to remove the liquidity of.
+        ///
+        /// # Returns:
+        ///
+        /// * `Bucket` - A Bucket of the share of the liquidity provider of the first token.
+        /// * `Bucket` - A Bucket of the share of the liquidity provider of the second token.
+        pub fn remove_liquidity(
+            &mut self,
+            position: Bucket
+        ) -> (Bucket, Bucket) {
+            // Checking that the position belongs to this liquidity pool.
+            assert_eq!(
+                position.resource_address(), self.position_address,
+                "[CL Remove Liquidity]: The position given does not belong to this liquidity pool."
+            );
+            assert_eq!(
+                position.amount(), Decimal::one(),
+                "[CL Remove Liquidity]: Positions may only be removed one at a time."
+            );
+            let position_data: ConcentratedPosition = position.non_fungible::<ConcentratedPosition>().data();
+
+            // Calculating the fees owed to the position before its ticks are updated.
+            let mut fees: HashMap<ResourceAddress, Decimal> = HashMap::new();
+            for address in self.addresses() {
+                let fee_growth_inside: Decimal = self.fee_growth_inside(
+                    position_data.lower_tick, position_data.upper_tick, address
+                );
+                fees.insert(
+                    address,
+                    position_data.liquidity * (fee_growth_inside - position_data.fee_growth_inside_last[&address])
+                );
+            }
+
+            // Removing the liquidity of the position and finding what it is worth at the current price.
+            self.update_position(position_data.lower_tick, position_data.upper_tick, -position_data.liquidity);
+            for tick in [position_data.lower_tick, position_data.upper_tick] {
+                if self.ticks[&tick].liquidity_gross == Decimal::zero() {
+                    self.ticks.remove(&tick);
+                }
+            }
+            let (amount1, amount2): (Decimal, Decimal) = amounts_for_liquidity(
+                self.sqrt_price,
+                sqrt_price_at_tick(position_data.lower_tick),
+                sqrt_price_at_tick(position_data.upper_tick),
+                position_data.liquidity
+            );
+            info!(
+                "[CL Remove Liquidity]: Removing {} liquidity and fees of {:?}", position_data.liquidity, fees
+            );
+
+            // Burning the position NFT
+            self.position_admin_badge.authorize(|| {
+                position.burn();
+            });
+
+            // Withdrawing the amount of tokens owed to this liquidity provider. The amounts are capped by the reserves
+            // to protect against rounding in the last decimal place.
+            let addresses: Vec<ResourceAddress> = self.addresses();
+            let owed1: Decimal = amount1 + fees[&addresses[0]];
+            let owed2: Decimal = amount2 + fees[&addresses[1]];
+            let reserves: (Decimal, Decimal) = (self.vaults[&addresses[0]].amount(), self.vaults[&addresses[1]].amount());
+            let owed1: Decimal = if owed1 > reserves.0 { reserves.0 } else { owed1 };
+            let owed2: Decimal = if owed2 > reserves.1 { reserves.1 } else { owed2 };
+            let bucket1: Bucket = self.withdraw(addresses[0], owed1);
+            let bucket2: Bucket = self.withdraw(addresses[1], owed2);
+
+            return (bucket1, bucket2);
+        }
+
+        /// Performs the swap of tokens and takes the pool fee in the process.
+        ///
+        /// This method performs a number of checks before the swap is performed:
+        ///
+        /// * **Check 1:** Checks that the tokens in the bucket do indeed belong to this liquidity pool.
+        ///
+        /// # Arguments:
+        ///
+        /// * `tokens` (Bucket) - A bucket containing the input tokens that will be swapped for other tokens.
+        ///
+        /// # Returns:
+        ///
+        /// * `Bucket` - A bucket of the other tokens.
+        pub fn swap(
+            &mut self,
+            tokens: Bucket
+        ) -> Bucket {
+            // Checking if the tokens belong to this liquidity pool.
+            self.assert_belongs_to_pool(tokens.resource_address(), String::from("CL Swap"));
+            info!("[CL Swap]: Square root of the price before swap: {}", self.sqrt_price);
+
+            // Simulating the swap, applying its outcome to the pool, and exchanging the tokens.
+            let input_resource_address: ResourceAddress = tokens.resource_address();
+            let swap_result: Option<SwapResult> = self.compute_swap(input_resource_address, tokens.amount(), true);
+            assert!(swap_result.is_some(), "[CL Swap]: Not enough liquidity in the pool for this swap.");
+            let swap_result: SwapResult = swap_result.unwrap();
+            let output_tokens: Bucket = self.withdraw(
+                self.other_resource_address(input_resource_address),
+                swap_result.amount_out
+            );
+            self.apply_swap(input_resource_address, swap_result);
+            self.deposit(tokens);
+
+            info!("[CL Swap]: Square root of the price after swap: {}", self.sqrt_price);
+            return output_tokens;
+        }
+
+        /// Swaps all of the given tokens for the other token.
+        ///
+        /// This method performs a number of checks before the swap is performed:
+        ///
+        /// * **Check 1:** Checks that the tokens in the bucket do indeed belong to this liquidity pool.
+        ///
+        /// # Arguments:
+        ///
+        /// * `tokens` (Bucket) - A bucket containing the input tokens that will be swapped for other tokens.
+        /// * `min_amount_out` (Decimal) - The minimum amount of tokens that the caller is willing to accept before the
+        /// method fails.
+        ///
+        /// # Returns:
+        ///
+        /// * `Bucket` - A bucket of the other tokens.
+        pub fn swap_exact_tokens_for_tokens(
+            &mut self,
+            tokens: Bucket,
+            min_amount_out: Decimal
+        ) -> Bucket {
+            // Checking that the bucket passed does indeed belong to this liquidity pool
+            self.assert_belongs_to_pool(tokens.resource_address(), String::from("CL Swap Exact"));
+
+            let output_tokens: Bucket = self.swap(tokens);
+            assert!(output_tokens.amount() >= min_amount_out, "[CL Swap Exact]: min_amount_out not satisfied.");
+
+            return output_tokens;
+        }
+
+        /// Swaps tokens for a specific amount of tokens.
+        ///
+        /// This method performs a number of checks before the swap is performed:
+        ///
+        /// * **Check 1:** Checks that the tokens in the bucket do indeed belong to this liquidity pool.
+        /// * **Check 2:** Checks that the tokens in the bucket are enough for the requested output.
+        ///
+        /// # Arguments:
+        ///
+        /// * `tokens` (Bucket) - A bucket containing the tokens that the user wishes to swap.
+        /// * `output_amount` (Decimal) - A decimal of the specific amount of output that the user wishes to receive
+        /// from this swap.
+        ///
+        /// # Returns:
+        ///
+        /// * `Bucket` - A bucket of the other tokens.
+        /// * `Bucket` - A bucket of the remaining input tokens.
+        pub fn swap_tokens_for_exact_tokens(
+            &mut self,
+            mut tokens: Bucket,
+            output_amount: Decimal
+        ) -> (Bucket, Bucket) {
+            // Checking that the bucket passed does indeed belong to this liquidity pool
+            self.assert_belongs_to_pool(tokens.resource_address(), String::from("CL Swap For Exact"));
+
+            // Simulating the swap for the exact output to find the amount of input that it requires.
+            let input_resource_address: ResourceAddress = tokens.resource_address();
+            let swap_result: Option<SwapResult> = self.compute_swap(input_resource_address, output_amount, false);
+            assert!(swap_result.is_some(), "[CL Swap For Exact]: Not enough liquidity in the pool for this swap.");
+            let swap_result: SwapResult = swap_result.unwrap();
+            assert!(
+                tokens.amount() >= swap_result.amount_in,
+                "[CL Swap For Exact]: Not enough input for the desired amount of output."
+            );
+
+            let input_tokens: Bucket = tokens.take(swap_result.amount_in);
+            let output_tokens: Bucket = self.withdraw(
+                self.other_resource_address(input_resource_address),
+                output_amount
+            );
+            self.apply_swap(input_resource_address, swap_result);
+            self.deposit(input_tokens);
+
+            return (output_tokens, tokens);
+        }
+
+        /// Deposits a bucket of tokens into this liquidity pool.
+        ///
+        /// # Arguments:
+        ///
+        /// * `bucket` (Bucket) - A buckets of the tokens to deposit into the liquidity pool
+        fn deposit(
+            &mut self,
+            bucket: Bucket
+        ) {
+            // Checking if the passed resource address belongs to this pool.
+            self.assert_belongs_to_pool(bucket.resource_address(), String::from("CL Deposit"));
+
+            self.vaults.get_mut(&bucket.resource_address()).unwrap().put(bucket);
+        }
+
+        /// Withdraws tokens from the liquidity pool.
+        ///
+        /// # Arguments:
+        ///
+        /// * `resource_address` (ResourceAddress) - The address of the resource to withdraw from the liquidity pool.
+        /// * `amount` (Decimal) - The amount of tokens to withdraw from the liquidity pool.
+        ///
+        /// # Returns:
+        ///
+        /// * `Bucket` - A bucket of the withdrawn tokens.
+        fn withdraw(
+            &mut self,
+            resource_address: ResourceAddress,
+            amount: Decimal
+        ) -> Bucket {
+            // Performing the checks to ensure tha the withdraw can actually go through
+            self.assert_belongs_to_pool(resource_address, String::from("CL Withdraw"));
+
+            let vault: &mut Vault = self.vaults.get_mut(&resource_address).unwrap();
+            assert!(
+                vault.amount() >= amount,
+                "[CL Withdraw]: Not enough liquidity available for the withdraw."
+            );
+
+            return vault.take(amount);
+        }
+
+        /// Finds the next initialized tick in the direction of a swap.
+        ///
+        /// # Arguments:
+        ///
+        /// * `current_tick` (i32) - The tick to start searching from.
+        /// * `zero_for_one` (bool) - Whether the price is moving down (the first token is being sold) or not.
+        ///
+        /// # Returns:
+        ///
+        /// * `Option<i32>` - The next initialized tick, if there is one.
+        fn next_initialized_tick(
+            &self,
+            current_tick: i32,
+            zero_for_one: bool
+        ) -> Option<i32> {
+            return if zero_for_one {
+                self.ticks.range(..=current_tick).next_back().map(|(tick, _)| *tick)
+            } else {
+                self.ticks.range((current_tick + 1)..).next().map(|(tick, _)| *tick)
+            };
+        }
+
+        /// Simulates a swap against the current state of the pool without changing it.
+        ///
+        /// The swap is performed in steps. Each step moves the price up to the next initialized tick (or less, if the
+        /// remaining amount runs out before then) using the constant product formula with the active liquidity. When a
+        /// tick is crossed the liquidity of the positions starting or ending at it is added or removed. The pool fee is
+        /// taken from the input of every step and accrues to the liquidity that was active during that step.
+        ///
+        /// If the swap runs past the last initialized tick before the whole amount is consumed, the pool does not have
+        /// enough liquidity for it and no outcome is returned. Callers decide whether this is an error or whether the
+        /// pool is simply skipped.
+        ///
+        /// # Arguments:
+        ///
+        /// * `input_resource_address` (ResourceAddress) - The resource address of the input token.
+        /// * `amount` (Decimal) - The amount of input tokens if `exact_input` is true, otherwise the amount of output
+        /// tokens.
+        /// * `exact_input` (bool) - Whether the amount given is the input or the output of the swap.
+        ///
+        /// # Returns:
+        ///
+        /// * `Option<SwapResult>` - The outcome of the swap, `None` if there is not enough liquidity in the pool for it.
+        fn compute_swap(
+            &self,
+            input_resource_address: ResourceAddress,
+            amount: Decimal,
+            exact_input: bool
+        ) -> Option<SwapResult> {
+            let zero_for_one: bool = input_resource_address == self.token_addresses.0;
+            let one: Decimal = Decimal::one();
+            let r: Decimal = (dec!("100") - self.fee_to_pool) / dec!("100");
+
+            let mut result: SwapResult = SwapResult {
+                amount_in: Decimal::zero(),
+                amount_out: Decimal::zero(),
+                sqrt_price: self.sqrt_price,
+                current_tick: self.current_tick,
+                liquidity: self.liquidity,
+                fee_growth_global: self.fee_growth_globals[&input_resource_address],
+                crossed_ticks: Vec::new(),
+            };
+
+            // When the input is exact, the fee is taken out first and the rest is what moves the price.
+            let mut remaining: Decimal = if exact_input { amount * r } else { amount };
+            while remaining > Decimal::zero() {
+                let next_tick: Option<i32> = self.next_initialized_tick(result.current_tick, zero_for_one);
+                if next_tick.is_none() {
+                    return None;
+                }
+                let tick: i32 = next_tick.unwrap();
+                let target_sqrt_price: Decimal = sqrt_price_at_tick(tick);
+                let sqrt_price: Decimal = result.sqrt_price;
+                let liquidity: Decimal = result.liquidity;
+
+                if liquidity > Decimal::zero() {
+                    // The amounts of input (without the fee) and output needed to move the price to the next tick.
+                    let (step_in_max, step_out_max): (Decimal, Decimal) = if zero_for_one {
+                        (
+                            liquidity * (one / target_sqrt_price - one / sqrt_price), 
+                            liquidity * (sqrt_price - target_sqrt_price)
+                        )
+                    } else {
+                        (
+                            liquidity * (target_sqrt_price - sqrt_price), 
+                            liquidity * (one / sqrt_price - one / target_sqrt_price)
+                        )
+                    };
+                    let reaches_tick: bool = if exact_input { 
+                        remaining >= step_in_max 
+                    } else { 
+                        remaining >= step_out_max 
+                    };
+
+                    let (step_in, step_out): (Decimal, Decimal) = if reaches_tick {
+                        result.sqrt_price = target_sqrt_price;
+                        (step_in_max, step_out_max)
+                    } else {
+                        // The remaining amount runs out before the next tick, so the price stops within this range.
+                        let new_sqrt_price: Decimal = match (zero_for_one, exact_input) {
+                            (true, true) => liquidity * sqrt_price / (liquidity + remaining * sqrt_price),
+                            (false, true) => sqrt_price + remaining / liquidity,
+                            (true, false) => sqrt_price - remaining / liquidity,
+                            (false, false) => liquidity * sqrt_price / (liquidity - remaining * sqrt_price),
+                        };
+                        let (step_in, step_out): (Decimal, Decimal) = if zero_for_one {
+                            (
+                                liquidity * (one / new_sqrt_price - one / sqrt_price), 
+                                liquidity * (sqrt_price - new_sqrt_price)
+                            )
+                        } else {
+                            (
+                                liquidity * (new_sqrt_price - sqrt_price), 
+                                liquidity * (one / sqrt_price - one / new_sqrt_price)
+                            )
+                        };
+                        result.sqrt_price = new_sqrt_price;
+
+                        // The new price is between the current tick and the next initialized tick.
+                        let new_tick: i32 = tick_at_sqrt_price(new_sqrt_price);
+                        result.current_tick = if zero_for_one {
+                            if new_tick < tick { tick } else { new_tick }
+                        } else {
+                            if new_tick >= tick { tick - 1 } else { new_tick }
+                        };
+
+                        if exact_input { (remaining, step_out) } else { (step_in, remaining) }
+                    };
+
+                    // Taking the fee on the input of this step and distributing it over the active liquidity.
+                    let step_fee: Decimal = step_in * (one - r) / r;
+                    result.fee_growth_global = result.fee_growth_global + step_fee / liquidity;
+                    result.amount_in = result.amount_in + step_in;
+                    result.amount_out = result.amount_out + step_out;
+                    remaining = remaining - if exact_input { step_in } else { step_out };
+
+                    if !reaches_tick {
+                        break;
+                    }
+                } else {
+                    // There is no active liquidity in this range, so the price jumps straight to the next tick.
+                    result.sqrt_price = target_sqrt_price;
+                }
+
+                // Crossing the tick and updating the active liquidity.
+                result.crossed_ticks.push((tick, result.fee_growth_global));
+                if zero_for_one {
+                    result.liquidity = result.liquidity - self.ticks[&tick].liquidity_net;
+                    result.current_tick = tick - 1;
+                } else {
+                    result.liquidity = result.liquidity + self.ticks[&tick].liquidity_net;
+                    result.current_tick = tick;
+                }
+            }
+
+            // The amount of input calculated so far does not include the fee.
+            result.amount_in = if exact_input { amount } else { result.amount_in / r };
+            return Some(result);
+        }
+
+        /// Applies the outcome of a simulated swap to the state of the pool.
+        ///
+        /// # Arguments:
+        ///
+        /// * `input_resource_address` (ResourceAddress) - The resource address of the input token.
+        /// * `swap_result` (SwapResult) - The outcome of the swap as calculated by `compute_swap`.
+        fn apply_swap(
+            &mut self,
+            input_resource_address: ResourceAddress,
+            swap_result: SwapResult
+        ) {
+            // When a tick is crossed, the fees earned on its other side flip to the side that the price moved away from.
+            let output_resource_address: ResourceAddress = self.other_resource_address(input_resource_address);
+            let output_fee_growth_global: Decimal = self.fee_growth_globals[&output_resource_address];
+            for (tick, input_fee_growth_global) in swap_result.crossed_ticks {
+                let tick_info: &mut TickInfo = self.ticks.get_mut(&tick).unwrap();
+                let input_outside: Decimal = tick_info.fee_growth_outside[&input_resource_address];
+                let output_outside: Decimal = tick_info.fee_growth_outside[&output_resource_address];
+                tick_info.fee_growth_outside.insert(input_resource_address, input_fee_growth_global - input_outside);
+                tick_info.fee_growth_outside.insert(output_resource_address, output_fee_growth_global - output_outside);
+            }
+
+            self.sqrt_price = swap_result.sqrt_price;
+            self.current_tick = swap_result.current_tick;
+            self.liquidity = swap_result.liquidity;
+            self.fee_growth_globals.insert(input_resource_address, swap_result.fee_growth_global);
+        }
+
+        /// Adds (or removes, if negative) the given amount of liquidity to the given tick range.
+        ///
+        /// Ticks which are not initialized yet are initialized. By convention, the fees earned before a tick is
+        /// initialized are assumed to have all been earned below it.
+        ///
+        /// # Arguments:
+        ///
+        /// * `lower_tick` (i32) - The tick at which the range starts.
+        /// * `upper_tick` (i32) - The tick at which the range ends.
+        /// * `liquidity_delta` (Decimal) - The amount of liquidity to add to the range.
+        fn update_position(
+            &mut self,
+            lower_tick: i32,
+            upper_tick: i32,
+            liquidity_delta: Decimal
+        ) {
+            for tick in [lower_tick, upper_tick] {
+                if !self.ticks.contains_key(&tick) {
+                    let mut fee_growth_outside: HashMap<ResourceAddress, Decimal> = HashMap::new();
+                    for address in self.addresses() {
+                        let value: Decimal = if tick <= self.current_tick {
+                            self.fee_growth_globals[&address]
+                        } else {
+                            Decimal::zero()
+                        };
+                        fee_growth_outside.insert(address, value);
+                    }
+                    self.ticks.insert(tick, TickInfo {
+                        liquidity_net: Decimal::zero(),
+                        liquidity_gross: Decimal::zero(),
+                        fee_growth_outside: fee_growth_outside
+                    });
+                }
+
+                let tick_info: &mut TickInfo = self.ticks.get_mut(&tick).unwrap();
+                tick_info.liquidity_gross = tick_info.liquidity_gross + liquidity_delta;
+                tick_info.liquidity_net = if tick == lower_tick {
+                    tick_info.liquidity_net + liquidity_delta
+                } else {
+                    tick_info.liquidity_net - liquidity_delta
+                };
+            }
+
+            // If the current price is within the range then the active liquidity changes as well.
+            if (lower_tick <= self.current_tick) & (self.current_tick < upper_tick) {
+                self.liquidity = self.liquidity + liquidity_delta;
+            }
+        }
+
+        /// Calculates the fees earned per unit of liquidity within the given tick range for the given token.
+        ///
+        /// # Arguments:
+        ///
+        /// * `lower_tick` (i32) - The tick at which the range starts. This tick must be initialized.
+        /// * `upper_tick` (i32) - The tick at which the range ends. This tick must be initialized.
+        /// * `resource_address` (ResourceAddress) - The resource address of the token to calculate the fees of.
+        ///
+        /// # Returns:
+        ///
+        /// * `Decimal` - The fees earned per unit of liquidity within the range since the creation of the pool.
+        fn fee_growth_inside(
+            &self,
+            lower_tick: i32,
+            upper_tick: i32,
+            resource_address: ResourceAddress
+        ) -> Decimal {
+            let global: Decimal = self.fee_growth_globals[&resource_address];
+            let lower_outside: Decimal = self.ticks[&lower_tick].fee_growth_outside[&resource_address];
+            let upper_outside: Decimal = self.ticks[&upper_tick].fee_growth_outside[&resource_address];
+
+            let below: Decimal = if self.current_tick >= lower_tick { lower_outside } else { global - lower_outside };
+            let above: Decimal = if self.current_tick < upper_tick { upper_outside } else { global - upper_outside };
+            return global - below - above;
+        }
+    }
+}
@@ -1,3 +1,5 @@
 mod utils;
 mod radex;
-mod liquidity_pool;
\ No newline at end of file
+mod liquidity_pool;
+mod concentrated_liquidity_pool;
+mod pool;
\ No newline at end of file
@@ -1,6 +1,24 @@
 use scrypto::prelude::*;
 use crate::utils::*;
 
+/// The maximum number of price observations that a liquidity pool keeps for each of its tokens. Once there are more,
+/// the oldest observation is dropped, so TWAPs can only be calculated over windows that start at or after the oldest
+/// observation that is still kept.
+pub const MAX_PRICE_OBSERVATIONS: usize = 256;
+
+/// A snapshot of the price accumulator of one of the tokens in a liquidity pool taken at the end of an epoch in which
+/// the reserves of the pool changed.
+#[derive(TypeId, Encode, Decode, Describe, Debug, Clone)]
+pub struct PriceObservation {
+    /// The value of the price accumulator at the start of the epoch of the observation. Trades that happen during an 
+    /// epoch only start counting towards the accumulator once that epoch is over.
+    pub price_cumulative: Decimal,
+
+    /// The spot price of the token after the last change to the reserves in the epoch of the observation. This is the
+    /// price which is accumulated for every epoch until the next observation.
+    pub closing_price: Decimal,
+}
+
 blueprint!{
     /// This is a struct used to define a liquidity pool in the RaDEX decentralized exchange. A typical liquidity pool 
     /// is made up of two vaults which are used to store the two tokens being traded against one another. In addition to 
@@ -29,6 +47,23 @@ blueprint!{
         /// This is a decimal value between 0 and 100 which defines the amount of fees paid to the liquidity pool (and
         /// in turn the liquidity providers) when a swap is made through this liquidity pool.
         fee_to_pool: Decimal,
+
+        /// These are Uniswap V2 style cumulative prices of the two tokens in the pool. The cumulative price of a token 
+        /// is the sum of its spot price (in terms of the other token) over every epoch that has passed since the pool 
+        /// was created. The difference between the cumulative prices at two epochs divided by the number of epochs 
+        /// between them gives the time weighted average price over that window, which is a lot more expensive to 
+        /// manipulate than the spot price.
+        price_cumulatives: HashMap<ResourceAddress, Decimal>,
+
+        /// The epoch at which the `price_cumulatives` were last updated.
+        last_update_epoch: u64,
+
+        /// A history of the price accumulators of each of the two tokens keyed by the epoch of the observation. An 
+        /// observation is recorded in every epoch where a swap, an addition, or a removal of liquidity took place. 
+        /// These observations allow for the time weighted average price to be calculated over any window of epochs 
+        /// that starts at or after the oldest observation. Only the last `MAX_PRICE_OBSERVATIONS` observations are 
+        /// kept so that the state of the pool does not grow with every epoch in which it is used.
+        price_observations: HashMap<ResourceAddress, BTreeMap<u64, PriceObservation>>,
     }
 
     impl LiquidityPool {
@@ -131,12 +166,33 @@ blueprint!{
                 .burnable(rule!(require(tracking_token_admin_badge.resource_address())), LOCKED)
                 .initial_supply(100);
 
+            // The price accumulators start at zero and the first price observation of each token is recorded with the
+            // initial ratio of the pool.
+            let current_epoch: u64 = Runtime::current_epoch();
+            let reserves: (Decimal, Decimal) = (vaults[&addresses.0].amount(), vaults[&addresses.1].amount());
+            let mut price_observations: HashMap<ResourceAddress, BTreeMap<u64, PriceObservation>> = HashMap::new();
+            price_observations.insert(addresses.0, BTreeMap::from([(
+                current_epoch, 
+                PriceObservation { price_cumulative: Decimal::zero(), closing_price: reserves.1 / reserves.0 }
+            )]));
+            price_observations.insert(addresses.1, BTreeMap::from([(
+                current_epoch, 
+                PriceObservation { price_cumulative: Decimal::zero(), closing_price: reserves.0 / reserves.1 }
+            )]));
+
+            let mut price_cumulatives: HashMap<ResourceAddress, Decimal> = HashMap::new();
+            price_cumulatives.insert(addresses.0, Decimal::zero());
+            price_cumulatives.insert(addresses.1, Decimal::zero());
+
             // Creating the liquidity pool component and instantiating it
             let liquidity_pool: ComponentAddress = Self { 
                 vaults: vaults,
                 tracking_token_address: tracking_tokens.resource_address(),
                 tracking_token_admin_badge: Vault::with_bucket(tracking_token_admin_badge),
                 fee_to_pool: fee_to_pool,
+                price_cumulatives: price_cumulatives,
+                last_update_epoch: current_epoch,
+                price_observations: price_observations,
             }
             .instantiate()
             .globalize();
@@ -233,6 +289,29 @@ blueprint!{
             return if addresses[0] == resource_address {addresses[1]} else {addresses[0]};
         }
 
+        /// Gets the amount of reserves of the given token in this liquidity pool.
+        /// 
+        /// This method performs a number of checks before the reserves are obtained:
+        /// 
+        /// * **Check 1:** Checks that the resource address given does indeed belong to this liquidity pool.
+        /// 
+        /// # Arguments:
+        /// 
+        /// * `resource_address` (ResourceAddress) - The resource address of the token to get the reserves of.
+        /// 
+        /// # Returns:
+        /// 
+        /// * `Decimal` - The amount of the given token currently held in the pool's vault.
+        pub fn reserve(
+            &self,
+            resource_address: ResourceAddress
+        ) -> Decimal {
+            // Checking if the passed resource address belongs to this pool.
+            self.assert_belongs_to_pool(resource_address, String::from("Reserve"));
+
+            return self.vaults[&resource_address].amount();
+        }
+
         /// Calculates the k in the constant market maker equation: `x * y = k`.
         /// 
         /// # Returns:
@@ -243,6 +322,167 @@ blueprint!{
             return self.vaults[&addresses[0]].amount() * self.vaults[&addresses[1]].amount()
         }
 
+        /// Calculates the spot price of the given token in terms of the other token in the pool.
+        /// 
+        /// This method performs a number of checks before the price is calculated:
+        /// 
+        /// * **Check 1:** Checks that the resource address given does indeed belong to this liquidity pool.
+        /// 
+        /// # Arguments:
+        /// 
+        /// * `resource_address` (ResourceAddress) - The resource address of the token to get the price of.
+        /// 
+        /// # Returns:
+        /// 
+        /// * `Decimal` - The amount of the other token that one unit of the given token is worth at the current ratio
+        /// of reserves. If the pool has no reserves of the given token then zero is returned.
+        pub fn spot_price(
+            &self,
+            resource_address: ResourceAddress
+        ) -> Decimal {
+            // Checking if the passed resource address belongs to this pool.
+            self.assert_belongs_to_pool(resource_address, String::from("Spot Price"));
+
+            let x: Decimal = self.vaults[&resource_address].amount();
+            let y: Decimal = self.vaults[&self.other_resource_address(resource_address)].amount();
+            return if x == Decimal::zero() { Decimal::zero() } else { y / x };
+        }
+
+        /// Gets the current value of the price accumulator of the given token.
+        /// 
+        /// The stored accumulators are only updated when the reserves of the pool change. This method accounts for the
+        /// epochs that have passed since the last update by adding the current spot price for each of them, which is 
+        /// the value that the accumulator would have if it was updated right now.
+        /// 
+        /// This method performs a number of checks before the accumulator is obtained:
+        /// 
+        /// * **Check 1:** Checks that the resource address given does indeed belong to this liquidity pool.
+        /// 
+        /// # Arguments:
+        /// 
+        /// * `resource_address` (ResourceAddress) - The resource address of the token to get the accumulator of.
+        /// 
+        /// # Returns:
+        /// 
+        /// * `Decimal` - The cumulative price of the token.
+        /// * `u64` - The current epoch which the cumulative price corresponds to.
+        pub fn get_price_cumulative(
+            &self,
+            resource_address: ResourceAddress
+        ) -> (Decimal, u64) {
+            // Checking if the passed resource address belongs to this pool.
+            self.assert_belongs_to_pool(resource_address, String::from("Price Cumulative"));
+
+            let current_epoch: u64 = Runtime::current_epoch();
+            return (self.price_cumulative_at(resource_address, current_epoch), current_epoch);
+        }
+
+        /// Calculates the time weighted average price of the given token over a window of epochs.
+        /// 
+        /// This method uses the price observations recorded by the pool to find the value of the price accumulator at
+        /// the start and the end of the window. The difference between the two accumulators divided by the length of
+        /// the window is the time weighted average price. Since a trade only starts counting towards the accumulator 
+        /// once its epoch is over, moving this price requires holding the manipulated price for entire epochs.
+        /// 
+        /// This method performs a number of checks before the price is calculated:
+        /// 
+        /// * **Check 1:** Checks that the resource address given does indeed belong to this liquidity pool.
+        /// * **Check 2:** Checks that the start epoch comes before the end epoch.
+        /// * **Check 3:** Checks that the end epoch is not in the future.
+        /// * **Check 4:** Checks that the start epoch is not before the oldest price observation kept by the pool, 
+        /// which is the creation of the pool until more than `MAX_PRICE_OBSERVATIONS` observations were recorded.
+        /// 
+        /// # Arguments:
+        /// 
+        /// * `resource_address` (ResourceAddress) - The resource address of the token to get the price of.
+        /// * `start_epoch` (u64) - The epoch at which the window starts.
+        /// * `end_epoch` (u64) - The epoch at which the window ends.
+        /// 
+        /// # Returns:
+        /// 
+        /// * `Decimal` - The time weighted average price of the given token in terms of the other token.
+        pub fn get_twap(
+            &self,
+            resource_address: ResourceAddress,
+            start_epoch: u64,
+            end_epoch: u64
+        ) -> Decimal {
+            // Performing the checks to ensure that the window is valid for this pool.
+            self.assert_belongs_to_pool(resource_address, String::from("TWAP"));
+            assert!(start_epoch < end_epoch, "[TWAP]: The start epoch must come before the end epoch.");
+            assert!(end_epoch <= Runtime::current_epoch(), "[TWAP]: The end epoch can not be in the future.");
+            let first_epoch: u64 = *self.price_observations[&resource_address].keys().next().unwrap();
+            assert!(start_epoch >= first_epoch, "[TWAP]: The start epoch is before the oldest price observation of the pool.");
+
+            let start_cumulative: Decimal = self.price_cumulative_at(resource_address, start_epoch);
+            let end_cumulative: Decimal = self.price_cumulative_at(resource_address, end_epoch);
+            return (end_cumulative - start_cumulative) / Decimal::from(end_epoch - start_epoch);
+        }
+
+        /// Calculates the value of the price accumulator of the given token at the given epoch.
+        /// 
+        /// The accumulator at a given epoch is found from the most recent price observation at or before that epoch. 
+        /// Since the reserves did not change between that observation and the given epoch, the closing price of the 
+        /// observation is accumulated for every epoch in between.
+        /// 
+        /// # Arguments:
+        /// 
+        /// * `resource_address` (ResourceAddress) - The resource address of the token to get the accumulator of.
+        /// * `epoch` (u64) - The epoch to get the value of the accumulator at.
+        /// 
+        /// # Returns:
+        /// 
+        /// * `Decimal` - The cumulative price of the token at the given epoch.
+        fn price_cumulative_at(
+            &self,
+            resource_address: ResourceAddress,
+            epoch: u64
+        ) -> Decimal {
+            let (observation_epoch, observation): (&u64, &PriceObservation) = self.price_observations[&resource_address]
+                .range(..=epoch)
+                .next_back()
+                .unwrap();
+            return observation.price_cumulative + observation.closing_price * Decimal::from(epoch - observation_epoch);
+        }
+
+        /// Updates the price accumulators of the two tokens up to the current epoch.
+        /// 
+        /// This method must be called before the reserves of the pool change so that the spot price accumulated for 
+        /// the epochs that have passed is the price that was in effect during those epochs.
+        fn update_price_accumulators(&mut self) {
+            let current_epoch: u64 = Runtime::current_epoch();
+            if current_epoch > self.last_update_epoch {
+                let elapsed_epochs: Decimal = Decimal::from(current_epoch - self.last_update_epoch);
+                for address in self.addresses() {
+                    let price_cumulative: Decimal = self.price_cumulatives[&address] 
+                        + self.spot_price(address) * elapsed_epochs;
+                    self.price_cumulatives.insert(address, price_cumulative);
+                }
+                self.last_update_epoch = current_epoch;
+            }
+        }
+
+        /// Records a price observation of the two tokens for the current epoch.
+        /// 
+        /// This method must be called after the reserves of the pool change so that the closing price of the current
+        /// epoch reflects the new reserves. If an observation already exists for the current epoch, it is replaced. 
+        /// When there are more than `MAX_PRICE_OBSERVATIONS` observations, the oldest one is dropped.
+        fn record_price_observation(&mut self) {
+            let current_epoch: u64 = Runtime::current_epoch();
+            for address in self.addresses() {
+                let observation: PriceObservation = PriceObservation {
+                    price_cumulative: self.price_cumulatives[&address],
+                    closing_price: self.spot_price(address)
+                };
+                let observations: &mut BTreeMap<u64, PriceObservation> = self.price_observations.get_mut(&address).unwrap();
+                observations.insert(current_epoch, observation);
+                if observations.len() > MAX_PRICE_OBSERVATIONS {
+                    let oldest_epoch: u64 = *observations.keys().next().unwrap();
+                    observations.remove(&oldest_epoch);
+                }
+            }
+        }
+
         /// Calculates the amount of output that can be given for for a given amount of input.
         /// 
         /// This method calculates the amount of output tokens that would be received for a given amount of an input
@@ -479,9 +719,12 @@ blueprint!{
                 bucket1.resource_address(), amount1, bucket2.resource_address(), amount2
             );
 
-            // Depositing the amount of tokens calculated into the liquidity pool
+            // Depositing the amount of tokens calculated into the liquidity pool and updating the price accumulators
+            // around the change in reserves.
+            self.update_price_accumulators();
             self.deposit(bucket1.take(amount1));
             self.deposit(bucket2.take(amount2));
+            self.record_price_observation();
 
             // Computing the amount of tracking tokens that the liquidity provider is owed and minting them. In the case
             // that the liquidity pool has been completely emptied out (tracking_tokens_manager.total_supply() == 0)  
@@ -543,10 +786,13 @@ blueprint!{
                 tracking_tokens.burn();
             });
 
-            // Withdrawing the amount of tokens owed to this liquidity provider
+            // Withdrawing the amount of tokens owed to this liquidity provider and updating the price accumulators 
+            // around the change in reserves.
+            self.update_price_accumulators();
             let addresses: Vec<ResourceAddress> = self.addresses();
             let bucket1: Bucket = self.withdraw(addresses[0], self.vaults[&addresses[0]].amount() * percentage);
             let bucket2: Bucket = self.withdraw(addresses[1], self.vaults[&addresses[1]].amount() * percentage);
+            self.record_price_observation();
 
             return (bucket1, bucket2);
         }
@@ -577,6 +823,7 @@ blueprint!{
             // Checking if the tokens belong to this liquidity pool.
             self.assert_belongs_to_pool(tokens.resource_address(), String::from("Swap"));
             info!("[Swap]: K before swap: {}", self.k());
+            self.update_price_accumulators();
 
             // Calculating the output amount for the given input amount of tokens and withdrawing it from the vault
             let output_amount: Decimal = self.calculate_output_amount(tokens.resource_address(), tokens.amount());
@@ -587,6 +834,7 @@ blueprint!{
 
             // Depositing the tokens into the liquidity pool and returning a bucket of the swapped tokens.
             self.deposit(tokens);
+            self.record_price_observation();
             info!("[Swap]: K after swap: {}", self.k());
             return output_tokens;
         }
@@ -674,11 +922,13 @@ blueprint!{
 
             // Depositing the amount of input required into the vaults and taking out the requested amount
             info!("[Swap For Exact]: K before swap: {}", self.k());
+            self.update_price_accumulators();
             self.deposit(tokens.take(input_required));
             let output_tokens: Bucket = self.withdraw(
                 self.other_resource_address(tokens.resource_address()), 
                 output_amount
             );
+            self.record_price_observation();
             info!("[Swap For Exact]: K after swap: {}", self.k());
             info!("[Swap For Exact]: Amount gievn out: {}", output_tokens.amount());
             return (output_tokens, tokens);
@@ -0,0 +1,149 @@
+use scrypto::prelude::*;
+use crate::liquidity_pool::*;
+use crate::concentrated_liquidity_pool::*;
+
+/// The kinds of liquidity pools that may be created on RaDEX.
+#[derive(TypeId, Encode, Decode, Describe, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolKind {
+    /// A `LiquidityPool` which uses the constant market maker function `x * y = k` over every possible price and
+    /// issues fungible tracking tokens to its liquidity providers.
+    ConstantProduct,
+
+    /// A `ConcentratedLiquidityPool` where each deposit covers a price range and is represented by an NFT position.
+    Concentrated,
+}
+
+/// A liquidity pool of any of the kinds supported by RaDEX.
+///
+/// This enum allows the RaDEX component to keep all of its liquidity pools in a single registry regardless of the
+/// curve that they use. The methods implemented on it route the call to the methods of the same name on the underlying
+/// liquidity pool component, since both kinds of pools expose the same interface for swaps and their calculations.
+#[derive(TypeId, Encode, Decode, Describe)]
+pub enum Pool {
+    ConstantProduct(LiquidityPool),
+    Concentrated(ConcentratedLiquidityPool),
+}
+
+impl Pool {
+    /// Gets the kind of this liquidity pool.
+    pub fn kind(&self) -> PoolKind {
+        return match self {
+            Pool::ConstantProduct(_) => PoolKind::ConstantProduct,
+            Pool::Concentrated(_) => PoolKind::Concentrated,
+        };
+    }
+
+    /// Gets the amount of the given token that can be swapped out of this liquidity pool.
+    pub fn reserve(&self, resource_address: ResourceAddress) -> Decimal {
+        return match self {
+            Pool::ConstantProduct(liquidity_pool) => liquidity_pool.reserve(resource_address),
+            Pool::Concentrated(liquidity_pool) => liquidity_pool.reserve(resource_address),
+        };
+    }
+
+    /// Calculates the amount of output that can be given for for a given amount of input.
+    pub fn calculate_output_amount(&self, input_resource_address: ResourceAddress, input_amount: Decimal) -> Decimal {
+        return match self {
+            Pool::ConstantProduct(liquidity_pool) => {
+                liquidity_pool.calculate_output_amount(input_resource_address, input_amount)
+            }
+            Pool::Concentrated(liquidity_pool) => {
+                liquidity_pool.calculate_output_amount(input_resource_address, input_amount)
+            }
+        };
+    }
+
+    /// Calculates the amount of input required to receive the specified amount of output tokens.
+    pub fn calculate_input_amount(&self, output_resource_address: ResourceAddress, output_amount: Decimal) -> Decimal {
+        return match self {
+            Pool::ConstantProduct(liquidity_pool) => {
+                liquidity_pool.calculate_input_amount(output_resource_address, output_amount)
+            }
+            Pool::Concentrated(liquidity_pool) => {
+                liquidity_pool.calculate_input_amount(output_resource_address, output_amount)
+            }
+        };
+    }
+
+    /// Calculates the amount of output that can be given for a given amount of input, or `None` if this liquidity 
+    /// pool does not have enough liquidity for the swap.
+    pub fn try_calculate_output_amount(
+        &self, 
+        input_resource_address: ResourceAddress, 
+        input_amount: Decimal
+    ) -> Option<Decimal> {
+        return match self {
+            Pool::ConstantProduct(liquidity_pool) => {
+                Some(liquidity_pool.calculate_output_amount(input_resource_address, input_amount))
+            }
+            Pool::Concentrated(liquidity_pool) => {
+                liquidity_pool.try_calculate_output_amount(input_resource_address, input_amount)
+            }
+        };
+    }
+
+    /// Calculates the amount of input required to receive the specified amount of output tokens, or `None` if this
+    /// liquidity pool does not have enough liquidity for the swap.
+    pub fn try_calculate_input_amount(
+        &self, 
+        output_resource_address: ResourceAddress, 
+        output_amount: Decimal
+    ) -> Option<Decimal> {
+        return match self {
+            // A constant product pool can never give out all (or more) of its reserves.
+            Pool::ConstantProduct(liquidity_pool) => {
+                if liquidity_pool.reserve(output_resource_address) <= output_amount {
+                    None
+                } else {
+                    Some(liquidity_pool.calculate_input_amount(output_resource_address, output_amount))
+                }
+            }
+            Pool::Concentrated(liquidity_pool) => {
+                liquidity_pool.try_calculate_input_amount(output_resource_address, output_amount)
+            }
+        };
+    }
+
+    /// Removes the liquidity represented by the given tracking tokens or position NFT from this liquidity pool.
+    pub fn remove_liquidity(&self, tracking_tokens: Bucket) -> (Bucket, Bucket) {
+        return match self {
+            Pool::ConstantProduct(liquidity_pool) => liquidity_pool.remove_liquidity(tracking_tokens),
+            Pool::Concentrated(liquidity_pool) => liquidity_pool.remove_liquidity(tracking_tokens),
+        };
+    }
+
+    /// Swaps the given tokens for the other token of this liquidity pool.
+    pub fn swap(&self, tokens: Bucket) -> Bucket {
+        return match self {
+            Pool::ConstantProduct(liquidity_pool) => liquidity_pool.swap(tokens),
+            Pool::Concentrated(liquidity_pool) => liquidity_pool.swap(tokens),
+        };
+    }
+
+    /// Swaps all of the given tokens for the other token with a minimum amount of output.
+    pub fn swap_exact_tokens_for_tokens(&self, tokens: Bucket, min_amount_out: Decimal) -> Bucket {
+        return match self {
+            Pool::ConstantProduct(liquidity_pool) => liquidity_pool.swap_exact_tokens_for_tokens(tokens, min_amount_out),
+            Pool::Concentrated(liquidity_pool) => liquidity_pool.swap_exact_tokens_for_tokens(tokens, min_amount_out),
+        };
+    }
+
+    /// Swaps the given tokens for a specific amount of the other token.
+    pub fn swap_tokens_for_exact_tokens(&self, tokens: Bucket, output_amount: Decimal) -> (Bucket, Bucket) {
+        return match self {
+            Pool::ConstantProduct(liquidity_pool) => liquidity_pool.swap_tokens_for_exact_tokens(tokens, output_amount),
+            Pool::Concentrated(liquidity_pool) => liquidity_pool.swap_tokens_for_exact_tokens(tokens, output_amount),
+        };
+    }
+
+    /// Gets the time weighted average price of the given token over a window of epochs, or `None` if this liquidity
+    /// pool does not keep price accumulators (only constant product pools do).
+    pub fn get_twap(&self, resource_address: ResourceAddress, start_epoch: u64, end_epoch: u64) -> Option<Decimal> {
+        return match self {
+            Pool::ConstantProduct(liquidity_pool) => {
+                Some(liquidity_pool.get_twap(resource_address, start_epoch, end_epoch))
+            }
+            Pool::Concentrated(_) => None,
+        };
+    }
+}
@@ -1,10 +1,17 @@
 use scrypto::prelude::*;
 use crate::liquidity_pool::*;
+use crate::concentrated_liquidity_pool::*;
+use crate::pool::*;
 use crate::utils::*;
 
+/// The maximum number of liquidity pools that a routed swap may go through. Every additional hop costs the swapper
+/// another pool fee and makes the route search more expensive, so routes are capped at this many pools.
+const MAX_ROUTE_HOPS: usize = 3;
+
 blueprint!{
     /// RaDEX is an implementation of an automated market maker decentralized exchange on the Radix ledger. The 
-    /// liquidity pools in this DEX use the constant market maker function `x * y = k` for the trading of tokens.
+    /// liquidity pools in this DEX use the constant market maker function `x * y = k` for the trading of tokens, 
+    /// either over every possible price or concentrated over price ranges chosen by the liquidity providers.
     /// 
     /// The RaDEX blueprint and components do not perform any kind of mathematics, calculation, or anything like that on 
     /// their own. Instead, a RaDEX component may be thought of as a registry of all of the RaDEX liquidity pools and as 
@@ -13,15 +20,22 @@ blueprint!{
         /// This is a hashmap that maps a tuple of two addresses to a Scrypto component. This scrypto component is a 
         /// liquidity pool meaning that this hashmap maps a tuple of two addresses to a liquidity pool. This hashmap is
         /// used as a way of quickly finding the liquidity pool associated with a given address pair. If a pair of 
-        /// addresses does not exist in this hashmap it means that there does not exist a liquidity pool for it on RaDEX
-        liquidity_pools: HashMap<(ResourceAddress, ResourceAddress), LiquidityPool>,
+        /// addresses does not exist in this hashmap it means that there does not exist a liquidity pool for it on RaDEX.
+        /// The liquidity pools may be of any of the kinds in `PoolKind`, but there may only be one pool per pair.
+        liquidity_pools: HashMap<(ResourceAddress, ResourceAddress), Pool>,
 
         /// That's quite the mouthful. This is a hashmap that is mainly used when liquidity providers are tying to 
         /// remove their portion of liquidity from the liquidity pool. This hashmap is used to find the address pair (
         /// and in turn the liquidity pool) associated with a given tracking token. If the resource address of a given
         /// tracking token does not exist as one of the keys to this hashmap, then this means that this tracking token
-        /// does not belong to any of the liquidity pools in RaDEX.
-        tracking_token_address_pair_mapping: HashMap<ResourceAddress, (ResourceAddress, ResourceAddress)>
+        /// does not belong to any of the liquidity pools in RaDEX. For concentrated liquidity pools, the resource 
+        /// address of their position NFTs is used in place of the tracking tokens.
+        tracking_token_address_pair_mapping: HashMap<ResourceAddress, (ResourceAddress, ResourceAddress)>,
+
+        /// This is a hashmap that maps the resource address of a token to the resource addresses of all of the tokens
+        /// that it has a liquidity pool with. The router uses it to only look at the pools adjacent to a token when 
+        /// searching for routes instead of going through all of the liquidity pools at every hop.
+        adjacent_tokens: HashMap<ResourceAddress, Vec<ResourceAddress>>
     }
 
     impl RaDEX {
@@ -34,7 +48,8 @@ blueprint!{
             // The RaDEX AMM does not take any arguments 
             return Self {
                 liquidity_pools: HashMap::new(), 
-                tracking_token_address_pair_mapping: HashMap::new()
+                tracking_token_address_pair_mapping: HashMap::new(),
+                adjacent_tokens: HashMap::new()
             }
             .instantiate()
             .globalize();
@@ -112,18 +127,24 @@ blueprint!{
         /// are just DEX checks to ensure that we don't create a liquidity pool for a token pair that already has a 
         /// liquidity pool.
         /// 
+        /// When a concentrated liquidity pool is created, its initial price is the ratio of the two buckets and the 
+        /// creator is given a position NFT covering the full range of prices instead of tracking tokens.
+        /// 
         /// # Arguments: 
         /// 
         /// * `token1` (Bucket) - A bucket containing the amount of the first token used to initialize the pool.
         /// * `token2` (Bucket) - A bucket containing the amount of the second token used to initialize the pool.
+        /// * `pool_kind` (PoolKind) - The kind of liquidity pool to create.
         /// 
         /// # Returns:
         /// 
-        /// * `Bucket` - A bucket containing the tracking tokens issued to the creator of the liquidity pool.
+        /// * `Bucket` - A bucket containing the tracking tokens (or the position NFT) issued to the creator of the 
+        /// liquidity pool.
         pub fn new_liquidity_pool(
             &mut self,
             token1: Bucket,
             token2: Bucket,
+            pool_kind: PoolKind
         ) -> Bucket {
             // Checking if a liquidity pool already exists between these two tokens
             self.assert_pool_doesnt_exists(
@@ -135,14 +156,25 @@ blueprint!{
             // two buckets.
             let (bucket1, bucket2): (Bucket, Bucket) = sort_buckets(token1, token2);
             let addresses: (ResourceAddress, ResourceAddress) = (bucket1.resource_address(), bucket2.resource_address()); 
-            let (liquidity_pool, tracking_tokens): (ComponentAddress, Bucket) = LiquidityPool::new(
-                bucket1, bucket2, dec!("0.3")
-            );
+            let (liquidity_pool, tracking_tokens): (Pool, Bucket) = match pool_kind {
+                PoolKind::ConstantProduct => {
+                    let (liquidity_pool, tracking_tokens): (ComponentAddress, Bucket) = LiquidityPool::new(
+                        bucket1, bucket2, dec!("0.3")
+                    );
+                    (Pool::ConstantProduct(liquidity_pool.into()), tracking_tokens)
+                }
+                PoolKind::Concentrated => {
+                    let (liquidity_pool, position): (ComponentAddress, Bucket) = ConcentratedLiquidityPool::new(
+                        bucket1, bucket2, dec!("0.3")
+                    );
+                    (Pool::Concentrated(liquidity_pool.into()), position)
+                }
+            };
 
             // Adding the liquidity pool to the hashmap of all liquidity pools
             self.liquidity_pools.insert(
                 addresses,
-                liquidity_pool.into()
+                liquidity_pool
             );
 
             // Adding the resource address of the tracking tokens to the hashmap that maps the tracking tokens with 
@@ -152,6 +184,10 @@ blueprint!{
                 addresses
             );
 
+            // Making the new pool reachable by the router from both of its tokens
+            self.adjacent_tokens.entry(addresses.0).or_insert(Vec::new()).push(addresses.1);
+            self.adjacent_tokens.entry(addresses.1).or_insert(Vec::new()).push(addresses.0);
+
             // Returning the tracking tokens back to the caller of this method (the initial liquidity provider).
             return tracking_tokens;
         }
@@ -160,7 +196,12 @@ blueprint!{
         /// 
         /// This method is used to add liquidity to a liquidity pool in the DEX. If a liquidity pool for the two tokens
         /// passed already exists then liquidity would be directly added to it. However, if a pool doesn't exist, then a
-        /// new liquidity pool is created from the two buckets passed to this method.
+        /// new constant product liquidity pool is created from the two buckets passed to this method.
+        /// 
+        /// This method performs a number of checks before liquidity is added:
+        /// 
+        /// * **Check 1:** Checks that the liquidity pool of the two tokens, if it exists, is a constant product pool.
+        /// Liquidity is added to concentrated liquidity pools through the `add_concentrated_liquidity` method.
         /// 
         /// # Arguments:
         /// 
@@ -182,24 +223,107 @@ blueprint!{
             let addresses: (ResourceAddress, ResourceAddress) = (bucket1.resource_address(), bucket2.resource_address()); 
 
             // Attempting to get the liquidity pool component associated with the provided address pair.
-            let optional_liquidity_pool: Option<&LiquidityPool> = self.liquidity_pools.get(&addresses);
+            let optional_liquidity_pool: Option<&Pool> = self.liquidity_pools.get(&addresses);
             match optional_liquidity_pool {
-                Some (liquidity_pool) => { // If it matches it means that the liquidity pool exists.
+                Some (Pool::ConstantProduct(liquidity_pool)) => { // If it matches it means that the liquidity pool exists.
                     info!("[DEX Add Liquidity]: Pool for {:?} already exists. Adding liquidity directly.", addresses);
                     let returns: (Bucket, Bucket, Bucket) = liquidity_pool.add_liquidity(bucket1, bucket2);
                     (Some(returns.0), Some(returns.1), returns.2)
                 }
+                Some (Pool::Concentrated(_)) => {
+                    panic!(
+                        "[DEX Add Liquidity]: The pool for {:?} is a concentrated liquidity pool. Use the \
+                        add_concentrated_liquidity method instead.", 
+                        addresses
+                    );
+                }
                 None => { // If this matches then there does not exist a liquidity pool for this token pair
                     // In here we are creating a new liquidity pool for this token pair since we failed to find an 
                     // already existing liquidity pool. The return statement below might seem somewhat redundant in 
                     // terms of the two empty buckets being returned, but this is done to allow for the add liquidity
                     // method to be general and allow for the possibility of the liquidity pool not being there.
                     info!("[DEX Add Liquidity]: Pool for {:?} doesn't exist. Creating a new one.", addresses);
-                    (None, None, self.new_liquidity_pool(bucket1, bucket2))
+                    (None, None, self.new_liquidity_pool(bucket1, bucket2, PoolKind::ConstantProduct))
                 }
             }
         }
 
+        /// Adds liquidity over a price range to an already existing concentrated liquidity pool.
+        /// 
+        /// This method is used to add liquidity to a concentrated liquidity pool in the DEX. The liquidity is only 
+        /// active (and only earns fees) while the price of the pool is between the prices at the two given ticks, 
+        /// where the price at tick `i` is `1.0001^i`. The liquidity provider is given a position NFT which can later be
+        /// passed to the `remove_liquidity` method to remove the liquidity along with the fees that it earned.
+        /// 
+        /// This method performs a number of checks before liquidity is added:
+        /// 
+        /// * **Check 1:** Checks that there does exist a liquidity pool for the given pair of tokens.
+        /// * **Check 2:** Checks that the liquidity pool is a concentrated liquidity pool.
+        /// 
+        /// # Arguments:
+        /// 
+        /// * `token1` (Bucket) - A bucket containing the amount of the first token to add to the pool.
+        /// * `token2` (Bucket) - A bucket containing the amount of the second token to add to the pool.
+        /// * `lower_tick` (i32) - The tick at which the price range of the position starts.
+        /// * `upper_tick` (i32) - The tick at which the price range of the position ends.
+        /// 
+        /// # Returns:
+        /// 
+        /// * `Bucket` - A bucket of the remaining tokens of the first token type (sorted).
+        /// * `Bucket` - A bucket of the remaining tokens of the second token type (sorted).
+        /// * `Bucket` - A bucket of the position NFT issued to the liquidity provider.
+        pub fn add_concentrated_liquidity(
+            &mut self,
+            token1: Bucket,
+            token2: Bucket,
+            lower_tick: i32,
+            upper_tick: i32
+        ) -> (Bucket, Bucket, Bucket) {
+            // Checking if there does exist a concentrated liquidity pool for the given pair of tokens
+            self.assert_pool_exists(
+                token1.resource_address(), token2.resource_address(), 
+                String::from("DEX Add Concentrated Liquidity")
+            );
+            let sorted_addresses: (ResourceAddress, ResourceAddress) = sort_addresses(
+                token1.resource_address(), 
+                token2.resource_address()
+            );
+            match &self.liquidity_pools[&sorted_addresses] {
+                Pool::Concentrated(liquidity_pool) => {
+                    liquidity_pool.add_liquidity(token1, token2, lower_tick, upper_tick)
+                }
+                Pool::ConstantProduct(_) => {
+                    panic!(
+                        "[DEX Add Concentrated Liquidity]: The pool for {:?} is a constant product liquidity pool.", 
+                        sorted_addresses
+                    );
+                }
+            }
+        }
+
+        /// Gets the kind of the liquidity pool of the given address pair.
+        /// 
+        /// This method performs a number of checks before the kind is obtained:
+        /// 
+        /// * **Check 1:** Checks that there does exist a liquidity pool for the given pair of tokens.
+        /// 
+        /// # Arguments:
+        /// 
+        /// * `address1` (ResourceAddress) - The resource address of the first token.
+        /// * `address2` (ResourceAddress) - The resource address of the second token.
+        /// 
+        /// # Returns:
+        /// 
+        /// * `PoolKind` - The kind of the liquidity pool.
+        pub fn pool_kind(
+            &self,
+            address1: ResourceAddress,
+            address2: ResourceAddress
+        ) -> PoolKind {
+            self.assert_pool_exists(address1, address2, String::from("DEX Pool Kind"));
+            return self.liquidity_pools[&sort_addresses(address1, address2)].kind();
+        }
+
         /// Removes liquidity from the appropriate liquidity pool in the DEX.
         /// 
         /// The main use of this method is to remove liquidity from one of the liquidity pools in the DEX and return 
@@ -215,7 +339,8 @@ blueprint!{
         /// # Arguments:
         /// 
         /// * `tracking_tokens` (Bucket) - A bucket of the tracking tokens that the liquidity provider wishes to 
-        /// exchange for their share of the liquidity.
+        /// exchange for their share of the liquidity. For concentrated liquidity pools, this is a bucket of a single 
+        /// position NFT.
         /// 
         /// # Returns:
         /// 
@@ -344,5 +469,360 @@ blueprint!{
             );
             return self.liquidity_pools[&sorted_addresses].swap_tokens_for_exact_tokens(tokens, output_amount);
         }
+
+        /// Gets the time weighted average price of a token from the liquidity pool of the given address pair.
+        /// 
+        /// This method allows other blueprints to use RaDEX as a price oracle. The price is read from the price 
+        /// accumulators of the liquidity pool of the two tokens, which makes it resistant to manipulation within a 
+        /// single transaction or epoch.
+        /// 
+        /// This method performs a number of checks before the price is obtained:
+        /// 
+        /// * **Check 1:** Checks that there does exist a liquidity pool for the given pair of tokens.
+        /// * **Check 2:** Checks that the liquidity pool is a constant product pool. Concentrated liquidity pools do 
+        /// not keep price accumulators.
+        /// 
+        /// # Arguments:
+        /// 
+        /// * `base_resource_address` (ResourceAddress) - The resource address of the token to get the price of.
+        /// * `quote_resource_address` (ResourceAddress) - The resource address of the token the price is given in.
+        /// * `start_epoch` (u64) - The epoch at which the window starts.
+        /// * `end_epoch` (u64) - The epoch at which the window ends.
+        /// 
+        /// # Returns:
+        /// 
+        /// * `Decimal` - The time weighted average price of the base token in terms of the quote token.
+        pub fn get_twap(
+            &self,
+            base_resource_address: ResourceAddress,
+            quote_resource_address: ResourceAddress,
+            start_epoch: u64,
+            end_epoch: u64
+        ) -> Decimal {
+            // Checking if there does exist a liquidity pool for the given pair of tokens
+            self.assert_pool_exists(base_resource_address, quote_resource_address, String::from("DEX TWAP"));
+
+            let sorted_addresses: (ResourceAddress, ResourceAddress) = sort_addresses(
+                base_resource_address, 
+                quote_resource_address
+            );
+            let twap: Option<Decimal> = self.liquidity_pools[&sorted_addresses].get_twap(
+                base_resource_address, 
+                start_epoch, 
+                end_epoch
+            );
+            assert!(
+                twap.is_some(), 
+                "[DEX TWAP]: The pool for {:?} is a concentrated liquidity pool which does not keep price accumulators. \
+                TWAPs are only available for constant product liquidity pools.", 
+                sorted_addresses
+            );
+            return twap.unwrap();
+        }
+
+        /// Finds the route through the liquidity pools which gives the most output for the given input.
+        /// 
+        /// This method performs a depth first search over the liquidity pools registered in RaDEX starting from the 
+        /// input token and going through at most `MAX_ROUTE_HOPS` pools. At every hop, only the pools of the current 
+        /// token are looked at through the `adjacent_tokens` hashmap. Every path that ends at the output token is
+        /// simulated using the `try_calculate_output_amount` method of the pools along the path and the path that 
+        /// results in the largest amount of output is returned. A path never visits the same token twice, and pools 
+        /// which do not have enough liquidity for the amount reaching them (thin concentrated liquidity pools) are 
+        /// skipped.
+        /// 
+        /// # Arguments:
+        /// 
+        /// * `input_resource_address` (ResourceAddress) - The resource address of the input token.
+        /// * `output_resource_address` (ResourceAddress) - The resource address of the output token.
+        /// * `input_amount` (Decimal) - The amount of input tokens to find the best route for.
+        /// 
+        /// # Returns:
+        /// 
+        /// * `Option<(Vec<ResourceAddress>, Decimal)>` - If a route exists, a tuple of the path of resource addresses
+        /// (starting with the input and ending with the output) and the amount of output expected from this path. 
+        /// `None` if the two tokens are not connected through any of the liquidity pools with enough liquidity.
+        pub fn find_best_route(
+            &self,
+            input_resource_address: ResourceAddress,
+            output_resource_address: ResourceAddress,
+            input_amount: Decimal
+        ) -> Option<(Vec<ResourceAddress>, Decimal)> {
+            let mut best_route: Option<(Vec<ResourceAddress>, Decimal)> = None;
+            let mut path: Vec<ResourceAddress> = vec![input_resource_address];
+            self.search_routes_for_output(output_resource_address, input_amount, &mut path, &mut best_route);
+            return best_route;
+        }
+
+        /// Finds the route through the liquidity pools which requires the least input for the given output.
+        /// 
+        /// This method is the mirror image of the `find_best_route` method. The search starts from the output token and 
+        /// walks backwards through at most `MAX_ROUTE_HOPS` pools, using the `try_calculate_input_amount` method of the 
+        /// pools to find the amount of input needed at every step. Pools which do not have enough reserves to give the 
+        /// required amount are skipped. The path which requires the smallest amount of the input token is returned.
+        /// 
+        /// # Arguments:
+        /// 
+        /// * `input_resource_address` (ResourceAddress) - The resource address of the input token.
+        /// * `output_resource_address` (ResourceAddress) - The resource address of the output token.
+        /// * `output_amount` (Decimal) - The amount of output tokens to find the best route for.
+        /// 
+        /// # Returns:
+        /// 
+        /// * `Option<(Vec<ResourceAddress>, Decimal)>` - If a route exists, a tuple of the path of resource addresses
+        /// (starting with the input and ending with the output) and the amount of input required by this path. `None`
+        /// if the two tokens are not connected through any of the liquidity pools with enough liquidity.
+        pub fn find_best_route_for_exact_tokens(
+            &self,
+            input_resource_address: ResourceAddress,
+            output_resource_address: ResourceAddress,
+            output_amount: Decimal
+        ) -> Option<(Vec<ResourceAddress>, Decimal)> {
+            let mut best_route: Option<(Vec<ResourceAddress>, Decimal)> = None;
+            let mut path: Vec<ResourceAddress> = vec![output_resource_address];
+            self.search_routes_for_input(input_resource_address, output_amount, &mut path, &mut best_route);
+
+            // The search is done backwards from the output, so the path needs to be reversed to start from the input.
+            return best_route.map(|(mut path, amount)| {
+                path.reverse();
+                (path, amount)
+            });
+        }
+
+        /// Swaps the exact amount of input tokens for tokens of the desired type through the best available route.
+        /// 
+        /// This method is used when there might not be a direct liquidity pool between the input and the output tokens
+        /// or when going through intermediate pools gives a better rate than the direct pool. The best route is found 
+        /// through the `find_best_route` method and the tokens are swapped through all of the pools along the route 
+        /// in a single call. The `min_amount_out` slippage bound applies to the final output of the entire route and 
+        /// not to the individual hops.
+        /// 
+        /// This method performs a number of checks before the swap is performed:
+        /// 
+        /// * **Check 1:** Checks that the input and output tokens are not the same.
+        /// * **Check 2:** Checks that there exists a route between the input and the output tokens.
+        /// 
+        /// # Arguments:
+        /// 
+        /// * `tokens` (Bucket) - A bucket containing the input tokens that will be swapped for other tokens.
+        /// * `output_resource_address` (ResourceAddress) - The resource address of the token to receive from the swap.
+        /// * `min_amount_out` (Decimal) - The minimum amount of output tokens that the caller is willing to accept 
+        /// from the whole route before the method fails.
+        /// 
+        /// # Returns:
+        /// 
+        /// * `Bucket` - A bucket of the other tokens.
+        /// * `Vec<ResourceAddress>` - The path of resource addresses that the swap went through.
+        pub fn routed_swap_exact_tokens_for_tokens(
+            &mut self,
+            tokens: Bucket,
+            output_resource_address: ResourceAddress,
+            min_amount_out: Decimal
+        ) -> (Bucket, Vec<ResourceAddress>) {
+            // Checking that the swap is between two different tokens and that there exists a route between them.
+            assert_ne!(
+                tokens.resource_address(), output_resource_address,
+                "[DEX Routed Swap Exact]: The input and output tokens must be different."
+            );
+            let route: Option<(Vec<ResourceAddress>, Decimal)> = self.find_best_route(
+                tokens.resource_address(), 
+                output_resource_address, 
+                tokens.amount()
+            );
+            assert!(route.is_some(), "[DEX Routed Swap Exact]: No route exists between the given tokens.");
+            let (path, _): (Vec<ResourceAddress>, Decimal) = route.unwrap();
+            info!("[DEX Routed Swap Exact]: Swapping through the path: {:?}", path);
+
+            // Swapping the tokens through each of the liquidity pools along the path. If the final amount does not
+            // satisfy the `min_amount_out` then all of the swaps along the route are reverted.
+            let mut output_tokens: Bucket = tokens;
+            for i in 0..path.len() - 1 {
+                let sorted_addresses: (ResourceAddress, ResourceAddress) = sort_addresses(path[i], path[i + 1]);
+                output_tokens = self.liquidity_pools[&sorted_addresses].swap(output_tokens);
+            }
+            assert!(
+                output_tokens.amount() >= min_amount_out, 
+                "[DEX Routed Swap Exact]: min_amount_out not satisfied."
+            );
+
+            return (output_tokens, path);
+        }
+
+        /// Swaps the input tokens for a specific amount of tokens of the desired type through the best available route.
+        /// 
+        /// This method finds the route which requires the least amount of input to produce the requested amount of 
+        /// output through the `find_best_route_for_exact_tokens` method. The amount of tokens in the `tokens` bucket 
+        /// acts as the slippage bound of the whole route: if the route requires more input than was provided then the 
+        /// swap fails. Otherwise, the required input is swapped through every pool along the path and the remaining 
+        /// input tokens are returned back to the caller.
+        /// 
+        /// This method performs a number of checks before the swap is performed:
+        /// 
+        /// * **Check 1:** Checks that the input and output tokens are not the same.
+        /// * **Check 2:** Checks that there exists a route between the input and the output tokens.
+        /// * **Check 3:** Checks that the input provided is enough for the best route.
+        /// 
+        /// # Arguments:
+        /// 
+        /// * `tokens` (Bucket) - A bucket containing the input tokens that will be swapped for other tokens.
+        /// * `output_resource_address` (ResourceAddress) - The resource address of the token to receive from the swap.
+        /// * `output_amount` (Decimal) - A decimal of the specific amount of output that the user wishes to receive 
+        /// from this swap.
+        /// 
+        /// # Returns:
+        /// 
+        /// * `Bucket` - A bucket of the other tokens.
+        /// * `Bucket` - A bucket of the remaining input tokens.
+        /// * `Vec<ResourceAddress>` - The path of resource addresses that the swap went through.
+        pub fn routed_swap_tokens_for_exact_tokens(
+            &mut self,
+            tokens: Bucket,
+            output_resource_address: ResourceAddress,
+            output_amount: Decimal
+        ) -> (Bucket, Bucket, Vec<ResourceAddress>) {
+            // Checking that the swap is between two different tokens and that there exists a route between them.
+            assert_ne!(
+                tokens.resource_address(), output_resource_address,
+                "[DEX Routed Swap For Exact]: The input and output tokens must be different."
+            );
+            let route: Option<(Vec<ResourceAddress>, Decimal)> = self.find_best_route_for_exact_tokens(
+                tokens.resource_address(), 
+                output_resource_address, 
+                output_amount
+            );
+            assert!(route.is_some(), "[DEX Routed Swap For Exact]: No route exists between the given tokens.");
+            let (path, input_required): (Vec<ResourceAddress>, Decimal) = route.unwrap();
+            assert!(
+                tokens.amount() >= input_required,
+                "[DEX Routed Swap For Exact]: Not enough input for the desired amount of output."
+            );
+            info!("[DEX Routed Swap For Exact]: Swapping through the path: {:?}", path);
+
+            // Working backwards from the output amount to find the exact amount of tokens that each of the hops along
+            // the path needs to output.
+            let mut hop_outputs: Vec<Decimal> = vec![output_amount];
+            for i in (1..path.len() - 1).rev() {
+                let sorted_addresses: (ResourceAddress, ResourceAddress) = sort_addresses(path[i], path[i + 1]);
+                let hop_input: Decimal = self.liquidity_pools[&sorted_addresses].calculate_input_amount(
+                    path[i + 1], 
+                    hop_outputs[0]
+                );
+                hop_outputs.insert(0, hop_input);
+            }
+
+            // Performing the swaps. The first hop returns the remaining input tokens of the caller. The intermediate 
+            // hops are given exactly the amount that they require, so they have nothing remaining.
+            let sorted_addresses: (ResourceAddress, ResourceAddress) = sort_addresses(path[0], path[1]);
+            let (mut output_tokens, remaining_tokens): (Bucket, Bucket) = self.liquidity_pools[&sorted_addresses]
+                .swap_tokens_for_exact_tokens(tokens, hop_outputs[0]);
+            for i in 1..path.len() - 1 {
+                let sorted_addresses: (ResourceAddress, ResourceAddress) = sort_addresses(path[i], path[i + 1]);
+                let (hop_output, hop_remaining): (Bucket, Bucket) = self.liquidity_pools[&sorted_addresses]
+                    .swap_tokens_for_exact_tokens(output_tokens, hop_outputs[i]);
+                assert!(
+                    hop_remaining.is_empty(), 
+                    "[DEX Routed Swap For Exact]: Intermediate hop did not consume all of its input."
+                );
+                output_tokens = hop_output;
+            }
+
+            return (output_tokens, remaining_tokens, path);
+        }
+
+        /// Recursively searches for the route which gives the most output for the current amount of tokens.
+        /// 
+        /// # Arguments:
+        /// 
+        /// * `output_resource_address` (ResourceAddress) - The resource address of the token at the end of the route.
+        /// * `amount` (Decimal) - The amount of tokens of the last resource in the `path`.
+        /// * `path` (&mut Vec<ResourceAddress>) - The path of resource addresses visited so far.
+        /// * `best_route` (&mut Option<(Vec<ResourceAddress>, Decimal)>) - The best route found so far.
+        fn search_routes_for_output(
+            &self,
+            output_resource_address: ResourceAddress,
+            amount: Decimal,
+            path: &mut Vec<ResourceAddress>,
+            best_route: &mut Option<(Vec<ResourceAddress>, Decimal)>
+        ) {
+            let current_address: ResourceAddress = *path.last().unwrap();
+            let adjacent_tokens: &Vec<ResourceAddress> = match self.adjacent_tokens.get(&current_address) {
+                Some(adjacent_tokens) => adjacent_tokens,
+                None => return
+            };
+            for next_address in adjacent_tokens.iter().copied() {
+                // Only the pools that lead to a token we have not visited are considered.
+                if path.contains(&next_address) {
+                    continue;
+                }
+                let liquidity_pool: &Pool = &self.liquidity_pools[&sort_addresses(current_address, next_address)];
+
+                // Concentrated liquidity pools may not have enough liquidity in their active ranges for the amount, 
+                // such pools are skipped instead of failing the whole search.
+                let next_amount: Decimal = match liquidity_pool.try_calculate_output_amount(current_address, amount) {
+                    Some(next_amount) => next_amount,
+                    None => continue
+                };
+                path.push(next_address);
+                if next_address == output_resource_address {
+                    let is_better: bool = match best_route {
+                        Some((_, best_amount)) => next_amount > *best_amount,
+                        None => true
+                    };
+                    if is_better {
+                        *best_route = Some((path.clone(), next_amount));
+                    }
+                } else if path.len() - 1 < MAX_ROUTE_HOPS {
+                    self.search_routes_for_output(output_resource_address, next_amount, path, best_route);
+                }
+                path.pop();
+            }
+        }
+
+        /// Recursively searches, backwards from the output, for the route which requires the least input.
+        /// 
+        /// # Arguments:
+        /// 
+        /// * `input_resource_address` (ResourceAddress) - The resource address of the token at the start of the route.
+        /// * `amount` (Decimal) - The amount of tokens of the last resource in the `path` that is required.
+        /// * `path` (&mut Vec<ResourceAddress>) - The reversed path of resource addresses visited so far.
+        /// * `best_route` (&mut Option<(Vec<ResourceAddress>, Decimal)>) - The best reversed route found so far.
+        fn search_routes_for_input(
+            &self,
+            input_resource_address: ResourceAddress,
+            amount: Decimal,
+            path: &mut Vec<ResourceAddress>,
+            best_route: &mut Option<(Vec<ResourceAddress>, Decimal)>
+        ) {
+            let current_address: ResourceAddress = *path.last().unwrap();
+            let adjacent_tokens: &Vec<ResourceAddress> = match self.adjacent_tokens.get(&current_address) {
+                Some(adjacent_tokens) => adjacent_tokens,
+                None => return
+            };
+            for previous_address in adjacent_tokens.iter().copied() {
+                if path.contains(&previous_address) {
+                    continue;
+                }
+                let liquidity_pool: &Pool = &self.liquidity_pools[&sort_addresses(current_address, previous_address)];
+
+                // A pool can never give out all (or more) of its reserves, so pools without enough liquidity are 
+                // skipped.
+                let previous_amount: Decimal = match liquidity_pool.try_calculate_input_amount(current_address, amount) {
+                    Some(previous_amount) => previous_amount,
+                    None => continue
+                };
+                path.push(previous_address);
+                if previous_address == input_resource_address {
+                    let is_better: bool = match best_route {
+                        Some((_, best_amount)) => previous_amount < *best_amount,
+                        None => true
+                    };
+                    if is_better {
+                        *best_route = Some((path.clone(), previous_amount));
+                    }
+                } else if path.len() - 1 < MAX_ROUTE_HOPS {
+                    self.search_routes_for_input(input_resource_address, previous_amount, path, best_route);
+                }
+                path.pop();
+            }
+        }
     }
 }
\ No newline at end of file
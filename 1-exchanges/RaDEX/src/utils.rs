@@ -90,4 +90,34 @@ pub fn address_pair_symbol(address1: ResourceAddress, address2: ResourceAddress)
 
     // Format the names and return them.
     return format!("{}-{}", names.0, names.1);
+}
+
+/// Calculates the square root of the given decimal number.
+/// 
+/// This function uses Newton's method to calculate the square root of the given number. The iterations stop once the
+/// approximation stops changing or after a fixed number of iterations, whichever comes first.
+/// 
+/// # Arguments:
+/// 
+/// * `value` (Decimal) - The number to calculate the square root of. This must not be negative.
+/// 
+/// # Returns:
+/// 
+/// `Decimal` - The square root of the given number.
+pub fn sqrt(value: Decimal) -> Decimal {
+    assert!(value >= Decimal::zero(), "[Square Root]: Can not calculate the square root of a negative number.");
+    if value == Decimal::zero() {
+        return Decimal::zero();
+    }
+
+    // Starting from a guess which is always larger than the square root so that the approximations decrease towards it
+    let mut approximation: Decimal = if value > Decimal::one() { value } else { Decimal::one() };
+    for _ in 0..256 {
+        let next_approximation: Decimal = (approximation + value / approximation) / dec!("2");
+        if next_approximation >= approximation {
+            break;
+        }
+        approximation = next_approximation;
+    }
+    return approximation;
 }
\ No newline at end of file
@@ -0,0 +1,574 @@
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+/// Mirrors the `PoolKind` enum of the package so that it can be passed as an argument to `new_liquidity_pool`.
+#[derive(TypeId, Encode, Decode)]
+#[allow(dead_code)]
+enum PoolKind {
+    ConstantProduct,
+    Concentrated,
+}
+
+/// Creates a new fungible token with the given symbol and deposits its initial supply into the given account.
+fn new_token(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    pk: EcdsaPublicKey,
+    sk: &EcdsaPrivateKey,
+    account: ComponentAddress,
+    symbol: &str,
+) -> ResourceAddress {
+    let mut metadata: HashMap<String, String> = HashMap::new();
+    metadata.insert("symbol".to_owned(), symbol.to_owned());
+
+    let transaction = TransactionBuilder::new()
+        .new_token_fixed(metadata, dec!("1000000"))
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    assert!(receipt.result.is_ok());
+    receipt.new_resource_addresses[0]
+}
+
+/// Creates a new constant product liquidity pool on the given RaDEX component from the given amounts of the two tokens.
+fn new_liquidity_pool(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    pk: EcdsaPublicKey,
+    sk: &EcdsaPrivateKey,
+    account: ComponentAddress,
+    radex: ComponentAddress,
+    token1: (ResourceAddress, Decimal),
+    token2: (ResourceAddress, Decimal),
+) {
+    let transaction = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(token1.1, token1.0, account)
+        .withdraw_from_account_by_amount(token2.1, token2.0, account)
+        .take_from_worktop(token1.0, |builder, bucket1_id| {
+            builder.take_from_worktop(token2.0, |builder, bucket2_id| {
+                builder.call_method(
+                    radex,
+                    "add_liquidity",
+                    args![
+                        scrypto::resource::Bucket(bucket1_id),
+                        scrypto::resource::Bucket(bucket2_id)
+                    ],
+                )
+            })
+        })
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    assert!(receipt.result.is_ok());
+}
+
+/// Sets up a RaDEX component with the pools `A/XRD`, `XRD/B` and `B/C`. There are no direct pools between `A` and `B`
+/// or between `A` and `C`, so swapping between them needs two and three hops respectively.
+fn setup() -> (
+    TransactionExecutor<'static, InMemorySubstateStore>,
+    EcdsaPublicKey,
+    EcdsaPrivateKey,
+    ComponentAddress,
+    ComponentAddress,
+    (ResourceAddress, ResourceAddress, ResourceAddress),
+) {
+    let ledger: &'static mut InMemorySubstateStore = Box::leak(Box::new(InMemorySubstateStore::with_bootstrap()));
+    let mut executor = TransactionExecutor::new(ledger, false);
+    let (pk, sk, account) = executor.new_account();
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let transaction = TransactionBuilder::new()
+        .call_function(package, "RaDEX", "new", args![])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    assert!(receipt.result.is_ok());
+    let radex = receipt.new_component_addresses[0];
+
+    let token_a = new_token(&mut executor, pk, &sk, account, "A");
+    let token_b = new_token(&mut executor, pk, &sk, account, "B");
+    let token_c = new_token(&mut executor, pk, &sk, account, "C");
+
+    new_liquidity_pool(&mut executor, pk, &sk, account, radex, (token_a, dec!("1000")), (RADIX_TOKEN, dec!("2000")));
+    new_liquidity_pool(&mut executor, pk, &sk, account, radex, (RADIX_TOKEN, dec!("2000")), (token_b, dec!("500")));
+    new_liquidity_pool(&mut executor, pk, &sk, account, radex, (token_b, dec!("500")), (token_c, dec!("5000")));
+
+    (executor, pk, sk, account, radex, (token_a, token_b, token_c))
+}
+
+/// Calculates the output of a constant product pool with a 0.3% fee the same way as `calculate_output_amount`.
+fn output_amount(x: Decimal, y: Decimal, dx: Decimal) -> Decimal {
+    let r: Decimal = (dec!("100") - dec!("0.3")) / dec!("100");
+    (dx * r * y) / (x + r * dx)
+}
+
+/// Finds the best route for swapping 10 `input` tokens for `output` tokens, returns the path and the output amount.
+fn find_best_route(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    pk: EcdsaPublicKey,
+    sk: &EcdsaPrivateKey,
+    radex: ComponentAddress,
+    input: ResourceAddress,
+    output: ResourceAddress,
+) -> Option<(Vec<ResourceAddress>, Decimal)> {
+    let transaction = TransactionBuilder::new()
+        .call_method(radex, "find_best_route", args![input, output, dec!("10")])
+        .build(executor.get_nonce([pk]))
+        .sign([sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+    scrypto_decode(&receipt.outputs[0].raw).unwrap()
+}
+
+/// Swaps an exact amount of `input` for `output` through the router with the given minimum amount out. Returns the
+/// path that the swap went through, or `None` if the swap failed. The minimum amount out is also asserted to be on the
+/// worktop after the swap.
+fn routed_swap_exact(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    pk: EcdsaPublicKey,
+    sk: &EcdsaPrivateKey,
+    account: ComponentAddress,
+    radex: ComponentAddress,
+    input: ResourceAddress,
+    output: ResourceAddress,
+    min_amount_out: Decimal,
+) -> Option<Vec<ResourceAddress>> {
+    let transaction = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(dec!("10"), input, account)
+        .take_from_worktop(input, |builder, bucket_id| {
+            builder.call_method(
+                radex,
+                "routed_swap_exact_tokens_for_tokens",
+                args![scrypto::resource::Bucket(bucket_id), output, min_amount_out],
+            )
+        })
+        .assert_worktop_contains_by_amount(min_amount_out, output)
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    if !receipt.result.is_ok() {
+        return None;
+    }
+    let (_, path): (scrypto::resource::Bucket, Vec<ResourceAddress>) = scrypto_decode(&receipt.outputs[2].raw).unwrap();
+    Some(path)
+}
+
+/// Swaps the given amount of `input` for `output` through the pool of the two tokens.
+fn swap(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    pk: EcdsaPublicKey,
+    sk: &EcdsaPrivateKey,
+    account: ComponentAddress,
+    radex: ComponentAddress,
+    input: (ResourceAddress, Decimal),
+    output: ResourceAddress,
+) {
+    let transaction = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(input.1, input.0, account)
+        .take_from_worktop(input.0, |builder, bucket_id| {
+            builder.call_method(radex, "swap", args![scrypto::resource::Bucket(bucket_id), output])
+        })
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+}
+
+/// Gets the time weighted average price of `base` in terms of `quote` over the given window of epochs.
+fn get_twap(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    pk: EcdsaPublicKey,
+    sk: &EcdsaPrivateKey,
+    radex: ComponentAddress,
+    base: ResourceAddress,
+    quote: ResourceAddress,
+    window: (u64, u64),
+) -> Decimal {
+    let transaction = TransactionBuilder::new()
+        .call_method(radex, "get_twap", args![base, quote, window.0, window.1])
+        .build(executor.get_nonce([pk]))
+        .sign([sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+    scrypto_decode(&receipt.outputs[0].raw).unwrap()
+}
+
+/// Creates a new concentrated liquidity pool on the given RaDEX component and returns the resource address of its 
+/// position NFTs. The creator is given the full range position with the id `0`.
+fn new_concentrated_pool(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    pk: EcdsaPublicKey,
+    sk: &EcdsaPrivateKey,
+    account: ComponentAddress,
+    radex: ComponentAddress,
+    token1: (ResourceAddress, Decimal),
+    token2: (ResourceAddress, Decimal),
+) -> ResourceAddress {
+    let transaction = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(token1.1, token1.0, account)
+        .withdraw_from_account_by_amount(token2.1, token2.0, account)
+        .take_from_worktop(token1.0, |builder, bucket1_id| {
+            builder.take_from_worktop(token2.0, |builder, bucket2_id| {
+                builder.call_method(
+                    radex,
+                    "new_liquidity_pool",
+                    args![
+                        scrypto::resource::Bucket(bucket1_id),
+                        scrypto::resource::Bucket(bucket2_id),
+                        PoolKind::Concentrated
+                    ],
+                )
+            })
+        })
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+
+    // The pool creates its position admin badge first and then the position NFT resource.
+    receipt.new_resource_addresses[1]
+}
+
+/// Adds liquidity over the given tick range to a concentrated liquidity pool. The position NFT and the remaining 
+/// tokens are deposited back into the account.
+fn add_concentrated_liquidity(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    pk: EcdsaPublicKey,
+    sk: &EcdsaPrivateKey,
+    account: ComponentAddress,
+    radex: ComponentAddress,
+    token1: (ResourceAddress, Decimal),
+    token2: (ResourceAddress, Decimal),
+    ticks: (i32, i32),
+) {
+    let transaction = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(token1.1, token1.0, account)
+        .withdraw_from_account_by_amount(token2.1, token2.0, account)
+        .take_from_worktop(token1.0, |builder, bucket1_id| {
+            builder.take_from_worktop(token2.0, |builder, bucket2_id| {
+                builder.call_method(
+                    radex,
+                    "add_concentrated_liquidity",
+                    args![
+                        scrypto::resource::Bucket(bucket1_id),
+                        scrypto::resource::Bucket(bucket2_id),
+                        ticks.0,
+                        ticks.1
+                    ],
+                )
+            })
+        })
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+}
+
+/// Removes the liquidity of the position with the given id and checks that at least the given amount of `token` is
+/// paid out for it.
+fn remove_position(
+    executor: &mut TransactionExecutor<InMemorySubstateStore>,
+    pk: EcdsaPublicKey,
+    sk: &EcdsaPrivateKey,
+    account: ComponentAddress,
+    radex: ComponentAddress,
+    position_address: ResourceAddress,
+    position_id: u64,
+    token: (ResourceAddress, Decimal),
+) -> bool {
+    let mut ids: BTreeSet<NonFungibleId> = BTreeSet::new();
+    ids.insert(NonFungibleId::from_u64(position_id));
+
+    let transaction = TransactionBuilder::new()
+        .withdraw_from_account_by_ids(&ids, position_address, account)
+        .take_from_worktop_by_ids(&ids, position_address, |builder, bucket_id| {
+            builder.call_method(radex, "remove_liquidity", args![scrypto::resource::Bucket(bucket_id)])
+        })
+        .assert_worktop_contains_by_amount(token.1, token.0)
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    receipt.result.is_ok()
+}
+
+#[test]
+fn routed_swap_exact_through_two_hops() {
+    let (mut executor, pk, sk, account, radex, (token_a, token_b, _)) = setup();
+
+    // 10 A -> ~19.7 XRD -> ~4.87 B through the A/XRD and XRD/B pools.
+    let xrd_out: Decimal = output_amount(dec!("1000"), dec!("2000"), dec!("10"));
+    let b_out: Decimal = output_amount(dec!("2000"), dec!("500"), xrd_out);
+    let path: Vec<ResourceAddress> = vec![token_a, RADIX_TOKEN, token_b];
+    assert!(b_out > dec!("4.87") && b_out < dec!("4.88"));
+    assert_eq!(find_best_route(&mut executor, pk, &sk, radex, token_a, token_b), Some((path.clone(), b_out)));
+
+    // The whole quoted amount is received, so it can be used as the minimum amount out.
+    let route = routed_swap_exact(&mut executor, pk, &sk, account, radex, token_a, token_b, b_out);
+    assert_eq!(route, Some(path));
+}
+
+#[test]
+fn routed_swap_exact_through_three_hops() {
+    let (mut executor, pk, sk, account, radex, (token_a, _, token_c)) = setup();
+
+    // 10 A -> ~19.7 XRD -> ~4.87 B -> ~48.1 C through the A/XRD, XRD/B and B/C pools.
+    let xrd_out: Decimal = output_amount(dec!("1000"), dec!("2000"), dec!("10"));
+    let b_out: Decimal = output_amount(dec!("2000"), dec!("500"), xrd_out);
+    let c_out: Decimal = output_amount(dec!("500"), dec!("5000"), b_out);
+    let path: Vec<ResourceAddress> = vec![token_a, RADIX_TOKEN, token_b, token_c];
+    assert!(c_out > dec!("48.1") && c_out < dec!("48.2"));
+    assert_eq!(find_best_route(&mut executor, pk, &sk, radex, token_a, token_c), Some((path.clone(), c_out)));
+
+    let route = routed_swap_exact(&mut executor, pk, &sk, account, radex, token_a, token_c, c_out);
+    assert_eq!(route, Some(path));
+}
+
+#[test]
+fn routed_swap_exact_fails_when_route_output_is_below_minimum() {
+    let (mut executor, pk, sk, account, radex, (token_a, _, token_c)) = setup();
+
+    // The slippage bound applies to the output of the whole route and not to the individual hops.
+    let route = routed_swap_exact(&mut executor, pk, &sk, account, radex, token_a, token_c, dec!("50"));
+    assert_eq!(route, None);
+}
+
+#[test]
+fn routed_swap_exact_fails_when_no_route_exists() {
+    let (mut executor, pk, sk, account, radex, (token_a, _, _)) = setup();
+    let token_d = new_token(&mut executor, pk, &sk, account, "D");
+
+    assert_eq!(find_best_route(&mut executor, pk, &sk, radex, token_a, token_d), None);
+    let route = routed_swap_exact(&mut executor, pk, &sk, account, radex, token_a, token_d, dec!("0"));
+    assert_eq!(route, None);
+}
+
+#[test]
+fn routed_swap_exact_fails_when_route_is_too_long() {
+    let (mut executor, pk, sk, account, radex, (token_a, _, token_c)) = setup();
+    let token_d = new_token(&mut executor, pk, &sk, account, "D");
+    new_liquidity_pool(&mut executor, pk, &sk, account, radex, (token_c, dec!("5000")), (token_d, dec!("5000")));
+
+    // A -> XRD -> B -> C -> D goes through four pools, one more than the router allows.
+    assert_eq!(find_best_route(&mut executor, pk, &sk, radex, token_a, token_d), None);
+    let route = routed_swap_exact(&mut executor, pk, &sk, account, radex, token_a, token_d, dec!("0"));
+    assert_eq!(route, None);
+}
+
+#[test]
+fn routed_swap_for_exact_through_three_hops() {
+    let (mut executor, pk, sk, account, radex, (token_a, _, token_c)) = setup();
+
+    // Getting exactly 40 C should need a little over 8 A, so 10 A is enough and the rest is returned.
+    let transaction = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(dec!("10"), token_a, account)
+        .take_from_worktop(token_a, |builder, bucket_id| {
+            builder.call_method(
+                radex,
+                "routed_swap_tokens_for_exact_tokens",
+                args![scrypto::resource::Bucket(bucket_id), token_c, dec!("40")],
+            )
+        })
+        .assert_worktop_contains_by_amount(dec!("40"), token_c)
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+    let (_, _, path): (scrypto::resource::Bucket, scrypto::resource::Bucket, Vec<ResourceAddress>) =
+        scrypto_decode(&receipt.outputs[2].raw).unwrap();
+    assert_eq!(path, vec![token_a, RADIX_TOKEN, token_b, token_c]);
+}
+
+#[test]
+fn routed_swap_for_exact_fails_when_input_is_not_enough() {
+    let (mut executor, pk, sk, account, radex, (token_a, token_b, _)) = setup();
+
+    // 10 A can only buy around 4.87 B through the route, so asking for 6 B must fail.
+    let transaction = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(dec!("10"), token_a, account)
+        .take_from_worktop(token_a, |builder, bucket_id| {
+            builder.call_method(
+                radex,
+                "routed_swap_tokens_for_exact_tokens",
+                args![scrypto::resource::Bucket(bucket_id), token_b, dec!("6")],
+            )
+        })
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(!receipt.result.is_ok());
+}
+
+#[test]
+fn concentrated_position_is_burned_for_its_deposit() {
+    let (mut executor, pk, sk, account, radex, _) = setup();
+    let token_x = new_token(&mut executor, pk, &sk, account, "X");
+    let token_y = new_token(&mut executor, pk, &sk, account, "Y");
+    let position_address = new_concentrated_pool(
+        &mut executor, pk, &sk, account, radex, (token_x, dec!("1000")), (token_y, dec!("1000"))
+    );
+
+    // At a price of 1, a range which is symmetric around tick 0 takes the same amount of both tokens. This mints the
+    // position with the id 1.
+    add_concentrated_liquidity(
+        &mut executor, pk, &sk, account, radex, (token_x, dec!("100")), (token_y, dec!("100")), (-1000, 1000)
+    );
+
+    // Without any swaps in between, the position is worth its deposit less rounding in the last decimal places.
+    assert!(remove_position(&mut executor, pk, &sk, account, radex, position_address, 1, (token_x, dec!("99.999"))));
+
+    // The position NFT was burned, so it can not be removed a second time.
+    assert!(!remove_position(&mut executor, pk, &sk, account, radex, position_address, 1, (token_x, dec!("0"))));
+}
+
+#[test]
+fn concentrated_swap_crosses_ticks_and_pays_fees_to_active_positions() {
+    let (mut executor, pk, sk, account, radex, _) = setup();
+    let token_x = new_token(&mut executor, pk, &sk, account, "X");
+    let token_y = new_token(&mut executor, pk, &sk, account, "Y");
+    let position_address = new_concentrated_pool(
+        &mut executor, pk, &sk, account, radex, (token_x, dec!("1000")), (token_y, dec!("1000"))
+    );
+
+    // A narrow position (id 1) over the ticks [-100, 100) and a wide position (id 2) over the ticks [-2000, 2000).
+    add_concentrated_liquidity(
+        &mut executor, pk, &sk, account, radex, (token_x, dec!("100")), (token_y, dec!("100")), (-100, 100)
+    );
+    add_concentrated_liquidity(
+        &mut executor, pk, &sk, account, radex, (token_x, dec!("100")), (token_y, dec!("100")), (-2000, 2000)
+    );
+
+    // Around 111 X are enough to push the price out of the narrow range, so 150 X cross its tick.
+    let transaction = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(dec!("150"), token_x, account)
+        .take_from_worktop(token_x, |builder, bucket_id| {
+            builder.call_method(radex, "swap", args![scrypto::resource::Bucket(bucket_id), token_y])
+        })
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+
+    // Once its tick is crossed the narrow position is made entirely out of X, which is 100 * (1 + 1.0001^50) ~ 200.50 X
+    // of liquidity. It was active for most of the swap, so it also earned ~0.30 X of the fees on top of that.
+    assert!(remove_position(&mut executor, pk, &sk, account, radex, position_address, 1, (token_x, dec!("200.7"))));
+}
+
+#[test]
+fn routed_swaps_skip_thin_concentrated_pools() {
+    let (mut executor, pk, sk, account, radex, (token_a, token_b, _)) = setup();
+
+    // An A/B pool at a price of 1 B per A would beat the ~0.49 B per A of the route through XRD, if it had liquidity.
+    let position_address = new_concentrated_pool(
+        &mut executor, pk, &sk, account, radex, (token_a, dec!("100")), (token_b, dec!("100"))
+    );
+
+    // Replacing the full range position of the creator with a narrow range that only holds about 1 A and 1 B.
+    assert!(remove_position(&mut executor, pk, &sk, account, radex, position_address, 0, (token_a, dec!("99.99"))));
+    add_concentrated_liquidity(
+        &mut executor, pk, &sk, account, radex, (token_a, dec!("1")), (token_b, dec!("1")), (-100, 100)
+    );
+
+    // 10 A run past the last tick of the concentrated pool, so the router goes through the A/XRD and XRD/B pools.
+    let route = routed_swap_exact(&mut executor, pk, &sk, account, radex, token_a, token_b, dec!("4.5"));
+    assert_eq!(route, Some(vec![token_a, RADIX_TOKEN, token_b]));
+
+    // The same applies when searching for the route backwards from an exact amount of output.
+    let transaction = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(dec!("10"), token_a, account)
+        .take_from_worktop(token_a, |builder, bucket_id| {
+            builder.call_method(
+                radex,
+                "routed_swap_tokens_for_exact_tokens",
+                args![scrypto::resource::Bucket(bucket_id), token_b, dec!("4")],
+            )
+        })
+        .assert_worktop_contains_by_amount(dec!("4"), token_b)
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+}
+
+#[test]
+fn get_twap_is_rejected_for_concentrated_pools() {
+    let (mut executor, pk, sk, account, radex, (token_a, _, _)) = setup();
+    let token_x = new_token(&mut executor, pk, &sk, account, "X");
+    let token_y = new_token(&mut executor, pk, &sk, account, "Y");
+    new_concentrated_pool(&mut executor, pk, &sk, account, radex, (token_x, dec!("1000")), (token_y, dec!("1000")));
+    executor.substate_store_mut().set_epoch(2);
+
+    // The constant product A/XRD pool keeps price accumulators over the window.
+    let transaction = TransactionBuilder::new()
+        .call_method(radex, "get_twap", args![token_a, RADIX_TOKEN, 0u64, 2u64])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+
+    // The concentrated X/Y pool does not, so the same window is rejected for it.
+    let transaction = TransactionBuilder::new()
+        .call_method(radex, "get_twap", args![token_x, token_y, 0u64, 2u64])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt = executor.validate_and_execute(&transaction).unwrap();
+    println!("{:?}\n", receipt);
+    assert!(!receipt.result.is_ok());
+}
+
+#[test]
+fn get_twap_weights_prices_by_the_epochs_they_were_held() {
+    let (mut executor, pk, sk, account, radex, (token_a, _, _)) = setup();
+    let r: Decimal = (dec!("100") - dec!("0.3")) / dec!("100");
+
+    // The A/XRD pool is created at epoch 0 with 1000 A and 2000 XRD, a price of 2 XRD per A. At epoch 2 and again at
+    // epoch 5, 100 XRD are swapped for A. The closing prices after each swap are computed the same way as the pool.
+    executor.substate_store_mut().set_epoch(2);
+    swap(&mut executor, pk, &sk, account, radex, (RADIX_TOKEN, dec!("100")), token_a);
+    let reserve_a1: Decimal = dec!("1000") - (dec!("100") * r * dec!("1000")) / (dec!("2000") + r * dec!("100"));
+    let price1: Decimal = dec!("2100") / reserve_a1;
+
+    executor.substate_store_mut().set_epoch(5);
+    swap(&mut executor, pk, &sk, account, radex, (RADIX_TOKEN, dec!("100")), token_a);
+    let reserve_a2: Decimal = reserve_a1 - (dec!("100") * r * reserve_a1) / (dec!("2100") + r * dec!("100"));
+    let price2: Decimal = dec!("2200") / reserve_a2;
+    assert!(price1 > dec!("2") && price2 > price1);
+
+    // A swap only counts towards the accumulator once its epoch is over, so a swap in the last epoch of a window does
+    // not move the price of that window.
+    executor.substate_store_mut().set_epoch(8);
+    swap(&mut executor, pk, &sk, account, radex, (RADIX_TOKEN, dec!("100")), token_a);
+
+    // 2 epochs at 2, 3 epochs at the first closing price and 3 epochs at the second.
+    let cumulative_at_5: Decimal = dec!("4") + price1 * dec!("3");
+    assert_eq!(
+        get_twap(&mut executor, pk, &sk, radex, token_a, RADIX_TOKEN, (0, 8)),
+        (cumulative_at_5 + price2 * dec!("3")) / dec!("8")
+    );
+    assert_eq!(get_twap(&mut executor, pk, &sk, radex, token_a, RADIX_TOKEN, (0, 2)), dec!("2"));
+    assert_eq!(
+        get_twap(&mut executor, pk, &sk, radex, token_a, RADIX_TOKEN, (2, 5)),
+        (cumulative_at_5 - dec!("4")) / dec!("3")
+    );
+}
@@ -13,10 +13,12 @@ blueprint! {
     struct CroqOrderBook {
         token_def: ResourceDef, // token to be buy or sell in this exchange
         cash_def: ResourceDef,  // currency used to buy or sell in this exchange
-        bid_list: Vec<(Address, Address, Decimal, Option<Vault>)>, // list of bid offers:
-                  // offer badge address, user badge address, price, Token Vault
-        ask_list: Vec<(Address, Address, Decimal, Option<Vault>)>, // list of ask offers:
-                  // offer badge address, user badge address, price, Cash Vault
+        bid_levels: BTreeMap<Decimal, Vec<(Address, Address, Vault)>>, // bid offers by price level, oldest first:
+                  // price => offer badge address, user badge address, Cash Vault
+        ask_levels: BTreeMap<Decimal, Vec<(Address, Address, Vault)>>, // ask offers by price level, oldest first:
+                  // price => offer badge address, user badge address, Token Vault
+        offer_prices: HashMap<Address, Decimal>, // price level of each open offer to find it quickly:
+                  // offer badge address, price
         user_vaults: HashMap<Address, (Vault, Vault)>, // this map hold the ressources that need to be collected:
                   // user badge address, Cash Vault, Token Vault
         dead_vault: Vec<Vault>, // just a vector of dead vault because we can't delete empty vault currenlty
@@ -28,8 +30,9 @@ blueprint! {
             Self {
                 token_def: ResourceDef::from(token),
                 cash_def: ResourceDef::from(cash),
-                bid_list: Vec::new(),
-                ask_list: Vec::new(),
+                bid_levels: BTreeMap::new(),
+                ask_levels: BTreeMap::new(),
+                offer_prices: HashMap::new(),
                 user_vaults: HashMap::new(),
                 dead_vault: Vec::new(),
             }
@@ -82,50 +85,44 @@ blueprint! {
 
             // prepare a new bucket to return tokens to the user if the order is executed immediately
             let mut ret_token_bucket = Bucket::new(self.token_def.clone());
-            // the ask_list is sorted in decreasing order of price, so we check if the last one has a selling price 
-            //  which is superior or equal to the amount our user is ready to buy
-            while !self.ask_list.is_empty() && self.ask_list[self.ask_list.len() - 1].2 <= price {
-                // if we enter in the loop it mean that our order will be at least partially filled
-                // we remove the most interesting offer from the list
-                let mut offer = self.ask_list.pop().unwrap();
-                let seller_badge = offer.1;
-                let ask_price = offer.2;
-                // we compute the amount of token which can be buyed at this price by our buyer
-                let offer_qty = cash.amount() / ask_price;
-                let vault = offer.3.as_mut().unwrap();
-                if offer_qty < vault.amount() {
-                    // if there is more tokens in the offer vault than needed
-
-                    // we add the money to the vault of the seller
-                    self.add_cash_to_user(seller_badge, cash);
-                    // we take what we need
-                    ret_token_bucket.put(vault.take(offer_qty));
-                    // we put back the offer in the list
-                    self.ask_list.push(offer);
-                    // and we return the tokens to the buyer
-                    return vec![ret_token_bucket];
-                } else if offer_qty == vault.amount() {
-                    // if there is the exact amount of token we want on the vault
-
-                    // we add the money to the vault of the seller
-                    self.add_cash_to_user(seller_badge, cash);
-                    // we take what we need (all)
-                    ret_token_bucket.put(vault.take_all());
-                    // we push the now empty vault in the list of dead_vault
-                    self.dead_vault.push(offer.3.take().unwrap());
-                    // and we return the tokens to the buyer
-                    return vec![ret_token_bucket];
+            // the ask levels are sorted in increasing order of price, so we check if the first one has a selling price
+            //  which is inferior or equal to the price our user is ready to buy at
+            while let Some(ask_price) = self.ask_levels.keys().next().copied() {
+                if ask_price > price || cash.is_empty() {
+                    break;
+                }
+                // if we arrive here it mean that our order will be at least partially filled
+                // we remove the most interesting price level from the book
+                let mut level = self.ask_levels.remove(&ask_price).unwrap();
+                // the offers of a price level are filled in the order they were made
+                while !level.is_empty() && !cash.is_empty() {
+                    let (offer_badge, seller_badge, mut vault) = level.remove(0);
+                    // we compute the amount of token which can be buyed at this price by our buyer
+                    let offer_qty = cash.amount() / ask_price;
+                    if offer_qty < vault.amount() {
+                        // if there is more tokens in the offer vault than needed
+                        // we add all the money to the vault of the seller
+                        self.add_cash_to_user(seller_badge, cash.take(cash.amount()));
+                        // we take what we need
+                        ret_token_bucket.put(vault.take(offer_qty));
+                        // and we put back the offer at the front of the level
+                        level.insert(0, (offer_badge, seller_badge, vault));
+                    } else {
+                        // if there is just enough or not enough token, we buy the whole offer
+                        let cost = if offer_qty == vault.amount() { cash.amount() } else { vault.amount() * ask_price };
+                        // we add the money to the vault of the seller
+                        self.add_cash_to_user(seller_badge, cash.take(cost));
+                        // we add all the tokens to the bucket which will be returned
+                        ret_token_bucket.put(vault.take_all());
+                        // the offer is closed, we push the now empty vault in the list of dead_vault
+                        self.offer_prices.remove(&offer_badge);
+                        self.dead_vault.push(vault);
+                    }
+                }
+                // we put back the price level if there are offers left in it
+                if !level.is_empty() {
+                    self.ask_levels.insert(ask_price, level);
                 }
-                // if there is not enough token, we arrive in this branch
-                let qty = vault.amount();
-                // we compute the cost of the whole offer
-                let cost = qty * ask_price;
-                // we add the money to the vault of the seller
-                self.add_cash_to_user(seller_badge, cash.take(cost));
-                // we add all the tokens to the bucket which will be returned
-                ret_token_bucket.put(vault.take_all());
-                // we push the now empty vault in the list of dead_vault
-                self.dead_vault.push(offer.3.take().unwrap());
             }
             if cash.amount() == Decimal::zero() {
                 return vec![ret_token_bucket];
@@ -133,15 +130,12 @@ blueprint! {
             // if we arrive here, the offer has not been completelly fullfilled
             // we create a badge for our buyer
             let badge = create_badge("bid offer");
-            // we add the offer to the bid_list
-            self.bid_list.push((
-                badge.resource_address(),
-                user_badge.resource_address(),
-                price,
-                Some(Vault::with_bucket(cash)),
-            ));
-            // we sort the list
-            self.bid_list.sort_by(|a, b| a.2.partial_cmp(&b.2).unwrap());
+            // we add the offer at the end of its price level
+            self.offer_prices.insert(badge.resource_address(), price);
+            self.bid_levels
+                .entry(price)
+                .or_insert_with(Vec::new)
+                .push((badge.resource_address(), user_badge.resource_address(), Vault::with_bucket(cash)));
             // and we return the tokens (if it has been partially filled) and offer badge
             vec![ret_token_bucket, badge]
         }
@@ -157,40 +151,39 @@ blueprint! {
             assert!(token.resource_def() == self.token_def, "wrong token type");
             assert!(price > Decimal::zero(), "negative or zero price");
             let mut ret_cash_bucket = Bucket::new(self.cash_def.clone());
-            while !self.bid_list.is_empty() && self.bid_list[self.bid_list.len() - 1].2 >= price {
-                let mut offer = self.bid_list.pop().unwrap();
-                let buyer_badge = offer.1;
-                let bid_price = offer.2;
-                let offer_cost = token.amount() * bid_price;
-                let vault = offer.3.as_mut().unwrap();
-                if offer_cost < vault.amount() {
-                    self.add_token_to_user(buyer_badge, token);
-                    ret_cash_bucket.put(vault.take(offer_cost));
-                    self.bid_list.push(offer);
-                    return vec![ret_cash_bucket];
-                } else if offer_cost == vault.amount() {
-                    self.add_token_to_user(buyer_badge, token);
-                    ret_cash_bucket.put(vault.take_all());
-                    self.dead_vault.push(offer.3.take().unwrap());
-                    return vec![ret_cash_bucket];
+            while let Some(bid_price) = self.bid_levels.keys().next_back().copied() {
+                if bid_price < price || token.is_empty() {
+                    break;
+                }
+                let mut level = self.bid_levels.remove(&bid_price).unwrap();
+                while !level.is_empty() && !token.is_empty() {
+                    let (offer_badge, buyer_badge, mut vault) = level.remove(0);
+                    let offer_cost = token.amount() * bid_price;
+                    if offer_cost < vault.amount() {
+                        self.add_token_to_user(buyer_badge, token.take(token.amount()));
+                        ret_cash_bucket.put(vault.take(offer_cost));
+                        level.insert(0, (offer_badge, buyer_badge, vault));
+                    } else {
+                        let qty = if offer_cost == vault.amount() { token.amount() } else { vault.amount() / bid_price };
+                        self.add_token_to_user(buyer_badge, token.take(qty));
+                        ret_cash_bucket.put(vault.take_all());
+                        self.offer_prices.remove(&offer_badge);
+                        self.dead_vault.push(vault);
+                    }
+                }
+                if !level.is_empty() {
+                    self.bid_levels.insert(bid_price, level);
                 }
-                let cash = vault.amount();
-                let qty = cash / bid_price;
-                self.add_token_to_user(buyer_badge, token.take(qty));
-                ret_cash_bucket.put(vault.take_all());
-                self.dead_vault.push(offer.3.take().unwrap());
             }
             if token.amount() == Decimal::zero() {
                 return vec![ret_cash_bucket];
             }
             let badge = create_badge("ask offer");
-            self.ask_list.push((
-                badge.resource_address(),
-                user_badge.resource_address(),
-                price,
-                Some(Vault::with_bucket(token)),
-            ));
-            self.ask_list.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap());
+            self.offer_prices.insert(badge.resource_address(), price);
+            self.ask_levels
+                .entry(price)
+                .or_insert_with(Vec::new)
+                .push((badge.resource_address(), user_badge.resource_address(), Vault::with_bucket(token)));
             vec![ret_cash_bucket, badge]
         }
 
@@ -198,37 +191,30 @@ blueprint! {
         pub fn cancel(&mut self, offer_badge: Bucket) -> (Bucket, Bucket) {
             let mut cash_bucket = Bucket::new(self.cash_def.clone());
             let mut token_bucket = Bucket::new(self.token_def.clone());
-            // we are looking for the offer_badge inside the bid_list
-            // a lot of the complexity come from the inability to discard empty vaults
-            // the filter allow us to keep only the offer which have the offer_badge
-            self.bid_list
-                .iter_mut()
-                .filter(|offer| offer.0 == offer_badge.resource_address())
-                .for_each(|mut offer| {
-                    // for each offer which have the offer badge we take the vault
-                    let vault_option = offer.3.take();
-                    let mut vault = vault_option.unwrap();
-                    // we empty the vault in the bucket
-                    cash_bucket.put(vault.take_all());
-                    // push the now empty vault in the dead_vault list
-                    self.dead_vault.push(vault);
-                    // set the offer price to zero (an invalid value)
-                    offer.2 = Decimal::zero();
-                });
-            // remove from the list all the offer with an invalid price
-            self.bid_list.retain(|offer| offer.2 > Decimal::zero());
-            // then we're doing the same for the ask_list
-            self.ask_list
-                .iter_mut()
-                .filter(|offer| offer.0 == offer_badge.resource_address())
-                .for_each(|mut offer| {
-                    let vault_option = offer.3.take();
-                    let mut vault = vault_option.unwrap();
-                    token_bucket.put(vault.take_all());
-                    self.dead_vault.push(vault);
-                    offer.2 = Decimal::zero();
-                });
-            self.ask_list.retain(|offer| offer.2 > Decimal::zero());
+            // we are looking for the price level of the offer, an offer which has been fullfilled is no longer indexed
+            let badge_address = offer_badge.resource_address();
+            if let Some(price) = self.offer_prices.remove(&badge_address) {
+                // the offer can be on both sides of the book at the same price, but a badge is only used for one offer
+                for (levels, bucket) in [(&mut self.bid_levels, &mut cash_bucket), (&mut self.ask_levels, &mut token_bucket)] {
+                    let level_is_empty = match levels.get_mut(&price) {
+                        Some(level) => {
+                            if let Some(index) = level.iter().position(|offer| offer.0 == badge_address) {
+                                // we empty the vault in the bucket
+                                let (_, _, mut vault) = level.remove(index);
+                                bucket.put(vault.take_all());
+                                // push the now empty vault in the dead_vault list
+                                self.dead_vault.push(vault);
+                            }
+                            level.is_empty()
+                        }
+                        None => false,
+                    };
+                    // remove the price level once its last offer is gone
+                    if level_is_empty {
+                        levels.remove(&price);
+                    }
+                }
+            }
             // the badge is now useless, we can burn it
             offer_badge.burn();
             // and we return to our caller the tokens and cash stored in the cancelled offer
@@ -274,24 +260,42 @@ blueprint! {
             info!("cash addr: {:?}", self.cash_def.address());
 
             info!("**bid**");
-            info!("floor price, quantity of tokens");
-            self.bid_list.iter().for_each(|item| {
-                info!(
-                    "{:?}, {:?}",
-                    item.2,
-                    item.3.as_ref().map_or(Decimal::zero(), |v| v.amount())
-                );
+            info!("floor price, amount of cash");
+            self.bid_levels.iter().for_each(|(price, level)| {
+                level.iter().for_each(|offer| info!("{:?}, {:?}", price, offer.2.amount()));
             });
 
             info!("**ask**");
-            info!("ceilling price, amount of cash");
-            self.ask_list.iter().for_each(|item| {
-                info!(
-                    "{:?}, {:?}",
-                    item.2,
-                    item.3.as_ref().map_or(Decimal::zero(), |v| v.amount())
-                );
+            info!("ceilling price, quantity of tokens");
+            self.ask_levels.iter().rev().for_each(|(price, level)| {
+                level.iter().for_each(|offer| info!("{:?}, {:?}", price, offer.2.amount()));
             });
         }
+
+        // this method return the aggregated depth of the book: for each side a map of price level to the total quantity
+        //  of tokens offered at that price, trimmed to the `levels` best price levels
+        pub fn depth(&self, levels: u32) -> (BTreeMap<Decimal, Decimal>, BTreeMap<Decimal, Decimal>) {
+            // the best bids are the highest prices, the cash of a bid offer is converted to a quantity of tokens
+            let bids = self
+                .bid_levels
+                .iter()
+                .rev()
+                .take(levels as usize)
+                .map(|(price, level)| {
+                    let cash = level.iter().fold(Decimal::zero(), |total, offer| total + offer.2.amount());
+                    (*price, cash / *price)
+                })
+                .collect();
+            // the best asks are the lowest prices
+            let asks = self
+                .ask_levels
+                .iter()
+                .take(levels as usize)
+                .map(|(price, level)| {
+                    (*price, level.iter().fold(Decimal::zero(), |total, offer| total + offer.2.amount()))
+                })
+                .collect();
+            (bids, asks)
+        }
     }
 }
@@ -443,6 +443,42 @@ impl<'a, L: SubstateStore> TestEnv<'a, L> {
         assert!(receipt.result.is_ok());
     }
 
+    fn depth(
+        &mut self,
+        levels: u32,
+        actor: (EcdsaPublicKey, Address),
+    ) -> (Vec<(Decimal, Decimal)>, Vec<(Decimal, Decimal)>) {
+        let receipt = self
+            .executor
+            .run(
+                TransactionBuilder::new(&self.executor)
+                    .call_method(self.instance, "depth", vec![format!("{}", levels)], Some(actor.1))
+                    .build(vec![actor.0])
+                    .unwrap(),
+            )
+            .unwrap();
+        println!("{:?}\n", receipt);
+        assert!(receipt.result.is_ok());
+        // each side is a map of price level to quantity, encoded as a flat list of alternating keys and values
+        let decode_side = |value: &Value| -> Vec<(Decimal, Decimal)> {
+            let decimals: Vec<Decimal> = match value {
+                Value::TreeMap { elements, .. } => elements
+                    .iter()
+                    .map(|element| match element {
+                        Value::Custom(kind, data) => Decimal::try_from(data.as_slice()).unwrap(),
+                        _ => Decimal::zero(),
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            decimals.chunks(2).map(|pair| (pair[0], pair[1])).collect()
+        };
+        match &receipt.outputs[receipt.outputs.len() - 1].dom {
+            Value::Tuple(elements) => (decode_side(&elements[0]), decode_side(&elements[1])),
+            _ => (Vec::new(), Vec::new()),
+        }
+    }
+
     fn check_wallet(&mut self, addr: Address, tkn_gain: &str, ccy_gain: &str, txt: &str) {
         let wallet_before = &self.initial_wallets[&addr];
         let wallet_after = get_account_vaults(self.executor.ledger(), addr);
@@ -710,3 +746,40 @@ fn test_sorting_bid() {
     env.check_wallet(actors[3].1, "0", "-20", "unexpected wallet content");
     env.check_wallet(actors[4].1, "-30", "140", "unexpected wallet content");
 }
+
+#[test]
+fn test_depth() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let (mut env, actors) = TestEnv::new(&mut ledger);
+
+    let user_badge0 = env.register(actors[0]);
+    let user_badge1 = env.register(actors[1]);
+    let user_badge2 = env.register(actors[2]);
+    let user_badge3 = env.register(actors[3]);
+    let user_badge4 = env.register(actors[4]);
+    env.push_bid(user_badge0, "3", "90", actors[0]);
+    env.push_bid(user_badge1, "4", "20", actors[1]);
+    env.push_bid(user_badge2, "4", "40", actors[2]);
+    env.push_ask(user_badge3, "5", "20", actors[3]);
+    env.push_ask(user_badge4, "5", "10", actors[4]);
+    env.push_ask(user_badge4, "6", "200", actors[4]);
+
+    // offers at the same price are aggregated and bids are converted to a quantity of tokens
+    let (bids, asks) = env.depth(10, actors[0]);
+    assert_eq!(bids, vec![(dec!(3), dec!(30)), (dec!(4), dec!(15))]);
+    assert_eq!(asks, vec![(dec!(5), dec!(30)), (dec!(6), dec!(200))]);
+
+    // only the best price levels are kept
+    let (bids, asks) = env.depth(1, actors[0]);
+    assert_eq!(bids, vec![(dec!(4), dec!(15))]);
+    assert_eq!(asks, vec![(dec!(5), dec!(30))]);
+
+    // the oldest offer of a level is filled first and a filled level disappears
+    env.push_ask_no_badge(user_badge3, "4", "10", actors[3]);
+    env.withdraw(user_badge1, actors[1]);
+    env.withdraw(user_badge2, actors[2]);
+    env.check_wallet(actors[1].1, "5", "-20", "unexpected wallet content");
+    env.check_wallet(actors[2].1, "5", "-40", "unexpected wallet content");
+    let (bids, _) = env.depth(10, actors[0]);
+    assert_eq!(bids, vec![(dec!(3), dec!(30)), (dec!(4), dec!(5))]);
+}
@@ -1,3 +1,4 @@
 mod util;
 mod simple;
-mod pmm;
\ No newline at end of file
+mod pmm;
+mod oracle;
\ No newline at end of file
@@ -0,0 +1,41 @@
+use scrypto::prelude::*;
+
+blueprint! {
+    /// A price feed whose prices are published by its admin, exposing the same `get_price` interface as DeXianOracle
+    /// so a `PMMPool` can be guided by it on a local ledger.
+    struct ManualOracle {
+        // pair name to the price (quote per base) and the epoch it was published at
+        prices: HashMap<String, (Decimal, u64)>,
+        admin_badge_def: ResourceDef
+    }
+
+    impl ManualOracle {
+
+        pub fn new() -> (Component, Bucket) {
+            let admin_badge = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
+                .metadata("name", "Manual Oracle Admin Badge")
+                .initial_supply_fungible(Decimal::one());
+
+            let oracle = Self {
+                prices: HashMap::new(),
+                admin_badge_def: admin_badge.resource_def()
+            }
+            .instantiate();
+
+            (oracle, admin_badge)
+        }
+
+        /// Publishes the price of a pair at the current epoch.
+        #[auth(admin_badge_def)]
+        pub fn set_price(&mut self, pair: String, price: Decimal) {
+            assert!(price > Decimal::zero(), "Invalid price");
+            self.prices.insert(pair, (price, Context::current_epoch()));
+        }
+
+        /// Returns the price of a pair and the epoch it was published at.
+        pub fn get_price(&self, pair: String) -> (Decimal, u64) {
+            assert!(self.prices.contains_key(&pair), "the pair not exists!");
+            *self.prices.get(&pair).unwrap()
+        }
+    }
+}
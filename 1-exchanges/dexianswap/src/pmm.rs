@@ -21,7 +21,12 @@ blueprint! {
         _i: Decimal,
         _k: Decimal,
         _fee: Decimal,
-        lp_token_def: ResourceDef
+        lp_token_def: ResourceDef,
+        admin_badge_def: ResourceDef,
+        // oracle component and pair name the guide price `_i` is read from before each trade
+        oracle: Option<(Address, String)>,
+        // oldest oracle price accepted, in epochs since it was published
+        max_price_age: u64
     }
 
     impl PMMPool{
@@ -34,7 +39,7 @@ blueprint! {
             lp_name: String,
             lp_url: String,
             lp_initial_supply: Decimal
-        ) -> (Component, Bucket){
+        ) -> (Component, Bucket, Bucket){
             assert!(
                 !base_tokens.is_empty() && !quote_tokens.is_empty(),
                 "You must pass in an initial supply of each token."
@@ -45,12 +50,22 @@ blueprint! {
                 "Invalid fee in thousandths"
             );
 
-            let mid_price = base_tokens.amount() / quote_tokens.amount();
+            assert!(
+                k >= Decimal::zero() && k <= Decimal::one(),
+                "Invalid k, must be between 0 and 1"
+            );
+
+            // the guide price `i` is the quote amount paid for one base token, the orientation oracle prices use
+            let mid_price = quote_tokens.amount() / base_tokens.amount();
 
             let lp_minter_badge = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
                 .metadata("name", "LP Token Mint Auth")
                 .metadata("symbol", "LP")
                 .initial_supply_fungible(Decimal::one());
+
+            let admin_badge = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
+                .metadata("name", "PMM Pool Admin Badge")
+                .initial_supply_fungible(Decimal::one());
             
             let lp_token_symbol = get_pool_token_pair(base_tokens.resource_address(), quote_tokens.resource_address());
             let mut lp_token_def = ResourceBuilder::new_fungible(DIVISIBILITY_MAXIMUM)
@@ -75,11 +90,53 @@ blueprint! {
                 _i:mid_price,
                 _k:k,
                 _fee:fee,
-                lp_token_def
+                lp_token_def,
+                admin_badge_def: admin_badge.resource_def(),
+                oracle: None,
+                max_price_age: 0
             }
             .instantiate();
 
-            (pmm_pool, lp_tokens)
+            (pmm_pool, lp_tokens, admin_badge)
+        }
+
+        /// Updates the `k` slippage factor of the curve, 0 is a constant price and 1 is as steep as a constant product curve.
+        #[auth(admin_badge_def)]
+        pub fn set_k(&mut self, k: Decimal) {
+            assert!(
+                k >= Decimal::zero() && k <= Decimal::one(),
+                "Invalid k, must be between 0 and 1"
+            );
+            self._k = k;
+        }
+
+        /// Sets the oracle component the guide price is read from on each trade, `None` keeps the current price fixed.
+        ///
+        /// The oracle must implement `get_price(pair: String) -> (Decimal, u64)` like DeXianOracle, returning the
+        /// quote amount paid for one base token and the epoch it was published at. Trades fail while the price is
+        /// more than `max_price_age` epochs old.
+        #[auth(admin_badge_def)]
+        pub fn set_oracle(&mut self, oracle: Option<Address>, pair: String, max_price_age: u64) {
+            self.oracle = oracle.map(|address| (address, pair));
+            self.max_price_age = max_price_age;
+            self.update_guide_price();
+        }
+
+        /// Returns the guide price `i` (quote per base) and the slippage factor `k` currently used by the curve.
+        pub fn get_parameters(&self) -> (Decimal, Decimal) {
+            (self._i, self._k)
+        }
+
+        fn update_guide_price(&mut self) {
+            if let Some((address, pair)) = &self.oracle {
+                let (price, epoch_at): (Decimal, u64) = Component::from(*address).call("get_price", args![pair.clone()]);
+                assert!(price > Decimal::zero(), "Invalid oracle price");
+                assert!(
+                    Context::current_epoch() <= epoch_at + self.max_price_age,
+                    "Stale oracle price"
+                );
+                self._i = price;
+            }
         }
 
         pub fn add_liquidity(
@@ -135,6 +192,7 @@ blueprint! {
             &mut self,
             base_bucket: Bucket
         ) -> Bucket {
+            self.update_guide_price();
             let pay_base_amnt = base_bucket.amount();
             let (quote_amnt, new_r_state) = PMMPool::sell_base_token(
                 pay_base_amnt,
@@ -156,7 +214,9 @@ blueprint! {
             &mut self,
             quote_bucket: Bucket
         ) -> Bucket {
+            self.update_guide_price();
             let pay_quote_amnt = quote_bucket.amount();
+            // selling quote prices the received base at the reciprocal of the guide price
             let (quote_amnt, new_r_state) = PMMPool::sell_quote_token(
                 pay_quote_amnt,
                 self._r_state,
@@ -164,7 +224,7 @@ blueprint! {
                 self.base_vault.amount(),
                 self.quote0_amnt,
                 self.quote_vault.amount(),
-                self._i,
+                Decimal::one() / self._i,
                 self._k
             );
             self._r_state = new_r_state;
@@ -1,5 +1,8 @@
+use radix_engine::engine::*;
 use radix_engine::ledger::*;
+use radix_engine::model::*;
 use radix_engine::transaction::*;
+use sbor::any::*;
 use scrypto::prelude::*;
 
 #[test]
@@ -32,3 +35,272 @@ fn test_hello() {
     println!("{:?}\n", receipt2);
     assert!(receipt2.result.is_ok());
 }
+
+fn get_vault_info<'a, L: SubstateStore>(
+    ledger: &'a L,
+    component: &Address,
+    id: &Vid,
+) -> (Address, Decimal) {
+    let vault = ledger.get_vault(component, id).unwrap();
+    (vault.resource_address(), vault.amount())
+}
+
+fn get_lazymap_info<'a, L: SubstateStore>(
+    ledger: &'a L,
+    component: &Address,
+    id: &Mid,
+) -> Vec<(Address, Decimal)> {
+    let lazy_map = ledger.get_lazy_map(component, id).unwrap();
+    lazy_map
+        .map()
+        .iter()
+        .flat_map(|(_, data)| {
+            let validated_data = validate_data(data).unwrap();
+            validated_data
+                .vaults
+                .iter()
+                .map(|vid| get_vault_info(ledger, component, vid))
+                .collect::<Vec<(Address, Decimal)>>()
+        })
+        .collect()
+}
+
+fn balance<'a, L: SubstateStore>(ledger: &'a L, account: Address, token: Address) -> Decimal {
+    let component = ledger.get_component(account).unwrap();
+    let validated_data = validate_data(component.state()).unwrap();
+    validated_data
+        .lazy_maps
+        .iter()
+        .flat_map(|mid| get_lazymap_info(ledger, &account, &mid))
+        .filter(|(address, _)| *address == token)
+        .map(|(_, amount)| amount)
+        .next()
+        .unwrap_or(Decimal::zero())
+}
+
+fn assert_gain(before: Decimal, after: Decimal, gain: &str) {
+    let diff = after - (before + Decimal::from_str(gain).unwrap());
+    let eps = Decimal::from_str("0.0000000001").unwrap();
+    assert!(diff < eps && diff > -eps, "expected a gain of {}, got {}", gain, after - before);
+}
+
+fn create_token<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+) -> Address {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .new_token_fixed(HashMap::new(), Decimal::from(1000000))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    receipt.resource_def(0).unwrap()
+}
+
+/// Instantiates a PMMPool of 100 base and 200 quote without fees, returns the pool and its admin badge
+fn create_pmm_pool<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    package: Address,
+    base: Address,
+    quote: Address,
+) -> (Address, Address) {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_function(
+                    package,
+                    "PMMPool",
+                    "new",
+                    vec![
+                        format!("100,{}", base),
+                        format!("200,{}", quote),
+                        "0".to_owned(),
+                        "0".to_owned(),
+                        "PMM LP".to_owned(),
+                        "https://dexian.io".to_owned(),
+                        "100".to_owned(),
+                    ],
+                    Some(account),
+                )
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+    // the LP minter badge is created first, then the admin badge
+    (receipt.component(0).unwrap(), receipt.resource_def(1).unwrap())
+}
+
+fn sell<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    pool: Address,
+    method: &str,
+    tokens: (&str, Address),
+) -> Receipt {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(pool, method, vec![format!("{},{}", tokens.0, tokens.1)], Some(account))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    receipt
+}
+
+fn get_parameters<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    pool: Address,
+) -> (Decimal, Decimal) {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(pool, "get_parameters", vec![], Some(account))
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    let decimal = |value: &Value| match value {
+        Value::Custom(_, data) => Decimal::try_from(data.as_slice()).unwrap(),
+        _ => panic!("not a decimal"),
+    };
+    match &receipt.outputs[receipt.outputs.len() - 1].dom {
+        Value::Tuple(elements) => (decimal(&elements[0]), decimal(&elements[1])),
+        _ => panic!("not a tuple"),
+    }
+}
+
+fn set_oracle_price<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    oracle: (Address, Address),
+    price: &str,
+) {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(
+                    oracle.0,
+                    "set_price",
+                    vec!["BASE/QUOTE".to_owned(), price.to_owned(), format!("1,{}", oracle.1)],
+                    Some(account),
+                )
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+}
+
+#[test]
+fn test_pmm_guide_price_is_quote_per_base() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let key = executor.new_public_key();
+    let account = executor.new_account(key);
+    let package = executor.publish_package(include_code!("dexianswap")).unwrap();
+    let base = create_token(&mut executor, key, account);
+    let quote = create_token(&mut executor, key, account);
+
+    // 100 base against 200 quote, one base is worth 2 quote
+    let (pool, _) = create_pmm_pool(&mut executor, key, account, package, base, quote);
+    let (i, k) = get_parameters(&mut executor, key, account, pool);
+    assert_eq!(i, Decimal::from(2));
+    assert_eq!(k, Decimal::zero());
+
+    // with k = 0 the curve trades at the guide price, base per quote would only fetch 5 quote here
+    let before = balance(executor.ledger(), account, quote);
+    let receipt = sell(&mut executor, key, account, pool, "sell_base", ("10", base));
+    assert!(receipt.result.is_ok());
+    assert_gain(before, balance(executor.ledger(), account, quote), "20");
+
+    // selling quote prices the base at the reciprocal of the guide price
+    let before = balance(executor.ledger(), account, base);
+    let receipt = sell(&mut executor, key, account, pool, "sell_quote", ("20", quote));
+    assert!(receipt.result.is_ok());
+    assert_gain(before, balance(executor.ledger(), account, base), "10");
+}
+
+#[test]
+fn test_pmm_oracle_guide_price_and_staleness() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let key = executor.new_public_key();
+    let account = executor.new_account(key);
+    let package = executor.publish_package(include_code!("dexianswap")).unwrap();
+    let base = create_token(&mut executor, key, account);
+    let quote = create_token(&mut executor, key, account);
+
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(&executor)
+                .call_function(package, "ManualOracle", "new", vec![], Some(account))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    let oracle = (receipt.component(0).unwrap(), receipt.resource_def(0).unwrap());
+    set_oracle_price(&mut executor, key, account, oracle, "4");
+
+    // the oracle publishes 4 quote per base while the pool reserves started at 2
+    let (pool, admin_badge) = create_pmm_pool(&mut executor, key, account, package, base, quote);
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(&executor)
+                .call_method(
+                    pool,
+                    "set_oracle",
+                    vec![
+                        format!("Some({})", oracle.0),
+                        "BASE/QUOTE".to_owned(),
+                        "2".to_owned(),
+                        format!("1,{}", admin_badge),
+                    ],
+                    Some(account),
+                )
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+    let (i, _) = get_parameters(&mut executor, key, account, pool);
+    assert_eq!(i, Decimal::from(4));
+
+    let before = balance(executor.ledger(), account, quote);
+    let receipt = sell(&mut executor, key, account, pool, "sell_base", ("10", base));
+    assert!(receipt.result.is_ok());
+    assert_gain(before, balance(executor.ledger(), account, quote), "40");
+
+    // the price was published at epoch 0 and is too old at epoch 3
+    executor.ledger_mut().set_epoch(3);
+    let receipt = sell(&mut executor, key, account, pool, "sell_base", ("10", base));
+    assert!(!receipt.result.is_ok());
+
+    // a fresh price lets trades through again
+    set_oracle_price(&mut executor, key, account, oracle, "4");
+    let receipt = sell(&mut executor, key, account, pool, "sell_base", ("10", base));
+    assert!(receipt.result.is_ok());
+}
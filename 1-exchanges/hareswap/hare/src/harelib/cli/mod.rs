@@ -20,6 +20,8 @@ use simulator::resim::*;
 // Only the imports needed to do off-ledger things, ie. the hareswap API
 use hareswap::api::*;
 
+use super::serve::{Quoter, Strategy};
+
 /* Top Level */
 
 /// hareswap CLI: Used for the off-ledger interactions supporting a swap
@@ -37,6 +39,9 @@ pub enum Command {
     RequestForQuote(RequestForQuote),
     MakeSignedOrder(MakeSignedOrder),
     TokenizeOrder(TokenizeOrder),
+    SignCancelOrder(SignCancelOrder),
+    SignInvalidateOrders(SignInvalidateOrders),
+    Serve(Serve),
     Test(TestCommands),
 }
 
@@ -73,6 +78,10 @@ pub enum Error {
     ParserNotEOFError,
     GenerateEntrypointError(transaction_manifest::DecompileError),
     GenerateEntrypointFormatError,
+    JsonError(serde_json::Error),
+    UnknownPairError,
+    StrategyLimitError,
+    HttpRequestError,
     TestError,
     RuntimeError,
 }
@@ -86,6 +95,9 @@ pub fn run() -> Result<(), Error> {
         Command::RequestForQuote(cmd) => cmd.run(),
         Command::MakeSignedOrder(cmd) => cmd.run(),
         Command::TokenizeOrder(cmd) => cmd.run(),
+        Command::SignCancelOrder(cmd) => cmd.run(),
+        Command::SignInvalidateOrders(cmd) => cmd.run(),
+        Command::Serve(cmd) => cmd.run(),
         Command::Test(cmd) => match cmd.command {
             TestCommand::NFTSetup(cmd) => cmd.run(),
         },
@@ -118,41 +130,23 @@ pub struct RequestForQuote {
     resource_taker_auth: String,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum)]
-enum QuoteType {
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ArgEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuoteType {
     SellBase,
     BuyBase,
 }
 
 impl RequestForQuote {
     pub fn run(&self) -> Result<(), Error> {
-        // parse arguments
-        let base_resource = ResourceDef::from(Address::from_str(&self.resource_b).map_err(Error::ParseAddressError)?);
-        let base_contents = BucketContents::from_str(&self.resource_b_amount).map_err(Error::ParseAmountError)?;
-        let quote_resource = ResourceDef::from(Address::from_str(&self.resource_q).map_err(Error::ParseAddressError)?);
-        let taker_auth_resource =
-            ResourceDef::from(Address::from_str(&self.resource_taker_auth).map_err(Error::ParseAddressError)?);
-        let taker_auth_amount = Decimal::from_str("1").map_err(Error::ParseDecimalError)?;
-
-        // combine the rosource_b information into a BucketRequirement
-        let base_requirement = BucketRequirement {
-            resource: base_resource,
-            contents: base_contents,
-        };
-
-        // combine the taker_auth information into a BucketRequirement
-        let taker_auth = BucketRequirement {
-            resource: taker_auth_resource,
-            contents: BucketContents::Fungible(taker_auth_amount),
-        };
-
-        // combine the above to create the PartialOrder which is the full RFQ
-        let partial_order = PartialOrder {
-            inverted: self.quote_type == QuoteType::SellBase,
-            base_requirement,
-            quote_resource,
-            taker_auth,
-        };
+        // parse arguments and build the PartialOrder which is the full RFQ
+        let partial_order = new_partial_order(
+            self.quote_type,
+            &self.resource_b_amount,
+            &self.resource_b,
+            &self.resource_q,
+            &self.resource_taker_auth,
+        )?;
 
         // and encode it
         let partial_order_encoded = scrypto_encode(&partial_order);
@@ -169,6 +163,44 @@ impl RequestForQuote {
     }
 }
 
+/// create the PartialOrder for a request-for-quote from its textual arguments
+///
+/// Shared by the `request-for-quote` subcommand and the RFQ server which receives the same fields as JSON
+pub fn new_partial_order(
+    quote_type: QuoteType,
+    resource_b_amount: &str,
+    resource_b: &str,
+    resource_q: &str,
+    resource_taker_auth: &str,
+) -> Result<PartialOrder, Error> {
+    // parse arguments
+    let base_resource = ResourceDef::from(Address::from_str(resource_b).map_err(Error::ParseAddressError)?);
+    let base_contents = BucketContents::from_str(resource_b_amount).map_err(Error::ParseAmountError)?;
+    let quote_resource = ResourceDef::from(Address::from_str(resource_q).map_err(Error::ParseAddressError)?);
+    let taker_auth_resource = ResourceDef::from(Address::from_str(resource_taker_auth).map_err(Error::ParseAddressError)?);
+    let taker_auth_amount = Decimal::from_str("1").map_err(Error::ParseDecimalError)?;
+
+    // combine the rosource_b information into a BucketRequirement
+    let base_requirement = BucketRequirement {
+        resource: base_resource,
+        contents: base_contents,
+    };
+
+    // combine the taker_auth information into a BucketRequirement
+    let taker_auth = BucketRequirement {
+        resource: taker_auth_resource,
+        contents: BucketContents::Fungible(taker_auth_amount),
+    };
+
+    // combine the above to create the PartialOrder
+    Ok(PartialOrder {
+        inverted: quote_type == QuoteType::SellBase,
+        base_requirement,
+        quote_resource,
+        taker_auth,
+    })
+}
+
 /* Make Signed Order */
 
 /// used by the maker: creates an order from a partial order and signs it sending the result to stdout
@@ -190,6 +222,12 @@ pub struct MakeSignedOrder {
     deadline_epoch: u64,
     /// optional callback to use instead of teh handle_order_default_callback.  This should be in the form of a CALL_METHOD instruction
     callback: Option<String>,
+    /// allow several takers to each fill part of the order with fill_order (only for Fungible amounts)
+    #[clap(long)]
+    partial: bool,
+    /// nonce to sign the order with, the Maker can revoke all orders below a nonce at once.  Defaults to the current epoch
+    #[clap(long)]
+    nonce: Option<u64>,
 }
 
 impl MakeSignedOrder {
@@ -206,8 +244,52 @@ impl MakeSignedOrder {
         let private_key_bytes = fs::read(&self.private_key_file).map_err(Error::IoError)?;
         //let deadline = u64::from_str(&self.deadline_epoch)?;//.map_err(Error::ParseDeadline)?;
         let deadline = self.deadline_epoch;
-        // set the callback by parsing the argument string or using the default
-        let maker_callback = if self.callback.is_none() {
+        let maker_callback = parse_callback(maker_component_address, self.callback.as_deref())?;
+        let nonce = match self.nonce {
+            Some(nonce) => nonce,
+            // get the on-disk ledger the same way resim does
+            None => RadixEngineDB::with_bootstrap(get_data_dir().map_err(Error::ResimError)?).get_epoch(),
+        };
+
+        // decode the PartialOrder
+        let partial_order_encoded = partial_order_bytes;
+        let partial_order: PartialOrder = scrypto_decode(&partial_order_encoded).map_err(Error::SBORDecodeError)?;
+
+        // create the MatchedOrder from the inputs
+        let matched_order = MatchedOrder {
+            partial_order,
+            quote_contents: resource_q_contents,
+            maker_address: maker_component_address,
+            maker_callback,
+            deadline,
+            partially_fillable: self.partial,
+        };
+
+        let signed_order = sign_order(matched_order, voucher_resource, voucher_key, nonce, &private_key_bytes)?;
+        let result = if self.partial {
+            // the order hash is needed to query the remaining amount or cancel it
+            eprintln!("order hash: {}", signed_order.order_hash());
+            fill_order_instruction(&signed_order)?
+        } else {
+            execute_order_instruction(&signed_order)?
+        };
+
+        // print the instruction to stdout in Radix Transaction Manifest (rtm) format
+        // so the sender can compose it with whatever they want
+        print!("{}", result);
+
+        // *SECURITY NOTICE*: care should be taken by the transaction submitter to
+        // validate this upon receipt and not introduce "instruction injection" vulnerabilities
+        // or any other "component redirection" vulnerabilities
+
+        Ok(())
+    }
+}
+
+/// set the callback by parsing the argument string or using the default
+pub fn parse_callback(maker_component_address: Address, callback: Option<&str>) -> Result<Callback, Error> {
+    let maker_callback = match callback {
+        None => {
             // this is the default callback expected in the Maker Component
             // It is used for "simple" swaps where the Maker has a SharedAccount
             // managing the bought and sold assets.
@@ -216,10 +298,10 @@ impl MakeSignedOrder {
                 method: "handle_order_default_callback".to_owned(),
                 args: vec![],
             }
-        } else {
+        }
+        Some(callback_str) => {
             // parse the CallMethod Instruction into the Callback type with the same args
-            let callback_str = self.callback.as_ref().unwrap(); // unwrap is safe on this branch
-            let tx: Transaction = transaction_manifest::compile(&callback_str).map_err(Error::CompileError)?;
+            let tx: Transaction = transaction_manifest::compile(callback_str).map_err(Error::CompileError)?;
             assert_eq!(tx.instructions.len(), 1, "callback error, too many instructions"); // backend only supports 1 for now
             match tx.instructions[0].clone() {
                 Instruction::CallMethod {
@@ -233,80 +315,229 @@ impl MakeSignedOrder {
                 },
                 _ => panic!("callback did not contain a CallMethod"),
             }
-        };
+        }
+    };
+    Ok(maker_callback)
+}
 
-        // decode the PartialOrder
-        let partial_order_encoded = partial_order_bytes;
-        let partial_order: PartialOrder = scrypto_decode(&partial_order_encoded).map_err(Error::SBORDecodeError)?;
+/// seal the MatchedOrder in a Voucher signed with the private key and return the resulting SignedOrder
+pub fn sign_order(
+    matched_order: MatchedOrder,
+    voucher_resource: ResourceDef,
+    voucher_key: NonFungibleKey,
+    nonce: u64,
+    private_key_bytes: &[u8],
+) -> Result<SignedOrder, Error> {
+    // create the SignedOrder for consumption by the submitter, without the signature for now
+    let mut signed_order = SignedOrder {
+        order: matched_order,
+        voucher_resource,
+        voucher_key,
+        nonce,
+        signature: vec![],
+    };
+
+    // construct the Voucher for the MatchedOrder and encode it
+    let voucher_encoded = scrypto_encode(&signed_order.to_voucher());
+
+    // sign the voucher
+    signed_order.signature = sign_message(&voucher_encoded, private_key_bytes)?;
+
+    Ok(signed_order)
+}
 
-        // create the MatchedOrder from the inputs
-        let matched_order = MatchedOrder {
-            partial_order,
-            quote_contents: resource_q_contents,
-            maker_address: maker_component_address,
-            maker_callback,
-            deadline,
-        };
+/// sign the message with the private key in the ASN.1 DER format expected by `verify`
+pub fn sign_message(message: &[u8], private_key_bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let signing_key = SigningKey::from_bytes(private_key_bytes).map_err(Error::BadPrivateKeyError)?;
+    let signature: Signature = signing_key.try_sign(message).map_err(Error::SigningError)?;
+    let sig_bytes = signature.to_der().to_bytes().to_vec();
 
-        // construct a Voucher for the MatchedOrder
+    // double check that the sig verifies (all the format conversions are ok)
+    let public_key = to_public_key(&signing_key);
+    verify(&public_key, message, &sig_bytes).map_err(Error::VerifyCheckError)?;
 
-        let voucher = Voucher::from_nfd(
-            voucher_resource.clone(),
-            Some(voucher_key.clone()),
-            matched_order.clone(),
-        );
+    Ok(sig_bytes)
+}
 
-        // and encode it
-        let voucher_encoded = scrypto_encode(&voucher);
+/// create the execute_order instruction for the SignedOrder in Radix Transaction Manifest (rtm) format, without the trailing
+/// semicolon since the sender must add the Bucket and BucketRef arguments
+pub fn execute_order_instruction(signed_order: &SignedOrder) -> Result<String, Error> {
+    // create the instruction the sender will use to execute this signed order (after adding the appropriate buckets)
+    // this is not technically part of the signed order and not authenticated.  The taker/sender is free to send the
+    // signed order (along with their badge) to the more advanced "tokenize_order" method.
+    // method name matches the Maker blueprint implementation, hardcoding this here
+    maker_instruction(signed_order, "execute_order", vec![scrypto_encode(signed_order)])
+}
 
-        // sign the voucher
-        let signing_key = SigningKey::from_bytes(&private_key_bytes).map_err(Error::BadPrivateKeyError)?;
-        let signature: Signature = signing_key.try_sign(&voucher_encoded).map_err(Error::SigningError)?;
-        let sig_bytes = signature.to_der().to_bytes().to_vec();
+/// create the fill_order instruction for a partially fillable SignedOrder, without the trailing semicolon since the
+/// sender must add the fill amount, Bucket and BucketRef arguments
+pub fn fill_order_instruction(signed_order: &SignedOrder) -> Result<String, Error> {
+    maker_instruction(signed_order, "fill_order", vec![scrypto_encode(signed_order)])
+}
 
-        // double check that the sig verifies (all the format conversions are ok)
-        let public_key = to_public_key(&signing_key);
-        verify(&public_key, &voucher_encoded, &sig_bytes).map_err(Error::VerifyCheckError)?;
+/// create a CALL_METHOD instruction on the order's Maker component without the trailing semicolon
+fn maker_instruction(signed_order: &SignedOrder, method: &str, args: Vec<Vec<u8>>) -> Result<String, Error> {
+    let execute_entrypoint = Instruction::CallMethod {
+        component_address: signed_order.order.maker_address,
+        method: method.to_owned(),
+        args,
+    };
+    // build the transaction so we can use the available API to easily print it
+    let tx = Transaction {
+        instructions: vec![execute_entrypoint], // following code (and the taker) assumes only a single instruction
+    };
+    let manifest = transaction_manifest::decompile(&tx).map_err(Error::GenerateEntrypointError)?;
+    // drop the trailing semicolon and newline since extra args are required
+    let (result, _) = manifest.rsplit_once(";").ok_or(Error::GenerateEntrypointFormatError)?;
+    Ok(result.to_owned())
+}
 
-        // create the SignedOrder for consumption by the submitter.
-        let signed_order = SignedOrder {
-            order: matched_order,
-            voucher_resource,
-            voucher_key,
-            signature: sig_bytes,
-        };
+/* Sign Cancel Order */
+
+/// used by the maker: sign the cancellation of the unfilled remainder of a partially fillable order and print the
+/// cancel_order instruction to stdout
+#[derive(Parser, Debug)]
+pub struct SignCancelOrder {
+    /// component address for the Maker component tracking the order, the signature is only valid for it
+    maker_component_address: String,
+    /// hash of the order, as printed by make-signed-order --partial
+    order_hash: String,
+    /// path to file containing the serialized private key which signed the order
+    private_key_file: PathBuf,
+}
+
+impl SignCancelOrder {
+    pub fn run(&self) -> Result<(), Error> {
+        // parse arguments
+        let maker_component_address =
+            Address::from_str(&self.maker_component_address).map_err(Error::ParseAddressError)?;
+        let private_key_bytes = fs::read(&self.private_key_file).map_err(Error::IoError)?;
+
+        let signature = sign_message(&cancel_order_message(maker_component_address, &self.order_hash), &private_key_bytes)?;
 
-        // and encode it
-        let signed_order_encoded = scrypto_encode(&signed_order);
-
-        // create the instruction the sender will use to execute this signed order (after adding the appropriate buckets)
-        // this is not technically part of the signed order and not authenticated.  The taker/sender is free to send the
-        // signed order (along with their badge) to the more advanced "tokenize_order" method.
-        let execute_entrypoint = Instruction::CallMethod {
-            component_address: maker_component_address,
-            method: "execute_order".to_owned(), // method name matches the Maker blueprint implementation, hardcoding this here
-            args: vec![signed_order_encoded],
-        };
-        // build the transaction so we can use the available API to easily print it
         let tx = Transaction {
-            instructions: vec![execute_entrypoint], // following code (and the taker) assumes only a single instruction
+            instructions: vec![Instruction::CallMethod {
+                component_address: maker_component_address,
+                method: "cancel_order".to_owned(),
+                args: vec![scrypto_encode(&self.order_hash), scrypto_encode(&signature)],
+            }],
         };
         let manifest = transaction_manifest::decompile(&tx).map_err(Error::GenerateEntrypointError)?;
-        // drop the trailing semicolon and newline since extra args are required
-        let (result, _) = manifest.rsplit_once(";").ok_or(Error::GenerateEntrypointFormatError)?;
 
-        // print the instruction to stdout in Radix Transaction Manifest (rtm) format
-        // so the sender can compose it with whatever they want
-        print!("{}", result);
+        // print the complete instruction to stdout in Radix Transaction Manifest (rtm) format
+        print!("{}", manifest);
 
-        // *SECURITY NOTICE*: care should be taken by the transaction submitter to
-        // validate this upon receipt and not introduce "instruction injection" vulnerabilities
-        // or any other "component redirection" vulnerabilities
+        Ok(())
+    }
+}
+
+/* Sign Invalidate Orders */
+
+/// used by the maker: sign the revocation of every order signed with a nonce lower than min_nonce and print the
+/// invalidate_orders instruction to stdout
+#[derive(Parser, Debug)]
+pub struct SignInvalidateOrders {
+    /// component address for the Maker component to revoke the orders on, the signature is only valid for it
+    maker_component_address: String,
+    /// orders signed with a lower nonce can no longer be executed.  Orders are signed with the epoch as nonce by default
+    min_nonce: u64,
+    /// path to file containing the serialized private key which signed the orders
+    private_key_file: PathBuf,
+}
+
+impl SignInvalidateOrders {
+    pub fn run(&self) -> Result<(), Error> {
+        // parse arguments
+        let maker_component_address =
+            Address::from_str(&self.maker_component_address).map_err(Error::ParseAddressError)?;
+        let private_key_bytes = fs::read(&self.private_key_file).map_err(Error::IoError)?;
+
+        let signature = sign_message(&invalidate_orders_message(maker_component_address, self.min_nonce), &private_key_bytes)?;
+
+        let tx = Transaction {
+            instructions: vec![Instruction::CallMethod {
+                component_address: maker_component_address,
+                method: "invalidate_orders".to_owned(),
+                args: vec![scrypto_encode(&self.min_nonce), scrypto_encode(&signature)],
+            }],
+        };
+        let manifest = transaction_manifest::decompile(&tx).map_err(Error::GenerateEntrypointError)?;
+
+        // print the complete instruction to stdout in Radix Transaction Manifest (rtm) format
+        print!("{}", manifest);
 
         Ok(())
     }
 }
 
+/* Serve */
+
+/// used by the maker: run a long-lived request-for-quote (RFQ) server
+///
+/// Each RFQ is received as JSON with the same fields as the `request-for-quote` arguments, priced using the
+/// strategy file and answered with the execute_order instruction for a signed order that expires after the
+/// strategy's `ttl_epochs`.  The current epoch is read from the same on-disk ledger as resim.
+#[derive(Parser, Debug)]
+pub struct Serve {
+    /// path to the JSON strategy file used to price each request
+    strategy_file: PathBuf,
+    /// component address for the Maker component which will settle the orders
+    maker_component_address: String,
+    /// resource address identifying the resource a "Transporter" will mint when converting a SignedOrder to an on-ledger NonFungible token
+    voucher_address: String,
+    /// path to file containing the serialized private key which will sign the orders - must match on-ledger public key
+    private_key_file: PathBuf,
+    /// local address to listen on for HTTP POST requests
+    #[clap(long, default_value = "127.0.0.1:8080")]
+    http: String,
+    /// path to a Unix socket to listen on instead of HTTP, one JSON request and response per line
+    #[clap(long)]
+    unix: Option<PathBuf>,
+    /// optional callback to use instead of the handle_order_default_callback.  This should be in the form of a CALL_METHOD instruction
+    #[clap(long)]
+    callback: Option<String>,
+}
+
+impl Serve {
+    pub fn run(&self) -> Result<(), Error> {
+        // parse arguments
+        let strategy_json = fs::read_to_string(&self.strategy_file).map_err(Error::IoError)?;
+        let strategy: Strategy = serde_json::from_str(&strategy_json).map_err(Error::JsonError)?;
+        let maker_component_address =
+            Address::from_str(&self.maker_component_address).map_err(Error::ParseAddressError)?;
+        let voucher_resource: ResourceDef = Address::from_str(&self.voucher_address)
+            .map_err(Error::ParseAddressError)?
+            .into();
+        let private_key_bytes = fs::read(&self.private_key_file).map_err(Error::IoError)?;
+        let maker_callback = parse_callback(maker_component_address, self.callback.as_deref())?;
+
+        // the epoch is read from the on-disk ledger the same way resim does
+        let ledger_dir = get_data_dir().map_err(Error::ResimError)?;
+
+        let mut quoter = Quoter::new(
+            strategy,
+            maker_component_address,
+            voucher_resource,
+            maker_callback,
+            private_key_bytes,
+            ledger_dir,
+        );
+
+        match &self.unix {
+            Some(path) => {
+                eprintln!("hare serve: listening on unix socket {}", path.display());
+                let listener = std::os::unix::net::UnixListener::bind(path).map_err(Error::IoError)?;
+                quoter.serve_unix(listener)
+            }
+            None => {
+                eprintln!("hare serve: listening on http://{}", self.http);
+                let listener = std::net::TcpListener::bind(&self.http).map_err(Error::IoError)?;
+                quoter.serve_http(listener)
+            }
+        }
+    }
+}
+
 /* TokenizeOrder */
 
 /// used by the taker: Converts a SignedOrder (execute_order instruction) to a tokenize_order instruction instead, for "advanced usage"
@@ -432,7 +663,7 @@ pub fn new_public_private_pair(ledger: &mut RadixEngineDB) -> (Vec<u8>, Vec<u8>)
 /// Convert SigningKey to an EcdsaPublicKey.
 ///
 /// WARNING: Makes some assumptions about the underlying type, curve paramaters etc for the SigningKey
-fn to_public_key(signing_key: &SigningKey) -> EcdsaPublicKey {
+pub fn to_public_key(signing_key: &SigningKey) -> EcdsaPublicKey {
     let verifying_key = signing_key.verifying_key();
     let compressed_point = verifying_key.to_bytes();
     let mut public_raw = [0u8; 33];
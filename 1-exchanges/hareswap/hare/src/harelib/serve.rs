@@ -0,0 +1,306 @@
+//! Request-for-quote (RFQ) server used by `hare serve`
+//!
+//! The maker/signer runs this to answer RFQs automatically instead of running `make-signed-order` by hand for each one.
+//! Requests are priced with a [Strategy] loaded from a JSON file and answered with a signed, sealed voucher order
+//! which expires `ttl_epochs` after the current ledger epoch.
+//!
+//! Two transports are supported:
+//! - HTTP: `POST` the request JSON as the body, the response JSON is the body of the reply
+//! - Unix socket: one request JSON per line, one response JSON per line
+//!
+//! WARNING: this is a prototype for local use.  There is no TLS, authentication or rate limiting.
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::os::unix::net::UnixListener;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+// non-scrypto dependencies
+use serde::{Deserialize, Serialize};
+
+// scrypto dependencies
+use radix_engine::ledger::*;
+use scrypto::buffer::scrypto_encode;
+use scrypto::prelude::*;
+use scrypto::utils::sha256;
+use simulator::ledger::*;
+
+// Only the imports needed to do off-ledger things, ie. the hareswap API
+use hareswap::api::*;
+
+use super::cli::{execute_order_instruction, fill_order_instruction, new_partial_order, sign_order, Error, QuoteType};
+
+/// The pricing strategy of the maker, loaded from a JSON file
+///
+/// Example:
+/// ```json
+/// {
+///   "ttl_epochs": 10,
+///   "partially_fillable": false,
+///   "min_nonce": 0,
+///   "pairs": [
+///     { "base": "03...", "quote": "03...", "bid": "0.49", "ask": "0.51", "max_base": "1000.0" }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct Strategy {
+    /// number of epochs after the current epoch the signed orders can still be executed
+    pub ttl_epochs: u64,
+    /// sign partially fillable orders (filled with fill_order) instead of all-or-nothing orders
+    #[serde(default)]
+    pub partially_fillable: bool,
+    /// orders are signed with the current epoch as the nonce, or this nonce if it is higher.  Raise it after
+    /// revoking orders with a nonce above the current epoch
+    #[serde(default)]
+    pub min_nonce: u64,
+    /// the pairs the maker is willing to quote
+    pub pairs: Vec<PairStrategy>,
+}
+
+/// Prices for one base/quote pair, in quote per unit of base
+#[derive(Debug, Clone, Deserialize)]
+pub struct PairStrategy {
+    /// resource address of the base asset
+    pub base: String,
+    /// resource address of the quote asset
+    pub quote: String,
+    /// price the maker pays when the taker sells base
+    pub bid: String,
+    /// price the maker asks when the taker buys base
+    pub ask: String,
+    /// optional limit on the amount of base (or number of NonFungibles) in a single order
+    pub max_base: Option<String>,
+}
+
+/// A request-for-quote as received by the server.  Same fields as the `request-for-quote` subcommand arguments
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuoteRequest {
+    pub quote_type: QuoteType,
+    pub resource_b_amount: String,
+    pub resource_b: String,
+    pub resource_q: String,
+    pub resource_taker_auth: String,
+}
+
+/// The answer to a request-for-quote
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteResponse {
+    /// amount of the quote resource the maker decided on
+    pub quote_amount: String,
+    /// unique identifier of the order (the NonFungibleKey of the voucher)
+    pub voucher_key: String,
+    /// last epoch the order can be executed
+    pub deadline: u64,
+    /// hash identifying the order on the Maker, used to track partial fills
+    pub order_hash: String,
+    /// the execute_order (or fill_order when partially fillable) instruction in Radix Transaction Manifest (rtm)
+    /// format, same as the output of `make-signed-order`
+    pub signed_order: String,
+}
+
+/// Prices and signs request-for-quotes
+pub struct Quoter {
+    strategy: Strategy,
+    maker_component_address: Address,
+    voucher_resource: ResourceDef,
+    maker_callback: Callback,
+    private_key_bytes: Vec<u8>,
+    ledger_dir: PathBuf,
+    /// mixed into every voucher key so keys of the same epoch and request differ
+    voucher_key_counter: u64,
+}
+
+impl Quoter {
+    pub fn new(
+        strategy: Strategy,
+        maker_component_address: Address,
+        voucher_resource: ResourceDef,
+        maker_callback: Callback,
+        private_key_bytes: Vec<u8>,
+        ledger_dir: PathBuf,
+    ) -> Self {
+        Self {
+            strategy,
+            maker_component_address,
+            voucher_resource,
+            maker_callback,
+            private_key_bytes,
+            ledger_dir,
+            voucher_key_counter: 0,
+        }
+    }
+
+    /// price the request with the strategy and sign the resulting order
+    pub fn quote(&mut self, request: &QuoteRequest) -> Result<QuoteResponse, Error> {
+        let partial_order = new_partial_order(
+            request.quote_type,
+            &request.resource_b_amount,
+            &request.resource_b,
+            &request.resource_q,
+            &request.resource_taker_auth,
+        )?;
+        let quote_amount = self.price(&partial_order)?;
+
+        // the ledger is opened for each request so resim can keep using it between requests
+        let epoch = RadixEngineDB::with_bootstrap(self.ledger_dir.clone()).get_epoch();
+        let deadline = epoch + self.strategy.ttl_epochs;
+        let voucher_key = self.new_voucher_key(&partial_order, epoch)?;
+
+        let matched_order = MatchedOrder {
+            partial_order,
+            quote_contents: BucketContents::Fungible(quote_amount),
+            maker_address: self.maker_component_address,
+            maker_callback: self.maker_callback.clone(),
+            deadline,
+            partially_fillable: self.strategy.partially_fillable,
+        };
+        let nonce = epoch.max(self.strategy.min_nonce);
+        let signed_order = sign_order(
+            matched_order,
+            self.voucher_resource.clone(),
+            voucher_key.clone(),
+            nonce,
+            &self.private_key_bytes,
+        )?;
+        let instruction = if signed_order.order.partially_fillable {
+            fill_order_instruction(&signed_order)?
+        } else {
+            execute_order_instruction(&signed_order)?
+        };
+
+        Ok(QuoteResponse {
+            quote_amount: quote_amount.to_string(),
+            voucher_key: voucher_key.to_string(),
+            deadline,
+            order_hash: signed_order.order_hash(),
+            signed_order: instruction,
+        })
+    }
+
+    /// decode a JSON request and encode the JSON response, errors are reported as `{"error": "..."}`
+    pub fn handle_json(&mut self, request_json: &str) -> String {
+        let response = serde_json::from_str::<QuoteRequest>(request_json)
+            .map_err(Error::JsonError)
+            .and_then(|request| self.quote(&request));
+        match response {
+            Ok(response) => serde_json::to_string(&response).expect("QuoteResponse is always serializable"),
+            Err(err) => serde_json::json!({ "error": format!("{:?}", err) }).to_string(),
+        }
+    }
+
+    /// answer HTTP POST requests until the listener fails
+    pub fn serve_http(&mut self, listener: TcpListener) -> Result<(), Error> {
+        for stream in listener.incoming() {
+            let mut stream = stream.map_err(Error::IoError)?;
+            let (status, body) = match read_http_body(&mut stream) {
+                Ok(request_json) => ("200 OK", self.handle_json(&request_json)),
+                Err(err) => ("400 Bad Request", serde_json::json!({ "error": format!("{:?}", err) }).to_string()),
+            };
+            let reply = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            // a client going away should not stop the server
+            if let Err(err) = stream.write_all(reply.as_bytes()) {
+                eprintln!("hare serve: failed to reply: {}", err);
+            }
+        }
+        Ok(())
+    }
+
+    /// answer one JSON request per line on each connection until the listener fails
+    pub fn serve_unix(&mut self, listener: UnixListener) -> Result<(), Error> {
+        for stream in listener.incoming() {
+            let stream = stream.map_err(Error::IoError)?;
+            let mut writer = stream.try_clone().map_err(Error::IoError)?;
+            for line in BufReader::new(stream).lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let response = self.handle_json(&line);
+                if writer.write_all(format!("{}\n", response).as_bytes()).is_err() {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// amount of the quote resource for the PartialOrder according to the strategy
+    fn price(&self, partial_order: &PartialOrder) -> Result<Decimal, Error> {
+        let base_address = partial_order.base_requirement.resource.address();
+        let quote_address = partial_order.quote_resource.address();
+        let pair = self
+            .strategy
+            .pairs
+            .iter()
+            .find(|pair| {
+                Address::from_str(&pair.base).ok() == Some(base_address)
+                    && Address::from_str(&pair.quote).ok() == Some(quote_address)
+            })
+            .ok_or(Error::UnknownPairError)?;
+
+        // NonFungibles are priced per unit
+        let base_amount = match &partial_order.base_requirement.contents {
+            BucketContents::Fungible(amount) => *amount,
+            BucketContents::NonFungible(keys) => Decimal::from(keys.len() as u64),
+        };
+        if let Some(max_base) = &pair.max_base {
+            let max_base = Decimal::from_str(max_base).map_err(Error::ParseDecimalError)?;
+            if base_amount > max_base {
+                return Err(Error::StrategyLimitError);
+            }
+        }
+
+        // inverted means the taker sells the base, so the maker bids
+        let price = if partial_order.inverted { &pair.bid } else { &pair.ask };
+        let price = Decimal::from_str(price).map_err(Error::ParseDecimalError)?;
+        Ok(base_amount * price)
+    }
+
+    /// unique key for each signed order so a voucher can never be redeemed twice
+    fn new_voucher_key(&mut self, partial_order: &PartialOrder, epoch: u64) -> Result<NonFungibleKey, Error> {
+        self.voucher_key_counter += 1;
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let mut seed = scrypto_encode(partial_order);
+        seed.extend(epoch.to_le_bytes());
+        seed.extend(self.voucher_key_counter.to_le_bytes());
+        seed.extend(nanos.to_le_bytes());
+        NonFungibleKey::from_str(&sha256(seed).to_string()).map_err(Error::ParseNonFungibleKeyError)
+    }
+}
+
+/// read the body of a minimal HTTP/1.1 request using its Content-Length header
+fn read_http_body<R: Read>(stream: R) -> Result<String, Error> {
+    let mut reader = BufReader::new(stream);
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        let read = reader.read_line(&mut line).map_err(Error::IoError)?;
+        if read == 0 {
+            return Err(Error::HttpRequestError);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break; // end of the headers
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| Error::HttpRequestError)?;
+            }
+        }
+    }
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).map_err(Error::IoError)?;
+    String::from_utf8(body).map_err(|err| Error::Utf8Error(err.utf8_error()))
+}
@@ -0,0 +1,374 @@
+//! Drives the `hare serve` request-for-quote server against a local RadixEngineDB ledger with a published Maker
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+
+use radix_engine::ledger::*;
+use radix_engine::model::*;
+use radix_engine::transaction::*;
+use scrypto::buffer::{scrypto_decode, scrypto_encode};
+use scrypto::prelude::*;
+use simulator::ledger::*;
+
+use hare::harelib::cli::{compile, new_public_private_pair, parse_callback};
+use hare::harelib::serve::{QuoteResponse, Quoter, Strategy};
+use hareswap::api::*;
+
+/// The Maker served by the Quoter, the taker holds the quote and the taker_auth badge
+struct Env {
+    dir: PathBuf,
+    public_key: EcdsaPublicKey,
+    private_key: Vec<u8>,
+    key: EcdsaPublicKey,
+    account: Address,
+    maker: Address,
+    voucher_resource: Address,
+    base: Address,
+    quote: Address,
+    taker_auth: Address,
+}
+
+fn create_token<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    supply: i32,
+) -> Address {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .new_token_fixed(HashMap::new(), Decimal::from(supply))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    receipt.resource_def(0).unwrap()
+}
+
+/// add the instructions to withdraw from the signer's account to a new bucket
+fn withdraw<'a, A: AbiProvider>(
+    builder: &mut TransactionBuilder<'a, A>,
+    account: Address,
+    amount: Decimal,
+    resource_address: Address,
+) -> Bid {
+    let rid = builder.add_instruction(Instruction::CloneBucketRef { rid: Rid(1) }).2.unwrap();
+    builder.add_instruction(Instruction::CallMethod {
+        component_address: account,
+        method: "withdraw".to_owned(),
+        args: vec![scrypto_encode(&amount), scrypto_encode(&resource_address), scrypto_encode(&rid)],
+    });
+    builder
+        .add_instruction(Instruction::TakeFromWorktop {
+            amount,
+            resource_address,
+        })
+        .1
+        .unwrap()
+}
+
+/// creates a ledger in a fresh directory at the given epoch, publishes the hareswap package and instantiates a Maker
+/// signed for by a key created the same way as `hare new-key-pair`, with 500 base in its SharedAccount
+fn setup(name: &str, epoch: u64) -> Env {
+    let dir = std::env::temp_dir().join(format!("hare-serve-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // the ledger is closed at the end of the setup so the Quoter can open it
+    let mut ledger = RadixEngineDB::with_bootstrap(dir.clone());
+    ledger.set_epoch(epoch);
+    let (public_bytes, private_key) = new_public_private_pair(&mut ledger);
+    let public_key: EcdsaPublicKey = scrypto_decode(&public_bytes).unwrap();
+
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let key = executor.new_public_key();
+    let account = executor.new_account(key);
+    let package = executor
+        .publish_package(&compile(concat!(env!("CARGO_MANIFEST_DIR"), "/.."), "hareswap"))
+        .unwrap();
+    let base = create_token(&mut executor, key, account, 1000);
+    let quote = create_token(&mut executor, key, account, 1000);
+    let taker_auth = create_token(&mut executor, key, account, 1);
+    let account_auth = create_token(&mut executor, key, account, 1);
+
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(&executor)
+                .call_function(package, "SharedAccount", "new_easy", vec![account_auth.to_string()], None)
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    let shared_account = receipt.component(0).unwrap();
+
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(&executor)
+                .call_method(shared_account, "deposit", vec![format!("500,{}", base)], Some(account))
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+
+    let mut builder = TransactionBuilder::new(&executor);
+    let auth_bucket = withdraw(&mut builder, account, Decimal::one(), account_auth);
+    builder.add_instruction(Instruction::CallFunction {
+        package_address: package,
+        blueprint_name: "Maker".to_owned(),
+        function: "instantiate".to_owned(),
+        args: vec![
+            scrypto_encode(&public_key),
+            scrypto_encode(&Option::<Bid>::None),
+            scrypto_encode(&shared_account),
+            scrypto_encode(&auth_bucket),
+        ],
+    });
+    let receipt = executor
+        .run(builder.call_method_with_all_resources(account, "deposit_batch").build(vec![key]).unwrap())
+        .unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+
+    let voucher_resource = receipt
+        .logs
+        .iter()
+        .find_map(|(_, log)| log.strip_prefix("tokenized order resource address: "))
+        .map(|address| Address::from_str(address).unwrap())
+        .unwrap();
+
+    Env {
+        dir,
+        public_key,
+        private_key,
+        key,
+        account,
+        // the Transporter is instantiated first
+        maker: receipt.component(1).unwrap(),
+        voucher_resource,
+        base,
+        quote,
+        taker_auth,
+    }
+}
+
+fn new_quoter(env: &Env, partially_fillable: bool) -> Quoter {
+    let strategy: Strategy = serde_json::from_str(&format!(
+        r#"{{
+            "ttl_epochs": 10,
+            "partially_fillable": {},
+            "pairs": [
+                {{ "base": "{}", "quote": "{}", "bid": "0.45", "ask": "0.5", "max_base": "1000.0" }}
+            ]
+        }}"#,
+        partially_fillable, env.base, env.quote
+    ))
+    .unwrap();
+    Quoter::new(
+        strategy,
+        env.maker,
+        env.voucher_resource.into(),
+        parse_callback(env.maker, None).unwrap(),
+        env.private_key.clone(),
+        env.dir.clone(),
+    )
+}
+
+fn request_json(env: &Env, quote_type: &str, amount: &str, base: Address) -> String {
+    format!(
+        r#"{{"quote_type": "{}", "resource_b_amount": "{}", "resource_b": "{}", "resource_q": "{}", "resource_taker_auth": "{}"}}"#,
+        quote_type, amount, base, env.quote, env.taker_auth
+    )
+}
+
+/// decodes the SignedOrder from the served instruction and checks the sealed voucher signature
+fn verified_signed_order(env: &Env, response: &QuoteResponse, method: &str) -> SignedOrder {
+    let tx = transaction_manifest::compile(&format!("{};", response.signed_order)).unwrap();
+    let signed_order: SignedOrder = match &tx.instructions[0] {
+        Instruction::CallMethod {
+            component_address,
+            method: called,
+            args,
+        } => {
+            assert_eq!(*component_address, env.maker);
+            assert_eq!(called, method);
+            scrypto_decode(&args[0]).unwrap()
+        }
+        _ => panic!("expected a CallMethod instruction"),
+    };
+
+    assert!(verify(&env.public_key, &scrypto_encode(&signed_order.to_voucher()), &signed_order.signature).is_ok());
+    signed_order
+}
+
+/// The taker fills fill_amount of the base of a served order on the ledger, paying the given quote amount
+fn fill<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    env: &Env,
+    signed_order: &SignedOrder,
+    fill_amount: i32,
+    payment: i32,
+) -> Receipt {
+    let mut builder = TransactionBuilder::new(executor);
+    let auth_bucket = withdraw(&mut builder, env.account, Decimal::one(), env.taker_auth);
+    let auth = builder.add_instruction(Instruction::CreateBucketRef { bid: auth_bucket }).2.unwrap();
+    let from_taker = withdraw(&mut builder, env.account, Decimal::from(payment), env.quote);
+    builder.add_instruction(Instruction::CallMethod {
+        component_address: env.maker,
+        method: "fill_order".to_owned(),
+        args: vec![
+            scrypto_encode(signed_order),
+            scrypto_encode(&Decimal::from(fill_amount)),
+            scrypto_encode(&from_taker),
+            scrypto_encode(&auth),
+        ],
+    });
+    let receipt = executor
+        .run(builder.call_method_with_all_resources(env.account, "deposit_batch").build(vec![env.key]).unwrap())
+        .unwrap();
+    println!("{:?}\n", receipt);
+    receipt
+}
+
+fn get_remaining<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    env: &Env,
+    signed_order: &SignedOrder,
+) -> Decimal {
+    let mut builder = TransactionBuilder::new(executor);
+    builder.add_instruction(Instruction::CallMethod {
+        component_address: env.maker,
+        method: "get_remaining".to_owned(),
+        args: vec![scrypto_encode(signed_order)],
+    });
+    let receipt = executor.run(builder.build(vec![env.key]).unwrap()).unwrap();
+    assert!(receipt.result.is_ok());
+    scrypto_decode(&receipt.outputs[0].raw).unwrap()
+}
+
+#[test]
+fn test_served_order_fills_on_ledger() {
+    let env = setup("fill", 7);
+    let mut quoter = new_quoter(&env, true);
+
+    let response: QuoteResponse =
+        serde_json::from_str(&quoter.handle_json(&request_json(&env, "buy-base", "200.0", env.base))).unwrap();
+    assert_eq!(response.deadline, 17);
+    let signed_order = verified_signed_order(&env, &response, "fill_order");
+    assert_eq!(signed_order.nonce, 7);
+    assert_eq!(signed_order.order_hash(), response.order_hash);
+    assert_eq!(signed_order.order.quote_contents, BucketContents::Fungible(Decimal::from(100)));
+
+    // the taker fills a quarter of the served order on the Maker, paying the scaled quote
+    let mut ledger = RadixEngineDB::with_bootstrap(env.dir.clone());
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    assert!(fill(&mut executor, &env, &signed_order, 50, 25).result.is_ok());
+    assert_eq!(get_remaining(&mut executor, &env, &signed_order), Decimal::from(150));
+
+    // the served price is enforced on the ledger
+    assert!(fill(&mut executor, &env, &signed_order, 50, 24).result.is_err());
+
+    // once the deadline passed the served order can't be filled anymore
+    executor.ledger_mut().set_epoch(18);
+    assert!(fill(&mut executor, &env, &signed_order, 50, 25).result.is_err());
+}
+
+#[test]
+fn test_quote_is_priced_and_signed() {
+    let env = setup("quote", 3);
+    let mut quoter = new_quoter(&env, false);
+
+    let buy: QuoteResponse =
+        serde_json::from_str(&quoter.handle_json(&request_json(&env, "buy-base", "200.0", env.base))).unwrap();
+    let buy_order = verified_signed_order(&env, &buy, "execute_order");
+    assert!(!buy_order.order.partially_fillable);
+    assert!(!buy_order.order.partial_order.inverted);
+    assert_eq!(buy_order.order.quote_contents, BucketContents::Fungible(Decimal::from(100)));
+
+    // selling base is priced at the bid and gets a different voucher key
+    let sell: QuoteResponse =
+        serde_json::from_str(&quoter.handle_json(&request_json(&env, "sell-base", "200.0", env.base))).unwrap();
+    let sell_order = verified_signed_order(&env, &sell, "execute_order");
+    assert_eq!(sell_order.order.quote_contents, BucketContents::Fungible(Decimal::from(90)));
+    assert_ne!(sell.voucher_key, buy.voucher_key);
+}
+
+#[test]
+fn test_quote_rejects_unknown_pair_and_limit() {
+    let env = setup("reject", 1);
+    let mut quoter = new_quoter(&env, false);
+
+    let unknown: serde_json::Value =
+        serde_json::from_str(&quoter.handle_json(&request_json(&env, "buy-base", "1.0", env.taker_auth))).unwrap();
+    assert!(unknown["error"].as_str().unwrap().contains("UnknownPairError"));
+
+    let too_big: serde_json::Value =
+        serde_json::from_str(&quoter.handle_json(&request_json(&env, "buy-base", "5000.0", env.base))).unwrap();
+    assert!(too_big["error"].as_str().unwrap().contains("StrategyLimitError"));
+
+    let malformed: serde_json::Value = serde_json::from_str(&quoter.handle_json("{")).unwrap();
+    assert!(malformed["error"].as_str().unwrap().contains("JsonError"));
+}
+
+#[test]
+fn test_serve_http() {
+    let env = setup("http", 3);
+    let mut quoter = new_quoter(&env, false);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    std::thread::spawn(move || quoter.serve_http(listener));
+
+    let body = request_json(&env, "buy-base", "10.0", env.base);
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "POST /rfq HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        address,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+
+    assert!(reply.starts_with("HTTP/1.1 200 OK"));
+    let (_, response_json) = reply.split_once("\r\n\r\n").unwrap();
+    let response: QuoteResponse = serde_json::from_str(response_json).unwrap();
+    assert_eq!(response.deadline, 13);
+    let signed_order = verified_signed_order(&env, &response, "execute_order");
+    assert_eq!(signed_order.order.quote_contents, BucketContents::Fungible(Decimal::from(5)));
+}
+
+#[test]
+fn test_serve_unix() {
+    let env = setup("unix", 5);
+    let mut quoter = new_quoter(&env, false);
+    let socket = env.dir.join("hare.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+    std::thread::spawn(move || quoter.serve_unix(listener));
+
+    // one response line per request line on the same connection, blank lines are skipped
+    let mut stream = UnixStream::connect(&socket).unwrap();
+    writeln!(stream, "{}", request_json(&env, "buy-base", "10.0", env.base)).unwrap();
+    writeln!(stream).unwrap();
+    writeln!(stream, "{{").unwrap();
+    writeln!(stream, "{}", request_json(&env, "sell-base", "10.0", env.base)).unwrap();
+    let mut lines = BufReader::new(stream).lines();
+
+    let buy: QuoteResponse = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+    assert_eq!(buy.deadline, 15);
+    let buy_order = verified_signed_order(&env, &buy, "execute_order");
+    assert_eq!(buy_order.order.quote_contents, BucketContents::Fungible(Decimal::from(5)));
+
+    // a bad request is answered with an error and the connection keeps being served
+    let malformed: serde_json::Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+    assert!(malformed["error"].as_str().unwrap().contains("JsonError"));
+
+    let sell: QuoteResponse = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+    let sell_order = verified_signed_order(&env, &sell, "execute_order");
+    assert_eq!(sell_order.order.quote_contents, BucketContents::Fungible(Decimal::from_str("4.5").unwrap()));
+}
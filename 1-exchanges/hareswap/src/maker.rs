@@ -6,11 +6,12 @@ use scrypto::prelude::*;
 
 use super::account::SharedAccount;
 use super::requirement::{BucketContents, BucketRequirement};
+use super::transporter::authentication::verify_or_panic;
 use super::transporter::blueprint::Transporter;
 use super::transporter::voucher::Voucher;
 
 // The HareSwap specific data types
-use super::model::{Callback, MatchedOrder, SignedOrder};
+use super::model::{cancel_order_message, invalidate_orders_message, Callback, FillState, MatchedOrder, SignedOrder};
 
 blueprint! {
     /// Storage for a Maker component
@@ -23,6 +24,7 @@ blueprint! {
         redeem_auth: Vault,
         account: SharedAccount,
         account_auth: Vault,
+        fills: LazyMap<String, FillState>, // partially fillable orders by order hash
     }
 
     impl Maker {
@@ -59,7 +61,8 @@ blueprint! {
                 order_def,
                 redeem_auth,
                 account: account.into(), // convert the Component (address) passed in to a SharedAccount for use by the default callback.  If the callback and account interface mismatch, panic ensues.
-                account_auth: Vault::with_bucket(account_auth)
+                account_auth: Vault::with_bucket(account_auth),
+                fills: LazyMap::new(),
             }.instantiate()
         }
 
@@ -87,6 +90,7 @@ blueprint! {
                 redeem_auth,
                 account: account.into(),
                 account_auth,
+                fills: LazyMap::new(),
             }.instantiate()
         }
 
@@ -247,13 +251,16 @@ blueprint! {
                 order,
                 voucher_resource,
                 voucher_key,
+                nonce,
                 signature,
             } = signed_order;
             // check taker_auth matches the order before redeeming it.  (if it matches but the signature is bad it wont redeem properly anyway.  This stops frontrunning)
             assert_eq!(order.partial_order.taker_auth.check_at_least_ref(&taker_auth), true, "tokenize_order: taker_auth not accepted");
+            // the fill tracking would be bypassed if a partially fillable order could also be executed in full
+            assert_eq!(order.partially_fillable, false, "tokenize_order: partially fillable orders must use fill_order");
 
             // rebuild a voucher from the SignedOrder contents (ie. the MatchedOrder data and voucher metadata)
-            let voucher = Voucher::from_nfd(voucher_resource, Some(voucher_key), order);
+            let voucher = Voucher::from_nfd(voucher_resource, Some(voucher_key), order).with_nonce(nonce);
 
             // and then rebuild the sealed_voucher by serializing and including the signature
             let sealed_voucher = voucher.to_sealed(signature);
@@ -264,6 +271,106 @@ blueprint! {
             )
         }
 
+        /// Fill part of a partially fillable SignedOrder.  fill_amount is the amount of the base asset to exchange, the
+        /// quote amount is scaled by the same ratio.  Returns the "from Maker" Bucket.
+        ///
+        /// Instead of redeeming the voucher (which only works once), the signature is verified here and the filled
+        /// amount is tracked by order hash so the same SignedOrder can be filled by several takers until it is used up,
+        /// expires or is cancelled.  Only fungible orders can be partially filled.
+        pub fn fill_order(&mut self, signed_order: SignedOrder, fill_amount: Decimal, from_taker: Bucket, taker_auth: BucketRef) -> Bucket {
+            let order_hash = signed_order.order_hash();
+            let voucher = signed_order.to_voucher();
+            let SignedOrder {
+                order,
+                voucher_resource,
+                signature,
+                ..
+            } = signed_order;
+            assert_eq!(order.partially_fillable, true, "fill_order: order is not partially fillable, use execute_order");
+            // the signature alone does not bind the order to this Maker, other Makers may share the verifying_key
+            assert_eq!(order.maker_address, self.component_address(), "fill_order: order was made for another Maker");
+            assert_eq!(voucher_resource, self.order_def, "fill_order: order was signed for another Maker's Transporter");
+            assert_eq!(order.partial_order.taker_auth.check_at_least_ref(&taker_auth), true, "fill_order: taker_auth not accepted");
+            taker_auth.drop();
+
+            // authenticate the order, panics on a bad signature or revoked nonce
+            let _ = voucher.to_sealed(signature).unseal(&self.verifying_key, self.transporter.min_nonce());
+
+            let epoch = Context::current_epoch();
+            assert!(epoch <= order.deadline, "The order has expired.  Current epoch ({}) is past the order deadline ({})", epoch, order.deadline);
+
+            let (base_total, quote_total) = match (&order.partial_order.base_requirement.contents, &order.quote_contents) {
+                (BucketContents::Fungible(base_total), BucketContents::Fungible(quote_total)) => (*base_total, *quote_total),
+                _ => panic!("fill_order: only fungible orders can be partially filled"),
+            };
+
+            // update the remaining capacity before settling
+            let mut fill_state = self.get_fill_state(order_hash.clone());
+            assert_eq!(fill_state.cancelled, false, "fill_order: order has been cancelled");
+            assert!(fill_amount > Decimal::zero(), "fill_order: fill_amount must be positive");
+            assert!(fill_state.filled + fill_amount <= base_total, "fill_order: fill_amount ({}) is more than the remaining amount ({})", fill_amount, base_total - fill_state.filled);
+            fill_state.filled += fill_amount;
+            self.fills.insert(order_hash, fill_state);
+
+            // settle a copy of the order scaled down to the fill, the callback checks the requirements as usual
+            let mut fill = order;
+            fill.partial_order.base_requirement.contents = BucketContents::Fungible(fill_amount);
+            fill.quote_contents = BucketContents::Fungible(quote_total * fill_amount / base_total);
+            info!("fill_order: filling {} of {}", fill_amount, base_total);
+            self.settle_order(fill, from_taker)
+        }
+
+        /// Returns the fill state of a partially fillable order.  Orders never filled or cancelled have nothing filled.
+        pub fn get_fill_state(&self, order_hash: String) -> FillState {
+            self.fills.get(&order_hash).unwrap_or(FillState {
+                filled: Decimal::zero(),
+                cancelled: false,
+            })
+        }
+
+        /// Returns how much of the base asset of a partially fillable order can still be filled
+        pub fn get_remaining(&self, signed_order: SignedOrder) -> Decimal {
+            let fill_state = self.get_fill_state(signed_order.order_hash());
+            match signed_order.order.partial_order.base_requirement.contents {
+                BucketContents::Fungible(base_total) if !fill_state.cancelled => base_total - fill_state.filled,
+                _ => Decimal::zero(),
+            }
+        }
+
+        /// Cancel the unfilled remainder of a partially fillable order early.
+        ///
+        /// signature must be made by the signer over `cancel_order_message(maker_address, order_hash)`, see `hare sign-cancel-order`
+        pub fn cancel_order(&mut self, order_hash: String, signature: Vec<u8>) {
+            verify_or_panic(&self.verifying_key, &cancel_order_message(self.component_address(), &order_hash), &signature);
+            let mut fill_state = self.get_fill_state(order_hash.clone());
+            fill_state.cancelled = true;
+            self.fills.insert(order_hash, fill_state);
+        }
+
+        /// Revoke every SignedOrder signed with a nonce lower than min_nonce in one transaction, ie. a leaked or mispriced batch of quotes.
+        ///
+        /// `hare` signs with the current epoch as the nonce by default, so this invalidates every order signed before
+        /// epoch min_nonce.  The minimum can only increase.
+        ///
+        /// signature must be made by the signer over `invalidate_orders_message(maker_address, min_nonce)`, see `hare sign-invalidate-orders`
+        ///
+        /// NOTE: orders already tokenized are not affected, the order token is redeemable until the order deadline
+        pub fn invalidate_orders(&mut self, min_nonce: u64, signature: Vec<u8>) {
+            verify_or_panic(&self.verifying_key, &invalidate_orders_message(self.component_address(), min_nonce), &signature);
+            self.redeem_auth.authorize(|auth| self.transporter.set_min_nonce(min_nonce, auth));
+            info!("invalidate_orders: orders signed with a nonce lower than {} are revoked", min_nonce);
+        }
+
+        /// Returns the lowest nonce a SignedOrder can be signed with and still be executed
+        pub fn get_min_nonce(&self) -> u64 {
+            self.transporter.min_nonce()
+        }
+
+        /// The address of this Maker component
+        fn component_address(&self) -> Address {
+            Context::actor().component_address().unwrap()
+        }
+
         /// Execute the SignedOrder sending in the "from Taker" Bucket and returning the "from Maker" Bucket.  ie. the entrypoint for doing things the easy way
         ///
         /// Only the actor with taker_auth is allowed to execute the order to avoid frontrunning.
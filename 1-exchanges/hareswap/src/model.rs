@@ -1,8 +1,10 @@
 //! This module holds the "application layer" data types including [PartialOrder] [MatchedOrder] and [SignedOrder]
 use sbor::{Decode, Describe, Encode, TypeId};
 use scrypto::prelude::*;
+use scrypto::utils::sha256;
 
 use super::requirement::{BucketContents, BucketRequirement};
+use super::transporter::voucher::Voucher;
 
 /// A description of a function or method call (optionally bound with arguments) exactly as in a normal transaction Instruction
 ///
@@ -59,6 +61,8 @@ pub struct MatchedOrder {
     pub maker_callback: Callback,
     /// The order deadline, specified as the last (greatest) epoch where the order can still be executed
     pub deadline: u64,
+    /// true if several takers may each fill part of the order with `fill_order` instead of the all-or-nothing `execute_order`
+    pub partially_fillable: bool,
 }
 
 /// mod signed_order contains only the SignedOrder struct because...
@@ -83,7 +87,42 @@ mod signed_order {
         pub order: MatchedOrder,
         pub voucher_resource: ResourceDef,
         pub voucher_key: NonFungibleKey,
+        pub nonce: u64,
         pub signature: Vec<u8>,
     }
 }
 pub use signed_order::SignedOrder;
+
+impl SignedOrder {
+    /// Rebuild the Voucher covered by the signature from the SignedOrder contents (ie. the MatchedOrder data and voucher metadata)
+    pub fn to_voucher(&self) -> Voucher {
+        Voucher::from_nfd(self.voucher_resource.clone(), Some(self.voucher_key.clone()), self.order.clone())
+            .with_nonce(self.nonce)
+    }
+
+    /// Identifies the order independently of who submits it, used by the Maker to track partial fills
+    ///
+    /// This is the hex encoded sha256 of the serialized Voucher, ie. exactly the bytes covered by the signature
+    pub fn order_hash(&self) -> String {
+        hex::encode(sha256(scrypto_encode(&self.to_voucher())))
+    }
+}
+
+/// The message the signer signs to cancel the unfilled remainder of a partially fillable order on the Maker component
+///
+/// The Maker is part of the message so the signature can not be replayed on another Maker sharing the same key
+pub fn cancel_order_message(maker_address: Address, order_hash: &str) -> Vec<u8> { scrypto_encode(&("cancel_order", maker_address, order_hash)) }
+
+/// The message the signer signs to revoke every order signed with a nonce lower than min_nonce on the Maker component
+///
+/// The Maker is part of the message so the signature can not be replayed on another Maker sharing the same key
+pub fn invalidate_orders_message(maker_address: Address, min_nonce: u64) -> Vec<u8> { scrypto_encode(&("invalidate_orders", maker_address, min_nonce)) }
+
+/// How much of a partially fillable order has been filled, tracked by the Maker by order hash
+#[derive(Debug, Clone, TypeId, Encode, Decode, PartialEq, Eq, Describe)]
+pub struct FillState {
+    /// amount of the base asset already exchanged
+    pub filled: Decimal,
+    /// true once the signer cancelled the unfilled remainder
+    pub cancelled: bool,
+}
@@ -28,6 +28,7 @@ blueprint! {
         burn_authority: Vault,
         count: u128,
         public_key: EcdsaPublicKey,
+        min_nonce: u64, // SealedVouchers with a lower nonce are revoked
         redeem_auth: ResourceDef, // avoid duplicates/frontrunning
     }
 
@@ -91,6 +92,7 @@ blueprint! {
                 burn_authority: Vault::with_bucket(burn_authority),
                 count: 0,
                 public_key,
+                min_nonce: 0,
                 redeem_auth,
             }
             .instantiate()
@@ -101,22 +103,34 @@ blueprint! {
             self.resource_def.clone()
         }
 
+        /// return the lowest Voucher nonce this Transporter still redeems
+        pub fn min_nonce(&self) -> u64 {
+            self.min_nonce
+        }
+
+        /// revoke every SealedVoucher signed with a nonce lower than min_nonce.  The minimum can only increase
+        #[auth(redeem_auth)]
+        pub fn set_min_nonce(&mut self, min_nonce: u64) {
+            assert!(min_nonce >= self.min_nonce, "Transporter::set_min_nonce: nonce can not decrease (current minimum is {})", self.min_nonce);
+            self.min_nonce = min_nonce;
+        }
+
         /// redeem SealedVoucher for Bucket without key (expecting Voucher has a baked in key)
         #[auth(redeem_auth)]
         pub fn redeem_without_key(&mut self, sealed_voucher: SealedVoucher) -> Bucket {
-            self.voucher_redeem(sealed_voucher.unseal(&self.public_key), None)
+            self.voucher_redeem(sealed_voucher.unseal(&self.public_key, self.min_nonce), None)
         }
 
         /// redeem SealedVoucher for Bucket with key
         #[auth(redeem_auth)]
         pub fn redeem_with_key(&mut self, sealed_voucher: SealedVoucher, key: NonFungibleKey) -> Bucket {
-            self.voucher_redeem(sealed_voucher.unseal(&self.public_key), Some(key))
+            self.voucher_redeem(sealed_voucher.unseal(&self.public_key, self.min_nonce), Some(key))
         }
 
         /// redeem SealedVoucher for Bucket with optional key (ie. the flexible API)
         #[auth(redeem_auth)]
         pub fn redeem(&mut self, sealed_voucher: SealedVoucher, optional_key: Option<NonFungibleKey>) -> Bucket {
-            self.voucher_redeem(sealed_voucher.unseal(&self.public_key), optional_key)
+            self.voucher_redeem(sealed_voucher.unseal(&self.public_key, self.min_nonce), optional_key)
         }
 
         /// redeem SealedVoucher for Bucket without key, expecting Transporter to manage the NonFungibleKey using a counter
@@ -125,7 +139,7 @@ blueprint! {
         #[auth(redeem_auth)]
         pub fn redeem_next(&mut self, sealed_voucher: SealedVoucher) -> Bucket {
             self.count += 1;
-            self.voucher_redeem(sealed_voucher.unseal(&self.public_key), Some(self.count.into()))
+            self.voucher_redeem(sealed_voucher.unseal(&self.public_key, self.min_nonce), Some(self.count.into()))
         }
 
         /// Dispose of the bucket and makes an unsigned opaque Voucher to be signed in it's place.
@@ -175,7 +189,7 @@ blueprint! {
                     resource_def.burn_with_auth(bucket, auth)
                 );
 
-                return Voucher::from_nfd(resource_def, key, nfd);  // purposeful "return".  More complex implemenation would handle multiple nfds
+                return Voucher::from_nfd(resource_def, key, nfd).with_nonce(self.min_nonce);  // purposeful "return".  More complex implemenation would handle multiple nfds
             };
             panic!("unreachable"); // asserted 1 in bucket
         }
@@ -84,6 +84,8 @@ pub struct Voucher {
     resource_def: ResourceDef,
     /// the NonFungibleKey for the asset the Voucher represents.  Optional because the redeemer may be expected to provide that information
     key: Option<NonFungibleKey>,
+    /// covered by the signature so the signer can revoke every Voucher signed with a lower nonce.  See `SealedVoucher::unseal`
+    nonce: u64,
     /// the NonFungibleData for the asset the Voucher represents, but serialized as a PassThruNFD to make everything work at runtime.
     nfd: PassThruNFD,
 }
@@ -110,6 +112,7 @@ impl Voucher {
             mut resource_def,
             key,
             nfd,
+            ..
         } = self;
         assert_eq!(
             resource_def, *required_resource_def,
@@ -137,10 +140,22 @@ impl Voucher {
         Voucher {
             resource_def,
             key,
+            nonce: 0,
             nfd: nfd.as_passthru(), // calling as_passthru is the "trick" and is an implementation detail for how Vouchers work which can be ignored for users.
         }
     }
 
+    /// set the nonce of this Voucher.  Vouchers created with `from_nfd` have nonce 0
+    pub fn with_nonce(mut self, nonce: u64) -> Voucher {
+        self.nonce = nonce;
+        self
+    }
+
+    /// the nonce this Voucher was (or will be) signed with
+    pub fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
     /// create a SealedVoucher from this Voucher and an opaque signature
     pub fn to_sealed(&self, signature: Vec<u8>) -> SealedVoucher {
         SealedVoucher {
@@ -163,15 +178,16 @@ impl PrivateDecode for Voucher {
         if index != ::sbor::type_id::FIELDS_TYPE_NAMED {
             return Err(::sbor::DecodeError::InvalidIndex(index));
         }
-        decoder.check_len(3)?;
+        decoder.check_len(4)?;
         let resource_def = ResourceDef::decode(decoder)?;
         let key = Option::<NonFungibleKey>::decode(decoder)?;
+        let nonce = u64::decode(decoder)?;
         // cannot derive Decode for Voucher because the decode method is
         // implemented for both  NonFungibleData and Decode traits.
         // Disambiguate here using "as sbor::Decode"
         let nfd = <PassThruNFD as sbor::Decode>::decode(decoder)?;
         decoder.check_end()?;
-        Ok(Voucher { resource_def, key, nfd })
+        Ok(Voucher { resource_def, key, nonce, nfd })
     }
 }
 
@@ -185,13 +201,18 @@ pub struct SealedVoucher {
 impl SealedVoucher {
     /// Converts a SealedVoucher back to a Voucher by verifying the signature against public_key.__rust_force_expr!
     ///
+    /// The Voucher nonce must be at least min_nonce.  Raising min_nonce revokes every Voucher signed with a lower nonce
+    /// at once, without having to know them.
+    ///
     /// # PANICS
     ///
-    /// Will panic if the signature does not verify or the data can not be decoded into a Voucher.
-    pub fn unseal(&self, public_key: &EcdsaPublicKey) -> Voucher {
+    /// Will panic if the signature does not verify, the data can not be decoded into a Voucher or the nonce has been revoked.
+    pub fn unseal(&self, public_key: &EcdsaPublicKey, min_nonce: u64) -> Voucher {
         debug!("SealedVoucher::unseal: serialized: {}", hex::encode(&self.serialized));
         debug!("SealedVoucher::unseal:  signature: {}", hex::encode(&self.signature));
         verify_or_panic(public_key, &self.serialized, &self.signature); // NOTE: panics on failure
-        private_decode_with_type(&self.serialized).unwrap()
+        let voucher: Voucher = private_decode_with_type(&self.serialized).unwrap();
+        assert!(voucher.nonce >= min_nonce, "SealedVoucher::unseal: nonce {} has been revoked (minimum nonce is {})", voucher.nonce, min_nonce);
+        voucher
     }
 }
@@ -1,7 +1,11 @@
 //! It's pretty hard write complex intergration tests, and they are slow to complie/link
 //! So the integration testing is happening externally utilizing resim and hare cli and
-//! a bunch of shell scripts
+//! a bunch of shell scripts.  The on-ledger order bookkeeping (fills, cancels, deadlines)
+//! is checked here with orders signed directly in the test
+use k256::ecdsa::{signature::Signer, Signature, SigningKey};
+use radix_engine::engine::*;
 use radix_engine::ledger::*;
+use radix_engine::model::*;
 use radix_engine::transaction::*;
 use scrypto::prelude::*;
 
@@ -16,3 +20,405 @@ fn test_publish() {
     let _account = executor.new_account(key);
     let _package = executor.publish_package(include_code!("hareswap")).unwrap();
 }
+
+/// The signer's off-ledger key, only used by these tests
+const MAKER_PRIVATE_KEY: [u8; 32] = [7u8; 32];
+
+/// Everything needed to sign orders for a Maker and fill them as the taker
+struct Env {
+    key: EcdsaPublicKey,
+    account: Address,
+    maker: Address,
+    voucher_resource: Address,
+    base: Address,
+    quote: Address,
+    taker_auth: Address,
+}
+
+fn get_vault_info<'a, L: SubstateStore>(ledger: &'a L, component: &Address, id: &Vid) -> (Address, Decimal) {
+    let vault = ledger.get_vault(component, id).unwrap();
+    (vault.resource_address(), vault.amount())
+}
+
+fn get_lazymap_info<'a, L: SubstateStore>(ledger: &'a L, component: &Address, id: &Mid) -> Vec<(Address, Decimal)> {
+    let lazy_map = ledger.get_lazy_map(component, id).unwrap();
+    lazy_map
+        .map()
+        .iter()
+        .flat_map(|(_, data)| {
+            let validated_data = validate_data(data).unwrap();
+            validated_data
+                .vaults
+                .iter()
+                .map(|vid| get_vault_info(ledger, component, vid))
+                .collect::<Vec<(Address, Decimal)>>()
+        })
+        .collect()
+}
+
+fn balance<'a, L: SubstateStore>(ledger: &'a L, account: Address, token: Address) -> Decimal {
+    let component = ledger.get_component(account).unwrap();
+    let validated_data = validate_data(component.state()).unwrap();
+    validated_data
+        .lazy_maps
+        .iter()
+        .flat_map(|mid| get_lazymap_info(ledger, &account, &mid))
+        .filter(|(address, _)| *address == token)
+        .map(|(_, amount)| amount)
+        .next()
+        .unwrap_or(Decimal::zero())
+}
+
+/// sign the message the same way `hare` does, ie. an ASN.1 DER signature
+fn sign_message(message: &[u8]) -> Vec<u8> {
+    let signing_key = SigningKey::from_bytes(&MAKER_PRIVATE_KEY).unwrap();
+    let signature: Signature = signing_key.sign(message);
+    signature.to_der().to_bytes().to_vec()
+}
+
+fn maker_public_key() -> EcdsaPublicKey {
+    let signing_key = SigningKey::from_bytes(&MAKER_PRIVATE_KEY).unwrap();
+    let mut public_raw = [0u8; 33];
+    public_raw[..].copy_from_slice(&signing_key.verifying_key().to_bytes());
+    EcdsaPublicKey(public_raw)
+}
+
+fn create_token<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    supply: i32,
+) -> Address {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .new_token_fixed(HashMap::new(), Decimal::from(supply))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    receipt.resource_def(0).unwrap()
+}
+
+/// add the instructions to withdraw from the signer's account to a new bucket
+fn withdraw<'a, A: AbiProvider>(
+    builder: &mut TransactionBuilder<'a, A>,
+    account: Address,
+    amount: Decimal,
+    resource_address: Address,
+) -> Bid {
+    let rid = builder.add_instruction(Instruction::CloneBucketRef { rid: Rid(1) }).2.unwrap();
+    builder.add_instruction(Instruction::CallMethod {
+        component_address: account,
+        method: "withdraw".to_owned(),
+        args: vec![scrypto_encode(&amount), scrypto_encode(&resource_address), scrypto_encode(&rid)],
+    });
+    builder
+        .add_instruction(Instruction::TakeFromWorktop {
+            amount,
+            resource_address,
+        })
+        .1
+        .unwrap()
+}
+
+/// Sets up a Maker with 500 base in its SharedAccount, the taker holds the quote and the taker_auth badge
+fn setup<'a, L: SubstateStore>(executor: &mut TransactionExecutor<'a, L>) -> Env {
+    let key = executor.new_public_key();
+    let account = executor.new_account(key);
+    let package = executor.publish_package(include_code!("hareswap")).unwrap();
+    let base = create_token(executor, key, account, 1000);
+    let quote = create_token(executor, key, account, 1000);
+    let taker_auth = create_token(executor, key, account, 1);
+    let account_auth = create_token(executor, key, account, 1);
+
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_function(package, "SharedAccount", "new_easy", vec![account_auth.to_string()], None)
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    let shared_account = receipt.component(0).unwrap();
+
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(shared_account, "deposit", vec![format!("500,{}", base)], Some(account))
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+
+    let mut builder = TransactionBuilder::new(executor);
+    let auth_bucket = withdraw(&mut builder, account, Decimal::one(), account_auth);
+    builder.add_instruction(Instruction::CallFunction {
+        package_address: package,
+        blueprint_name: "Maker".to_owned(),
+        function: "instantiate".to_owned(),
+        args: vec![
+            scrypto_encode(&maker_public_key()),
+            scrypto_encode(&Option::<Bid>::None),
+            scrypto_encode(&shared_account),
+            scrypto_encode(&auth_bucket),
+        ],
+    });
+    let receipt = executor
+        .run(builder.call_method_with_all_resources(account, "deposit_batch").build(vec![key]).unwrap())
+        .unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+
+    let voucher_resource = receipt
+        .logs
+        .iter()
+        .find_map(|(_, log)| log.strip_prefix("tokenized order resource address: "))
+        .map(|address| Address::from_str(address).unwrap())
+        .unwrap();
+
+    Env {
+        key,
+        account,
+        // the Transporter is instantiated first
+        maker: receipt.component(1).unwrap(),
+        voucher_resource,
+        base,
+        quote,
+        taker_auth,
+    }
+}
+
+/// Signs a partially fillable order where the taker buys 200 base for 100 quote
+fn sign_order(env: &Env, voucher_key: u128, deadline: u64, nonce: u64) -> SignedOrder {
+    let order = MatchedOrder {
+        partial_order: PartialOrder {
+            inverted: false,
+            base_requirement: BucketRequirement {
+                resource: env.base.into(),
+                contents: BucketContents::Fungible(Decimal::from(200)),
+            },
+            quote_resource: env.quote.into(),
+            taker_auth: BucketRequirement {
+                resource: env.taker_auth.into(),
+                contents: BucketContents::Fungible(Decimal::one()),
+            },
+        },
+        quote_contents: BucketContents::Fungible(Decimal::from(100)),
+        maker_address: env.maker,
+        maker_callback: Callback::CallMethod {
+            component_address: env.maker,
+            method: "handle_order_default_callback".to_owned(),
+            args: vec![],
+        },
+        deadline,
+        partially_fillable: true,
+    };
+    let mut signed_order = SignedOrder {
+        order,
+        voucher_resource: env.voucher_resource.into(),
+        voucher_key: voucher_key.into(),
+        nonce,
+        signature: vec![],
+    };
+    resign(&mut signed_order);
+    signed_order
+}
+
+/// Signs the SignedOrder again after its contents were changed
+fn resign(signed_order: &mut SignedOrder) {
+    signed_order.signature = sign_message(&scrypto_encode(&signed_order.to_voucher()));
+}
+
+/// The taker fills fill_amount of the base paying the given quote amount, returns the base received or None if the fill failed
+fn fill<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    env: &Env,
+    signed_order: &SignedOrder,
+    fill_amount: i32,
+    payment: i32,
+) -> Option<Decimal> {
+    let before = balance(executor.ledger(), env.account, env.base);
+
+    let mut builder = TransactionBuilder::new(executor);
+    let auth_bucket = withdraw(&mut builder, env.account, Decimal::one(), env.taker_auth);
+    let auth = builder.add_instruction(Instruction::CreateBucketRef { bid: auth_bucket }).2.unwrap();
+    let from_taker = withdraw(&mut builder, env.account, Decimal::from(payment), env.quote);
+    builder.add_instruction(Instruction::CallMethod {
+        component_address: env.maker,
+        method: "fill_order".to_owned(),
+        args: vec![
+            scrypto_encode(signed_order),
+            scrypto_encode(&Decimal::from(fill_amount)),
+            scrypto_encode(&from_taker),
+            scrypto_encode(&auth),
+        ],
+    });
+    let receipt = executor
+        .run(builder.call_method_with_all_resources(env.account, "deposit_batch").build(vec![env.key]).unwrap())
+        .unwrap();
+    println!("{:?}\n", receipt);
+
+    match receipt.result {
+        Ok(_) => Some(balance(executor.ledger(), env.account, env.base) - before),
+        Err(_) => None,
+    }
+}
+
+fn get_remaining<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    env: &Env,
+    signed_order: &SignedOrder,
+) -> Decimal {
+    let mut builder = TransactionBuilder::new(executor);
+    builder.add_instruction(Instruction::CallMethod {
+        component_address: env.maker,
+        method: "get_remaining".to_owned(),
+        args: vec![scrypto_encode(signed_order)],
+    });
+    let receipt = executor.run(builder.build(vec![env.key]).unwrap()).unwrap();
+    assert!(receipt.result.is_ok());
+    scrypto_decode(&receipt.outputs[0].raw).unwrap()
+}
+
+/// Calls a Maker method authenticated by a signature of the message, as printed by the hare sign-* commands
+fn call_signed<'a, L: SubstateStore, T: Encode>(
+    executor: &mut TransactionExecutor<'a, L>,
+    env: &Env,
+    method: &str,
+    arg: &T,
+    message: &[u8],
+) -> Receipt {
+    let mut builder = TransactionBuilder::new(executor);
+    builder.add_instruction(Instruction::CallMethod {
+        component_address: env.maker,
+        method: method.to_owned(),
+        args: vec![scrypto_encode(arg), scrypto_encode(&sign_message(message))],
+    });
+    let receipt = executor.run(builder.build(vec![env.key]).unwrap()).unwrap();
+    println!("{:?}\n", receipt);
+    receipt
+}
+
+#[test]
+fn test_fill_order_partially() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let env = setup(&mut executor);
+    let signed_order = sign_order(&env, 1, 10, 0);
+
+    // the quote is scaled to the fill, 50 base for 25 quote
+    assert_eq!(fill(&mut executor, &env, &signed_order, 50, 25), Some(Decimal::from(50)));
+    assert_eq!(get_remaining(&mut executor, &env, &signed_order), Decimal::from(150));
+
+    // paying less than the scaled quote is refused
+    assert_eq!(fill(&mut executor, &env, &signed_order, 50, 24), None);
+
+    // the same SignedOrder can be filled again until it is used up
+    assert_eq!(fill(&mut executor, &env, &signed_order, 150, 75), Some(Decimal::from(150)));
+    assert_eq!(get_remaining(&mut executor, &env, &signed_order), Decimal::zero());
+    assert_eq!(fill(&mut executor, &env, &signed_order, 1, 1), None);
+}
+
+#[test]
+fn test_order_for_another_maker_is_refused() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let env = setup(&mut executor);
+
+    // a valid signature of the same key, but the order names another Maker component
+    let mut signed_order = sign_order(&env, 1, 10, 0);
+    signed_order.order.maker_address = env.account;
+    resign(&mut signed_order);
+    assert_eq!(fill(&mut executor, &env, &signed_order, 50, 25), None);
+
+    // or it was signed for the Transporter of another Maker
+    let mut signed_order = sign_order(&env, 1, 10, 0);
+    signed_order.voucher_resource = env.taker_auth.into();
+    resign(&mut signed_order);
+    assert_eq!(fill(&mut executor, &env, &signed_order, 50, 25), None);
+
+    let signed_order = sign_order(&env, 1, 10, 0);
+    assert_eq!(fill(&mut executor, &env, &signed_order, 50, 25), Some(Decimal::from(50)));
+}
+
+#[test]
+fn test_cancelled_order_is_refused() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let env = setup(&mut executor);
+    let signed_order = sign_order(&env, 1, 10, 0);
+    let order_hash = signed_order.order_hash();
+
+    assert_eq!(fill(&mut executor, &env, &signed_order, 50, 25), Some(Decimal::from(50)));
+
+    // only the signer can cancel the order
+    let receipt = call_signed(&mut executor, &env, "cancel_order", &order_hash, &cancel_order_message(env.maker, "another order"));
+    assert!(receipt.result.is_err());
+    // a cancellation signed for another Maker sharing the key can not be replayed here
+    let receipt = call_signed(&mut executor, &env, "cancel_order", &order_hash, &cancel_order_message(env.account, &order_hash));
+    assert!(receipt.result.is_err());
+    let receipt = call_signed(&mut executor, &env, "cancel_order", &order_hash, &cancel_order_message(env.maker, &order_hash));
+    assert!(receipt.result.is_ok());
+
+    assert_eq!(get_remaining(&mut executor, &env, &signed_order), Decimal::zero());
+    assert_eq!(fill(&mut executor, &env, &signed_order, 50, 25), None);
+}
+
+#[test]
+fn test_expired_order_is_refused() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let env = setup(&mut executor);
+    let signed_order = sign_order(&env, 1, 5, 0);
+
+    // the deadline is the last epoch the order can be filled
+    executor.ledger_mut().set_epoch(5);
+    assert_eq!(fill(&mut executor, &env, &signed_order, 50, 25), Some(Decimal::from(50)));
+
+    executor.ledger_mut().set_epoch(6);
+    assert_eq!(fill(&mut executor, &env, &signed_order, 50, 25), None);
+}
+
+#[test]
+fn test_invalidate_orders_revokes_lower_nonces() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let env = setup(&mut executor);
+    let old_order = sign_order(&env, 1, 10, 3);
+    let new_order = sign_order(&env, 2, 10, 5);
+
+    // a fill before the revocation works
+    assert_eq!(fill(&mut executor, &env, &old_order, 50, 25), Some(Decimal::from(50)));
+
+    // only the signer can revoke orders
+    let receipt = call_signed(&mut executor, &env, "invalidate_orders", &5u64, &invalidate_orders_message(env.maker, 6));
+    assert!(receipt.result.is_err());
+    let receipt = call_signed(&mut executor, &env, "invalidate_orders", &5u64, &invalidate_orders_message(env.account, 5));
+    assert!(receipt.result.is_err());
+    let receipt = call_signed(&mut executor, &env, "invalidate_orders", &5u64, &invalidate_orders_message(env.maker, 5));
+    assert!(receipt.result.is_ok());
+
+    let mut builder = TransactionBuilder::new(&executor);
+    builder.add_instruction(Instruction::CallMethod {
+        component_address: env.maker,
+        method: "get_min_nonce".to_owned(),
+        args: vec![],
+    });
+    let receipt = executor.run(builder.build(vec![env.key]).unwrap()).unwrap();
+    assert!(receipt.result.is_ok());
+    assert_eq!(scrypto_decode::<u64>(&receipt.outputs[0].raw).unwrap(), 5);
+
+    // the previously signed order is refused, orders signed with the minimum nonce or later still fill
+    assert_eq!(fill(&mut executor, &env, &old_order, 50, 25), None);
+    assert_eq!(fill(&mut executor, &env, &new_order, 50, 25), Some(Decimal::from(50)));
+
+    // the minimum can not go back down
+    let receipt = call_signed(&mut executor, &env, "invalidate_orders", &4u64, &invalidate_orders_message(env.maker, 4));
+    assert!(receipt.result.is_err());
+    assert_eq!(fill(&mut executor, &env, &old_order, 50, 25), None);
+}
@@ -9,23 +9,27 @@ pub struct Order {
     pub buy: bool,
     /// Kind of token that is being bought or sold
     pub token: ResourceDef,
-    /// Price (in market's currency) the buyer is willing to bid or seller is asking
+    /// Price (in the order's currency) the buyer is willing to bid or seller is asking
     pub price: Decimal,
     /// Vault holding the purchased (or to be sold) tokens
     pub purse: Vault,
     /// Vault from which the payment for any purchases or sales will be withdrawn
-    /// (must be in market's currency)
+    /// (must be in one of the market's currencies, this is the order's currency)
     pub payment: Vault
 }
 
 #[allow(dead_code)]
 impl Order {
     pub fn token_symbol(&self) -> String {
-        self.token.metadata()["symbol"].clone()
+        symbol(self.token.address())
     }
 
     pub fn currency(&self) -> String {
-        self.payment.resource_def().metadata()["symbol"].clone()
+        symbol(self.payment.resource_address())
+    }
+
+    pub fn currency_address(&self) -> Address {
+        self.payment.resource_address()
     }
 
     pub fn is_filled(&self) -> bool {
@@ -53,36 +57,66 @@ impl Order {
 pub struct OrderTicket {
   pub order_number: i64,
   pub order_token_address: String,
-  pub order_currency: String
+  pub order_currency: String,
+  pub order_currency_address: String
 }
 
+/// Last traded prices of each asset in each of the currencies it has been traded in.
+/// Keyed by resource address, as different resources may share a symbol.
 #[derive(Debug, TypeId, Encode, Decode, Describe)]
 pub struct MarketPrices {
-    asset_prices: HashMap<String, Decimal>
+    /// asset address -> currency address -> price
+    asset_prices: BTreeMap<Address, BTreeMap<Address, Decimal>>
 }
 
 #[allow(dead_code)]
 impl MarketPrices {
     pub fn new() -> MarketPrices {
-        MarketPrices { asset_prices: HashMap::new() }
+        MarketPrices { asset_prices: BTreeMap::new() }
     }
 
-    pub fn assets(&self) -> Vec<String> {
+    pub fn assets(&self) -> Vec<Address> {
         self.asset_prices.keys().cloned().collect()
     }
 
-    pub fn get(&self, asset_symbol: String) -> Option<Decimal> {
-        self.asset_prices.get(&asset_symbol).cloned()
+    /// Addresses of the currencies the given asset has a price in.
+    pub fn currencies(&self, asset: Address) -> Vec<Address> {
+        self.asset_prices.get(&asset).map(|prices| prices.keys().cloned().collect()).unwrap_or_default()
     }
 
-    pub fn for_address(&self, address: Address) -> Option<Decimal> {
-        let resource_def = ResourceDef::from(address);
-        let asset_symbol = resource_def.metadata()["symbol"].clone();
+    pub fn get(&self, asset: Address, currency: Address) -> Option<Decimal> {
+        self.asset_prices.get(&asset).and_then(|prices| prices.get(&currency).cloned())
+    }
 
-        self.get(asset_symbol)
+    pub fn update(&mut self, asset: Address, currency: Address, price: Decimal) {
+        self.asset_prices.entry(asset).or_insert_with(BTreeMap::new).insert(currency, price);
     }
 
-    pub fn update(&mut self, asset_symbol: String, price: Decimal) {
-        self.asset_prices.insert(asset_symbol, price);
+    /// Amount of the `to` currency one unit of the `from` currency is worth.
+    ///
+    /// Uses the price of `from` in `to` if it has been traded directly. Otherwise the rate is implied
+    /// by the first asset (in address order) with a price in both currencies.
+    pub fn cross_rate(&self, from: Address, to: Address) -> Option<Decimal> {
+        if from == to {
+            return Some(Decimal::one());
+        }
+        if let Some(price) = self.get(from, to) {
+            return Some(price);
+        }
+        if let Some(price) = self.get(to, from) {
+            if price > Decimal::zero() { return Some(Decimal::one() / price) }
+        }
+
+        self.asset_prices.values()
+            .filter_map(|prices| match (prices.get(&from), prices.get(&to)) {
+                (Some(from_price), Some(to_price)) if *from_price > Decimal::zero() => Some(*to_price / *from_price),
+                _ => None
+            })
+            .next()
     }
 }
+
+/// Symbol of the resource for display, its address if it has no symbol.
+pub fn symbol(address: Address) -> String {
+    ResourceDef::from(address).metadata().get("symbol").cloned().unwrap_or_else(|| address.to_string())
+}
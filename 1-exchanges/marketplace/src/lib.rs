@@ -7,15 +7,24 @@ use data::*;
 blueprint! {
     struct Market {
         order_count: i64,
-        currency: Address,
+        /// Quote currencies tokens can be traded against, the first one is the base currency
+        currencies: Vec<Address>,
         orders: Vec<Order>,
         ticket_minter_badge: Vault,
         ticket_nft_def: ResourceDef,
+        /// Badge required to add quote currencies
+        admin_badge: ResourceDef,
         market_prices: MarketPrices
     }
 
     impl Market {
-        pub fn open(currency: Address) -> Component {
+        /// Opens a market quoting tokens in the given base currency. More quote currencies can be added with `add_currency`
+        /// by the holder of the returned admin badge.
+        pub fn open(currency: Address) -> (Component, Bucket) {
+            let admin_badge = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
+                .metadata("name", "Market Admin Badge")
+                .initial_supply_fungible(1);
+
             let ticket_minter_badge = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
                 .metadata("name", "Order Ticket Minter Badge")
                 .initial_supply_fungible(1);
@@ -29,24 +38,57 @@ blueprint! {
                 )
                 .no_initial_supply();
 
-            Self {
+            let market = Self {
                 order_count: 0,
-                currency: currency,
+                currencies: vec![currency],
                 orders: vec![],
                 ticket_minter_badge: Vault::with_bucket(ticket_minter_badge),
                 ticket_nft_def: ticket_nft_def,
+                admin_badge: admin_badge.resource_def(),
                 market_prices: MarketPrices::new()
             }
-                .instantiate()
+                .instantiate();
+
+            (market, admin_badge)
+        }
+
+        /// Lets the same tokens also be listed against the given quote currency, each currency having its own book.
+        #[auth(admin_badge)]
+        pub fn add_currency(&mut self, currency: Address) {
+            if !self.currencies.contains(&currency) {
+                self.currencies.push(currency);
+            }
+        }
+
+        pub fn currencies(&self) -> Vec<Address> {
+            self.currencies.clone()
+        }
+
+        /// Last traded price of the token in the given currency.
+        pub fn get_price(&self, token: Address, currency: Address) -> Option<Decimal> {
+            self.market_prices.get(token, currency)
+        }
+
+        /// Amount of the `to` currency one unit of the `from` currency is worth, implied by the last traded prices.
+        pub fn get_cross_rate(&self, from: Address, to: Address) -> Option<Decimal> {
+            self.market_prices.cross_rate(from, to)
+        }
+
+        fn assert_currency(&self, currency: Address) {
+            assert!(
+                self.currencies.contains(&currency),
+                "Expecting payment in one of the market currencies!"
+            );
         }
 
         /// Yields a ticket (NFT) specifically for this order which can be used to withdraw
         /// from it once it's filled.
-        fn order_ticket(&mut self, order_number: i64, token_address: Address) -> Bucket {
+        fn order_ticket(&mut self, order_number: i64, token_address: Address, currency: Address) -> Bucket {
             let ticket = OrderTicket {
                 order_number: order_number,
                 order_token_address: token_address.to_string(),
-                order_currency: self.base_currency()
+                order_currency: symbol(currency),
+                order_currency_address: currency.to_string()
             };
 
             self.ticket_minter_badge.authorize(|badge|{
@@ -62,39 +104,172 @@ blueprint! {
             number
         }
 
+        /// Buys the token at market price, paying in any of the market currencies.
+        ///
+        /// The order is placed in the book where the payment buys the most tokens. That is either the book of the
+        /// payment currency, or the book of another currency after first converting the whole payment to it
+        /// by buying that currency with the payment (if it is listed as a token against the payment currency).
         pub fn market_buy(&mut self, token: Address, payment: Bucket) -> Bucket {
-            self.limit_buy(token, 0.into(), payment)
+            let currency = payment.resource_address();
+            self.assert_currency(currency);
+
+            let route = self.best_route(token, currency, payment.amount());
+
+            if route == currency {
+                return self.limit_buy(token, 0.into(), payment);
+            }
+
+            info!("Routing market buy through the {} book", symbol(route));
+            let (converted, change) = self.take_from_book(route, currency, payment);
+            assert!(change.is_empty(), "Payment could not be fully converted!");
+
+            self.limit_buy(token, 0.into(), converted)
         }
 
         pub fn market_sell(&mut self, tokens: Bucket) -> Bucket {
             self.limit_sell(tokens, 0.into())
         }
 
+        /// Sells the tokens in the base currency.
         pub fn limit_sell(&mut self, tokens: Bucket, price: Decimal) -> Bucket {
+            let currency = self.currencies[0];
+
+            self.limit_sell_in(tokens, price, currency)
+        }
+
+        /// Sells the tokens in the given market currency.
+        pub fn limit_sell_in(&mut self, tokens: Bucket, price: Decimal, currency: Address) -> Bucket {
+            self.assert_currency(currency);
             let token = tokens.resource_def().address();
-            let currency = self.currency;
 
-            self.create_limit_order(token, tokens, |order_number, _tokens_| Market::make_sell_order(order_number, _tokens_, price, currency))
+            self.create_limit_order(token, currency, tokens, |order_number, _tokens_| Market::make_sell_order(order_number, _tokens_, price, currency))
         }
 
+        /// Buys the token in the currency of the payment, which must be one of the market currencies.
         pub fn limit_buy(&mut self, token: Address, price: Decimal, payment: Bucket) -> Bucket {
-            assert!(
-                payment.resource_def().address() == self.currency,
-                "Expecting payment in market currency!"
-            );
+            let currency = payment.resource_address();
+            self.assert_currency(currency);
 
-            self.create_limit_order(token, payment, |order_number, _payment_| Market::make_buy_order(order_number, token, price, _payment_))
+            self.create_limit_order(token, currency, payment, |order_number, _payment_| Market::make_buy_order(order_number, token, price, _payment_))
+        }
+
+        /// Finds the currency whose book gives the most tokens for the payment, starting from the payment currency.
+        ///
+        /// Other currencies are only considered if the payment can be fully converted to them.
+        fn best_route(&self, token: Address, currency: Address, amount: Decimal) -> Address {
+            let (mut best_amount, _) = self.quote_buy(token, currency, amount);
+            let mut best_route = currency;
+
+            for via in self.currencies.iter().filter(|c| **c != currency) {
+                let (converted, change) = self.quote_buy(*via, currency, amount);
+
+                if converted == Decimal::zero() || change > Decimal::zero() {
+                    continue;
+                }
+
+                let (tokens, _) = self.quote_buy(token, *via, converted);
+
+                if tokens > best_amount {
+                    best_amount = tokens;
+                    best_route = *via;
+                }
+            }
+
+            best_route
+        }
+
+        /// Amount of tokens and change a market buy would get right now from the sell orders in the given currency.
+        fn quote_buy(&self, token: Address, currency: Address, amount: Decimal) -> (Decimal, Decimal) {
+            let mut remaining = amount;
+            let mut tokens = Decimal::zero();
+
+            for order in self.sell_book(token, currency) {
+                let full_payment_amount = order.price * order.purse.amount();
+
+                if full_payment_amount <= remaining {
+                    tokens = tokens + order.purse.amount();
+                    remaining = remaining - full_payment_amount;
+                } else {
+                    tokens = tokens + remaining / order.price;
+                    remaining = Decimal::zero();
+                }
+
+                if remaining == Decimal::zero() {
+                    break;
+                }
+            }
+
+            (tokens, remaining)
+        }
+
+        /// Buys the token with the payment directly from the sell orders in the payment's currency, returning
+        /// the tokens and any change.
+        fn take_from_book(&mut self, token: Address, currency: Address, mut payment: Bucket) -> (Bucket, Bucket) {
+            let mut tokens = Bucket::new(token);
+            let mut last_price: Option<Decimal> = None;
+
+            let mut sell_orders = (&mut self.orders)
+                .into_iter()
+                .filter(|o| Market::is_in_sell_book(o, token, currency))
+                .collect::<Vec<&mut Order>>();
+
+            sell_orders.sort_by(|a, b| a.price.cmp(&b.price)); // sort by lowest price first
+
+            for sell_order in sell_orders {
+                let price = sell_order.price;
+                let full_payment_amount = price * sell_order.purse.amount();
+
+                if full_payment_amount <= payment.amount() {
+                    sell_order.payment.put(payment.take(full_payment_amount));
+                    tokens.put(sell_order.purse.take_all());
+                } else {
+                    let partial_token_amount = payment.amount() / price;
+
+                    sell_order.payment.put(payment.take_all());
+                    tokens.put(sell_order.purse.take(partial_token_amount));
+                }
+
+                last_price = Some(price);
+
+                if payment.is_empty() {
+                    break;
+                }
+            }
+
+            for price in last_price {
+                self.market_prices.update(token, currency, price);
+            }
+
+            (tokens, payment)
+        }
+
+        /// Open sell orders of the token in the given currency, lowest price first.
+        fn sell_book(&self, token: Address, currency: Address) -> Vec<&Order> {
+            let mut orders = (&self.orders)
+                .into_iter()
+                .filter(|o| Market::is_in_sell_book(o, token, currency))
+                .collect::<Vec<&Order>>();
+
+            orders.sort_by(|a, b| a.price.cmp(&b.price));
+
+            orders
+        }
+
+        fn is_in_sell_book(order: &Order, token: Address, currency: Address) -> bool {
+            order.is_sell_order() && !order.is_filled() && !order.is_market_order()
+                && order.token.address() == token && order.currency_address() == currency
         }
 
         ///
         /// Creates a new limit order using the given factory method and inserts into the list of orders.
         ///
         /// @param token The address of the kind of token that is traded.
+        /// @param currency The address of the market currency the token is traded in.
         /// @param bucket Used if the factory needs a bucket since Buckets cannot be captured in closures as far as I can tell.
         /// @param make_order Function creating the new order using the given order number.
-        fn create_limit_order<F>(&mut self, token: Address, bucket: Bucket, make_order: F) -> Bucket where F: Fn(i64, Bucket) -> Order {
+        fn create_limit_order<F>(&mut self, token: Address, currency: Address, bucket: Bucket, make_order: F) -> Bucket where F: Fn(i64, Bucket) -> Order {
             let order_number = self.next_order_number();
-            let ticket = self.order_ticket(order_number, token);
+            let ticket = self.order_ticket(order_number, token, currency);
 
             let mut order = make_order(order_number, bucket);
 
@@ -143,7 +318,7 @@ blueprint! {
             }
 
             for price in last_price {
-                self.market_prices.update(order.token_symbol(), price);
+                self.market_prices.update(order.token.address(), order.currency_address(), price);
             }
         }
 
@@ -210,7 +385,9 @@ blueprint! {
 
                 let token_resource_address = Address::from_str(&ticket.order_token_address).unwrap();
 
-                (Bucket::new(token_resource_address), Bucket::new(self.currency), ticket_bucket)
+                let currency_address = Address::from_str(&ticket.order_currency_address).unwrap();
+
+                (Bucket::new(token_resource_address), Bucket::new(currency_address), ticket_bucket)
             }
         }
 
@@ -236,6 +413,7 @@ blueprint! {
 
         fn is_matching_sell_order(buy_order: &Order, sell_order: &Order) -> bool {
             if sell_order.is_filled() || sell_order.token != buy_order.token { return false }
+            if sell_order.currency_address() != buy_order.currency_address() { return false }
             if buy_order.is_market_order() { return true }
 
             sell_order.price <= buy_order.price
@@ -243,15 +421,12 @@ blueprint! {
 
         fn is_matching_buy_order(sell_order: &Order, buy_order: &Order) -> bool {
             if buy_order.is_filled() || buy_order.token != sell_order.token { return false }
+            if buy_order.currency_address() != sell_order.currency_address() { return false }
             if sell_order.is_market_order() { return true }
 
             buy_order.price >= sell_order.price
         }
 
-        fn base_currency(&self) -> String {
-            ResourceDef::from(self.currency).metadata()["symbol"].clone()
-        }
-
         fn ticket_nft_address(&self) -> Address {
             self.ticket_nft_def.address()
         }
@@ -261,26 +436,35 @@ blueprint! {
         }
 
         pub fn print_order_book(&self) {
-            self.print_orders(true);
+            for (i, currency) in self.currencies.iter().enumerate() {
+                if i > 0 {
+                    info!("");
+                }
 
-            info!("");
+                self.print_orders(true, *currency);
 
-            self.print_orders(false);
+                info!("");
+
+                self.print_orders(false, *currency);
+            }
         }
 
-        fn print_orders(&self, buy: bool) {
+        fn print_orders(&self, buy: bool, currency: Address) {
             let title = if buy { "BUY" } else { "SELL" };
             let kind = if buy { "Bid" } else { "Ask" };
             let store = if buy { "Bought" } else { "For sale" };
 
             info!(" \\----------------------------------------------------/");
             info!("");
-            info!(" /'''''''''''''''''' {:>5} {:>4} ORDERS '''''''''''''''''\\", self.base_currency(), title);
+            info!(" /'''''''''''''''''' {:>5} {:>4} ORDERS '''''''''''''''''\\", symbol(currency), title);
             info!(" +------------------------------------------------------+");
             info!(" | #    | Token | {:>7} | Filled | {:>8} | Payment |", kind, store);
             info!(" +------------------------------------------------------+");
 
-            let orders = (&self.orders).into_iter().filter(|o| o.is_buy_order() == buy).collect::<Vec<&Order>>();
+            let orders = (&self.orders)
+                .into_iter()
+                .filter(|o| o.is_buy_order() == buy && o.currency_address() == currency)
+                .collect::<Vec<&Order>>();
 
             for order in orders {
                 let filled = if order.is_filled() {
@@ -308,19 +492,43 @@ blueprint! {
         }
 
         pub fn print_market_prices(&self) {
-            info!(" /' MARKET PRICES '\\");
-            info!(" +-----------------+");
-            info!(" | Asset |  Price  |");
-            info!(" +-----------------+");
+            info!(" /' MARKET PRICES '''''''\\");
+            info!(" +-------------------------+");
+            info!(" | Asset |  Price  | Curr. |");
+            info!(" +-------------------------+");
 
             for asset in self.market_prices.assets() {
-                let name = asset.clone();
-                let price = self.market_prices.get(asset).unwrap();
+                for currency in self.market_prices.currencies(asset) {
+                    let price = self.market_prices.get(asset, currency).unwrap();
+
+                    info!(" | {:>5} | {:>7} | {:>5} |", symbol(asset), self.truncate(price.to_string(), 7), symbol(currency));
+                }
+            }
 
-                info!(" | {:>5} | {:>7} |", name, self.truncate(price.to_string(), 7));
+            info!(" \\-------------------------/");
+
+            if self.currencies.len() < 2 {
+                return;
+            }
+
+            info!("");
+            info!(" /' CROSS RATES '''''''''\\");
+            info!(" +-------------------------+");
+            info!(" |  From |    To |   Rate  |");
+            info!(" +-------------------------+");
+
+            for from in &self.currencies {
+                for to in self.currencies.iter().filter(|c| *c != from) {
+                    let rate = match self.get_cross_rate(*from, *to) {
+                        Some(rate) => self.truncate(rate.to_string(), 7),
+                        None => String::from("-")
+                    };
+
+                    info!(" | {:>5} | {:>5} | {:>7} |", symbol(*from), symbol(*to), rate);
+                }
             }
 
-            info!(" \\-----------------/");
+            info!(" \\-------------------------/");
         }
     }
 }
@@ -0,0 +1,166 @@
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+
+fn create_token<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+) -> Address {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .new_token_fixed(HashMap::new(), Decimal::from(1000))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    receipt.resource_def(0).unwrap()
+}
+
+fn add_currency<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    market: Address,
+    currency: Address,
+    badge: Address,
+) -> Receipt {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(
+                    market,
+                    "add_currency",
+                    vec![currency.to_string(), format!("1,{}", badge)],
+                    Some(account),
+                )
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    receipt
+}
+
+fn currencies<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    market: Address,
+) -> Vec<Address> {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(market, "currencies", vec![], Some(account))
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    scrypto_decode(&receipt.outputs[receipt.outputs.len() - 1].raw).unwrap()
+}
+
+#[test]
+fn test_add_currency_requires_admin_badge() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let key = executor.new_public_key();
+    let account = executor.new_account(key);
+    let package = executor.publish_package(include_code!("marketplace")).unwrap();
+
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(&executor)
+                .call_function(package, "Market", "open", vec![RADIX_TOKEN.to_string()], Some(account))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+    // the admin badge is created first
+    let market = receipt.component(0).unwrap();
+    let admin_badge = receipt.resource_def(0).unwrap();
+
+    let usdt = create_token(&mut executor, key, account);
+    let other_badge = create_token(&mut executor, key, account);
+
+    // any other badge is refused
+    let receipt = add_currency(&mut executor, key, account, market, usdt, other_badge);
+    assert!(receipt.result.is_err());
+    assert_eq!(currencies(&mut executor, key, account, market), vec![RADIX_TOKEN]);
+
+    let receipt = add_currency(&mut executor, key, account, market, usdt, admin_badge);
+    assert!(receipt.result.is_ok());
+    assert_eq!(currencies(&mut executor, key, account, market), vec![RADIX_TOKEN, usdt]);
+}
+
+fn call<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    market: Address,
+    method: &str,
+    args: Vec<String>,
+) -> Receipt {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(market, method, args, Some(account))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    receipt
+}
+
+#[test]
+fn test_prices_are_kept_per_token_address() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let key = executor.new_public_key();
+    let account = executor.new_account(key);
+    let package = executor.publish_package(include_code!("marketplace")).unwrap();
+
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(&executor)
+                .call_function(package, "Market", "open", vec![RADIX_TOKEN.to_string()], Some(account))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    let market = receipt.component(0).unwrap();
+
+    // neither token has a symbol, each is still traded at its own price
+    for (token, price) in [(create_token(&mut executor, key, account), 2), (create_token(&mut executor, key, account), 3)] {
+        let receipt = call(&mut executor, key, account, market, "limit_sell", vec![format!("10,{}", token), price.to_string()]);
+        assert!(receipt.result.is_ok());
+        let receipt = call(
+            &mut executor,
+            key,
+            account,
+            market,
+            "limit_buy",
+            vec![token.to_string(), price.to_string(), format!("{},{}", price * 10, RADIX_TOKEN)],
+        );
+        assert!(receipt.result.is_ok());
+
+        let receipt = call(&mut executor, key, account, market, "get_price", vec![token.to_string(), RADIX_TOKEN.to_string()]);
+        assert!(receipt.result.is_ok());
+        let traded: Option<Decimal> = scrypto_decode(&receipt.outputs[0].raw).unwrap();
+        assert_eq!(traded, Some(Decimal::from(price)));
+    }
+
+    let receipt = call(&mut executor, key, account, market, "print_market_prices", vec![]);
+    assert!(receipt.result.is_ok());
+}
@@ -23,6 +23,13 @@ resim publish --address $PACKAGE .
 
 $XRD_MARKET = resim call-function $PACKAGE Market open $XRD | Get-Component
 
+# Add a quote currency, only the holder of the admin badge may do so
+
+$ADMIN_BADGE = resim show $BUYER_ACC | Get-Resource-Def "Market Admin Badge"
+$DAI = resim new-token-fixed --name Dai --symbol DAI 1000 | Get-New-Def
+
+resim call-method $XRD_MARKET add_currency $DAI 1,$ADMIN_BADGE
+
 # Create orders
 
 resim set-default-account $BUYER_ACC $BUYER_PUB
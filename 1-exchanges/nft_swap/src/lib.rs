@@ -1,5 +1,7 @@
 use scrypto::prelude::*;
 
+mod pool;
+
 #[derive(NonFungibleData)]
 pub struct NftCollection{
     #[scrypto(mutable)]
@@ -0,0 +1,140 @@
+use scrypto::prelude::*;
+
+#[derive(TypeId, Encode, Decode, Describe, Debug, Clone, Copy, PartialEq)]
+pub enum BondingCurve {
+    //spot price moves up or down by delta XRD after each trade
+    Linear,
+
+    //spot price is multiplied or divided by delta after each trade
+    Exponential,
+}
+
+blueprint! {
+    struct NftPool {
+        //NFTs of the collection held by the pool are kept in this Vault
+        nft_vault: Vault,
+
+        //XRD used to buy NFTs and collected from NFT sales are kept in this Vault
+        xrd_vault: Vault,
+
+        //Bonding curve that moves the spot price after each trade
+        curve: BondingCurve,
+
+        //XRD the pool pays for the next NFT sold into it
+        spot_price: Decimal,
+
+        //XRD added/removed for a Linear curve, multiplier for an Exponential curve
+        delta: Decimal,
+
+        //Fraction of the price the pool keeps on each trade
+        fee: Decimal,
+
+        //Resource definition of the badge allowed to withdraw from the pool
+        owner_badge: ResourceDef,
+    }
+
+    impl NftPool {
+
+        //curve is "Linear" or "Exponential", so that the pool can be instantiated from a transaction
+        pub fn new(nfts: Bucket, xrd: Bucket, curve: String, spot_price: Decimal, delta: Decimal, fee: Decimal) -> (Component, Bucket) {
+            let curve: BondingCurve = match curve.as_str() {
+                "Linear" => BondingCurve::Linear,
+                "Exponential" => BondingCurve::Exponential,
+                _ => panic!("Curve must be Linear or Exponential"),
+            };
+            assert!(xrd.resource_address() == RADIX_TOKEN, "Pool must be funded with XRD");
+            assert!(spot_price > Decimal::zero(), "Spot price must be positive");
+            assert!(fee >= Decimal::zero() && fee < Decimal::one(), "Fee must be between 0 and 1");
+            match curve {
+                BondingCurve::Linear => assert!(delta >= Decimal::zero(), "Linear delta can not be negative"),
+                BondingCurve::Exponential => assert!(delta > Decimal::one(), "Exponential delta must be greater than 1"),
+            }
+
+            //Creating owner badge to withdraw NFTs and XRD from the pool
+            let owner_badge: Bucket = ResourceBuilder::new_fungible(DIVISIBILITY_NONE)
+                .metadata("name", "NFT Pool Owner Badge")
+                .initial_supply_fungible(1);
+
+            let component: Component = Self {
+                nft_vault: Vault::with_bucket(nfts),
+                xrd_vault: Vault::with_bucket(xrd),
+                curve: curve,
+                spot_price: spot_price,
+                delta: delta,
+                fee: fee,
+                owner_badge: owner_badge.resource_def(),
+            }
+
+            .instantiate();
+
+            return (component, owner_badge);
+        }
+
+        //XRD a buyer pays for the next NFT, fee included
+        pub fn get_buy_price(&self) -> Decimal {
+            let price: Decimal = self.next_buy_spot_price();
+            price + price * self.fee
+        }
+
+        //XRD a seller receives for the next NFT, fee deducted
+        pub fn get_sell_price(&self) -> Decimal {
+            self.spot_price - self.spot_price * self.fee
+        }
+
+        pub fn get_spot_price(&self) -> Decimal {
+            self.spot_price
+        }
+
+        pub fn buy_nft(&mut self, key: NonFungibleKey, mut payment: Bucket) -> (Bucket, Bucket) {
+            //buyers pay the spot price after it moves up the curve
+            let price: Decimal = self.next_buy_spot_price();
+            let cost: Decimal = price + price * self.fee;
+            assert!(payment.amount() >= cost, "Not enough XRD to buy NFT");
+
+            //take the cost of nft out of payment Bucket and move the spot price up
+            self.xrd_vault.put(payment.take(cost));
+            self.spot_price = price;
+
+            //takes the nft by id out of the vault and puts it in a Bucket
+            let nft_bucket: Bucket = self.nft_vault.take_non_fungible(&key);
+
+            //Return 2 Buckets with nft and any remaining change
+            (nft_bucket, payment)
+        }
+
+        pub fn sell_nft(&mut self, nft: Bucket, min_xrd: Decimal) -> Bucket {
+            assert!(nft.resource_def() == self.nft_vault.resource_def(), "NFT is not from this collection");
+            assert!(nft.amount() == Decimal::one(), "Sell one NFT at a time");
+
+            //sellers receive the current spot price
+            let proceeds: Decimal = self.get_sell_price();
+            assert!(proceeds >= min_xrd, "Sell price is below the minimum");
+            assert!(self.xrd_vault.amount() >= proceeds, "Not enough XRD in the pool");
+
+            //move the spot price down the curve
+            self.spot_price = match self.curve {
+                BondingCurve::Linear => {
+                    assert!(self.spot_price >= self.delta, "Spot price can not go below zero");
+                    self.spot_price - self.delta
+                }
+                BondingCurve::Exponential => self.spot_price / self.delta,
+            };
+
+            self.nft_vault.put(nft);
+            self.xrd_vault.take(proceeds)
+        }
+
+        //Returns all NFTs and XRD of the pool to its owner
+        #[auth(owner_badge)]
+        pub fn withdraw(&mut self) -> (Bucket, Bucket) {
+            (self.nft_vault.take_all(), self.xrd_vault.take_all())
+        }
+
+        fn next_buy_spot_price(&self) -> Decimal {
+            match self.curve {
+                BondingCurve::Linear => self.spot_price + self.delta,
+                BondingCurve::Exponential => self.spot_price * self.delta,
+            }
+        }
+    }
+}
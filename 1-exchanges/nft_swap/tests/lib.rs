@@ -1,4 +1,6 @@
+use radix_engine::engine::*;
 use radix_engine::ledger::*;
+use radix_engine::model::*;
 use radix_engine::transaction::*;
 use scrypto::prelude::*;
 
@@ -32,3 +34,284 @@ fn test_hello() {
     println!("{:?}\n", receipt2);
     assert!(receipt2.result.is_ok());
 }
+
+fn get_vault_info<'a, L: SubstateStore>(
+    ledger: &'a L,
+    component: &Address,
+    id: &Vid,
+) -> (Address, Decimal) {
+    let vault = ledger.get_vault(component, id).unwrap();
+    (vault.resource_address(), vault.amount())
+}
+
+fn get_lazymap_info<'a, L: SubstateStore>(
+    ledger: &'a L,
+    component: &Address,
+    id: &Mid,
+) -> Vec<(Address, Decimal)> {
+    let lazy_map = ledger.get_lazy_map(component, id).unwrap();
+    lazy_map
+        .map()
+        .iter()
+        .flat_map(|(_, data)| {
+            let validated_data = validate_data(data).unwrap();
+            validated_data
+                .vaults
+                .iter()
+                .map(|vid| get_vault_info(ledger, component, vid))
+                .collect::<Vec<(Address, Decimal)>>()
+        })
+        .collect()
+}
+
+fn balance<'a, L: SubstateStore>(ledger: &'a L, account: Address, token: Address) -> Decimal {
+    let component = ledger.get_component(account).unwrap();
+    let validated_data = validate_data(component.state()).unwrap();
+    validated_data
+        .lazy_maps
+        .iter()
+        .flat_map(|mid| get_lazymap_info(ledger, &account, &mid))
+        .filter(|(address, _)| *address == token)
+        .map(|(_, amount)| amount)
+        .next()
+        .unwrap_or(Decimal::zero())
+}
+
+fn nft_arg(keys: &[u128], collection: Address) -> String {
+    let keys: Vec<String> = keys.iter().map(|key| format!("#{:032x}", key)).collect();
+    format!("{},{}", keys.join(","), collection)
+}
+
+/// Buys NFTs 1, 2 and 3 of the collection, returns (key, account, package, collection)
+fn setup<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+) -> (EcdsaPublicKey, Address, Address, Address) {
+    let key = executor.new_public_key();
+    let account = executor.new_account(key);
+    let package = executor.publish_package(include_code!("nft_swap")).unwrap();
+
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_function(package, "Hello", "new", vec![], None)
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    let hello = receipt.component(0).unwrap();
+    // the admin badge and the share resource are created before the collection
+    let collection = receipt.resource_def(2).unwrap();
+
+    for nft in 1..=3u128 {
+        let receipt = executor
+            .run(
+                TransactionBuilder::new(executor)
+                    .call_method(
+                        hello,
+                        "buy_nft",
+                        vec![format!("{:032x}", nft), format!("1000,{}", RADIX_TOKEN)],
+                        Some(account),
+                    )
+                    .call_method_with_all_resources(account, "deposit_batch")
+                    .build(vec![key])
+                    .unwrap(),
+            )
+            .unwrap();
+        assert!(receipt.result.is_ok());
+    }
+
+    (key, account, package, collection)
+}
+
+/// Instantiates an NftPool with the given NFTs and XRD, returns the pool and its owner badge
+fn new_pool<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    package: Address,
+    nfts: String,
+    xrd: &str,
+    curve: &str,
+    params: (&str, &str, &str),
+) -> (Address, Address) {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_function(
+                    package,
+                    "NftPool",
+                    "new",
+                    vec![
+                        nfts,
+                        format!("{},{}", xrd, RADIX_TOKEN),
+                        curve.to_owned(),
+                        params.0.to_owned(),
+                        params.1.to_owned(),
+                        params.2.to_owned(),
+                    ],
+                    Some(account),
+                )
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+    (receipt.component(0).unwrap(), receipt.resource_def(0).unwrap())
+}
+
+fn get_price<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    pool: Address,
+    method: &str,
+) -> Decimal {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(pool, method, vec![], None)
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    scrypto_decode(&receipt.outputs[0].raw).unwrap()
+}
+
+/// Buys the NFT out of the pool, returns the XRD paid
+fn buy<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    pool: Address,
+    nft: u128,
+) -> Decimal {
+    let before = balance(executor.ledger(), account, RADIX_TOKEN);
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(
+                    pool,
+                    "buy_nft",
+                    vec![format!("{:032x}", nft), format!("500,{}", RADIX_TOKEN)],
+                    Some(account),
+                )
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+    before - balance(executor.ledger(), account, RADIX_TOKEN)
+}
+
+/// Sells the NFT into the pool, returns the XRD received or None if the sale failed
+fn sell<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    key: EcdsaPublicKey,
+    account: Address,
+    pool: Address,
+    collection: Address,
+    nft: u128,
+) -> Option<Decimal> {
+    let before = balance(executor.ledger(), account, RADIX_TOKEN);
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(pool, "sell_nft", vec![nft_arg(&[nft], collection), "0".to_owned()], Some(account))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    match receipt.result {
+        Ok(_) => Some(balance(executor.ledger(), account, RADIX_TOKEN) - before),
+        Err(_) => None,
+    }
+}
+
+#[test]
+fn test_pool_linear_curve_prices() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (key, account, package, collection) = setup(&mut executor);
+    let (pool, _) = new_pool(&mut executor, key, account, package, nft_arg(&[1, 2], collection), "1000", "Linear", ("100", "10", "0"));
+
+    // buyers pay the spot price after it moves up by delta
+    assert_eq!(get_price(&mut executor, key, pool, "get_buy_price"), Decimal::from(110));
+    assert_eq!(buy(&mut executor, key, account, pool, 1), Decimal::from(110));
+    assert_eq!(buy(&mut executor, key, account, pool, 2), Decimal::from(120));
+    assert_eq!(get_price(&mut executor, key, pool, "get_spot_price"), Decimal::from(120));
+
+    // sellers receive the spot price before it moves down by delta
+    assert_eq!(get_price(&mut executor, key, pool, "get_sell_price"), Decimal::from(120));
+    assert_eq!(sell(&mut executor, key, account, pool, collection, 3), Some(Decimal::from(120)));
+    assert_eq!(sell(&mut executor, key, account, pool, collection, 1), Some(Decimal::from(110)));
+    assert_eq!(get_price(&mut executor, key, pool, "get_spot_price"), Decimal::from(100));
+}
+
+#[test]
+fn test_pool_exponential_curve_prices() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (key, account, package, collection) = setup(&mut executor);
+    let (pool, _) = new_pool(&mut executor, key, account, package, nft_arg(&[1, 2], collection), "1000", "Exponential", ("100", "1.1", "0"));
+
+    // buyers pay the spot price after it is multiplied by delta
+    assert_eq!(buy(&mut executor, key, account, pool, 1), Decimal::from(110));
+    assert_eq!(buy(&mut executor, key, account, pool, 2), Decimal::from(121));
+
+    // sellers receive the spot price before it is divided by delta
+    assert_eq!(sell(&mut executor, key, account, pool, collection, 3), Some(Decimal::from(121)));
+    assert_eq!(sell(&mut executor, key, account, pool, collection, 1), Some(Decimal::from(110)));
+    assert_eq!(get_price(&mut executor, key, pool, "get_spot_price"), Decimal::from(100));
+}
+
+#[test]
+fn test_pool_fee_stays_in_pool() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (key, account, package, collection) = setup(&mut executor);
+    let (pool, owner_badge) = new_pool(&mut executor, key, account, package, nft_arg(&[1, 2], collection), "1000", "Linear", ("100", "10", "0.1"));
+
+    // 110 plus a 10% fee, then 110 less a 10% fee
+    assert_eq!(get_price(&mut executor, key, pool, "get_buy_price"), Decimal::from(121));
+    assert_eq!(buy(&mut executor, key, account, pool, 1), Decimal::from(121));
+    assert_eq!(get_price(&mut executor, key, pool, "get_sell_price"), Decimal::from(99));
+    assert_eq!(sell(&mut executor, key, account, pool, collection, 1), Some(Decimal::from(99)));
+
+    // the owner withdraws the starting XRD and both fees
+    let before = balance(executor.ledger(), account, RADIX_TOKEN);
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(&executor)
+                .call_method(pool, "withdraw", vec![format!("1,{}", owner_badge)], Some(account))
+                .call_method_with_all_resources(account, "deposit_batch")
+                .build(vec![key])
+                .unwrap(),
+        )
+        .unwrap();
+    println!("{:?}\n", receipt);
+    assert!(receipt.result.is_ok());
+    assert_eq!(balance(executor.ledger(), account, RADIX_TOKEN) - before, Decimal::from(1022));
+}
+
+#[test]
+fn test_sell_into_empty_pool() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (key, account, package, collection) = setup(&mut executor);
+    let (pool, _) = new_pool(&mut executor, key, account, package, nft_arg(&[1], collection), "0", "Linear", ("100", "10", "0"));
+
+    // the pool has no XRD to pay for the NFT
+    assert_eq!(sell(&mut executor, key, account, pool, collection, 2), None);
+
+    // once the only NFT is bought the pool has XRD but no NFTs, and buys again
+    assert_eq!(buy(&mut executor, key, account, pool, 1), Decimal::from(110));
+    assert_eq!(sell(&mut executor, key, account, pool, collection, 2), Some(Decimal::from(110)));
+    assert_eq!(get_price(&mut executor, key, pool, "get_spot_price"), Decimal::from(100));
+}
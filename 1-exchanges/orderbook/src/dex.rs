@@ -8,17 +8,25 @@
 //! StateOrder is temporary struct to manage order logic using type state.   
 //!
 use crate::order::BTreeSetOrder;
+use crate::order::Candle;
+use crate::order::FillPosition;
+use crate::order::FillReport;
+use crate::order::MatchReport;
+use crate::order::MatchRule;
 use crate::order::Order;
 use crate::order::OrderType;
 use crate::order::OrdersContainer;
 use crate::order::PriceOrder;
+use crate::order::Side;
+use crate::order::StopOrder;
+use crate::order::Trade;
 use crate::order::UserOrders;
 use sbor::*;
 use scrypto::prelude::*;
 use scrypto::rust::marker::PhantomData;
 
-static MAKER_FEE: usize = 5;
-static TAKER_FEE: usize = 10;
+//Fee are in basis points of the transferred asset.
+static MAX_FEE: usize = 10000;
 
 #[derive(Debug, TypeId, Encode, Decode, Describe)]
 pub struct DexParameters {
@@ -28,33 +36,82 @@ pub struct DexParameters {
     pub taker_fee: Decimal,
 }
 
+impl DexParameters {
+    /// Check and update the maker and taker fee in basis points.
+    pub fn set_fees(&mut self, maker_fee: Decimal, taker_fee: Decimal) {
+        let max_fee: Decimal = From::<usize>::from(MAX_FEE);
+        assert!(
+            maker_fee >= Decimal::zero() && maker_fee <= max_fee,
+            "Maker fee must be between 0 and 10000 basis points."
+        );
+        assert!(
+            taker_fee >= Decimal::zero() && taker_fee <= max_fee,
+            "Taker fee must be between 0 and 10000 basis points."
+        );
+        self.maker_fee = maker_fee;
+        self.taker_fee = taker_fee;
+    }
+
+    /// Return the maker and taker fee in basis points applied to the trader.
+    /// If a fee tier badge is deposited in the trader openorders, its fee are used when they are lower than the market fee.
+    pub fn fee_rates(&self, trader_orders: &UserOrders) -> (Decimal, Decimal) {
+        trader_orders
+            .fee_tier()
+            .map_or((self.maker_fee, self.taker_fee), |tier| {
+                (
+                    std::cmp::min(self.maker_fee, tier.maker_fee),
+                    std::cmp::min(self.taker_fee, tier.taker_fee),
+                )
+            })
+    }
+}
+
 #[derive(Debug, TypeId, Encode, Decode, Describe)]
 pub struct Dex {
     // Define dex data
     pub fee_quote_vault: Vault,
     pub fee_base_vault: Vault,
     pub containers: OrdersContainer,
+    pub stop_orders: Vec<StopOrder>,
+    /// Match report of the triggered stop orders keyed by order id.
+    /// Stop orders are mostly triggered by the orders of other traders so their owner read what happened here.
+    pub stop_reports: LazyMap<u32, MatchReport>,
     pub user_orders: LazyMap<NonFungibleKey, UserOrders>,
     pub params: DexParameters,
+    pub last_trade_price: Option<Decimal>,
+    /// Trade history keyed by trade sequence number, so queries only load the trades they return.
+    pub trades: LazyMap<u64, Trade>,
+    trade_count: u64,
     counter: u32,
 }
 
 impl Dex {
-    pub fn new(quote_token: Address, base_token: Address) -> Self {
-        Dex {
+    pub fn new(
+        quote_token: Address,
+        base_token: Address,
+        maker_fee: Decimal,
+        taker_fee: Decimal,
+    ) -> Self {
+        let mut dex = Dex {
             fee_quote_vault: Vault::new(quote_token),
             fee_base_vault: Vault::new(base_token),
             user_orders: LazyMap::new(),
             counter: 0,
             containers: Default::default(),
-            //rm I do it like that because I can't find a way to have const decimal without knowing the u128 value.
+            stop_orders: vec![],
+            stop_reports: LazyMap::new(),
+            last_trade_price: None,
+            trades: LazyMap::new(),
+            trade_count: 0,
             params: DexParameters {
                 quote_token: ResourceDef::from(quote_token),
                 base_token: ResourceDef::from(base_token),
-                maker_fee: From::<usize>::from(MAKER_FEE),
-                taker_fee: From::<usize>::from(TAKER_FEE),
+                maker_fee: Decimal::zero(),
+                taker_fee: Decimal::zero(),
             },
-        }
+        };
+        dex.params.set_fees(maker_fee, taker_fee);
+        dex
     }
     ///
     /// To manage a bid, user must provide enougth quote to buy the base at max limite price.
@@ -69,15 +126,202 @@ impl Dex {
         amount: Decimal,
         mut quote: Bucket,
         order_type: OrderType,
-    ) -> (Bucket, Bucket) {
-        let (mut bid, mut bid_trader_orders) =
+    ) -> (Bucket, Bucket, MatchReport) {
+        let (bid, bid_trader_orders) =
             StateOrder::<BidSide, TakerPos>::init_match(owner, price, amount, &mut quote, self);
 
         let new_id = bid.order.id;
+        let report = self.execute_bid(bid, bid_trader_orders, order_type);
+        self.trigger_stop_orders();
+        (create_order_id(new_id), quote, report)
+    }
+
+    // Same as bid but for ask.
+    // pushed order are AskSide and TakerPos
+    // they are matched against BidSide and MakerPos
+    pub fn ask(
+        &mut self,
+        owner: NonFungibleKey,
+        price: Decimal,
+        amount: Decimal,
+        mut base: Bucket,
+        order_type: OrderType,
+    ) -> (Bucket, Bucket, MatchReport) {
+        let (ask, ask_trader_orders) =
+            StateOrder::<AskSide, TakerPos>::init_match(owner, price, amount, &mut base, self);
+        let new_id = ask.order.id;
+        let report = self.execute_ask(ask, ask_trader_orders, order_type);
+        self.trigger_stop_orders();
+        (create_order_id(new_id), base, report)
+    }
+
+    /// Push a stop limit bid order. The quote is locked like for a bid order
+    /// but the order is only matched when the last trade price is greater or equals to the stop price.
+    pub fn stop_bid(
+        &mut self,
+        owner: NonFungibleKey,
+        stop_price: Decimal,
+        price: Decimal,
+        amount: Decimal,
+        mut quote: Bucket,
+    ) -> (Bucket, Bucket, MatchReport) {
+        let (bid, _) =
+            StateOrder::<BidSide, TakerPos>::init_match(owner, price, amount, &mut quote, self);
+        let new_id = bid.order.id;
+        let report = self.push_stop_order(Side::Bid, stop_price, bid.order);
+        (create_order_id(new_id), quote, report)
+    }
+
+    /// Push a stop limit ask order. The base is locked like for an ask order
+    /// but the order is only matched when the last trade price is less or equals to the stop price.
+    pub fn stop_ask(
+        &mut self,
+        owner: NonFungibleKey,
+        stop_price: Decimal,
+        price: Decimal,
+        amount: Decimal,
+        mut base: Bucket,
+    ) -> (Bucket, Bucket, MatchReport) {
+        let (ask, _) =
+            StateOrder::<AskSide, TakerPos>::init_match(owner, price, amount, &mut base, self);
+        let new_id = ask.order.id;
+        let report = self.push_stop_order(Side::Ask, stop_price, ask.order);
+        (create_order_id(new_id), base, report)
+    }
+
+    pub fn get_next_id(&mut self) -> u32 {
+        let id = self.counter;
+        self.counter += 1;
+        id
+    }
+
+    pub fn cancel_order(&mut self, order_id_badge: Bucket, owner: NonFungibleKey) -> Bucket {
+        let data = order_id_badge.resource_def().metadata();
+        let id: u32 = data.get("id").unwrap().parse().unwrap();
+        self.containers
+            .asks
+            .take_order_with_id(id)
+            .map(|order| StateOrder::<AskSide, TakerPos>::cancel_order(&order, &owner, &self))
+            .or_else(|| {
+                self.containers.bids.take_order_with_id(id).map(|order| {
+                    StateOrder::<BidSide, TakerPos>::cancel_order(&order, &owner, &self);
+                });
+                None
+            });
+        //pending stop order are not in the book.
+        if let Some(position) = self.stop_orders.iter().position(|stop| stop.order.id == id) {
+            let stop = self.stop_orders.remove(position);
+            match stop.side {
+                Side::Bid => StateOrder::<BidSide, TakerPos>::cancel_order(&stop.order, &owner, &self),
+                Side::Ask => StateOrder::<AskSide, TakerPos>::cancel_order(&stop.order, &owner, &self),
+            }
+        }
+
+        order_id_badge
+    }
+
+    /// Return the match report of a triggered stop order, None while it's pending.
+    pub fn get_stop_report(&self, order_id: u32) -> Option<MatchReport> {
+        self.stop_reports.get(&order_id)
+    }
+
+    /// Return the recorded trades from the most recent one.
+    /// Skip the offset most recent trades and return at most limit trades.
+    pub fn get_trades(&self, offset: usize, limit: usize) -> Vec<Trade> {
+        let end = self.trade_count.saturating_sub(offset as u64);
+        let start = end.saturating_sub(limit as u64);
+        (start..end).rev().map(|index| self.get_trade(index)).collect()
+    }
+
+    /// Aggregate the trades done between start_epoch included and end_epoch excluded in candles of period epochs.
+    /// Period without trade have no candle.
+    /// Trades are recorded in epoch order so only the trades of the epoch range are loaded.
+    pub fn get_candles(&self, period: u64, start_epoch: u64, end_epoch: u64) -> Vec<Candle> {
+        assert!(period > 0, "Candle period must be at least one epoch.");
+        let mut candles: Vec<Candle> = vec![];
+        for index in self.first_trade_at(start_epoch)..self.first_trade_at(end_epoch) {
+            let trade = self.get_trade(index);
+            let candle_start = start_epoch + (trade.epoch - start_epoch) / period * period;
+            match candles.last_mut() {
+                Some(candle) if candle.start_epoch == candle_start => candle.add_trade(&trade),
+                _ => candles.push(Candle::new(candle_start, &trade)),
+            }
+        }
+        candles
+    }
+
+    fn get_trade(&self, index: u64) -> Trade {
+        self.trades.get(&index).unwrap()
+    }
+
+    /// Index of the first trade done at or after the epoch, trade_count if there is none.
+    fn first_trade_at(&self, epoch: u64) -> u64 {
+        let (mut low, mut high) = (0, self.trade_count);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.get_trade(middle).epoch < epoch {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        low
+    }
+
+    /// Record the trade of a match in the trade history. The side is the taker order side.
+    fn record_trade(&mut self, matched: &Match, side: Side) {
+        self.last_trade_price = Some(matched.match_price);
+        self.trades.insert(
+            self.trade_count,
+            Trade {
+                price: matched.match_price,
+                amount: matched.transfert_user_base,
+                side,
+                epoch: Context::current_epoch(),
+            },
+        );
+        self.trade_count += 1;
+    }
+
+    /// Match the bid order depending on its type and return what happened to it.
+    fn execute_bid(
+        &mut self,
+        mut bid: StateOrder<BidSide, TakerPos>,
+        mut bid_trader_orders: UserOrders,
+        order_type: OrderType,
+    ) -> MatchReport {
+        let id = bid.order.id;
+        let amount = bid.order.amount;
+        let mut fills = vec![];
 
         //manage order type
-        match order_type {
-            OrderType::Limit | OrderType::ImmediateOrCancel => {
+        let (rule, remaining_amount) = match order_type {
+            OrderType::PostOnly => {
+                let cross = self
+                    .containers
+                    .asks
+                    .peek_order(&StateOrder::<BidSide, TakerPos>::find_order)
+                    .map_or(false, |ask| {
+                        StateOrder::<BidSide, TakerPos>::order_match(bid.order.price, &ask)
+                    });
+                if cross {
+                    StateOrder::<BidSide, TakerPos>::cancel_order(&bid.order, &bid.order.owner, self);
+                    (MatchRule::Rejected, amount)
+                } else {
+                    self.containers.bids.insert(bid.order);
+                    (MatchRule::Rested, amount)
+                }
+            }
+            OrderType::FillOrKill
+                if self.containers.asks.matchable_amount(
+                    bid.order.price,
+                    StateOrder::<BidSide, TakerPos>::order_match,
+                ) < amount =>
+            {
+                StateOrder::<BidSide, TakerPos>::cancel_order(&bid.order, &bid.order.owner, self);
+                (MatchRule::Killed, amount)
+            }
+            _ => {
                 let (remain_bid, remain_ask) = loop {
                     match match_taker_order(
                         &bid,
@@ -85,25 +329,29 @@ impl Dex {
                         StateOrder::<BidSide, TakerPos>::find_order,
                         StateOrder::<BidSide, TakerPos>::order_match,
                         &self.params,
+                        &self.user_orders,
                     ) {
                         None => break (Some(bid), None), //no match found
                         Some((matched, ask)) => {
+                            self.record_trade(&matched, Side::Bid);
                             let mut ask_trader_orders =
                                 UserOrders::get_user_orders(&ask.order.owner, &self);
-                            let remain_bid = bid.transfer_bid_match(
+                            let (remain_bid, bid_fill) = bid.transfer_bid_match(
                                 &mut bid_trader_orders,
                                 &mut ask_trader_orders,
                                 matched.remainder_taker_base,
                                 &matched,
                                 self,
                             );
-                            let remain_ask = ask.transfer_ask_match(
+                            let (remain_ask, ask_fill) = ask.transfer_ask_match(
                                 &mut bid_trader_orders,
                                 &mut ask_trader_orders,
                                 matched.remainder_maker_base,
                                 &matched,
                                 self,
                             );
+                            fills.push(bid_fill);
+                            fills.push(ask_fill);
                             match (remain_bid, remain_ask) {
                                 (None, None) => break (None, None),
                                 (None, remain_ask) => {
@@ -122,35 +370,78 @@ impl Dex {
                     }
                 };
                 remain_ask.map(|ask| self.containers.asks.insert(ask.order));
-                if let OrderType::Limit = order_type {
-                    remain_bid.map(|bid| self.containers.bids.insert(bid.order));
+                match remain_bid {
+                    None => (MatchRule::Filled, Decimal::zero()),
+                    Some(bid) => {
+                        let remaining_amount = bid.order.amount;
+                        match order_type {
+                            OrderType::Limit | OrderType::StopLimit => {
+                                self.containers.bids.insert(bid.order);
+                                (MatchRule::Rested, remaining_amount)
+                            }
+                            _ => {
+                                //unlock the quote of the remaining amount.
+                                StateOrder::<BidSide, TakerPos>::cancel_order(
+                                    &bid.order,
+                                    &bid.order.owner,
+                                    self,
+                                );
+                                (MatchRule::Cancelled, remaining_amount)
+                            }
+                        }
+                    }
                 }
             }
-            OrderType::PostOnly => {
-                self.containers.bids.insert(bid.order);
-            }
         };
-        (create_order_id(new_id), quote)
+        MatchReport {
+            order_id: id,
+            order_type,
+            rule,
+            filled_amount: amount - remaining_amount,
+            remaining_amount,
+            fills,
+        }
     }
 
-    // Same as bid but for ask.
-    // pushed order are AskSide and TakerPos
-    // they are matched against BidSide and MakerPos
-    pub fn ask(
+    /// Match the ask order depending on its type and return what happened to it.
+    fn execute_ask(
         &mut self,
-        owner: NonFungibleKey,
-        price: Decimal,
-        amount: Decimal,
-        mut base: Bucket,
+        mut ask: StateOrder<AskSide, TakerPos>,
+        mut ask_trader_orders: UserOrders,
         order_type: OrderType,
-    ) -> (Bucket, Bucket) {
-        let (mut ask, mut ask_trader_orders) =
-            StateOrder::<AskSide, TakerPos>::init_match(owner, price, amount, &mut base, self);
-        let new_id = ask.order.id;
+    ) -> MatchReport {
+        let id = ask.order.id;
+        let amount = ask.order.amount;
+        let mut fills = vec![];
 
         //manage order type
-        match order_type {
-            OrderType::Limit | OrderType::ImmediateOrCancel => {
+        let (rule, remaining_amount) = match order_type {
+            OrderType::PostOnly => {
+                let cross = self
+                    .containers
+                    .bids
+                    .peek_order(&StateOrder::<AskSide, TakerPos>::find_order)
+                    .map_or(false, |bid| {
+                        StateOrder::<AskSide, TakerPos>::order_match(ask.order.price, &bid)
+                    });
+                if cross {
+                    StateOrder::<AskSide, TakerPos>::cancel_order(&ask.order, &ask.order.owner, self);
+                    (MatchRule::Rejected, amount)
+                } else {
+                    self.containers.asks.insert(ask.order);
+                    (MatchRule::Rested, amount)
+                }
+            }
+            OrderType::FillOrKill
+                if self.containers.bids.matchable_amount(
+                    ask.order.price,
+                    StateOrder::<AskSide, TakerPos>::order_match,
+                ) < amount =>
+            {
+                StateOrder::<AskSide, TakerPos>::cancel_order(&ask.order, &ask.order.owner, self);
+                (MatchRule::Killed, amount)
+            }
+            _ => {
                 let (remain_bid, remain_ask) = loop {
                     match match_taker_order(
                         &ask,
@@ -158,25 +449,29 @@ impl Dex {
                         StateOrder::<AskSide, TakerPos>::find_order,
                         StateOrder::<AskSide, TakerPos>::order_match,
                         &self.params,
+                        &self.user_orders,
                     ) {
                         None => break (None, Some(ask)), //no match found
                         Some((matched, bid)) => {
+                            self.record_trade(&matched, Side::Ask);
                             let mut bid_trader_orders =
                                 UserOrders::get_user_orders(&bid.order.owner, &self);
-                            let remain_bid = bid.transfer_bid_match(
+                            let (remain_bid, bid_fill) = bid.transfer_bid_match(
                                 &mut bid_trader_orders,
                                 &mut ask_trader_orders,
                                 matched.remainder_maker_base,
                                 &matched,
                                 self,
                             );
-                            let remain_ask = ask.transfer_ask_match(
+                            let (remain_ask, ask_fill) = ask.transfer_ask_match(
                                 &mut bid_trader_orders,
                                 &mut ask_trader_orders,
                                 matched.remainder_taker_base,
                                 &matched,
                                 self,
                             );
+                            fills.push(ask_fill);
+                            fills.push(bid_fill);
                             match (remain_bid, remain_ask) {
                                 (None, None) => break (None, None),
                                 (None, remain_ask) => {
@@ -193,37 +488,94 @@ impl Dex {
                     }
                 };
                 remain_bid.map(|bid| self.containers.bids.insert(bid.order));
-                if let OrderType::Limit = order_type {
-                    remain_ask.map(|ask| self.containers.asks.insert(ask.order));
+                match remain_ask {
+                    None => (MatchRule::Filled, Decimal::zero()),
+                    Some(ask) => {
+                        let remaining_amount = ask.order.amount;
+                        match order_type {
+                            OrderType::Limit | OrderType::StopLimit => {
+                                self.containers.asks.insert(ask.order);
+                                (MatchRule::Rested, remaining_amount)
+                            }
+                            _ => {
+                                //unlock the base of the remaining amount.
+                                StateOrder::<AskSide, TakerPos>::cancel_order(
+                                    &ask.order,
+                                    &ask.order.owner,
+                                    self,
+                                );
+                                (MatchRule::Cancelled, remaining_amount)
+                            }
+                        }
+                    }
                 }
             }
-            OrderType::PostOnly => {
-                self.containers.asks.insert(ask.order);
-            }
         };
-        (create_order_id(new_id), base)
-    }
-    pub fn get_next_id(&mut self) -> u32 {
-        let id = self.counter;
-        self.counter += 1;
-        id
+        MatchReport {
+            order_id: id,
+            order_type,
+            rule,
+            filled_amount: amount - remaining_amount,
+            remaining_amount,
+            fills,
+        }
     }
 
-    pub fn cancel_order(&mut self, order_id_badge: Bucket, owner: NonFungibleKey) -> Bucket {
-        let data = order_id_badge.resource_def().metadata();
-        let id: u32 = data.get("id").unwrap().parse().unwrap();
-        self.containers
-            .asks
-            .take_order_with_id(id)
-            .map(|order| StateOrder::<AskSide, TakerPos>::cancel_order(&order, &owner, &self))
-            .or_else(|| {
-                self.containers.bids.take_order_with_id(id).map(|order| {
-                    StateOrder::<BidSide, TakerPos>::cancel_order(&order, &owner, &self);
-                });
-                None
-            });
+    /// Store the stop order until it's triggered.
+    /// If the last trade price already reached the stop price, the order is matched immediately.
+    fn push_stop_order(&mut self, side: Side, stop_price: Decimal, order: Order) -> MatchReport {
+        let id = order.id;
+        let amount = order.amount;
+        self.stop_orders.push(StopOrder {
+            side,
+            stop_price,
+            order,
+        });
+        self.trigger_stop_orders()
+            .into_iter()
+            .find(|report| report.order_id == id)
+            .unwrap_or(MatchReport {
+                order_id: id,
+                order_type: OrderType::StopLimit,
+                rule: MatchRule::StopPending,
+                filled_amount: Decimal::zero(),
+                remaining_amount: amount,
+                fills: vec![],
+            })
+    }
 
-        order_id_badge
+    /// Match all stop orders triggered by the last trade price and record their match report.
+    /// A triggered order can move the last trade price and trigger other stop orders so loop until none is triggered.
+    fn trigger_stop_orders(&mut self) -> Vec<MatchReport> {
+        let mut reports = vec![];
+        while let Some(position) = self.last_trade_price.and_then(|last_trade_price| {
+            self.stop_orders
+                .iter()
+                .position(|stop| stop.is_triggered(last_trade_price))
+        }) {
+            let stop = self.stop_orders.remove(position);
+            let trader_orders = UserOrders::get_user_orders(&stop.order.owner, &self);
+            let (_, taker_fee) = self.params.fee_rates(&trader_orders);
+            let report = match stop.side {
+                Side::Bid => self.execute_bid(
+                    StateOrder::<BidSide, TakerPos>::new_with_order(stop.order, taker_fee),
+                    trader_orders,
+                    OrderType::StopLimit,
+                ),
+                Side::Ask => self.execute_ask(
+                    StateOrder::<AskSide, TakerPos>::new_with_order(stop.order, taker_fee),
+                    trader_orders,
+                    OrderType::StopLimit,
+                ),
+            };
+            info!(
+                "stop order {} triggered: {:?}, filled {}",
+                report.order_id, report.rule, report.filled_amount
+            );
+            self.stop_reports.insert(report.order_id, report.clone());
+            reports.push(report);
+        }
+        reports
     }
 }
 
@@ -231,6 +583,7 @@ impl Dex {
 // determine the amount of asset to transfer between the order.
 #[derive(Debug)]
 struct Match {
+    match_price: Decimal,
     transfert_user_base: Decimal,
     transfert_user_quote: Decimal,
     remainder_maker_base: Decimal,
@@ -247,6 +600,23 @@ struct MakerPos;
 #[derive(Debug)]
 struct TakerPos;
 
+/// Position state report its position in the fill report.
+trait Position {
+    fn fill_position() -> FillPosition;
+}
+
+impl Position for MakerPos {
+    fn fill_position() -> FillPosition {
+        FillPosition::Maker
+    }
+}
+
+impl Position for TakerPos {
+    fn fill_position() -> FillPosition {
+        FillPosition::Taker
+    }
+}
+
 #[derive(Debug)]
 struct StateOrder<SIDE, POSITION> {
     order: Order,
@@ -297,8 +667,9 @@ impl StateOrder<BidSide, TakerPos> {
             amount,
             locked_amount,
         };
+        let (_, taker_fee) = dex.params.fee_rates(&bid_trader_orders);
         (
-            StateOrder::<BidSide, TakerPos>::new_with_order(order, dex.params.taker_fee),
+            StateOrder::<BidSide, TakerPos>::new_with_order(order, taker_fee),
             bid_trader_orders,
         )
     }
@@ -348,8 +719,9 @@ impl StateOrder<AskSide, TakerPos> {
             amount,
             locked_amount: Decimal::zero(),
         };
+        let (_, taker_fee) = dex.params.fee_rates(&ask_trader_orders);
         (
-            StateOrder::<AskSide, TakerPos>::new_with_order(order, dex.params.taker_fee),
+            StateOrder::<AskSide, TakerPos>::new_with_order(order, taker_fee),
             ask_trader_orders,
         )
     }
@@ -375,8 +747,9 @@ impl StateOrder<AskSide, TakerPos> {
     }
 }
 
-impl<POSITION> StateOrder<BidSide, POSITION> {
+impl<POSITION: Position> StateOrder<BidSide, POSITION> {
     /// Transfert the asset for a bid order what ever the position is.
+    /// Return the remaining order and the fee charged for the fill.
     fn transfer_bid_match(
         mut self,
         bid_trader: &mut UserOrders,
@@ -384,11 +757,11 @@ impl<POSITION> StateOrder<BidSide, POSITION> {
         remain_base: Decimal,
         matched: &Match,
         dex: &mut Dex,
-    ) -> Option<StateOrder<BidSide, POSITION>> {
+    ) -> (Option<StateOrder<BidSide, POSITION>>, FillReport) {
         //Calculate fee and transfert matched order in trader's Vaults.
         //for bid side take fee from base because bid receive base.
         let bid_fee_base_amount =
-            matched.transfert_user_base * self.order_fee / Into::<Decimal>::into(100);
+            matched.transfert_user_base * self.order_fee / Into::<Decimal>::into(MAX_FEE);
         dex.fee_base_vault
             .put(ask_trader.locked_base_vault.take(bid_fee_base_amount));
 
@@ -412,12 +785,21 @@ impl<POSITION> StateOrder<BidSide, POSITION> {
             self.order.locked_amount -= matched.transfert_user_quote;
         }
 
-        (self.order.amount > Decimal::zero()).then(|| self)
+        let fill = FillReport {
+            order_id: self.order.id,
+            position: POSITION::fill_position(),
+            price: matched.match_price,
+            amount: matched.transfert_user_base,
+            fee: bid_fee_base_amount,
+            fee_token: dex.params.base_token.address(),
+        };
+        ((self.order.amount > Decimal::zero()).then(|| self), fill)
     }
 }
 
-impl<POSITION> StateOrder<AskSide, POSITION> {
+impl<POSITION: Position> StateOrder<AskSide, POSITION> {
     /// Transfert the asset for an ask order what ever the position is.
+    /// Return the remaining order and the fee charged for the fill.
     fn transfer_ask_match(
         mut self,
         bid_trader: &mut UserOrders,
@@ -425,11 +807,11 @@ impl<POSITION> StateOrder<AskSide, POSITION> {
         remain_base: Decimal,
         matched: &Match,
         dex: &mut Dex,
-    ) -> Option<StateOrder<AskSide, POSITION>> {
+    ) -> (Option<StateOrder<AskSide, POSITION>>, FillReport) {
         //Calculate fee and transfert matched order in trader's Vaults.
         //for ask side take fee from quote because ask receive quote.
         let ask_fee_base_amount =
-            matched.transfert_user_quote * self.order_fee / Into::<Decimal>::into(100);
+            matched.transfert_user_quote * self.order_fee / Into::<Decimal>::into(MAX_FEE);
         dex.fee_quote_vault
             .put(bid_trader.locked_quote_vault.take(ask_fee_base_amount));
         ask_trader.quote_vault.put(
@@ -440,7 +822,15 @@ impl<POSITION> StateOrder<AskSide, POSITION> {
         //update bid and ask order with remainding
         self.order.amount = remain_base;
 
-        (self.order.amount > Decimal::zero()).then(|| self)
+        let fill = FillReport {
+            order_id: self.order.id,
+            position: POSITION::fill_position(),
+            price: matched.match_price,
+            amount: matched.transfert_user_base,
+            fee: ask_fee_base_amount,
+            fee_token: dex.params.quote_token.address(),
+        };
+        ((self.order.amount > Decimal::zero()).then(|| self), fill)
     }
 }
 
@@ -453,11 +843,19 @@ fn match_taker_order<SIDE, SIDE2: std::fmt::Debug, F, M>(
     find_order: F,
     order_match: M,
     dex: &DexParameters,
+    user_orders: &LazyMap<NonFungibleKey, UserOrders>,
 ) -> Option<(Match, StateOrder<SIDE2, MakerPos>)>
 where
     F: Fn(&BTreeSet<PriceOrder>) -> Option<PriceOrder>,
     M: Fn(Decimal, &Order) -> bool,
 {
+    //only take the found order from the set when its price match, otherwise it stays in the book.
+    let compatible = set
+        .peek_order(&find_order)
+        .map_or(false, |found_order| order_match(taker.order.price, &found_order));
+    if !compatible {
+        return None;
+    }
     //BTreeSet min only in Nigthly.
     let matched = set
         .find_match_and_take_order(find_order)
@@ -465,14 +863,20 @@ where
             if order_match(taker.order.price, &found_order) {
                 let match_price = std::cmp::min(taker.order.price, found_order.price);
                 let base_to_transfert = std::cmp::min(found_order.amount, taker.order.amount);
+                //maker fee depend on the fee tier of the maker owner when it's matched.
+                let maker_orders = user_orders.get(&found_order.owner).unwrap_or_else(|| {
+                    panic!("Badge provided not declared call create_openorders to get one")
+                });
+                let (maker_fee, _) = dex.fee_rates(&maker_orders);
                 Some((
                     Match {
+                        match_price,
                         transfert_user_quote: base_to_transfert * match_price,
                         transfert_user_base: base_to_transfert,
                         remainder_maker_base: found_order.amount - base_to_transfert,
                         remainder_taker_base: taker.order.amount - base_to_transfert,
                     },
-                    StateOrder::<SIDE2, MakerPos>::new_with_order(found_order, dex.maker_fee),
+                    StateOrder::<SIDE2, MakerPos>::new_with_order(found_order, maker_fee),
                 ))
             } else {
                 None
@@ -11,6 +11,9 @@ blueprint! {
         //manage open orders user access
         orders_badge_minter: Vault,
         orders_badge_def: ResourceDef,
+        //admin access to withdraw fee and manage fee tier
+        admin_badge_def: ResourceDef,
+        fee_tier_badge_def: ResourceDef,
 
         // Define market data
         pub dex: dex::Dex,
@@ -27,10 +30,14 @@ blueprint! {
         ///  * admin: badge return by this function that can withdraw fee gain during asset transfert
         ///  * trader or user badge needed to push order to the book
         ///
+        /// Maker and taker fee are in basis points (1/10000) of the transferred asset.
+        ///
         pub fn instantiate_market(
             quote_token: Address,
             base_token: Address,
             name: String,
+            maker_fee: Decimal,
+            taker_fee: Decimal,
         ) -> (Component, Bucket) {
 
             // Define the admin badge
@@ -51,12 +58,21 @@ blueprint! {
                 .badge(orders_badge_minter.resource_def(), MAY_MINT)
                 .no_initial_supply();
 
+            // Fee tier badge are minted by the admin and give lower fee to the trader that deposit it in its openorders.
+            let fee_tier_badge_def = ResourceBuilder::new_non_fungible()
+                .metadata("name", &format!("Fee tier market:{}", name))
+                .flags(MINTABLE)
+                .badge(orders_badge_minter.resource_def(), MAY_MINT)
+                .no_initial_supply();
+
             (
                 Self {
                     orders_badge_minter: Vault::with_bucket(orders_badge_minter),
                     orders_badge_def,
+                    admin_badge_def: admin_badge.resource_def(),
+                    fee_tier_badge_def,
                     name,
-                    dex: dex::Dex::new(quote_token, base_token),
+                    dex: dex::Dex::new(quote_token, base_token, maker_fee, taker_fee),
                 }
                 .instantiate(),
                 admin_badge
@@ -77,7 +93,11 @@ blueprint! {
                 )
             });
 
-            let orders = order::UserOrders::new(self.dex.params.quote_token.clone(), self.dex.params.base_token.clone());
+            let orders = order::UserOrders::new(
+                self.dex.params.quote_token.clone(),
+                self.dex.params.base_token.clone(),
+                self.fee_tier_badge_def.clone(),
+            );
             self.dex.user_orders.insert(
                 badge.get_non_fungible_keys().get(0).unwrap().clone(),
                 orders,
@@ -94,12 +114,14 @@ blueprint! {
         ///    If some amount can't be matched, the remaining is added to the order book.
         ///  * Immediate or Cancel: 1 same as limit but if a remaining amount can't be matched, it's cancelled.
         ///  * Post: 2 the order is not matched an immediately added to the order book. It can be useful to decrease the fee (see Fee management).
+        ///    If the order would match an order of the book, it's rejected.
+        ///  * Fill or Kill: 3 the order is matched only if its whole amount can be matched, otherwise it's cancelled.
         ///
-        /// Return the order id address use to cancel it and the rest of the quote not used.
+        /// Return the order id address use to cancel it, the rest of the quote not used and the match report that tell which rule has been applied.
         /// Provided quote is locked while the order is still pending and it's free when the order is cancelled or transferred when it's matched.
         ///
         #[auth(orders_badge_def)]
-        pub fn bid_order(&mut self, price: Decimal, amount: Decimal, ordre_type: u8, quote: Bucket) -> (Bucket, Bucket) { //, auth: BucketRef
+        pub fn bid_order(&mut self, price: Decimal, amount: Decimal, ordre_type: u8, quote: Bucket) -> (Bucket, Bucket, order::MatchReport) { //, auth: BucketRef
             info!("buy_order");
             let owner_keys = auth.get_non_fungible_keys();
             let data: BadgeData = auth
@@ -122,12 +144,14 @@ blueprint! {
         ///    If some amount can't be matched, the remaining is added to the order book.
         ///  * Immediate or Cancel: 1 same as limit but if a remaining amount can't be matched, it's cancelled.
         ///  * Post: 2 the order is not matched an immediately added to the order book. It can be useful to decrease the fee (see Fee management).
+        ///    If the order would match an order of the book, it's rejected.
+        ///  * Fill or Kill: 3 the order is matched only if its whole amount can be matched, otherwise it's cancelled.
         ///
-        /// Return the order id address use to cancel it and the rest of the quote not used.
+        /// Return the order id address use to cancel it, the rest of the base not used and the match report that tell which rule has been applied.
         /// Provided base is locked while the order is still pending and it's free when the order is cancelled or transferred when it's matched.
         ///
         #[auth(orders_badge_def)]
-        pub fn ask_order(&mut self, price: Decimal, amount: Decimal, ordre_type: u8, base: Bucket) -> (Bucket, Bucket) {
+        pub fn ask_order(&mut self, price: Decimal, amount: Decimal, ordre_type: u8, base: Bucket) -> (Bucket, Bucket, order::MatchReport) {
             info!("sell order");
             let owner_keys = auth.get_non_fungible_keys();
             let data: BadgeData = auth
@@ -143,6 +167,71 @@ blueprint! {
             )
         }
 
+        /// Push a stop limit bid order. The quote is locked like for `bid_order` but the order is kept outside the order book
+        /// until the last trade price of the market is greater or equals to the stop price. Then it's matched as a limit order with the limit price.
+        ///
+        /// Return the order id address use to cancel it, the rest of the quote not used and the match report.
+        #[auth(orders_badge_def)]
+        pub fn bid_stop_order(&mut self, stop_price: Decimal, price: Decimal, amount: Decimal, quote: Bucket) -> (Bucket, Bucket, order::MatchReport) {
+            info!("stop buy order");
+            let owner_keys = auth.get_non_fungible_keys();
+            let data: BadgeData = auth
+                .resource_def()
+                .get_non_fungible_data(owner_keys.get(0).unwrap());
+            assert!(data.name == self.name, "Not current market open order badge");
+            self.dex.stop_bid(
+                owner_keys.get(0).unwrap().clone(),
+                stop_price,
+                price,
+                amount,
+                quote,
+            )
+        }
+
+        /// Push a stop limit ask order. The base is locked like for `ask_order` but the order is kept outside the order book
+        /// until the last trade price of the market is less or equals to the stop price. Then it's matched as a limit order with the limit price.
+        ///
+        /// Return the order id address use to cancel it, the rest of the base not used and the match report.
+        #[auth(orders_badge_def)]
+        pub fn ask_stop_order(&mut self, stop_price: Decimal, price: Decimal, amount: Decimal, base: Bucket) -> (Bucket, Bucket, order::MatchReport) {
+            info!("stop sell order");
+            let owner_keys = auth.get_non_fungible_keys();
+            let data: BadgeData = auth
+                .resource_def()
+                .get_non_fungible_data(owner_keys.get(0).unwrap());
+            assert!(data.name == self.name, "Not current market open order badge");
+            self.dex.stop_ask(
+                owner_keys.get(0).unwrap().clone(),
+                stop_price,
+                price,
+                amount,
+                base,
+            )
+        }
+
+        /// Return the price of the last matched order. None if no order has been matched yet.
+        pub fn last_trade_price(&self) -> Option<Decimal> {
+            self.dex.last_trade_price
+        }
+
+        /// Return the match report of a stop order once the last trade price has triggered it, None while it's pending.
+        /// The report tells what the stop order filled even when it was triggered by the order of another trader.
+        pub fn get_stop_order_report(&self, order_id: u32) -> Option<order::MatchReport> {
+            self.dex.get_stop_report(order_id)
+        }
+
+        /// Return the recent trades of the market, most recent first.
+        /// Skip the offset most recent trades and return at most limit trades so the history can be read page by page.
+        pub fn get_trades(&self, offset: u32, limit: u32) -> Vec<order::Trade> {
+            self.dex.get_trades(offset as usize, limit as usize)
+        }
+
+        /// Return the OHLCV candles of the trades done between start_epoch included and end_epoch excluded.
+        /// Each candle aggregate the trades of period epochs. Period without trade have no candle.
+        pub fn get_candles(&self, period: u64, start_epoch: u64, end_epoch: u64) -> Vec<order::Candle> {
+            self.dex.get_candles(period, start_epoch, end_epoch)
+        }
+
         ///
         /// Withdraw all the asset (quote, base) store in the provided badge openorders and return it.
         /// Locked quote or base can't be withdrawn until associated order are pending in the order book.
@@ -164,6 +253,73 @@ blueprint! {
             (user_orders.quote_vault.take_all(), user_orders.base_vault.take_all())
         }
 
+        /// Deposit a fee tier badge in the openorders of the provided badge.
+        /// While deposited, the lower of the market fee and the fee tier fee are applied to the trader orders.
+        /// Only one fee tier badge can be deposited at a time.
+        #[auth(orders_badge_def)]
+        pub fn deposit_fee_tier(&mut self, fee_tier: Bucket) {
+            info!("deposit fee tier");
+            let owner_keys = auth.get_non_fungible_keys();
+            let data: BadgeData = auth
+                .resource_def()
+                .get_non_fungible_data(owner_keys.get(0).unwrap());
+            assert!(data.name == self.name, "Not current market open order badge");
+            assert!(fee_tier.resource_def() == self.fee_tier_badge_def, "Not current market fee tier badge");
+
+            let mut user_orders = order::UserOrders::get_user_orders(owner_keys.get(0).unwrap(), &self.dex);
+            assert!(user_orders.fee_tier_vault.is_empty(), "A fee tier badge is already deposited");
+            user_orders.fee_tier_vault.put(fee_tier);
+        }
+
+        /// Withdraw the fee tier badge deposited in the openorders of the provided badge.
+        /// Market fee are applied again to the orders pushed after.
+        #[auth(orders_badge_def)]
+        pub fn withdraw_fee_tier(&mut self) -> Bucket {
+            info!("withdraw fee tier");
+            let owner_keys = auth.get_non_fungible_keys();
+            let data: BadgeData = auth
+                .resource_def()
+                .get_non_fungible_data(owner_keys.get(0).unwrap());
+            assert!(data.name == self.name, "Not current market open order badge");
+
+            let mut user_orders = order::UserOrders::get_user_orders(owner_keys.get(0).unwrap(), &self.dex);
+            user_orders.fee_tier_vault.take_all()
+        }
+
+        /// Admin mint a fee tier badge with a maker and taker fee in basis points.
+        /// The badge can be given to a trader to lower its fee.
+        #[auth(admin_badge_def)]
+        pub fn mint_fee_tier_badge(&mut self, maker_fee: Decimal, taker_fee: Decimal) -> Bucket {
+            assert!(
+                maker_fee >= Decimal::zero() && taker_fee >= Decimal::zero(),
+                "Fee tier fee can't be negative"
+            );
+            let fee_tier_badge_def = &self.fee_tier_badge_def;
+            self.orders_badge_minter.authorize(|auth| {
+                fee_tier_badge_def.mint_non_fungible(
+                    &NonFungibleKey::from(Uuid::generate()),
+                    FeeTierData {
+                        maker_fee,
+                        taker_fee,
+                    },
+                    auth,
+                )
+            })
+        }
+
+        /// Admin update the market maker and taker fee in basis points.
+        /// New fee are applied to the orders pushed after.
+        #[auth(admin_badge_def)]
+        pub fn set_fees(&mut self, maker_fee: Decimal, taker_fee: Decimal) {
+            self.dex.params.set_fees(maker_fee, taker_fee);
+        }
+
+        /// Admin withdraw all the fee collected by the market: quote fee taken from ask order and base fee taken from bid order.
+        #[auth(admin_badge_def)]
+        pub fn withdraw_fees(&mut self) -> (Bucket, Bucket) {
+            (self.dex.fee_quote_vault.take_all(), self.dex.fee_base_vault.take_all())
+        }
+
         /// Cancel the order with the specified order_id_address. The order must owned bu the auth user badge provided.
         /// Order locked asset are transferred to the user openordersquote and base vault.
         ///
@@ -185,3 +341,10 @@ blueprint! {
 pub struct BadgeData {
     name: String,
 }
+
+/// Fee in basis points given by a fee tier badge.
+#[derive(Debug, Clone, NonFungibleData)]
+pub struct FeeTierData {
+    pub maker_fee: Decimal,
+    pub taker_fee: Decimal,
+}
@@ -1,6 +1,7 @@
 //! Contains the order struct and storage collection.
 //!
 use crate::dex::Dex;
+use crate::FeeTierData;
 use sbor::*;
 use scrypto::prelude::*;
 use std::cmp::Ordering;
@@ -25,6 +26,26 @@ impl BTreeSetOrder {
             .and_then(|order| self.take_order_with_price(order))
     }
 
+    /// Return a copy of the order that find_order_and_take_order would take without removing it from the set.
+    pub fn peek_order<F>(&self, find_order: &F) -> Option<Order>
+    where
+        F: Fn(&BTreeSet<PriceOrder>) -> Option<PriceOrder>,
+    {
+        find_order(&self.set).and_then(|price_order| price_order.order_list.first().cloned())
+    }
+
+    /// Return the total amount of base of the orders whose price is compatible with the specified price.
+    pub fn matchable_amount<M>(&self, price: Decimal, order_match: M) -> Decimal
+    where
+        M: Fn(Decimal, &Order) -> bool,
+    {
+        self.set
+            .iter()
+            .flat_map(|price_order| price_order.order_list.iter())
+            .filter(|order| order_match(price, order))
+            .fold(Decimal::zero(), |total, order| total + order.amount)
+    }
+
     fn take_order_with_price(&mut self, order: &PriceOrder) -> Option<Order> {
         //rm not get_mut in BtreeSet
         let found = self.set.take(order);
@@ -134,18 +155,28 @@ pub struct UserOrders {
     pub locked_quote_vault: Vault,
     pub base_vault: Vault,
     pub locked_base_vault: Vault,
+    pub fee_tier_vault: Vault,
 }
 
 impl UserOrders {
-    pub fn new(quote: ResourceDef, base: ResourceDef) -> Self {
+    pub fn new(quote: ResourceDef, base: ResourceDef, fee_tier: ResourceDef) -> Self {
         Self {
             quote_vault: Vault::new(quote.clone()),
             locked_quote_vault: Vault::new(quote),
             base_vault: Vault::new(base.clone()),
             locked_base_vault: Vault::new(base),
+            fee_tier_vault: Vault::new(fee_tier),
         }
     }
 
+    /// Return the fee of the fee tier badge deposited in the openorders if any.
+    pub fn fee_tier(&self) -> Option<FeeTierData> {
+        self.fee_tier_vault
+            .get_non_fungible_keys()
+            .first()
+            .map(|key| self.fee_tier_vault.resource_def().get_non_fungible_data(key))
+    }
+
     pub fn get_user_orders(owner: &NonFungibleKey, dex: &Dex) -> UserOrders {
         dex.user_orders.get(owner).unwrap_or_else(|| {
             panic!("Badge provided not declared call create_openorders to get one")
@@ -184,6 +215,9 @@ pub enum OrderType {
     Limit,
     ImmediateOrCancel,
     PostOnly,
+    FillOrKill,
+    //only pushed with the stop order methods because it needs a stop price.
+    StopLimit,
 }
 
 impl From<u8> for OrderType {
@@ -191,7 +225,116 @@ impl From<u8> for OrderType {
         match order_type {
             1 => OrderType::ImmediateOrCancel,
             2 => OrderType::PostOnly,
+            3 => OrderType::FillOrKill,
             _ => OrderType::Limit,
         }
     }
 }
+
+/// Stop limit order waiting for the last trade price to reach its stop price.
+/// Bid stop order are triggered when the last trade price is greater or equals to the stop price,
+/// Ask stop order when it's less or equals.
+/// When triggered, the order is matched as a limit order.
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe, PartialEq, Eq)]
+pub struct StopOrder {
+    pub side: Side,
+    pub stop_price: Decimal,
+    pub order: Order,
+}
+
+impl StopOrder {
+    pub fn is_triggered(&self, last_trade_price: Decimal) -> bool {
+        match self.side {
+            Side::Bid => last_trade_price >= self.stop_price,
+            Side::Ask => last_trade_price <= self.stop_price,
+        }
+    }
+}
+
+/// Rule applied to a pushed order.
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe, PartialEq, Eq)]
+pub enum MatchRule {
+    /// The whole amount has been matched.
+    Filled,
+    /// The remaining amount has been added to the order book (limit, post only and triggered stop order).
+    Rested,
+    /// The remaining amount of an immediate or cancel order has been cancelled and its asset unlocked.
+    Cancelled,
+    /// The fill or kill order can't be matched for its whole amount so nothing has been matched.
+    Killed,
+    /// The post only order would have matched an order of the book so it has been rejected.
+    Rejected,
+    /// The stop order is waiting for the last trade price to reach its stop price.
+    StopPending,
+}
+
+/// Report return when an order is pushed to tell what happened to it.
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe, PartialEq, Eq)]
+pub struct MatchReport {
+    pub order_id: u32,
+    pub order_type: OrderType,
+    pub rule: MatchRule,
+    pub filled_amount: Decimal,
+    pub remaining_amount: Decimal,
+    pub fills: Vec<FillReport>,
+}
+
+/// Position of an order in a fill.
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe, PartialEq, Eq)]
+pub enum FillPosition {
+    Maker,
+    Taker,
+}
+
+/// Fee charged to an order for one fill.
+/// Bid order pay the fee in base and ask order in quote.
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe, PartialEq, Eq)]
+pub struct FillReport {
+    pub order_id: u32,
+    pub position: FillPosition,
+    pub price: Decimal,
+    pub amount: Decimal, //amount of base transferred.
+    pub fee: Decimal,
+    pub fee_token: Address,
+}
+
+/// Trade recorded each time an order is matched.
+/// Side is the side of the taker order.
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe, PartialEq, Eq)]
+pub struct Trade {
+    pub price: Decimal,
+    pub amount: Decimal, //amount of base transferred.
+    pub side: Side,
+    pub epoch: u64,
+}
+
+/// Open, high, low, close price and base volume of the trades done during a period of epochs.
+#[derive(Debug, Clone, TypeId, Encode, Decode, Describe, PartialEq, Eq)]
+pub struct Candle {
+    pub start_epoch: u64,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal,
+}
+
+impl Candle {
+    pub fn new(start_epoch: u64, trade: &Trade) -> Self {
+        Candle {
+            start_epoch,
+            open: trade.price,
+            high: trade.price,
+            low: trade.price,
+            close: trade.price,
+            volume: trade.amount,
+        }
+    }
+
+    pub fn add_trade(&mut self, trade: &Trade) {
+        self.high = std::cmp::max(self.high, trade.price);
+        self.low = std::cmp::min(self.low, trade.price);
+        self.close = trade.price;
+        self.volume += trade.amount;
+    }
+}
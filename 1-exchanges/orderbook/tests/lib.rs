@@ -81,7 +81,7 @@ fn create_market<'a, L: SubstateStore>(
     quote_token: Address,
     base_token: Address,
     key: EcdsaPublicKey,
-) -> Address {
+) -> (Address, Address) {
     let receipt = executor
         .run(
             TransactionBuilder::new(executor)
@@ -93,6 +93,8 @@ fn create_market<'a, L: SubstateStore>(
                         format!("{}", quote_token),
                         format!("{}", base_token),
                         "TestMarket".to_string(),
+                        "500".to_string(),
+                        "1000".to_string(),
                     ],
                     Some(account),
                 )
@@ -101,7 +103,7 @@ fn create_market<'a, L: SubstateStore>(
                 .unwrap(),
         )
         .unwrap();
-    receipt.component(0).unwrap()
+    (receipt.component(0).unwrap(), receipt.resource_def(0).unwrap())
 }
 
 fn transfer_token<'a, L: SubstateStore>(
@@ -130,6 +132,96 @@ fn transfer_token<'a, L: SubstateStore>(
     assert!(receipt.result.is_ok());
 }
 
+// mirrors of the blueprint history types, fields in the same order
+#[derive(TypeId, Decode, Debug, PartialEq)]
+enum Side {
+    Bid,
+    Ask,
+}
+
+#[derive(TypeId, Decode, Debug, PartialEq)]
+struct Trade {
+    price: Decimal,
+    amount: Decimal,
+    side: Side,
+    epoch: u64,
+}
+
+#[derive(TypeId, Decode, Debug, PartialEq)]
+struct Candle {
+    start_epoch: u64,
+    open: Decimal,
+    high: Decimal,
+    low: Decimal,
+    close: Decimal,
+    volume: Decimal,
+}
+
+#[derive(TypeId, Decode, Debug, PartialEq)]
+enum OrderType {
+    Limit,
+    ImmediateOrCancel,
+    PostOnly,
+    FillOrKill,
+    StopLimit,
+}
+
+#[derive(TypeId, Decode, Debug, PartialEq)]
+enum MatchRule {
+    Filled,
+    Rested,
+    Cancelled,
+    Killed,
+    Rejected,
+    StopPending,
+}
+
+#[derive(TypeId, Decode, Debug, PartialEq)]
+enum FillPosition {
+    Maker,
+    Taker,
+}
+
+#[derive(TypeId, Decode, Debug, PartialEq)]
+struct FillReport {
+    order_id: u32,
+    position: FillPosition,
+    price: Decimal,
+    amount: Decimal,
+    fee: Decimal,
+    fee_token: Address,
+}
+
+#[derive(TypeId, Decode, Debug, PartialEq)]
+struct MatchReport {
+    order_id: u32,
+    order_type: OrderType,
+    rule: MatchRule,
+    filled_amount: Decimal,
+    remaining_amount: Decimal,
+    fills: Vec<FillReport>,
+}
+
+fn trade(price: u32, amount: u32, side: Side, epoch: u64) -> Trade {
+    Trade {
+        price: From::<u32>::from(price),
+        amount: From::<u32>::from(amount),
+        side,
+        epoch,
+    }
+}
+
+fn candle(start_epoch: u64, ohlc: (u32, u32, u32, u32), volume: u32) -> Candle {
+    Candle {
+        start_epoch,
+        open: From::<u32>::from(ohlc.0),
+        high: From::<u32>::from(ohlc.1),
+        low: From::<u32>::from(ohlc.2),
+        close: From::<u32>::from(ohlc.3),
+        volume: From::<u32>::from(volume),
+    }
+}
+
 struct Trader {
     key: EcdsaPublicKey,
     address: Address,
@@ -141,6 +233,14 @@ struct Trader {
 fn init<'a, L: SubstateStore>(
     ledger: &'a mut L,
 ) -> (TransactionExecutor<'a, L>, Address, Vec<Trader>) {
+    let (executor, instance, traders, _) = init_with_admin(ledger);
+    (executor, instance, traders)
+}
+
+// the returned admin use the market admin badge as access badge.
+fn init_with_admin<'a, L: SubstateStore>(
+    ledger: &'a mut L,
+) -> (TransactionExecutor<'a, L>, Address, Vec<Trader>, Trader) {
     let mut executor = TransactionExecutor::new(ledger, false);
     let package = executor
         .publish_package(include_code!("orderbook"))
@@ -159,7 +259,7 @@ fn init<'a, L: SubstateStore>(
         market_hand.0,
     );
 
-    let instance = create_market(
+    let (instance, admin_badge_address) = create_market(
         &mut executor,
         package,
         market_hand.1,
@@ -198,7 +298,14 @@ fn init<'a, L: SubstateStore>(
             }
         })
         .collect();
-    (executor, instance, traders)
+    let admin = Trader {
+        key: market_hand.0,
+        address: market_hand.1,
+        access_badge_address: admin_badge_address,
+        quote_token,
+        base_token,
+    };
+    (executor, instance, traders, admin)
 }
 
 fn create_trader_openorders<'a, L: radix_engine::ledger::SubstateStore>(
@@ -295,6 +402,81 @@ fn push_ask_order<'a, L: SubstateStore>(
     receipt.new_entities[0]
 }
 
+fn push_stop_bid_order<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    instance: Address,
+    stop_price: usize,
+    price: usize,
+    amount_base: usize,
+    amount_token: usize,
+    trader: &Trader,
+) -> Address {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(
+                    instance,
+                    "bid_stop_order",
+                    vec![
+                        format!("{}", stop_price),
+                        format!("{}", price),
+                        format!("{}", amount_base),
+                        format!("{},{}", amount_token, trader.quote_token),
+                        format!("{},{}", 1, trader.access_badge_address),
+                    ],
+                    Some(trader.address),
+                )
+                .call_method_with_all_resources(trader.address, "deposit_batch")
+                .build(vec![trader.key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok());
+    assert!(receipt.new_entities.len() > 0, "push stop bid no order created.");
+    receipt.new_entities[0]
+}
+
+fn call_with_badges<'a, L: SubstateStore>(
+    executor: &mut TransactionExecutor<'a, L>,
+    instance: Address,
+    method: &str,
+    args: Vec<String>,
+    trader: &Trader,
+    deposit_to: Address,
+) {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(instance, method, args, Some(trader.address))
+                .call_method_with_all_resources(deposit_to, "deposit_batch")
+                .build(vec![trader.key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok(), "{} failed", method);
+}
+
+// call a read only method and decode its output.
+fn query<'a, L: SubstateStore, T: Decode>(
+    executor: &mut TransactionExecutor<'a, L>,
+    instance: Address,
+    method: &str,
+    args: Vec<String>,
+    trader: &Trader,
+) -> T {
+    let receipt = executor
+        .run(
+            TransactionBuilder::new(executor)
+                .call_method(instance, method, args, Some(trader.address))
+                .call_method_with_all_resources(trader.address, "deposit_batch")
+                .build(vec![trader.key])
+                .unwrap(),
+        )
+        .unwrap();
+    assert!(receipt.result.is_ok(), "{} failed", method);
+    scrypto_decode(&receipt.outputs[receipt.outputs.len() - 2].raw).unwrap()
+}
+
 fn push_withdraw<'a, L: SubstateStore>(
     executor: &mut TransactionExecutor<'a, L>,
     instance: Address,
@@ -594,3 +776,288 @@ fn test_cancel() {
         From::<u32>::from(99980),
     );
 }
+
+#[test]
+fn test_immediate_or_cancel_unlock_remaining() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let (mut executor, instance, traders) = init(&mut ledger);
+    push_ask_order(&mut executor, instance, 20, 10, 0, &traders[1]);
+    // only 10 of the 20 can be matched, the quote locked for the remaining 10 is unlocked.
+    push_bid_order(&mut executor, instance, 20, 20, 400, 1, &traders[0]);
+    push_withdraw(&mut executor, instance, &traders[0]);
+    check_wallet(
+        &mut executor,
+        0,
+        &traders[0],
+        From::<u32>::from(99800),
+        From::<u32>::from(100009),
+    );
+}
+
+#[test]
+fn test_fill_or_kill() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let (mut executor, instance, traders) = init(&mut ledger);
+    push_ask_order(&mut executor, instance, 20, 10, 0, &traders[1]);
+    // not enough ask in the book, nothing is matched.
+    push_bid_order(&mut executor, instance, 20, 20, 400, 3, &traders[0]);
+    push_withdraw(&mut executor, instance, &traders[0]);
+    check_wallet(
+        &mut executor,
+        0,
+        &traders[0],
+        From::<u32>::from(100000),
+        From::<u32>::from(100000),
+    );
+
+    push_ask_order(&mut executor, instance, 20, 10, 0, &traders[1]);
+    push_bid_order(&mut executor, instance, 20, 20, 400, 3, &traders[0]);
+    push_withdraw(&mut executor, instance, &traders[0]);
+    check_wallet(
+        &mut executor,
+        0,
+        &traders[0],
+        From::<u32>::from(99600),
+        From::<u32>::from(100018),
+    );
+}
+
+#[test]
+fn test_post_only_rejected_when_crossing() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let (mut executor, instance, traders) = init(&mut ledger);
+    push_ask_order(&mut executor, instance, 20, 10, 0, &traders[1]);
+    push_bid_order(&mut executor, instance, 20, 10, 200, 2, &traders[0]);
+    push_withdraw(&mut executor, instance, &traders[0]);
+    check_wallet(
+        &mut executor,
+        0,
+        &traders[0],
+        From::<u32>::from(100000),
+        From::<u32>::from(100000),
+    );
+
+    // doesn't cross the ask so it stays in the book with its quote locked.
+    push_bid_order(&mut executor, instance, 19, 10, 190, 2, &traders[0]);
+    push_withdraw(&mut executor, instance, &traders[0]);
+    check_wallet(
+        &mut executor,
+        0,
+        &traders[0],
+        From::<u32>::from(99810),
+        From::<u32>::from(100000),
+    );
+}
+
+#[test]
+fn test_stop_order_triggered_by_last_trade() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let (mut executor, instance, traders) = init(&mut ledger);
+    push_ask_order(&mut executor, instance, 20, 10, 0, &traders[1]);
+    push_ask_order(&mut executor, instance, 25, 10, 0, &traders[1]);
+    // no trade yet, the stop order is pending.
+    push_stop_bid_order(&mut executor, instance, 20, 25, 10, 250, &traders[0]);
+    let report: Option<MatchReport> = query(
+        &mut executor,
+        instance,
+        "get_stop_order_report",
+        vec!["2".to_string()],
+        &traders[0],
+    );
+    assert_eq!(report, None);
+    push_withdraw(&mut executor, instance, &traders[0]);
+    check_wallet(
+        &mut executor,
+        0,
+        &traders[0],
+        From::<u32>::from(99750),
+        From::<u32>::from(100000),
+    );
+
+    // the trade at 20 trigger the stop order that match the ask at 25.
+    push_bid_order(&mut executor, instance, 20, 10, 200, 0, &traders[0]);
+    let report: MatchReport = query::<_, Option<MatchReport>>(
+        &mut executor,
+        instance,
+        "get_stop_order_report",
+        vec!["2".to_string()],
+        &traders[0],
+    )
+    .unwrap();
+    assert_eq!(report.order_type, OrderType::StopLimit);
+    assert_eq!(report.rule, MatchRule::Filled);
+    assert_eq!(report.filled_amount, From::<u32>::from(10));
+    assert!(report
+        .fills
+        .iter()
+        .any(|fill| fill.order_id == 2 && fill.position == FillPosition::Taker && fill.price == From::<u32>::from(25)));
+    push_withdraw(&mut executor, instance, &traders[0]);
+    check_wallet(
+        &mut executor,
+        0,
+        &traders[0],
+        From::<u32>::from(99550),
+        From::<u32>::from(100018),
+    );
+}
+
+#[test]
+fn test_fee_tier_and_admin_withdraw() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let (mut executor, instance, traders, admin) = init_with_admin(&mut ledger);
+    // admin mint a 0 fee tier badge for trader 0.
+    call_with_badges(
+        &mut executor,
+        instance,
+        "mint_fee_tier_badge",
+        vec![
+            "0".to_string(),
+            "0".to_string(),
+            format!("{},{}", 1, admin.access_badge_address),
+        ],
+        &admin,
+        traders[0].address,
+    );
+    let fee_tier = get_account_vaults(executor.ledger(), traders[0].address)
+        .into_iter()
+        .filter_map(|(address, amount)| {
+            (amount == Decimal::one() && address != traders[0].access_badge_address)
+                .then(|| address)
+        })
+        .next()
+        .unwrap();
+    call_with_badges(
+        &mut executor,
+        instance,
+        "deposit_fee_tier",
+        vec![
+            format!("{},{}", 1, fee_tier),
+            format!("{},{}", 1, traders[0].access_badge_address),
+        ],
+        &traders[0],
+        traders[0].address,
+    );
+
+    // trader 0 is taker without fee, trader 1 maker pay 5%.
+    push_ask_order(&mut executor, instance, 20, 10, 0, &traders[1]);
+    push_bid_order(&mut executor, instance, 20, 10, 200, 0, &traders[0]);
+    push_withdraw(&mut executor, instance, &traders[0]);
+    push_withdraw(&mut executor, instance, &traders[1]);
+    check_wallet(
+        &mut executor,
+        0,
+        &traders[0],
+        From::<u32>::from(99800),
+        From::<u32>::from(100010),
+    );
+    check_wallet(
+        &mut executor,
+        1,
+        &traders[1],
+        From::<u32>::from(100190),
+        From::<u32>::from(99990),
+    );
+
+    call_with_badges(
+        &mut executor,
+        instance,
+        "withdraw_fees",
+        vec![format!("{},{}", 1, admin.access_badge_address)],
+        &admin,
+        admin.address,
+    );
+    check_wallet(
+        &mut executor,
+        0,
+        &admin,
+        From::<u32>::from(9800010),
+        From::<u32>::from(9800000),
+    );
+}
+
+#[test]
+fn test_trade_history_queries() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let (mut executor, instance, traders) = init(&mut ledger);
+    push_ask_order(&mut executor, instance, 20, 10, 0, &traders[1]);
+    push_ask_order(&mut executor, instance, 21, 10, 0, &traders[1]);
+    // the bid takes both asks, each at the maker price.
+    push_bid_order(&mut executor, instance, 21, 20, 420, 0, &traders[0]);
+
+    // at epoch 3 the ask is the taker, a match is done at the lower of the two prices.
+    executor.ledger_mut().set_epoch(3);
+    push_bid_order(&mut executor, instance, 23, 5, 115, 0, &traders[0]);
+    push_ask_order(&mut executor, instance, 22, 5, 0, &traders[1]);
+
+    // trades are returned newest first.
+    let trades: Vec<Trade> = query(
+        &mut executor,
+        instance,
+        "get_trades",
+        vec!["0".to_string(), "10".to_string()],
+        &traders[0],
+    );
+    assert_eq!(
+        trades,
+        vec![
+            trade(22, 5, Side::Ask, 3),
+            trade(21, 10, Side::Bid, 0),
+            trade(20, 10, Side::Bid, 0),
+        ]
+    );
+    let trades: Vec<Trade> = query(
+        &mut executor,
+        instance,
+        "get_trades",
+        vec!["1".to_string(), "1".to_string()],
+        &traders[0],
+    );
+    assert_eq!(trades, vec![trade(21, 10, Side::Bid, 0)]);
+    let trades: Vec<Trade> = query(
+        &mut executor,
+        instance,
+        "get_trades",
+        vec!["3".to_string(), "10".to_string()],
+        &traders[0],
+    );
+    assert_eq!(trades, vec![]);
+
+    // one candle per epoch with trades.
+    let candles: Vec<Candle> = query(
+        &mut executor,
+        instance,
+        "get_candles",
+        vec!["1".to_string(), "0".to_string(), "10".to_string()],
+        &traders[0],
+    );
+    assert_eq!(
+        candles,
+        vec![candle(0, (20, 21, 20, 21), 20), candle(3, (22, 22, 22, 22), 5)]
+    );
+
+    // a period of 4 epochs aggregates everything, the end epoch is excluded.
+    let candles: Vec<Candle> = query(
+        &mut executor,
+        instance,
+        "get_candles",
+        vec!["4".to_string(), "0".to_string(), "10".to_string()],
+        &traders[0],
+    );
+    assert_eq!(candles, vec![candle(0, (20, 22, 20, 22), 25)]);
+    let candles: Vec<Candle> = query(
+        &mut executor,
+        instance,
+        "get_candles",
+        vec!["4".to_string(), "0".to_string(), "3".to_string()],
+        &traders[0],
+    );
+    assert_eq!(candles, vec![candle(0, (20, 21, 20, 21), 20)]);
+    let candles: Vec<Candle> = query(
+        &mut executor,
+        instance,
+        "get_candles",
+        vec!["4".to_string(), "1".to_string(), "10".to_string()],
+        &traders[0],
+    );
+    assert_eq!(candles, vec![candle(1, (22, 22, 22, 22), 5)]);
+}
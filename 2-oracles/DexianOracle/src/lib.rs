@@ -1,29 +1,56 @@
 use scrypto::prelude::*;
 
+mod price_subscriber;
+
+/// subscriptions of a pair, bounds the work of every feed
+const MAX_SUBSCRIPTIONS_PER_PAIR: usize = 50;
+
+/// undelivered notifications queued per subscription, bounds the work of every delivery
+const MAX_PENDING_NOTIFICATIONS: usize = 20;
+
 blueprint! {
     struct DeXianOracle {
          /// This is just a reular admin badge, for register/remove DataProvider
          admin_badge: ResourceAddress,
         
-         // /// DataProvider badge ResourceDef
-         // dataprovider_badge_def: ResourceDef,
+         /// DataProvider badge, every publisher holds its own badge
+         dataprovider_badge: ResourceAddress,
+
          // minter
          callback_minter: Vault,
-        
-         // /// DataProvider badge ResourceDef
-         // dataprovider_badge_def: ResourceDef,
+
+         /// pair => ids of the DataProvider badges that may publish the pair
+         publishers: HashMap<String, HashSet<NonFungibleId>>,
+
+         /// pair => (price, epoch_at) of the latest submission of each publisher
+         submissions: HashMap<String, HashMap<NonFungibleId, (Decimal, u64)>>,
+
+         /// submissions and prices older than this number of epochs are stale
+         max_age: u64,
          
          /// callbacks
          callback_vaults: Vault,
 
-         /// callback that have not yet been triggered 
-         unfilful_vec: Vec<NonFungibleId>,
+         /// pair => callbacks that have not yet been triggered, in request order
+         unfilful_requests: HashMap<String, Vec<NonFungibleId>>,
+
+         /// Subscription badge, held by the subscriber to cancel the subscription
+         subscription_badge: ResourceAddress,
+
+         /// subscription id => subscription
+         subscriptions: HashMap<NonFungibleId, Subscription>,
+
+         /// pair => subscriptions of the pair, in subscription order
+         pair_subscriptions: HashMap<String, Vec<NonFungibleId>>,
+
+         /// prepaid fee balances of the subscriptions
+         subscription_vault: Vault,
  
          /// fee
          fee: Decimal,
  
-         /// oracle (price, epoch_at) for XRD/USD
-         price_map: HashMap<String, (Decimal, u64)>,
+         /// oracle price data for each pair, e.g. XRD/USD
+         price_map: HashMap<String, PriceData>,
  
          /// balance(fee) vault
          vault: Vault 
@@ -31,9 +58,11 @@ blueprint! {
     
     impl DeXianOracle {
         pub fn new(
-            fee: Decimal
+            fee: Decimal,
+            max_age: u64
         ) -> (ComponentAddress, Bucket) {
             assert!( fee > Decimal::zero(), "invalid fee value.");
+            assert!( max_age > 0, "invalid max age value.");
 
             let admin_badge : Bucket = ResourceBuilder::new_fungible()
                 .metadata("name","DeXianOracle Admin Badge").metadata("symbol","DXADM")
@@ -45,43 +74,127 @@ blueprint! {
             
             let callback_bucket = ResourceBuilder::new_non_fungible()
                 .metadata("name", "DeXianOracle Callback").metadata("symbol", "DXCB")
-                .mintable(rule!(require(admin_badge.resource_address())), LOCKED)
-                .burnable(rule!(require(admin_badge.resource_address())), LOCKED)
+                .mintable(rule!(require(minter_badge.resource_address())), LOCKED)
+                .burnable(rule!(require(minter_badge.resource_address())), LOCKED)
+                .no_initial_supply();
+
+            let subscription_badge = ResourceBuilder::new_non_fungible()
+                .metadata("name", "DeXianOracle Subscription").metadata("symbol", "DXSUB")
+                .mintable(rule!(require(minter_badge.resource_address())), LOCKED)
+                .burnable(rule!(require(minter_badge.resource_address())), LOCKED)
+                .no_initial_supply();
+
+            let dataprovider_badge = ResourceBuilder::new_non_fungible()
+                .metadata("name", "DeXianOracle DataProvider Badge").metadata("symbol", "DXDP")
+                .mintable(rule!(require(minter_badge.resource_address())), LOCKED)
+                .burnable(rule!(require(minter_badge.resource_address())), LOCKED)
                 .no_initial_supply();
             
             let component = Self {
                 admin_badge: admin_badge.resource_address(),
+                dataprovider_badge,
+                publishers: HashMap::new(),
+                submissions: HashMap::new(),
+                max_age,
                 price_map: HashMap::new(),
                 vault: Vault::new(RADIX_TOKEN),
                 callback_vaults: Vault::new(callback_bucket),
-                unfilful_vec: Vec::new(),
+                unfilful_requests: HashMap::new(),
+                subscription_badge,
+                subscriptions: HashMap::new(),
+                pair_subscriptions: HashMap::new(),
+                subscription_vault: Vault::new(RADIX_TOKEN),
                 callback_minter: Vault::with_bucket(minter_badge),
                 fee
             }.instantiate();
 
             let access_rules = AccessRules::new()
-                .method("feed_price", rule!(require(admin_badge.resource_address())))
+                .method("feed_price", rule!(allow_all))
+                .method("feed_prices", rule!(allow_all))
+                .method("subscribe", rule!(allow_all))
+                .method("top_up_subscription", rule!(allow_all))
+                .method("cancel_subscription", rule!(allow_all))
+                .method("get_subscription_balance", rule!(allow_all))
+                .method("deliver_notifications", rule!(allow_all))
+                .method("remove_subscription", rule!(require(admin_badge.resource_address())))
                 .method("withdraw_fee", rule!(require(admin_badge.resource_address())))
                 .method("request_price", rule!(allow_all))
-                .method("get_price", rule!(allow_all));
-                // .method("register_dataprovider", rule(!require(admin_badge.resource_address())))
-                // .method("remove_dataprovider", rule(!require(admin_badge.resource_address())));
+                .method("get_price", rule!(allow_all))
+                .method("get_price_data", rule!(allow_all))
+                .method("register_dataprovider", rule!(require(admin_badge.resource_address())))
+                .method("authorize_dataprovider", rule!(require(admin_badge.resource_address())))
+                .method("remove_dataprovider", rule!(require(admin_badge.resource_address())))
+                .method("set_max_age", rule!(require(admin_badge.resource_address())));
 
             (component.add_access_check(access_rules).globalize(), admin_badge)
 
         }
 
-        pub fn feed_price(&mut self, pair: String, price: String) -> bool {
-            let epoch_at = Runtime::current_epoch();
-            let dec_price = Decimal::from(price.clone());
-            self.price_map.insert(pair.clone(), (dec_price, epoch_at));
-            self.filfull_request(&pair, price.clone(), epoch_at);
+        /// Mint a DataProvider badge that is authorized to publish the given pairs
+        pub fn register_dataprovider(&mut self, name: String, pairs: Vec<String>) -> Bucket {
+            let provider_id = NonFungibleId::random();
+            info!("registered dataprovider {}", provider_id);
+            let badge = self.callback_minter.authorize(|| {
+                let rm = borrow_resource_manager!(self.dataprovider_badge);
+                rm.mint_non_fungible(&provider_id, DataProvider{ name })
+            });
+            for pair in pairs {
+                self.authorize_dataprovider(provider_id.clone(), pair);
+            }
+            badge
+        }
+
+        /// Authorize an already registered DataProvider to publish another pair
+        pub fn authorize_dataprovider(&mut self, provider_id: NonFungibleId, pair: String) {
+            self.publishers.entry(pair).or_insert(HashSet::new()).insert(provider_id);
+        }
+
+        /// Revoke the authorization of a DataProvider for the pair, its last submission is discarded.
+        /// The price of the pair is aggregated again from the remaining fresh submissions, or removed if none is left.
+        pub fn remove_dataprovider(&mut self, provider_id: NonFungibleId, pair: String) {
+            assert!(self.publishers.get_mut(&pair).map_or(false, |ids| ids.remove(&provider_id)),
+                "the dataprovider is not authorized for the pair!");
+            if let Some(submissions) = self.submissions.get_mut(&pair) {
+                submissions.remove(&provider_id);
+            }
+            self.aggregate_price(&pair, Runtime::current_epoch());
+        }
+
+        pub fn set_max_age(&mut self, max_age: u64) {
+            assert!( max_age > 0, "invalid max age value.");
+            self.max_age = max_age;
+        }
+
+        /// Submit a price for the pair. The stored price is the median of all fresh submissions,
+        /// i.e. the latest submission of each publisher that is not older than max_age epochs.
+        pub fn feed_price(&mut self, pair: String, price: String, provider: Proof) -> bool {
+            let provider_id = self.verify_dataprovider(&provider);
+            self.submit_price(&provider_id, pair, price);
+            true
+        }
+
+        /// Submit the prices of many pairs at once, pair => price.
+        /// The pairs are processed in order, the callbacks of each pair are triggered before the next pair.
+        pub fn feed_prices(&mut self, prices: BTreeMap<String, String>, provider: Proof) -> bool {
+            let provider_id = self.verify_dataprovider(&provider);
+            for (pair, price) in prices {
+                self.submit_price(&provider_id, pair, price);
+            }
             true
         }
 
         pub fn get_price(&self, pair:String) -> (Decimal, u64){
+            let price_data = self.get_price_data(pair);
+            (price_data.price, price_data.epoch_at)
+        }
+
+        /// The price together with the number of publishers and the spread it is aggregated from.
+        /// Panics if the price is older than max_age epochs.
+        pub fn get_price_data(&self, pair: String) -> PriceData {
             assert!(self.price_map.contains_key(&pair), "the pair not exists!");
-            *self.price_map.get(&pair).unwrap()
+            let price_data = *self.price_map.get(&pair).unwrap();
+            assert!(Runtime::current_epoch() - price_data.epoch_at <= self.max_age, "the price is stale!");
+            price_data
         }
 
         pub fn request_price(&mut self,  fee: Bucket,  pair: String, component: ComponentAddress, 
@@ -90,7 +203,7 @@ blueprint! {
             self.vault.put(fee);
 
             let callback_id = NonFungibleId::random();
-            let callback_data = CallbackData::new_instance(callback_id.clone(), component, method, pair, args!(arg));
+            let callback_data = CallbackData::new_instance(callback_id.clone(), component, method, pair.clone(), args!(arg));
 
             let callback = self.callback_minter.authorize(|| {
                 let rm = borrow_resource_manager!(self.callback_vaults.resource_address());
@@ -98,33 +211,289 @@ blueprint! {
             });
             // Store the Callback NFR inside this component
             self.callback_vaults.put(callback);
-            self.unfilful_vec.push(callback_id.clone());
+            self.unfilful_requests.entry(pair).or_insert(Vec::new()).push(callback_id.clone());
             callback_id
         }
 
+        /// Subscribe to the updates of a pair with a prepaid fee balance, every delivered callback costs the fee.
+        /// The callback is called with (price, epoch_at, sequence, args), where sequence counts the updates.
+        /// With a threshold of 0 every update is delivered, otherwise only updates that move the price more
+        /// than threshold percent from the last delivered price.
+        /// Returns the subscription badge, which is needed to cancel the subscription.
+        pub fn subscribe(&mut self, fee: Bucket, pair: String, component: ComponentAddress,
+            method: String, arg: String, threshold: Decimal) -> Bucket {
+            assert!(fee.amount() >= self.fee, "Fees are lower than required!");
+            assert!(threshold >= Decimal::zero(), "invalid threshold value.");
+            assert!(self.pair_subscriptions.get(&pair).map_or(0, |ids| ids.len()) < MAX_SUBSCRIPTIONS_PER_PAIR,
+                "too many subscriptions for the pair!");
+
+            let subscription_id = NonFungibleId::random();
+            let badge = self.callback_minter.authorize(|| {
+                let rm = borrow_resource_manager!(self.subscription_badge);
+                rm.mint_non_fungible(&subscription_id, SubscriptionData{ pair: pair.clone(), threshold })
+            });
+            self.subscriptions.insert(subscription_id.clone(), Subscription {
+                pair: pair.clone(),
+                component,
+                method,
+                args: args!(arg),
+                threshold,
+                balance: fee.amount(),
+                last_price: None,
+                sequence: 0,
+                notifications: Vec::new()
+            });
+            info!("subscribed {}", subscription_id);
+            self.pair_subscriptions.entry(pair).or_insert(Vec::new()).push(subscription_id);
+            self.subscription_vault.put(fee);
+            badge
+        }
+
+        /// Anyone can top up the fee balance of a subscription
+        pub fn top_up_subscription(&mut self, subscription_id: NonFungibleId, fee: Bucket) {
+            assert!(self.subscriptions.contains_key(&subscription_id), "the subscription not exists!");
+            self.subscriptions.get_mut(&subscription_id).unwrap().balance += fee.amount();
+            self.subscription_vault.put(fee);
+        }
+
+        /// Cancel the subscription, the remaining fee balance is refunded.
+        /// Notifications that were not delivered yet were not paid and are discarded.
+        pub fn cancel_subscription(&mut self, subscription: Bucket) -> Bucket {
+            assert!(subscription.resource_address() == self.subscription_badge && subscription.amount() == Decimal::ONE,
+                "invalid subscription badge!");
+            let subscription_id = subscription.non_fungible::<SubscriptionData>().id();
+            let removed = self.subscriptions.remove(&subscription_id).unwrap();
+            if let Some(ids) = self.pair_subscriptions.get_mut(&removed.pair) {
+                ids.retain(|id| *id != subscription_id);
+            }
+            self.callback_minter.authorize(|| subscription.burn());
+            self.subscription_vault.take(removed.balance)
+        }
+
+        pub fn get_subscription_balance(&self, subscription_id: NonFungibleId) -> Decimal {
+            assert!(self.subscriptions.contains_key(&subscription_id), "the subscription not exists!");
+            self.subscriptions[&subscription_id].balance
+        }
+
+        /// Call the subscriber with the notifications queued for the subscription, oldest first, every callback costs the fee.
+        /// Anyone can deliver, e.g. the data provider right after a feed or the subscriber itself. Every subscription is
+        /// delivered in its own call, so a failing subscriber only reverts its own delivery and keeps its notifications queued.
+        /// Returns the number of delivered notifications.
+        pub fn deliver_notifications(&mut self, subscription_id: NonFungibleId) -> u64 {
+            assert!(self.subscriptions.contains_key(&subscription_id), "the subscription not exists!");
+            let subscription = self.subscriptions.get_mut(&subscription_id).unwrap();
+            let notifications: Vec<Notification> = subscription.notifications.drain(..).collect();
+            for _ in notifications.iter() {
+                subscription.balance -= self.fee;
+                self.vault.put(self.subscription_vault.take(self.fee));
+            }
+
+            // the subscriber is called once the state is updated
+            let subscription = &self.subscriptions[&subscription_id];
+            for notification in notifications.iter() {
+                subscription.call(notification);
+            }
+            notifications.len() as u64
+        }
+
+        /// Stop queueing notifications for the subscription, e.g. to free a slot of the pair.
+        /// The queued notifications can still be delivered, the holder of the badge can cancel it for the remaining balance.
+        pub fn remove_subscription(&mut self, subscription_id: NonFungibleId) {
+            assert!(self.subscriptions.contains_key(&subscription_id), "the subscription not exists!");
+            let pair = &self.subscriptions[&subscription_id].pair;
+            if let Some(ids) = self.pair_subscriptions.get_mut(pair) {
+                ids.retain(|id| *id != subscription_id);
+            }
+        }
+
         pub fn withdraw_fee(&mut self, amount: Decimal) -> Bucket{
             assert!(self.vault.amount () < amount, "balance insufficient!");
 
             self.vault.take(amount)
         }
 
+        /// Returns the id of the DataProvider badge in the proof
+        fn verify_dataprovider(&self, provider: &Proof) -> NonFungibleId {
+            assert!(provider.resource_address() == self.dataprovider_badge && provider.amount() == Decimal::ONE,
+                "invalid dataprovider badge!");
+            provider.non_fungible::<DataProvider>().id()
+        }
+
+        /// Submit a price for the pair. The stored price is the median of all fresh submissions,
+        /// i.e. the latest submission of each publisher that is not older than max_age epochs.
+        fn submit_price(&mut self, provider_id: &NonFungibleId, pair: String, price: String) {
+            assert!(self.publishers.get(&pair).map_or(false, |ids| ids.contains(provider_id)),
+                "the dataprovider is not authorized for the pair!");
+
+            let epoch_at = Runtime::current_epoch();
+            let dec_price = Decimal::from(price);
+            assert!(dec_price > Decimal::zero(), "invalid price value.");
+
+            self.submissions.entry(pair.clone()).or_insert(HashMap::new())
+                .insert(provider_id.clone(), (dec_price, epoch_at));
+            let price_data = self.aggregate_price(&pair, epoch_at).unwrap();
+            self.filfull_request(&pair, price_data.price.to_string(), epoch_at);
+            self.notify_subscribers(&pair, price_data.price, epoch_at);
+        }
+
+        /// Store the median of the fresh submissions of the pair as its price, stale submissions are dropped.
+        /// The price of the pair is removed when no fresh submission is left.
+        fn aggregate_price(&mut self, pair: &String, current_epoch: u64) -> Option<PriceData> {
+            let max_age = self.max_age;
+            let submissions = self.submissions.entry(pair.clone()).or_insert(HashMap::new());
+            submissions.retain(|_, (_, submitted_at)| current_epoch - *submitted_at <= max_age);
+            if submissions.is_empty() {
+                self.price_map.remove(pair);
+                return None;
+            }
+
+            let mut prices: Vec<Decimal> = submissions.values().map(|(price, _)| *price).collect();
+            prices.sort();
+            let price_data = PriceData {
+                price: median(&prices),
+                epoch_at: submissions.values().map(|(_, submitted_at)| *submitted_at).max().unwrap(),
+                publishers: prices.len() as u32,
+                spread: prices[prices.len() - 1] - prices[0]
+            };
+            self.price_map.insert(pair.clone(), price_data);
+            Some(price_data)
+        }
+
+        /// Trigger the pending requests of the pair in request order, the fulfilled callbacks are burned
         fn filfull_request(&mut self, pair: &String, price: String, epoch_at: u64) {
-            let mut i = 0;
-            while i < self.unfilful_vec.len() {
-                if self.callback_vaults.non_fungible_ids().contains(&self.unfilful_vec[i]){
-                    let callback = self.callback_vaults.take_non_fungible(&self.unfilful_vec[i]);
+            if let Some(request_ids) = self.unfilful_requests.remove(pair) {
+                for request_id in request_ids {
+                    let callback = self.callback_vaults.take_non_fungible(&request_id);
                     let callback_data = callback.non_fungible::<CallbackData>().data();
-                    if callback_data.pair.eq(pair) {
-                        callback_data.call(&price, epoch_at);
-                        self.unfilful_vec.remove(i);
-                    }
-                    i += 1;
-                }                
+                    callback_data.call(&price, epoch_at);
+                    self.callback_minter.authorize(|| callback.burn());
+                }
+            }
+        }
+
+        /// Queue the update on the subscriptions of the pair in subscription order, delivered with deliver_notifications.
+        /// No subscriber code runs here, so a subscriber can't make a feed fail.
+        /// Subscriptions whose balance can't pay the fee of every queued notification are skipped until they are topped up.
+        fn notify_subscribers(&mut self, pair: &String, price: Decimal, epoch_at: u64) {
+            let subscription_ids = match self.pair_subscriptions.get(pair) {
+                Some(ids) => ids.clone(),
+                None => return
+            };
+            for subscription_id in subscription_ids {
+                let subscription = self.subscriptions.get_mut(&subscription_id).unwrap();
+                let queued = Decimal::from(subscription.notifications.len() as u64 + 1);
+                if subscription.balance < self.fee * queued || !subscription.is_triggered_by(price) {
+                    continue;
+                }
+                subscription.last_price = Some(price);
+                subscription.sequence += 1;
+                if subscription.notifications.len() == MAX_PENDING_NOTIFICATIONS {
+                    // not charged, the subscriber sees the gap in the sequence
+                    info!("subscription {} missed update {}, {} notifications are waiting for delivery",
+                        subscription_id, subscription.sequence, MAX_PENDING_NOTIFICATIONS);
+                    continue;
+                }
+                subscription.notifications.push(Notification { price, epoch_at, sequence: subscription.sequence });
             }
         }
     }
 }
 
+/// Median of the sorted prices
+fn median(sorted: &Vec<Decimal>) -> Decimal {
+    let mid = sorted.len() / 2;
+    if sorted.len() % 2 == 0 {
+        (sorted[mid - 1] + sorted[mid]) / dec!("2")
+    } else {
+        sorted[mid]
+    }
+}
+
+#[derive(scrypto::Encode, scrypto::Decode, scrypto::TypeId, scrypto::Describe, Clone, Copy)]
+pub struct PriceData {
+    /// median of the fresh submissions
+    pub price: Decimal,
+
+    /// epoch of the latest submission
+    pub epoch_at: u64,
+
+    /// number of publishers the price is aggregated from
+    pub publishers: u32,
+
+    /// difference between the highest and the lowest fresh submission
+    pub spread: Decimal,
+}
+
+#[derive(scrypto::Encode, scrypto::Decode, scrypto::TypeId, scrypto::Describe, Clone, Copy)]
+pub struct Notification {
+    pub price: Decimal,
+
+    pub epoch_at: u64,
+
+    /// number of the update, counted from 1 for each subscription
+    pub sequence: u64,
+}
+
+#[derive(NonFungibleData)]
+pub struct DataProvider {
+    pub name: String,
+}
+
+#[derive(NonFungibleData)]
+pub struct SubscriptionData {
+    pub pair: String,
+
+    /// minimum price move in percent to deliver an update, 0 to deliver every update
+    pub threshold: Decimal,
+}
+
+#[derive(scrypto::Encode, scrypto::Decode, scrypto::TypeId, scrypto::Describe)]
+pub struct Subscription {
+    pub pair: String,
+
+    /// The target component of the callback
+    pub component: ComponentAddress,
+
+    /// The target method of the callback
+    pub method: String,
+
+    /// The args that should be passed to the target method
+    pub args: Vec<Vec<u8>>,
+
+    /// minimum price move in percent to deliver an update, 0 to deliver every update
+    pub threshold: Decimal,
+
+    /// remaining prepaid fee balance
+    pub balance: Decimal,
+
+    /// the last queued price
+    pub last_price: Option<Decimal>,
+
+    /// number of updates, including the ones missed while the queue was full
+    pub sequence: u64,
+
+    /// queued updates that were not delivered yet, at most MAX_PENDING_NOTIFICATIONS
+    pub notifications: Vec<Notification>,
+}
+
+impl Subscription {
+
+    pub fn is_triggered_by(&self, price: Decimal) -> bool {
+        match self.last_price {
+            Some(last_price) if self.threshold > Decimal::zero() => {
+                let change = if price > last_price { price - last_price } else { last_price - price };
+                change * dec!("100") / last_price > self.threshold
+            }
+            _ => true
+        }
+    }
+
+    pub fn call(&self, notification: &Notification){
+        Runtime::call_method(self.component, &self.method,
+            args![notification.price.to_string(), notification.epoch_at, notification.sequence, self.args.to_vec()]);
+    }
+}
+
 #[derive(NonFungibleData)]
 pub struct CallbackData {
     /// request id
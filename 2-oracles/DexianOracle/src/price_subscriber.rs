@@ -0,0 +1,36 @@
+use scrypto::prelude::*;
+
+blueprint! {
+    /// An example subscriber, it keeps the delivered updates of its subscription
+    struct PriceSubscriber {
+        /// (price, epoch_at, sequence) of the delivered updates
+        updates: Vec<(String, u64, u64)>,
+        /// reject the deliveries, to show that a failing subscriber keeps its notifications queued
+        failing: bool
+    }
+
+    impl PriceSubscriber {
+        pub fn new() -> ComponentAddress {
+            Self {
+                updates: Vec::new(),
+                failing: false
+            }
+            .instantiate()
+            .globalize()
+        }
+
+        /// The callback of the subscription
+        pub fn on_price_update(&mut self, price: String, epoch_at: u64, sequence: u64, _args: Vec<Vec<u8>>) {
+            assert!(!self.failing, "the subscriber rejects the update!");
+            self.updates.push((price, epoch_at, sequence));
+        }
+
+        pub fn set_failing(&mut self, failing: bool) {
+            self.failing = failing;
+        }
+
+        pub fn get_updates(&self) -> Vec<(String, u64, u64)> {
+            self.updates.clone()
+        }
+    }
+}
@@ -30,3 +30,214 @@ fn test_hello() {
     println!("{:?}\n", receipt2);
     assert!(receipt2.result.is_ok());
 }
+
+#[test]
+fn test_subscriber_cannot_block_feeds() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (pk, sk, admin_account) = executor.new_account();
+    let (subscriber_pk, subscriber_sk, subscriber_account) = executor.new_account();
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let transaction1 = TransactionBuilder::new()
+        .call_function(package, "DeXianOracle", "new", args![dec!("20"), 10u64])
+        .call_method_with_all_resources(admin_account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt1 = executor.validate_and_execute(&transaction1).unwrap();
+    println!("{:?}\n", receipt1);
+    assert!(receipt1.result.is_ok());
+    let component = receipt1.new_component_addresses[0];
+    let admin_badge = receipt1.new_resource_addresses[0];
+    let dataprovider_badge = receipt1.new_resource_addresses[4];
+
+    // The admin registers itself as the data provider of XRD/USD
+    let transaction2 = TransactionBuilder::new()
+        .create_proof_from_account_by_amount(dec!("1"), admin_badge, admin_account)
+        .call_method(component, "register_dataprovider", args!["provider1".to_string(), vec!["XRD/USD".to_string()]])
+        .call_method_with_all_resources(admin_account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt2 = executor.validate_and_execute(&transaction2).unwrap();
+    assert!(receipt2.result.is_ok());
+
+    let transaction3 = TransactionBuilder::new()
+        .call_function(package, "PriceSubscriber", "new", args![])
+        .build(executor.get_nonce([subscriber_pk]))
+        .sign([&subscriber_sk]);
+    let receipt3 = executor.validate_and_execute(&transaction3).unwrap();
+    assert!(receipt3.result.is_ok());
+    let subscriber = receipt3.new_component_addresses[0];
+
+    // The subscriber prepays 100 XRD, i.e. 5 notifications
+    let transaction4 = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(dec!("100"), RADIX_TOKEN, subscriber_account)
+        .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+            builder.call_method(
+                component,
+                "subscribe",
+                args![
+                    scrypto::resource::Bucket(bucket_id),
+                    "XRD/USD".to_string(),
+                    subscriber,
+                    "on_price_update".to_string(),
+                    "xxx".to_string(),
+                    dec!("0")
+                ],
+            )
+        })
+        .call_method_with_all_resources(subscriber_account, "deposit_batch")
+        .build(executor.get_nonce([subscriber_pk]))
+        .sign([&subscriber_sk]);
+    let receipt4 = executor.validate_and_execute(&transaction4).unwrap();
+    println!("{:?}\n", receipt4);
+    assert!(receipt4.result.is_ok());
+    let log = &receipt4.logs[0].1;
+    let subscription_id = NonFungibleId::from_str(log.split(' ').last().unwrap()).unwrap();
+
+    let call = |executor: &mut TransactionExecutor<InMemorySubstateStore>, component: ComponentAddress, method: &str, args: Vec<Vec<u8>>| {
+        let transaction = TransactionBuilder::new()
+            .call_method(component, method, args)
+            .build(executor.get_nonce([subscriber_pk]))
+            .sign([&subscriber_sk]);
+        let receipt = executor.validate_and_execute(&transaction).unwrap();
+        println!("{:?}\n", receipt);
+        receipt
+    };
+    assert!(call(&mut executor, subscriber, "set_failing", args![true]).result.is_ok());
+
+    for price in ["0.0813", "0.0820"] {
+        // Feeding a pair with a failing subscriber runs no subscriber code
+        let feed = TransactionBuilder::new()
+            .create_proof_from_account_by_amount(dec!("1"), dataprovider_badge, admin_account)
+            .create_proof_from_auth_zone(dataprovider_badge, |builder, proof_id| {
+                builder.call_method(
+                    component,
+                    "feed_price",
+                    args!["XRD/USD".to_string(), price.to_string(), scrypto::resource::Proof(proof_id)],
+                )
+            })
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+        let feed_receipt = executor.validate_and_execute(&feed).unwrap();
+        println!("{:?}\n", feed_receipt);
+        assert!(feed_receipt.result.is_ok());
+
+        // The failing delivery only reverts itself, nothing is charged
+        let deliver_receipt = call(&mut executor, component, "deliver_notifications", args![subscription_id.clone()]);
+        assert!(deliver_receipt.result.is_err());
+        let balance_receipt = call(&mut executor, component, "get_subscription_balance", args![subscription_id.clone()]);
+        assert!(balance_receipt.outputs[0].to_string().contains("100"));
+    }
+
+    // Both updates stayed queued and are delivered in order once the subscriber accepts them
+    assert!(call(&mut executor, subscriber, "set_failing", args![false]).result.is_ok());
+    let deliver_receipt = call(&mut executor, component, "deliver_notifications", args![subscription_id.clone()]);
+    assert!(deliver_receipt.result.is_ok());
+    assert!(deliver_receipt.outputs[0].to_string().contains("2u64"));
+    let updates = call(&mut executor, subscriber, "get_updates", args![]).outputs[0].to_string();
+    let first = updates.find("\"0.0813\"").unwrap();
+    let second = updates.find("\"0.082\"").unwrap();
+    assert!(first < second);
+    let balance_receipt = call(&mut executor, component, "get_subscription_balance", args![subscription_id.clone()]);
+    assert!(balance_receipt.outputs[0].to_string().contains("60"));
+
+    // Nothing is left to deliver
+    let deliver_receipt = call(&mut executor, component, "deliver_notifications", args![subscription_id]);
+    assert!(deliver_receipt.outputs[0].to_string().contains("0u64"));
+}
+
+#[test]
+fn test_remove_dataprovider_recomputes_price() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (pk, sk, admin_account) = executor.new_account();
+    let (provider_pk, provider_sk, provider_account) = executor.new_account();
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let transaction1 = TransactionBuilder::new()
+        .call_function(package, "DeXianOracle", "new", args![dec!("20"), 10u64])
+        .call_method_with_all_resources(admin_account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt1 = executor.validate_and_execute(&transaction1).unwrap();
+    assert!(receipt1.result.is_ok());
+    let component = receipt1.new_component_addresses[0];
+    let admin_badge = receipt1.new_resource_addresses[0];
+    let dataprovider_badge = receipt1.new_resource_addresses[4];
+
+    // Two data providers of XRD/USD, the admin holds the badge of the first one
+    let mut provider_ids: Vec<NonFungibleId> = Vec::new();
+    for (name, account) in [("provider1", admin_account), ("provider2", provider_account)] {
+        let register = TransactionBuilder::new()
+            .create_proof_from_account_by_amount(dec!("1"), admin_badge, admin_account)
+            .call_method(component, "register_dataprovider", args![name.to_string(), vec!["XRD/USD".to_string()]])
+            .call_method_with_all_resources(account, "deposit_batch")
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+        let register_receipt = executor.validate_and_execute(&register).unwrap();
+        assert!(register_receipt.result.is_ok());
+        let log = &register_receipt.logs[0].1;
+        provider_ids.push(NonFungibleId::from_str(log.split(' ').last().unwrap()).unwrap());
+    }
+
+    let feed = |executor: &mut TransactionExecutor<InMemorySubstateStore>, provider_id: &NonFungibleId, price: &str| {
+        let (account, signer_pk, signer_sk) = if provider_id == &provider_ids[0] {
+            (admin_account, pk, &sk)
+        } else {
+            (provider_account, provider_pk, &provider_sk)
+        };
+        let mut ids = BTreeSet::new();
+        ids.insert(provider_id.clone());
+        let transaction = TransactionBuilder::new()
+            .create_proof_from_account_by_ids(&ids, dataprovider_badge, account)
+            .create_proof_from_auth_zone(dataprovider_badge, |builder, proof_id| {
+                builder.call_method(
+                    component,
+                    "feed_price",
+                    args!["XRD/USD".to_string(), price.to_string(), scrypto::resource::Proof(proof_id)],
+                )
+            })
+            .build(executor.get_nonce([signer_pk]))
+            .sign([signer_sk]);
+        let receipt = executor.validate_and_execute(&transaction).unwrap();
+        println!("{:?}\n", receipt);
+        assert!(receipt.result.is_ok());
+    };
+    feed(&mut executor, &provider_ids[0], "0.0800");
+    feed(&mut executor, &provider_ids[1], "0.0900");
+
+    let get_price_data = |executor: &mut TransactionExecutor<InMemorySubstateStore>| {
+        let transaction = TransactionBuilder::new()
+            .call_method(component, "get_price_data", args!["XRD/USD".to_string()])
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+        executor.validate_and_execute(&transaction).unwrap()
+    };
+    let price_receipt = get_price_data(&mut executor);
+    assert!(price_receipt.result.is_ok());
+    assert!(price_receipt.outputs[0].to_string().contains("0.085"));
+
+    let remove = |executor: &mut TransactionExecutor<InMemorySubstateStore>, provider_id: &NonFungibleId| {
+        let transaction = TransactionBuilder::new()
+            .create_proof_from_account_by_amount(dec!("1"), admin_badge, admin_account)
+            .call_method(component, "remove_dataprovider", args![provider_id.clone(), "XRD/USD".to_string()])
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+        let receipt = executor.validate_and_execute(&transaction).unwrap();
+        println!("{:?}\n", receipt);
+        assert!(receipt.result.is_ok());
+    };
+
+    // Without the second provider the price is the submission of the first one alone
+    remove(&mut executor, &provider_ids[1]);
+    let price_receipt = get_price_data(&mut executor);
+    assert!(price_receipt.result.is_ok());
+    let price_data = price_receipt.outputs[0].to_string();
+    assert!(price_data.contains("0.08") && !price_data.contains("0.085"));
+    assert!(price_data.contains("1u32"));
+
+    // Without any provider there is no price left
+    remove(&mut executor, &provider_ids[0]);
+    assert!(get_price_data(&mut executor).result.is_err());
+}
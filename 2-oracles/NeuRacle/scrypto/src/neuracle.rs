@@ -10,6 +10,7 @@
 //! This mean beside validating transaction, Radix validator can also participate on data feeding and create more utility for Radix token.
 
 use scrypto::prelude::*;
+use std::str::FromStr;
 use crate::neura_stable_coin::NStableCoin;
 use crate::validator::Validator;
 use crate::utilities::*;
@@ -60,16 +61,22 @@ blueprint! {
         unstake_delay: u64, 
         /// After having a good decentralization and security, Admin can advance stage to allow anyone can become validator. Initial stage is stage 1.
         stage: u8, 
-        /// The frequent of data feeding. Current Scrypto version only allow using epoch time unit, so the lowest is 1 epoch, estimated 1 hour per data update.
-        round_length: u64, 
+        /// The length of the commit phase, validators commit their data hash in this phase. Current Scrypto version only allow using epoch time unit, so the lowest is 1 epoch.
+        commit_length: u64, 
+        /// The length of the reveal phase, validators reveal their data in this phase. The frequent of data feeding is commit length + reveal length, the lowest is 2 epoch.
+        reveal_length: u64, 
         /// The inflation percent each round. If reward_rate is 0.0015% and 1 round = 1 epoch ~ 1 hour, that's about 13.14% APY.
         reward_rate: Decimal, 
         /// Untruthful validator behavior will be punished * times per reward rate. Eg: punishment = 5, reward rate = 0.0015 > punish 0.0075% per round.
         punishment: Decimal, 
+        /// Numeric datas within this percent of the stake-weighted median are deemed truthful. Eg: deviation band = 1%, median = 100 > accept 99 to 101.
+        deviation_band: Decimal, 
         /// NeuRacle system time, caculated by current epoch / round length.
         system_time: u64, 
         /// Keep track of the round status.
         round_start: bool, 
+        /// Epoch when the reveal phase of current round end.
+        reveal_end: u64, 
         /// Keep track of the active validators per round.
         active_validators: HashMap<ComponentAddress, Decimal>, 
         /// The badge to mint new NeuRacle ecosystem controller badge when new L2 project created.
@@ -85,14 +92,17 @@ blueprint! {
             mint_controller_badge: Bucket, 
             controller_badge: ResourceAddress, 
             validator_cap: usize, 
-            round_length: u64, 
+            commit_length: u64, 
+            reveal_length: u64, 
             pay_rate: Decimal, 
             fee_stablecoin: Decimal, 
             unstake_delay: u64, 
             reward_rate: Decimal, 
             punishment: Decimal) -> ComponentAddress {
 
-            let system_time = Runtime::current_epoch() / round_length;
+            assert_phase_length(commit_length, reveal_length);
+
+            let system_time = Runtime::current_epoch() / (commit_length + reveal_length);
 
             assert_fee(fee_stablecoin);
 
@@ -125,6 +135,7 @@ blueprint! {
                 .method("set_unstake_delay", rule!(require(admin_badge)))
                 .method("new_stable_coin_project", rule!(require(admin_badge)))
                 .method("set_round_length", rule!(require(admin_badge)))
+                .method("set_deviation_band", rule!(require(admin_badge)))
                 .method("new_api", rule!(require(controller_badge)))
                 .default(rule!(allow_all));
 
@@ -142,11 +153,14 @@ blueprint! {
                 fee_stablecoin: fee_stablecoin,
                 unstake_delay: unstake_delay,
                 stage: 1,
-                round_length: round_length,
+                commit_length: commit_length,
+                reveal_length: reveal_length,
                 reward_rate: reward_rate / dec!("100"),
                 punishment: punishment,
+                deviation_band: dec!("1") / dec!("100"),
                 system_time: system_time,
                 round_start: false,
+                reveal_end: 0,
                 active_validators: HashMap::new(),
                 mint_controller_badge: Vault::with_bucket(mint_controller_badge)
                 }
@@ -242,7 +256,7 @@ blueprint! {
 
             let current = Runtime::current_epoch();
 
-            let end = current + length.to_string().parse::<u64>().unwrap() * self.round_length;
+            let end = current + length.to_string().parse::<u64>().unwrap() * self.round_length();
 
             let badge = self.controller_badge.authorize(|| {
                 borrow_resource_manager!(self.user_badge)
@@ -279,7 +293,7 @@ blueprint! {
 
             let current = Runtime::current_epoch();
 
-            let end = current + length.to_string().parse::<u64>().unwrap() * self.round_length;
+            let end = current + length.to_string().parse::<u64>().unwrap() * self.round_length();
 
             let mut data: UserData = identity.non_fungible().data();
 
@@ -310,7 +324,8 @@ blueprint! {
             return (identity, my_data)
         }
 
-        /// This method will check the staking weight of each validator, set their round start status (so they're able to update data), and reset their active status.
+        /// This method will check the staking weight of each validator, set their round start status (so they're able to commit, then reveal data), and reset their active status.
+        /// The commit phase last commit length from now, the reveal phase last reveal length after that.
         /// On stage 1, this method will also eliminate all validators that aren't on top 100 staking weight.
         /// The method can only be called after 1 "round length" of last round end time.
         /// The person who call new round will be rewarded a payrate amount.
@@ -324,10 +339,13 @@ blueprint! {
             let current = Runtime::current_epoch();
 
             assert!(
-                current/self.round_length >= self.system_time,
+                current/self.round_length() >= self.system_time,
                 "Not time to start a new round yet!"
             );
 
+            let commit_end = current + self.commit_length;
+            self.reveal_end = commit_end + self.reveal_length;
+
             self.controller_badge.authorize(|| {
 
                 self.validators.iter_mut().for_each(|(validator_address, weight)| {
@@ -336,12 +354,12 @@ blueprint! {
 
                     *weight = validator.get_current_staked_value();
                 
-                    validator.round_start();
+                    validator.round_start(self.system_time, commit_end, self.reveal_end);
 
                 });
             });
 
-            info!("Start voting round number {} of NeuRacle", self.system_time);
+            info!("Start voting round number {} of NeuRacle, commit phase end at epoch {}, reveal phase end at epoch {}", self.system_time, commit_end, self.reveal_end);
 
             let reward = self.controller_badge.authorize(|| {
                 borrow_resource_manager!(self.neura).mint(self.pay_rate)
@@ -372,9 +390,15 @@ blueprint! {
             return reward
         }
 
-        /// This method will check on the active status of validators and can only advance if >2/3 validator is active.
-        /// After that, this method will get the datas with the most weight, check if it > 2/3 vote weight,
-        /// feed that on NeuRacle, reward the validators provided same datas and punish those didn't.
+        /// This method can only be called after the reveal phase, validators that committed but didn't reveal their data will get the full punishment.
+        /// It will check on the active (revealed) status of validators and can only advance if >2/3 validator is active.
+        /// After that, each api data is aggregated separately:
+        /// - Numeric datas (most of the submitted weight is a number) are aggregated by stake-weighted median of the numeric submissions,
+        /// validators within the deviation band of the median are deemed truthful, non-numeric submissions are as far as can be.
+        /// - Other datas are aggregated by the value with the most weight, only validators provided that value are deemed truthful.
+        /// The data is fed on NeuRacle if the truthful validators have >2/3 vote weight.
+        /// Validators are then rewarded or punished by their average distance from the fed datas, counted in deviation bands (capped at 2):
+        /// distance 0 get full reward rate, distance 1 get half reward rate, distance above 1 get punished up to the full punishment at distance 2.
         /// The person who end a round will be rewarded 2 times payrate (Assuming call this method will more costly on xrd fee than start a round)
         pub fn end_round(&mut self) -> Bucket {
             
@@ -382,8 +406,14 @@ blueprint! {
                 self.round_start == true,
                 "New round hasn't started yet!"
             );
+
+            assert!(
+                Runtime::current_epoch() >= self.reveal_end,
+                "The reveal phase hasn't ended yet!"
+            );
         
             let mut val: HashMap<ComponentAddress, Decimal> = HashMap::new();
+            let mut unrevealed: Vec<ComponentAddress> = Vec::new();
 
             self.active_validators.iter().for_each(|(&address, &weight)| {
     
@@ -394,6 +424,19 @@ blueprint! {
                     val.insert(address, weight);
                     
                 }
+                else if validator.get_commit_status() {
+
+                    unrevealed.push(address);
+
+                }
+            });
+
+            self.controller_badge.authorize(|| {
+                unrevealed.iter().for_each(|&address| {
+
+                    let validator: Validator = address.into();
+                    validator.burn(self.reward_rate * self.punishment)
+                })
             });
             
             assert!(
@@ -403,37 +446,95 @@ blueprint! {
 
             self.active_validators = val;
 
-            let mut all_datas: HashMap<BTreeMap<String, String>, Decimal> = HashMap::new();
+            let mut submissions: Vec<(ComponentAddress, Decimal, BTreeMap<String, String>)> = Vec::new();
             let mut total_weight = Decimal::zero();
 
             self.active_validators.iter().for_each(|(&address, &weight)| {
 
                 let validator: Validator = address.into();
-                let datas = validator.get_data();
-                if all_datas.contains_key(&datas) {
-                    if let Some(x) = all_datas.get_mut(&datas) {
-                        *x += weight;
-                    }
-                }
-                else {
-                    all_datas.insert(datas.clone(), weight);
-                }
+                submissions.push((address, weight, validator.get_data()));
                 total_weight += weight
             });
 
-            let result = all_datas.iter().max_by_key(|entry | entry.1).unwrap();
-            let data = result.0.clone();
-            let weight = result.1.clone();
+            let mut distances: HashMap<ComponentAddress, Decimal> = HashMap::new();
+            let mut validated: u64 = 0;
+            let apis: Vec<String> = self.datas.keys().cloned().collect();
+
+            for api in apis {
+
+                let submitted: Vec<(ComponentAddress, Decimal, String)> = submissions.iter()
+                    .filter_map(|(address, weight, datas)| datas.get(&api).map(|data| (*address, *weight, data.clone())))
+                    .collect();
+
+                if submitted.is_empty() {
+                    continue
+                }
+
+                let numbers: Vec<Option<Decimal>> = submitted.iter().map(|(_, _, data)| Decimal::from_str(data).ok()).collect();
+
+                let numeric_weight = numbers.iter().zip(submitted.iter())
+                    .filter(|(number, _)| number.is_some())
+                    .fold(Decimal::zero(), |total, (_, (_, weight, _))| total + *weight);
+
+                let submitted_weight = submitted.iter().fold(Decimal::zero(), |total, (_, weight, _)| total + *weight);
+
+                // The data is numeric when most of the submitted weight is numeric, non-numeric submissions are then as far as can be.
+                let (data, api_distances): (String, HashMap<ComponentAddress, Decimal>) = if numeric_weight * dec!("2") > submitted_weight {
+
+                    let values: Vec<(Decimal, Decimal)> = numbers.iter().zip(submitted.iter())
+                        .filter_map(|(number, (_, weight, _))| number.map(|number| (number, *weight)))
+                        .collect();
+                    let median = weighted_median(values);
 
-            if weight*dec!("3") >= total_weight*dec!("2") {
-                self.datas = data;
+                    (
+                        median.to_string(), 
+                        numbers.iter().zip(submitted.iter()).map(|(number, (address, _, _))| (*address, number.map_or(dec!("2"), |number| band_distance(number, median, self.deviation_band)))).collect()
+                    )
+                }
+                else {
+
+                    let mut all_datas: HashMap<String, Decimal> = HashMap::new();
+
+                    submitted.iter().for_each(|(_, weight, data)| {
+                        *all_datas.entry(data.clone()).or_insert(Decimal::zero()) += *weight
+                    });
+
+                    let data = all_datas.iter().max_by_key(|entry| entry.1).unwrap().0.clone();
+
+                    (
+                        data.clone(), 
+                        submitted.iter().map(|(address, _, submitted_data)| (*address, if *submitted_data == data {Decimal::zero()} else {dec!("2")})).collect()
+                    )
+                };
+
+                let mut truthful_weight = Decimal::zero();
+
+                submitted.iter().for_each(|(address, weight, _)| {
+                    if api_distances[address] <= dec!("1") {truthful_weight += *weight}
+                });
+
+                if truthful_weight*dec!("3") >= total_weight*dec!("2") {
+
+                    self.datas.insert(api, data);
+                    validated += 1;
+
+                    // Validators that didn't provide this data are as far as can be.
+                    self.active_validators.keys().for_each(|address| {
+                        *distances.entry(*address).or_insert(Decimal::zero()) += *api_distances.get(address).unwrap_or(&dec!("2"))
+                    });
+                }
+            }
+
+            if validated > 0 {
                 self.controller_badge.authorize(|| {
-                    self.active_validators.iter().for_each(|(&address, _weight)| {
-    
+                    distances.iter().for_each(|(&address, &distance)| {
+
                         let validator: Validator = address.into();
-                        if validator.get_data() == self.datas.clone() {validator.mint(self.reward_rate)}
-                            else {validator.burn(self.reward_rate * self.punishment)}
-                    })  
+                        let distance = distance / Decimal::from(validated);
+
+                        if distance <= dec!("1") {validator.mint(self.reward_rate * (dec!("1") - distance / dec!("2")))}
+                            else {validator.burn(self.reward_rate * self.punishment * (distance - dec!("1")))}
+                    })
                 })
             }
 
@@ -441,7 +542,7 @@ blueprint! {
 
             let current = Runtime::current_epoch();
 
-            self.system_time = current/self.round_length + 1;
+            self.system_time = current/self.round_length() + 1;
 
             self.round_start = false;
 
@@ -515,8 +616,24 @@ blueprint! {
             self.unstake_delay = new_unstake_delay
         }
 
-        pub fn set_round_length(&mut self, new_round_length:u64) {
-            self.round_length = new_round_length
+        /// Set the commit phase and reveal phase length, the round length will be the sum of both.
+        pub fn set_round_length(&mut self, new_commit_length: u64, new_reveal_length: u64) {
+            assert_phase_length(new_commit_length, new_reveal_length);
+            self.commit_length = new_commit_length;
+            self.reveal_length = new_reveal_length
+        }
+
+        /// Set the deviation band in percent, Eg: 0.5 > numeric datas within 0.5% of the median are deemed truthful.
+        pub fn set_deviation_band(&mut self, new_deviation_band: Decimal) {
+            assert!(
+                (new_deviation_band > Decimal::zero()) && (new_deviation_band <= dec!("100")),
+                "Deviation band must be in the range of 0 to 100"
+            );
+            self.deviation_band = new_deviation_band / dec!("100")
+        }
+
+        fn round_length(&self) -> u64 {
+            self.commit_length + self.reveal_length
         }
     }
 }
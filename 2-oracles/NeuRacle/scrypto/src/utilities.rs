@@ -19,4 +19,77 @@ pub fn assert_fee(fee: Decimal) {
         (fee >= Decimal::zero()) && (fee <= dec!("100")),
         "Fee must be in the range of 0 to 100"
     );
-}
\ No newline at end of file
+}
+pub fn assert_phase_length(commit_length: u64, reveal_length: u64) {
+    assert!(
+        (commit_length > 0) && (reveal_length > 0),
+        "Commit and reveal phase must last at least 1 epoch"
+    );
+}
+
+/// The commitment a validator submit in the commit phase: the hex sha256 of the validator address, the round number,
+/// every key and value of the datas (in key order) and the salt, each field written as "<byte length>:<field>".
+/// Binding the validator and round stop a validator from copying and replaying another validator's commitment,
+/// the length prefixes make the preimage unambiguous whatever the keys and values contain.
+/// Eg: validator = "02ab", round = 7, datas = {"a": "1", "b": "2"}, salt = "xyz" > sha256("4:02ab1:71:a1:11:b1:23:xyz").
+pub fn hash_datas(validator: &str, round: u64, datas: &BTreeMap<String, String>, salt: &str) -> String {
+
+    let mut preimage = String::new();
+
+    let mut push_field = |field: &str| preimage.push_str(&format!("{}:{}", field.len(), field));
+
+    push_field(validator);
+
+    push_field(&round.to_string());
+
+    datas.iter().for_each(|(key, value)| {
+        push_field(key);
+        push_field(value)
+    });
+
+    push_field(salt);
+
+    sha256(preimage).to_string()
+}
+
+/// The stake-weighted median of (value, weight) pairs, that is the lowest value at which
+/// the cumulative weight reaches half of the total weight.
+pub fn weighted_median(mut values: Vec<(Decimal, Decimal)>) -> Decimal {
+
+    values.sort_by_key(|a| a.0);
+
+    let total_weight = values.iter().fold(Decimal::zero(), |total, (_value, weight)| total + *weight);
+
+    let mut cumulative_weight = Decimal::zero();
+
+    for (value, weight) in values.iter() {
+
+        cumulative_weight += *weight;
+
+        if cumulative_weight * dec!("2") >= total_weight {
+            return *value
+        }
+    }
+
+    values.last().unwrap().0
+}
+
+/// The distance of a submitted value from the median, counted in deviation bands and capped at 2.
+/// Eg: deviation band = 1%, median = 100, value = 100.5 > distance 0.5; value = 97 > distance 2.
+pub fn band_distance(value: Decimal, median: Decimal, deviation_band: Decimal) -> Decimal {
+
+    let difference = if value > median { value - median } else { median - value };
+
+    if difference == Decimal::zero() {
+        return Decimal::zero()
+    }
+
+    let allowed = if median > Decimal::zero() { median * deviation_band } else { Decimal::zero() - median * deviation_band };
+
+    if allowed == Decimal::zero() || difference >= allowed * dec!("2") {
+        dec!("2")
+    }
+    else {
+        difference / allowed
+    }
+}
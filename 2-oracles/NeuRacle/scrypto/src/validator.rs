@@ -40,8 +40,18 @@ blueprint! {
         unstake_delay: u64,
         /// Store new datas on-chain
         datas: BTreeMap<String, String>,
+        /// Hash of the datas committed in the commit phase
+        commitment: String,
+        /// NeuRacle round number of the current round, part of the commitment
+        round: u64,
+        /// Epoch when the commit phase end and the reveal phase start
+        commit_end: u64,
+        /// Epoch when the reveal phase end
+        reveal_end: u64,
         /// Keep track of round status
         round_start: bool,
+        /// Keep track of validator commit status
+        committed: bool,
         /// Keep track of validator status
         active: bool
 
@@ -68,7 +78,8 @@ blueprint! {
             let rules = AccessRules::new()
                 .method("change_fee", rule!(require(badge)))
                 .method("withdraw_fee", rule!(require(badge)))
-                .method("update_data", rule!(require(badge)))
+                .method("commit_data", rule!(require(badge)))
+                .method("reveal_data", rule!(require(badge)))
                 .method("round_start", rule!(require(neura_controller_badge)))
                 .method("get_datas", rule!(require(neura_controller_badge)))
                 .method("mint", rule!(require(neura_controller_badge)))
@@ -87,7 +98,12 @@ blueprint! {
                 medium_token: medium_token,
                 unstake_delay: unstake_delay,
                 datas: BTreeMap::new(),
+                commitment: String::default(),
+                round: 0,
+                commit_end: 0,
+                reveal_end: 0,
                 round_start: false,
+                committed: false,
                 active: false
                 }
                 .instantiate()
@@ -289,12 +305,46 @@ blueprint! {
             
         }
 
-        /// Validator can only update data on round start.
-        pub fn update_data(&mut self, datas: BTreeMap<String, String>) {
+        /// Validator can only commit data hash on the commit phase, the hash is made by [hash_datas] with this validator address,
+        /// the round number (see get_round) and a secret salt.
+        /// Committing again in the commit phase will replace the last commitment.
+        pub fn commit_data(&mut self, commitment: String) {
 
             assert!(
                 self.round_start == true,
-                "The round haven't started, you can't update data yet"
+                "The round haven't started, you can't commit data yet"
+            );
+
+            assert!(
+                Runtime::current_epoch() < self.commit_end,
+                "The commit phase has ended, you can't commit data anymore"
+            );
+
+            self.commitment = commitment;
+            self.committed = true
+
+        }
+
+        /// Validator can only reveal data on the reveal phase, the datas and salt must match the commitment.
+        pub fn reveal_data(&mut self, datas: BTreeMap<String, String>, salt: String) {
+
+            assert!(
+                self.round_start == true && self.committed == true,
+                "You haven't committed data on this round, you can't reveal data"
+            );
+
+            let current = Runtime::current_epoch();
+
+            assert!(
+                (current >= self.commit_end) && (current < self.reveal_end),
+                "Not on the reveal phase, you can't reveal data now"
+            );
+
+            let validator = Runtime::actor().component_address().unwrap().to_string();
+
+            assert!(
+                hash_datas(&validator, self.round, &datas, &salt) == self.commitment,
+                "The datas doesn't match your commitment"
             );
             
             self.datas = datas;
@@ -303,8 +353,13 @@ blueprint! {
 
         }
 
-        pub fn round_start(&mut self) {
+        pub fn round_start(&mut self, round: u64, commit_end: u64, reveal_end: u64) {
             self.active = false;
+            self.committed = false;
+            self.commitment = String::default();
+            self.round = round;
+            self.commit_end = commit_end;
+            self.reveal_end = reveal_end;
             self.round_start = true
         }
 
@@ -312,6 +367,14 @@ blueprint! {
             self.active
         }
 
+        pub fn get_commit_status(&self) -> bool {
+            self.committed
+        }
+
+        pub fn get_round(&self) -> u64 {
+            self.round
+        }
+
         pub fn get_data(&self) -> BTreeMap<String, String> {
             self.datas.clone()
         }
@@ -0,0 +1,62 @@
+#!/usr/bin/env bash
+
+# Helpers for the commit-reveal data feeding of validators, $1 is the validator prefix (VAL1,...,VAL5)
+
+# Use the validator account and component for the next transaction manifest
+use_validator () {
+    local acc=${1}_ACC piv=${1}_PIV address=${1}_ADDRESS
+    resim set-default-account ${!acc} ${!piv}
+    export VALUP_ACC=${!acc}
+    export VALUP_ADDRESS=${!address}
+}
+
+# A field of the commitment preimage: "<byte length>:<field>"
+hash_field () {
+    local LC_ALL=C
+    printf '%s:%s' "${#1}" "$1"
+}
+
+# The round number of the validator in $VALUP_ADDRESS
+validator_round () {
+    resim call-method $VALUP_ADDRESS get_round | grep -o '[0-9]*u64' | tail -1 | sed 's/u64//'
+}
+
+# Same as hash_datas on-chain: sha256 of the validator address, the round, every key and value of $RESULTS (in key order) and $SALT
+datas_hash () {
+    eval "set -- ${RESULTS//,/ }"
+    {
+        hash_field "$VALUP_ADDRESS"
+        hash_field "`validator_round`"
+        while [ $# -gt 1 ]; do
+            printf '%s\t%s\n' "$1" "$2"
+            shift 2
+        done | LC_ALL=C sort -t $'\t' -k1,1 | while IFS=$'\t' read -r key value; do
+            hash_field "$key"
+            hash_field "$value"
+        done
+        hash_field "$SALT"
+    } | sha256sum | cut -d' ' -f1
+}
+
+# Commit the hash of $RESULTS with a random salt, the datas and salt are kept for the reveal phase
+commit_datas () {
+    use_validator $1
+    export SALT=$RANDOM$RANDOM$RANDOM
+    export COMMITMENT=`datas_hash`
+    export ${1}_COMMITMENT=$COMMITMENT
+    export ${1}_RESULTS="$RESULTS"
+    export ${1}_SALT=$SALT
+    resim run ./transaction_manifest/commit_data
+}
+
+# Reveal the datas and salt committed on the commit phase
+reveal_datas () {
+    use_validator $1
+    local results=${1}_RESULTS salt=${1}_SALT
+    export RESULTS="${!results}"
+    export SALT=${!salt}
+    resim run ./transaction_manifest/reveal_data
+}
+
+# Datas of a validator intervening in the Gateway process
+export MALICIOUS_RESULTS='"https://api.bitfinex.com/v2/ticker/tXRDUSD", "111", "https://api.coingecko.com/api/v3/simple/price?ids=bitcoin&vs_currencies=usd", "222", "https://api.coingecko.com/api/v3/simple/price?ids=radix&vs_currencies=usd", "333", "https://www.timeapi.io/api/Time/current/zone?timeZone=Asia/Ho_Chi_Minh", "4444", "https://www.timeapi.io/api/Time/current/zone?timeZone=Europe/London", "555"'
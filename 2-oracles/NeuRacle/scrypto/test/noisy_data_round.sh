@@ -0,0 +1,133 @@
+#!/usr/bin/env bash
+
+#set -x
+set -e
+
+source ./log.sh
+source ./commit_reveal.sh
+
+# Datas with the prices given in $1 $2 $3 (XRD on bitfinex, BTC and XRD on coingecko)
+noisy_results () {
+    echo "\"https://api.bitfinex.com/v2/ticker/tXRDUSD\", \"$1\", \"https://api.coingecko.com/api/v3/simple/price?ids=bitcoin&vs_currencies=usd\", \"$2\", \"https://api.coingecko.com/api/v3/simple/price?ids=radix&vs_currencies=usd\", \"$3\", \"https://www.timeapi.io/api/Time/current/zone?timeZone=Asia/Ho_Chi_Minh\", \"2022-05-20T10:00:00\", \"https://www.timeapi.io/api/Time/current/zone?timeZone=Europe/London\", \"2022-05-20T04:00:00\""
+}
+
+logc "A person start a round"
+
+resim run ./transaction_manifest/start_round
+
+logc "Validators commit prices that differ by a few decimals, numeric datas are aggregated by stake-weighted median within a 1% deviation band"
+
+export RESULTS=`noisy_results 0.1201 29500.5 0.1203`
+commit_datas VAL1
+
+export RESULTS=`noisy_results 0.1202 29501 0.1202`
+commit_datas VAL2
+
+export RESULTS=`noisy_results 0.1200 29499 0.1204`
+commit_datas VAL3
+
+logy "Let one validator commit prices further from the others but still within the deviation band and one validator commit prices far from the median"
+
+export RESULTS=`noisy_results 0.1211 29700 0.1212`
+commit_datas VAL4
+
+export RESULTS=`noisy_results 0.2 40000 0.2`
+commit_datas VAL5
+
+logc "Advance epoch by 1 to the reveal phase, validators reveal their datas"
+epoch=$(($epoch + 1))
+resim set-current-epoch $epoch
+
+reveal_datas VAL1
+
+reveal_datas VAL2
+
+reveal_datas VAL3
+
+reveal_datas VAL4
+
+reveal_datas VAL5
+
+logc "Advance epoch by 1 to end the reveal phase"
+epoch=$(($epoch + 1))
+resim set-current-epoch $epoch
+
+logc "A person begin conclude the round, this should feed the stake-weighted medians of the prices instead of failing on differing decimals"
+
+resim run ./transaction_manifest/end_round
+
+logc "Check user's staked amount, this should show user2 (validator 1) and user4 (validator 3) got reward, user5 (validator 4) got a smaller reward and user3 (validator 5) lose the full punishment"
+resim set-default-account $USER2_ACC $USER2_PIV
+resim call-method $VAL1_ADDRESS show_my_stake_amount 1,$STAKER_VAL1_BADGE
+
+resim set-default-account $USER4_ACC $USER4_PIV
+resim call-method $VAL3_ADDRESS show_my_stake_amount 1,$STAKER_VAL3_BADGE
+
+resim set-default-account $USER5_ACC $USER5_PIV
+resim call-method $VAL4_ADDRESS show_my_stake_amount 1,$STAKER_VAL4_BADGE
+
+resim set-default-account $USER3_ACC $USER3_PIV
+resim call-method $VAL5_ADDRESS show_my_stake_amount 1,$STAKER_VAL5_BADGE
+
+logc "Advance epoch by 2."
+epoch=$(($epoch + 2))
+resim set-current-epoch $epoch
+
+logc "A person start another round"
+
+resim run ./transaction_manifest/start_round
+
+logy "Let one validator commit a non-numeric XRD price, the others commit prices that differ by a few decimals"
+
+export RESULTS=`noisy_results 0.1201 29500.5 0.1203`
+commit_datas VAL1
+
+export RESULTS=`noisy_results 0.1202 29501 0.1202`
+commit_datas VAL2
+
+export RESULTS=`noisy_results 0.1200 29499 0.1204`
+commit_datas VAL3
+
+export RESULTS=`noisy_results 0.1203 29500 0.1201`
+commit_datas VAL4
+
+export RESULTS=`noisy_results not-a-price 29500 not-a-price`
+commit_datas VAL5
+
+logc "Advance epoch by 1 to the reveal phase, validators reveal their datas"
+epoch=$(($epoch + 1))
+resim set-current-epoch $epoch
+
+reveal_datas VAL1
+
+reveal_datas VAL2
+
+reveal_datas VAL3
+
+reveal_datas VAL4
+
+reveal_datas VAL5
+
+logc "Advance epoch by 1 to end the reveal phase"
+epoch=$(($epoch + 1))
+resim set-current-epoch $epoch
+
+logc "A person begin conclude the round, this should still feed the stake-weighted medians of the numeric XRD prices"
+
+resim run ./transaction_manifest/end_round
+
+logc "Check user's staked amount, this should show user2 (validator 1) and user4 (validator 3) got reward and user3 (validator 5) got punished for the non-numeric prices"
+resim set-default-account $USER2_ACC $USER2_PIV
+resim call-method $VAL1_ADDRESS show_my_stake_amount 1,$STAKER_VAL1_BADGE
+
+resim set-default-account $USER4_ACC $USER4_PIV
+resim call-method $VAL3_ADDRESS show_my_stake_amount 1,$STAKER_VAL3_BADGE
+
+resim set-default-account $USER3_ACC $USER3_PIV
+resim call-method $VAL5_ADDRESS show_my_stake_amount 1,$STAKER_VAL5_BADGE
+
+logc "Advance epoch by 2."
+epoch=$(($epoch + 2))
+resim set-current-epoch $epoch
+
+completed
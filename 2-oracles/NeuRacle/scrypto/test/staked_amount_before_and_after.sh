@@ -22,7 +22,7 @@ resim call-method $VAL5_ADDRESS show_my_stake_amount 1,$STAKER_VAL5_BADGE
 
 source ./data_refresh_round.sh
 
-logc "Check user's staked amount again, this should show user2, user3 got reward, user4 got no reward and user5 lose some NAR token"
+logc "Check user's staked amount again, this should show user2, user3 got reward, user4 lose the full punishment for not revealing and user5 lose some NAR token"
 resim set-default-account $USER2_ACC $USER2_PIV
 resim call-method $VAL1_ADDRESS show_my_stake_amount 1,$STAKER_VAL1_BADGE
 
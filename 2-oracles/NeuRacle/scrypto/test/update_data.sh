@@ -4,33 +4,45 @@
 set -e
 
 source ./log.sh
+source ./commit_reveal.sh
 
-logc "The Gateway get api list from NeuRacle component, fetch and feed data to the system"
+logc "The Gateway get api list from NeuRacle component, fetch data and commit the data hash on validators behalf"
 
 logy "This will take a bit"
 
 resim set-default-account $VAL1_ACC $VAL1_PIV
-export VALUP_ADDRESS=$VAL1_ADDRESS
-export VALUP_ACC=$VAL1_ACC
 
 export RESULTS=`./neuracle_gateway_prototype.exe`
 
-resim run ./transaction_manifest/update_data
+commit_datas VAL1
 
-resim set-default-account $VAL2_ACC $VAL2_PIV
-export VALUP_ADDRESS=$VAL2_ADDRESS
-export VALUP_ACC=$VAL2_ACC
-resim run ./transaction_manifest/update_data
+commit_datas VAL2
 
-logy "Let one validator non-active and one validator to intervene in the Gateway process and behave malicious"
-resim set-default-account $VAL4_ACC $VAL4_PIV
-export VALUP_ADDRESS=$VAL4_ADDRESS
-export VALUP_ACC=$VAL4_ACC
-resim run ./transaction_manifest/update_data_malicious
+logy "Let one validator to intervene in the Gateway process and behave malicious and one validator commit but never reveal"
 
-resim set-default-account $VAL5_ACC $VAL5_PIV
-export VALUP_ADDRESS=$VAL5_ADDRESS
-export VALUP_ACC=$VAL5_ACC
-resim run ./transaction_manifest/update_data
+commit_datas VAL3
 
-completed
\ No newline at end of file
+export GATEWAY_RESULTS="$RESULTS"
+export RESULTS="$MALICIOUS_RESULTS"
+commit_datas VAL4
+export RESULTS="$GATEWAY_RESULTS"
+
+commit_datas VAL5
+
+logc "Advance epoch by 1 to the reveal phase, validators reveal their datas"
+epoch=$(($epoch + 1))
+resim set-current-epoch $epoch
+
+reveal_datas VAL1
+
+reveal_datas VAL2
+
+reveal_datas VAL4
+
+reveal_datas VAL5
+
+logc "Advance epoch by 1 to end the reveal phase"
+epoch=$(($epoch + 1))
+resim set-current-epoch $epoch
+
+completed
@@ -4,6 +4,7 @@
 set -e
 
 source ./log.sh
+source ./commit_reveal.sh
 
 logc "Users use badge to get data"
 
@@ -33,28 +34,57 @@ resim set-current-epoch $epoch
 logg "That person call new round again."
 resim run ./transaction_manifest/start_round
 
-resim set-default-account $VAL1_ACC $VAL1_PIV
-export VALUP_ADDRESS=$VAL1_ADDRESS
-export VALUP_ACC=$VAL1_ACC
-resim run ./transaction_manifest/update_data
+commit_datas VAL1
 
-logr "Only one validator is active, but that validator try to end the round. This should show error!"
+commit_datas VAL2
+
+logy "A lazy validator copy validator 1 commitment instead of fetching datas"
+use_validator VAL3
+export COMMITMENT=$VAL1_COMMITMENT
+resim run ./transaction_manifest/commit_data
+
+commit_datas VAL4
+
+export GATEWAY_RESULTS="$RESULTS"
+export RESULTS="$MALICIOUS_RESULTS"
+commit_datas VAL5
+export RESULTS="$GATEWAY_RESULTS"
+
+logr "A validator try to reveal data on the commit phase. This should show error!"
+reveal_datas VAL1 || true
+
+logc "Advance epoch by 1 to the reveal phase."
+epoch=$(($epoch + 1))
+resim set-current-epoch $epoch
+
+logr "A validator try to commit data on the reveal phase. This should show error!"
+commit_datas VAL3 || true
+
+logr "A validator try to reveal different datas from it's commitment. This should show error!"
+use_validator VAL1
+export SALT=$VAL1_SALT
+export RESULTS="$MALICIOUS_RESULTS"
+resim run ./transaction_manifest/reveal_data || true
+
+reveal_datas VAL1
+
+logr "The lazy validator replay validator 1 revealed datas and salt on its copied commitment. This should show error!"
+use_validator VAL3
+export RESULTS="$VAL1_RESULTS" SALT=$VAL1_SALT
+resim run ./transaction_manifest/reveal_data || true
+
+logr "Only one validator is active, but that validator try to end the round on the reveal phase. This should show error!"
 resim run ./transaction_manifest/end_round || true
 
-resim set-default-account $VAL2_ACC $VAL2_PIV
-export VALUP_ADDRESS=$VAL2_ADDRESS
-export VALUP_ACC=$VAL2_ACC
-resim run ./transaction_manifest/update_data
+reveal_datas VAL2
+
+reveal_datas VAL4
 
-resim set-default-account $VAL4_ACC $VAL4_PIV
-export VALUP_ADDRESS=$VAL4_ADDRESS
-export VALUP_ACC=$VAL4_ACC
-resim run ./transaction_manifest/update_data
+reveal_datas VAL5
 
-resim set-default-account $VAL5_ACC $VAL5_PIV
-export VALUP_ADDRESS=$VAL5_ADDRESS
-export VALUP_ACC=$VAL5_ACC
-resim run ./transaction_manifest/update_data_malicious
+logc "Advance epoch by 1 to end the reveal phase."
+epoch=$(($epoch + 1))
+resim set-current-epoch $epoch
 
 logg "Now 4/5 validator voted, someone try to end the round."
 resim run ./transaction_manifest/end_round
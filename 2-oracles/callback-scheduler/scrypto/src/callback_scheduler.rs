@@ -1,3 +1,4 @@
+use crate::condition::Condition;
 use crate::utils;
 use scrypto::prelude::*;
 use std::fmt::Display;
@@ -5,6 +6,14 @@ use std::fmt::Display;
 /// Key for a resource metadata field that stores the address of the Callback resource
 const METADATA_CALLBACK_RESOURCE_ADDRESS: &str = "callback_resource_address";
 
+/// The method that is called on the time oracle component to retrieve the current time.
+/// It must take no arguments and return the current UNIX time in seconds as a string.
+const TIME_ORACLE_METHOD: &str = "get_time";
+
+/// The method that is called on the auth provider to give back the CallbackHandle after
+/// an execution of a recurring callback that is not its last execution
+const RETURN_CALLBACK_HANDLE_METHOD: &str = "return_callback_handle";
+
 blueprint! {
 
     /// A component that users can use to schedule callbacks to methods in their own components
@@ -17,8 +26,8 @@ blueprint! {
 
         /// A vault that stores all newly created CallbackAdminHandles.
         /// Whenever a user schedules a callback, an admin handle for that callback will be
-        /// put into this vault. The operator of this component must retrieve these handles
-        /// before being able to execute the callbacks.
+        /// put into this vault. The operator of this component can retrieve these handles
+        /// to keep track of newly scheduled callbacks and to cancel them.
         new_callback_admin_handles: Vault,
 
         /// The address of the CallbackHandle NFRs that are given to users after scheduling
@@ -28,16 +37,40 @@ blueprint! {
         /// A vault for collecting fees
         fees: Vault,
 
-        /// The amount in XRD that is charged for scheduling a callback
-        fee_amount: Decimal
+        /// The amount in XRD that is charged for each execution of a callback
+        fee_amount: Decimal,
+
+        /// The amount in XRD that is paid to whoever executes a callback
+        bounty_amount: Decimal,
+
+        /// A vault holding the prepaid XRD deposits of all scheduled callbacks
+        deposits: Vault,
+
+        /// The deposit that is left for each scheduled callback.
+        /// Each execution draws the fee and the bounty from the callback's deposit.
+        deposit_balances: HashMap<NonFungibleId, Decimal>,
+
+        /// The deposits that were left for callbacks canceled by the admin/operator.
+        /// They stay in the deposits vault until the user cancels the callback with its
+        /// CallbackHandle, which returns them.
+        unclaimed_refunds: HashMap<NonFungibleId, Decimal>,
+
+        /// The time oracle component that is used to check AtDateTime triggers.
+        /// Callbacks with an AtDateTime trigger can only be scheduled once this is set.
+        time_oracle: Option<ComponentAddress>
     }
 
     impl CallbackScheduler {
 
         /// Instantiate a new CallbackScheduler component
         /// # Arguments
-        /// * `fee` - The fee to take for each scheduling
-        pub fn instantiate_callback_scheduler(fee_amount: Decimal) -> (ComponentAddress, Bucket) {
+        /// * `fee_amount` - The fee to take for each execution
+        /// * `bounty_amount` - The bounty to pay to the caller for each execution
+        pub fn instantiate_callback_scheduler(fee_amount: Decimal, bounty_amount: Decimal)
+            -> (ComponentAddress, Bucket) {
+
+            assert!(fee_amount >= Decimal::zero() && bounty_amount >= Decimal::zero(),
+                "The fee and bounty amounts must not be negative");
 
             // Define an admin badge. This will be used to administer the component
             let admin_badge = ResourceBuilder::new_fungible()
@@ -56,6 +89,8 @@ blueprint! {
                 .metadata("name", "Callback")
                 .mintable(rule!(require(minter.resource_address())), LOCKED)
                 .burnable(rule!(require(minter.resource_address())), LOCKED)
+                // The execution count of recurring callbacks is tracked in the Callback NFR
+                .updateable_non_fungible_data(rule!(require(minter.resource_address())), LOCKED)
                 .no_initial_supply();
 
             // Define a resource that can be given to the admin/operator of
@@ -88,9 +123,13 @@ blueprint! {
                 .method("get_new_callback_admin_handles",
                     rule!(require(admin_badge.resource_address())))
                 .method("execute_callback", rule!(allow_all))
+                .method("top_up_callback", rule!(allow_all))
+                .method("get_deposit", rule!(allow_all))
+                .method("get_execution_cost", rule!(allow_all))
                 .method("cancel_callbacks", rule!(allow_all))
                 .method("get_callback_handle_resource", rule!(allow_all))
-                .method("withdraw_fees", rule!(require(admin_badge.resource_address())));
+                .method("withdraw_fees", rule!(require(admin_badge.resource_address())))
+                .method("set_time_oracle", rule!(require(admin_badge.resource_address())));
 
             // Instantiate the component
             let component = Self {
@@ -99,7 +138,12 @@ blueprint! {
                 new_callback_admin_handles: Vault::new(callback_admin_handle_resource),
                 callback_handle_resource,
                 fees:Vault::new(RADIX_TOKEN),
-                fee_amount
+                fee_amount,
+                bounty_amount,
+                deposits: Vault::new(RADIX_TOKEN),
+                deposit_balances: HashMap::new(),
+                unclaimed_refunds: HashMap::new(),
+                time_oracle: None
             }
             .instantiate()
             .add_access_check(rules)
@@ -113,16 +157,22 @@ blueprint! {
         /// # Arguments:
         ///
         /// * `callback_request` A struct that describes the callback that should be scheduled
-        /// * `fee` A bucket with the fee in XRD that is being payed for the scheduling service
+        /// * `deposit` A bucket with the XRD deposit that pays for the executions of the callback.
+        /// It must cover at least one execution. For callbacks with a limited number of executions,
+        /// anything more than needed for all executions is returned.
         ///
         /// # Returns:
         /// * a bucket containing a CallbackHandle that may be used to cancel the callback
-        /// * a bucket containing any XRD that are left in the fee bucket after deducting the payment
-        pub fn schedule_callback(&mut self, callback_request: CallbackRequest, mut fee: Bucket)
+        /// * a bucket containing any XRD that are left in the deposit bucket
+        pub fn schedule_callback(&mut self, callback_request: CallbackRequest, mut deposit: Bucket)
             -> (Bucket, Bucket) {
 
             // First, validate the callback request
-            callback_request.assert_valid();
+            callback_request.assert_valid(self.time_oracle);
+            utils::assert_resource_eq!(deposit, self.deposits);
+            assert!(deposit.amount() >= self.execution_cost(),
+                "Insufficient deposit: at least {} XRD are required for one execution",
+                self.execution_cost());
 
             // Mint a new Callback NFR
             // The same callback ID is used for the Callback, the CallbackHandle and the
@@ -156,10 +206,51 @@ blueprint! {
             });
             utils::debug_log_non_fungible("Minted CallbackHandle", &callback_handle);
 
-            // Collect the fee
-            self.fees.put(fee.take(self.fee_amount));
+            // Collect the deposit
+            let amount = self.deposit_needed(&callback_request.trigger, 0, deposit.amount());
+            self.deposits.put(deposit.take(amount));
+            self.deposit_balances.insert(callback_id, amount);
             // Return the callback handle as well as any change to the caller
-            (callback_handle, fee)
+            (callback_handle, deposit)
+        }
+
+        /// Adds XRD to the deposit of a scheduled callback, e.g. to keep a recurring
+        /// callback without an execution limit running. Anyone may top up a callback.
+        ///
+        /// # Arguments:
+        /// * `callback_id` - The ID of the callback
+        /// * `deposit` - A bucket with the XRD to add to the deposit
+        ///
+        /// # Returns:
+        /// A bucket with any XRD that are not needed for the remaining executions
+        pub fn top_up_callback(&mut self, callback_id: NonFungibleId, mut deposit: Bucket) -> Bucket {
+            utils::assert_resource_eq!(deposit, self.deposits);
+            assert!(
+                self.scheduled_callbacks.non_fungible_ids().contains(&callback_id),
+                "Callback does not exist or was canceled"
+            );
+            let callback_data: Callback = borrow_resource_manager!(self.scheduled_callbacks.resource_address())
+                .get_non_fungible_data(&callback_id);
+            let balance = self.deposit_balances[&callback_id];
+            let amount = self.deposit_needed(&callback_data.trigger, callback_data.executions,
+                balance + deposit.amount()) - balance;
+            self.deposits.put(deposit.take(amount));
+            self.deposit_balances.insert(callback_id, balance + amount);
+            deposit
+        }
+
+        /// Returns the deposit that is left for the given callback. For a callback that was
+        /// canceled by the admin/operator, this is the deposit the user can still claim.
+        pub fn get_deposit(&self, callback_id: NonFungibleId) -> Decimal {
+            *self.deposit_balances.get(&callback_id)
+                .or(self.unclaimed_refunds.get(&callback_id))
+                .unwrap_or(&Decimal::zero())
+        }
+
+        /// Returns the amount in XRD that is drawn from a callback's deposit for each
+        /// execution, i.e. the fee plus the bounty
+        pub fn get_execution_cost(&self) -> Decimal {
+            self.execution_cost()
         }
 
         /// Retrieves all CallbackAdminHandle NFRs that were created after the last call to
@@ -173,53 +264,63 @@ blueprint! {
 
         /// Execute the given callback
         ///
-        /// This method has multiple failure modes to which an admin/operator must react:
+        /// Anyone may call this method, the caller (a keeper) is paid a bounty from the
+        /// callback's deposit for each successful execution. The operator's fee is also
+        /// drawn from the deposit.
+        ///
+        /// This method has multiple failure modes:
         /// * The callback is ill-defined and e.g. references a non existing method.
         /// In this case the callback should be canceled in a separate transaction.
         /// * The callback is well-defined but still fails e.g. because it's trigger
-        /// condition is not met (e.g. executed in the wrong epoch). In this case the
-        /// admin/operator must determine if the trigger condition can possibly be met
+        /// condition is not met (e.g. executed in the wrong epoch). In this case keepers
+        /// must determine if the trigger condition can possibly be met
         /// at a later time and if so, call this method again at such an appropriate time.
-        /// If the condition cannot possibly be met anymore, they should cancel the callback
+        /// If the condition cannot possibly be met anymore, the callback should be canceled
         /// in a separate transaction.
-        /// * The target component fails to authorize the callback. In this case the admin/operator
-        /// should cancel the callback in a separate transaction.
-        /// * The callback may have been canceled by the user. Because recallable resources
-        /// are not implemented as of Scrypto 0.4.0, the CallbackAdminHandle NFR that is
-        /// associated with the canceled callback still remains in the admins/operators
-        /// account. If this happens, the admin/operator should cancel the callback in a
-        /// separate transaction.
+        /// * The deposit of the callback does not cover another execution. In this case the
+        /// callback must be topped up before it can be executed again.
+        /// * The target component fails to authorize the callback. In this case the callback
+        /// should be canceled in a separate transaction.
+        /// * The callback may have been canceled.
+        ///
+        /// After the last execution of a callback, the Callback and CallbackHandle NFRs are burned.
+        /// If the CallbackAdminHandle has already been retrieved by the admin/operator, it
+        /// remains in their account and can be burned by calling `cancel_callbacks` with it.
         ///
         /// # Arguments:
-        /// * `callback_admin_handle` - A bucket containing the CallbackAdminHandle that is
-        /// associated with the callback that should be executed.
-        pub fn execute_callback(&mut self, callback_admin_handle: Bucket) {
-            // Make sure we have been given a CallbackAdminHandle NFR
-            utils::assert_resource_eq!(callback_admin_handle, self.new_callback_admin_handles);
-
-            // Get the callback ID
-            let callback_id = callback_admin_handle.non_fungible::<CallbackHandle>().id();
-
-            // TODO: This check will not be necessary once recallable resources are implemented and
-            // when the user cancels a callback, the CallbackAdminHandle will be recalled from
-            // the operator's wallet
-            // Also see the documentation on method cancel_callbacks
+        /// * `callback_id` - The ID of the callback that should be executed.
+        ///
+        /// # Returns:
+        /// A bucket with the bounty in XRD
+        pub fn execute_callback(&mut self, callback_id: NonFungibleId) -> Bucket {
             assert!(
                 self.scheduled_callbacks.non_fungible_ids().contains(&callback_id),
-                "Callback was canceled by the user"
+                "Callback does not exist or was canceled"
             );
 
             // Retrieve the callback NFR from the internal vault
             let callback = self.scheduled_callbacks.take_non_fungible(&callback_id);
-            let callback_data = callback.non_fungible::<Callback>().data();
+            let mut callback_data = callback.non_fungible::<Callback>().data();
             debug!("Executing {}", callback_data);
 
-            // Assert that the callback can be executed now
-            callback_data.trigger.assert_valid_now();
+            // Assert that the callback can be executed now.
+            // This happens before the callback is authorized, so any methods that are called to
+            // evaluate an OnCondition trigger are called without the callback's proofs
+            callback_data.trigger.assert_valid_now(self.time_oracle, callback_data.last_execution_epoch);
+
+            // Draw the fee and the bounty for this execution from the callback's deposit
+            let balance = self.deposit_balances[&callback_id];
+            assert!(
+                balance >= self.execution_cost(),
+                "Insufficient deposit: the callback must be topped up before it can be executed"
+            );
+            self.deposit_balances.insert(callback_id.clone(), balance - self.execution_cost());
+            self.fees.put(self.deposits.take(self.fee_amount));
+            let bounty = self.deposits.take(self.bounty_amount);
 
             // Authorize the callback we want to perform with the callee component
             // This will yield us 1) the callee's callback_handle which we must burn
-            // after executing the callback and 2) any proofs that are expected by
+            // after the last execution of the callback and 2) any proofs that are expected by
             // the method that should be called. If no explicit auth_provider has been configured
             // for the callback, assume that the callee component itself implements the
             // `authorize_callback` method.
@@ -238,18 +339,46 @@ blueprint! {
             // Execute the callback using the proofs we were given
             callback_data.execute_call(proofs);
 
-            // Burn all three NFRs that are associated with the executed callback
-            self.minter.authorize(|| {
-                callback.burn();
-                callback_handle.burn();
-                callback_admin_handle.burn();
-            });
+            callback_data.executions += 1;
+            callback_data.last_execution_epoch = Some(Runtime::current_epoch());
+
+            if callback_data.trigger.max_executions() == Some(callback_data.executions) {
+                // This was the last execution. Burn the Callback and the CallbackHandle NFRs
+                // as well as the CallbackAdminHandle if it has not been retrieved yet
+                let mut ids = BTreeSet::new();
+                ids.insert(callback_id.clone());
+                let admin_handles_to_burn = ids
+                    .intersection(&self.new_callback_admin_handles.non_fungible_ids())
+                    .cloned().collect();
+                self.minter.authorize(|| {
+                    callback.burn();
+                    callback_handle.burn();
+                    self.new_callback_admin_handles.take_non_fungibles(&admin_handles_to_burn).burn();
+                });
+                self.deposit_balances.remove(&callback_id);
+            } else {
+                // The callback recurs. Store the execution and give the CallbackHandle back to
+                // the auth provider so that it can authorize the next execution
+                self.minter.authorize(|| {
+                    callback.non_fungible::<Callback>().update_data(callback_data)
+                });
+                auth_provider.call::<()>(
+                    RETURN_CALLBACK_HANDLE_METHOD,
+                    args!(callback.create_proof(), callback_handle)
+                );
+                self.scheduled_callbacks.put(callback);
+            }
+
+            bounty
         }
 
         /// Cancels all of the callbacks that are referenced by the supplied callback handles.
         /// This method can be called from either party, by the user as well as by the
         /// SchedulerComponent admin/operator.
-        /// At the moment fees will not be reimbursed.
+        /// The deposits that are left for the canceled callbacks always belong to the user:
+        /// if the user cancels with CallbackHandles, they are returned right away. If the
+        /// admin/operator cancels with CallbackAdminHandles, they are kept in this component
+        /// until the user cancels with the "orphan" CallbackHandles, which returns them.
         ///
         /// Because resources are not yet recallable as of Scrypto 0.4.1,
         /// when one party cancels a callback, the Callback NFR will be burned but the corresponding
@@ -265,7 +394,11 @@ blueprint! {
         /// # Arguments
         /// * `callback_handles` - A bucket containing the CallbackHandle or CallbackAdminHandle
         /// NFRs of the callbacks that should be canceled
-        pub fn cancel_callbacks(&mut self, callback_handles: Bucket) {
+        ///
+        /// # Returns
+        /// A bucket with the deposits that are returned to the user, empty if the
+        /// admin/operator canceled
+        pub fn cancel_callbacks(&mut self, callback_handles: Bucket) -> Bucket {
             // Determine the actual resource that was given to us and
             // assert that it is either a CallbackAdminHandle or CallbackHandle
             let handle_resource = callback_handles.resource_address();
@@ -276,12 +409,31 @@ blueprint! {
             );
 
             // Determine the IDs of the callbacks that should be canceled
-            let callbacks_to_cancel = callback_handles.non_fungible_ids()
+            let callbacks_to_cancel: BTreeSet<NonFungibleId> = callback_handles.non_fungible_ids()
                 // TODO Once recallable resources are implemented, we can prevent "orphan" handles
                 // and this intersection computation will not be necessary
                 .intersection(&self.scheduled_callbacks.non_fungible_ids())
                 .cloned().collect();
 
+            // The deposits that are left for the canceled callbacks belong to the user.
+            // Keep them for the user if the admin/operator cancels, otherwise reimburse them
+            // together with the deposits of callbacks the admin/operator canceled before
+            let mut refund = Decimal::zero();
+            for callback_id in callbacks_to_cancel.iter() {
+                if let Some(balance) = self.deposit_balances.remove(callback_id) {
+                    if handle_resource == self.callback_handle_resource {
+                        refund += balance;
+                    } else {
+                        self.unclaimed_refunds.insert(callback_id.clone(), balance);
+                    }
+                }
+            }
+            if handle_resource == self.callback_handle_resource {
+                for callback_id in callback_handles.non_fungible_ids() {
+                    refund += self.unclaimed_refunds.remove(&callback_id).unwrap_or(Decimal::zero());
+                }
+            }
+
             // Determine the IDs of the CallbackAdminHandles that have not yet been
             // retrieved by the component admin/operator and that now should be burned
             let callback_admin_handles_to_burn = callback_handles.non_fungible_ids()
@@ -297,6 +449,8 @@ blueprint! {
                 // TODO Recall and burn the corresponding CallbackHandle/CallbackAdminHandle
                 // of the other party once recallable resources are implemented
             });
+
+            self.deposits.take(refund)
         }
 
         /// Returns the resource address of the CallbackHandle NFR
@@ -308,6 +462,33 @@ blueprint! {
         pub fn withdraw_fees(&mut self) -> Bucket {
             self.fees.take_all()
         }
+
+        /// Sets the time oracle component that is used to check AtDateTime triggers
+        ///
+        /// # Arguments
+        /// * `time_oracle` - A component with a `get_time` method that takes no arguments and
+        /// returns the current UNIX time in seconds as a string
+        pub fn set_time_oracle(&mut self, time_oracle: ComponentAddress) {
+            self.time_oracle = Some(time_oracle);
+        }
+
+        /// The amount that is drawn from a callback's deposit for each execution
+        fn execution_cost(&self) -> Decimal {
+            self.fee_amount + self.bounty_amount
+        }
+
+        /// Determines how much of the available XRD should be kept as deposit for a callback.
+        /// For callbacks with a limited number of executions no more than needed for the
+        /// remaining executions is kept.
+        fn deposit_needed(&self, trigger: &Trigger, executions: u64, available: Decimal) -> Decimal {
+            match trigger.max_executions() {
+                Some(max_executions) => {
+                    let needed = self.execution_cost() * Decimal::from(max_executions - executions);
+                    if available < needed { available } else { needed }
+                }
+                None => available,
+            }
+        }
     }
 }
 
@@ -325,26 +506,102 @@ pub enum Trigger {
         /// On DLTs precise execution times cannot be guaranteed.
         /// This specifies the tolerated deviation from the given
         /// date_time when executing the callback.
-        /// Note that at this moment no date_time verification is
-        /// implemented. This is left for future work.
+        /// The current time is retrieved from the time oracle configured
+        /// on the CallbackScheduler component.
         tolerance_seconds: u8,
     },
 
     /// Execute the callback when a specific condition is met
     ///
-    /// This is only in hear for demo purposes.
-    /// At the moment there is no specification for OnCondition expressions.
-    /// TODO - Future work: Implement an OnCondition specification
+    /// The condition is an expression that compares numbers, the current epoch and
+    /// the results of read-only method calls, e.g.
+    /// `<component address>.get_price() < 100 && epoch >= 10`.
+    /// See [Condition] for the full syntax.
     OnCondition(String),
+
+    /// Execute the callback every `interval_epochs` epochs, starting at `start_epoch`.
+    ///
+    /// Each execution is allowed from the epoch it is due in. If an execution is late,
+    /// the next one is still due on the original schedule and executions that were missed
+    /// entirely are skipped.
+    Recurring {
+        /// The epoch of the first execution
+        start_epoch: u64,
+
+        /// The number of epochs between executions
+        interval_epochs: u64,
+
+        /// The maximum number of executions or `None` to execute the callback
+        /// until it is canceled or its deposit is used up
+        max_executions: Option<u64>,
+    },
 }
 
 impl Trigger {
+    /// The number of times a callback with this trigger is executed,
+    /// `None` if there is no limit
+    pub fn max_executions(&self) -> Option<u64> {
+        match self {
+            Trigger::Recurring { max_executions, .. } => *max_executions,
+            _ => Some(1),
+        }
+    }
+
+    /// Asserts that a callback with this trigger can be scheduled, i.e. that the trigger
+    /// is well-formed and can still be met in the future.
+    ///
+    /// Panics if the trigger is invalid
+    fn assert_valid(&self, time_oracle: Option<ComponentAddress>) {
+        match self {
+            Trigger::AtEpoch(epoch) => assert!(
+                *epoch > Runtime::current_epoch(),
+                "Callback execution must be in the future"
+            ),
+            Trigger::AtDateTime {
+                date_time,
+                tolerance_seconds,
+            } => {
+                let time_oracle = time_oracle.expect(
+                    "Invalid trigger: AtDateTime triggers require a time oracle \
+                    but none is configured for this CallbackScheduler",
+                );
+                let time = parse_trigger_date_time(date_time);
+                assert!(
+                    time + *tolerance_seconds as u64 >= current_time(time_oracle),
+                    "Callback execution must be in the future"
+                );
+            }
+            Trigger::OnCondition(condition) => {
+                parse_trigger_condition(condition);
+            }
+            Trigger::Recurring {
+                start_epoch,
+                interval_epochs,
+                max_executions,
+            } => {
+                assert!(
+                    *start_epoch >= Runtime::current_epoch(),
+                    "Callback execution must not start in the past"
+                );
+                assert!(*interval_epochs > 0, "Invalid trigger: the interval must be at least one epoch");
+                assert!(
+                    *max_executions != Some(0),
+                    "Invalid trigger: the callback must be executed at least once"
+                );
+            }
+        }
+    }
+
     /// Asserts that the trigger is valid now.
-    /// This can for example be a check that we are in the right epoch or
-    /// it also could be a check against a time oracle (not implemented yet).
+    /// This is a check that we are in the right epoch, a check against the time oracle
+    /// or the evaluation of a condition, depending on the type of trigger.
+    ///
+    /// # Arguments:
+    /// * `time_oracle` - The time oracle of the CallbackScheduler, required by AtDateTime triggers
+    /// * `last_execution_epoch` - The epoch of the last execution, if there was one
     ///
     /// Panics if the trigger is not valid now
-    fn assert_valid_now(&self) {
+    fn assert_valid_now(&self, time_oracle: Option<ComponentAddress>, last_execution_epoch: Option<u64>) {
         match self {
             Trigger::AtEpoch(epoch) => {
                 let now = Runtime::current_epoch();
@@ -356,12 +613,86 @@ impl Trigger {
                     now
                 );
             }
-            Trigger::AtDateTime { .. } => (), // TODO - Future work: implement this check e.g. by calling a 3rd-party time oracle
-            Trigger::OnCondition(_) => (), // TODO - Future work: implement an OnCondition specification and this check
+            Trigger::AtDateTime {
+                date_time,
+                tolerance_seconds,
+            } => {
+                let time_oracle = time_oracle.expect(
+                    "Invalid execution: no time oracle is configured for this CallbackScheduler",
+                );
+                let time = parse_trigger_date_time(date_time);
+                let now = current_time(time_oracle);
+                let deviation = if now > time { now - time } else { time - now };
+                assert!(
+                    deviation <= *tolerance_seconds as u64,
+                    "Invalid execution, trigger restriction violated: \
+                execution is only allowed at {} (UNIX time {}) ±{}s. Current UNIX time: {}",
+                    date_time,
+                    time,
+                    tolerance_seconds,
+                    now
+                );
+            }
+            Trigger::OnCondition(condition) => {
+                assert!(
+                    parse_trigger_condition(condition).evaluate(),
+                    "Invalid execution, trigger restriction violated: \
+                condition '{}' is not met",
+                    condition
+                );
+            }
+            Trigger::Recurring {
+                start_epoch,
+                interval_epochs,
+                ..
+            } => {
+                // The next execution is due in the first scheduled epoch after the last execution
+                let due = match last_execution_epoch {
+                    Some(last) => {
+                        start_epoch + ((last - start_epoch) / interval_epochs + 1) * interval_epochs
+                    }
+                    None => *start_epoch,
+                };
+                let now = Runtime::current_epoch();
+                assert!(
+                    now >= due,
+                    "Invalid execution, trigger restriction violated: \
+                the next execution is due at epoch {}. Current epoch: {}",
+                    due,
+                    now
+                );
+            }
         }
     }
 }
 
+/// Parses the date_time of an AtDateTime trigger into a UNIX time
+///
+/// Panics if the date_time is invalid
+fn parse_trigger_date_time(date_time: &str) -> u64 {
+    utils::parse_date_time(date_time)
+        .unwrap_or_else(|error| panic!("Invalid AtDateTime trigger: {}", error))
+}
+
+/// Parses the condition of an OnCondition trigger
+///
+/// Panics if the condition is invalid
+fn parse_trigger_condition(condition: &str) -> Condition {
+    Condition::parse(condition).unwrap_or_else(|error| {
+        panic!("Invalid OnCondition trigger '{}': {}", condition, error)
+    })
+}
+
+/// Retrieves the current UNIX time in seconds from the given time oracle component
+fn current_time(time_oracle: ComponentAddress) -> u64 {
+    let time: String = borrow_component!(time_oracle).call(TIME_ORACLE_METHOD, args!());
+    // Ignore any fractional seconds
+    let seconds = time.trim().split('.').next().unwrap_or_default();
+    seconds.parse().unwrap_or_else(|_| {
+        panic!("Invalid time oracle response: expected a UNIX time but got '{}'", time)
+    })
+}
+
 impl Display for Trigger {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -371,6 +702,17 @@ impl Display for Trigger {
                 tolerance_seconds,
             } => write!(f, "AtDateTime({date_time} ±{tolerance_seconds}s)"),
             Self::OnCondition(condition) => write!(f, "OnCondition({condition})"),
+            Self::Recurring {
+                start_epoch,
+                interval_epochs,
+                max_executions,
+            } => match max_executions {
+                Some(max_executions) => write!(
+                    f,
+                    "Recurring(every {interval_epochs} epochs from {start_epoch}, {max_executions} times)"
+                ),
+                None => write!(f, "Recurring(every {interval_epochs} epochs from {start_epoch})"),
+            },
         }
     }
 }
@@ -423,16 +765,15 @@ impl CallbackRequest {
         }
     }
 
-    /// Validates the request, e.g. by checking that the trigger is not in the past
+    /// Validates the request, e.g. by checking that the trigger is well-formed and not in the past
+    ///
+    /// # Arguments:
+    /// * `time_oracle` - The time oracle of the CallbackScheduler, required by AtDateTime triggers
+    ///
     /// Panics if the request is invalid
-    pub fn assert_valid(&self) {
-        match self.trigger {
-            Trigger::AtEpoch(epoch) => assert!(
-                epoch > Runtime::current_epoch(),
-                "Callback execution must be in the future"
-            ),
-            _ => (), // TODO - Future work: Implement validations for the other triggers
-        }
+    pub fn assert_valid(&self, time_oracle: Option<ComponentAddress>) {
+        assert!(!self.method.is_empty(), "Invalid callback request: the method must not be empty");
+        self.trigger.assert_valid(time_oracle);
     }
 
     /// Schedules a callback for this request. This calls the scheduler component with this request
@@ -440,20 +781,20 @@ impl CallbackRequest {
     ///
     /// # Arguments:
     /// * `scheduler_component` - The component that should execute the callback
-    /// * `fee` - The fee in XRD that will be paid to the scheduler component
+    /// * `deposit` - The deposit in XRD that pays for the executions of the callback
     ///
     /// # Returns
     /// Two buckets where:
     /// - the first bucket contains the CallbackHandle NFR that the callee component will hold on to
-    /// - the second bucket contains any fee amount that was overpaid
+    /// - the second bucket contains any deposit amount that was not needed
     pub fn schedule_callback(
         &self,
         scheduler_component: ComponentAddress,
-        fee: Bucket,
+        deposit: Bucket,
     ) -> (Bucket, Bucket) {
         let scheduler = borrow_component!(scheduler_component);
-        let (callback_handle, fee) = scheduler.call::<(Bucket, Bucket)>("schedule_callback", args!(*self, fee));
-        (callback_handle, fee)
+        let (callback_handle, deposit) = scheduler.call::<(Bucket, Bucket)>("schedule_callback", args!(*self, deposit));
+        (callback_handle, deposit)
     }
 }
 
@@ -480,6 +821,14 @@ pub struct Callback {
     /// that is required by the method that should be called.
     /// If set to `None`, the target component itself will be used to obtain any necessary proofs.
     auth_provider: Option<ComponentAddress>,
+
+    /// How often the callback has been executed
+    #[scrypto(mutable)]
+    pub executions: u64,
+
+    /// The epoch of the last execution, if there was one
+    #[scrypto(mutable)]
+    pub last_execution_epoch: Option<u64>,
 }
 
 impl Callback {
@@ -496,6 +845,8 @@ impl Callback {
             method: request.method.to_owned(),
             args: request.args.to_vec(),
             auth_provider: request.auth_provider,
+            executions: 0,
+            last_execution_epoch: None,
         }
     }
 
@@ -552,6 +903,37 @@ impl Callback {
             "Invalid CallbackHandle provided: id does not match that of the Callback"
         );
 
+        Self::assert_callback_resource(actual_callback_resource, &callback_handle);
+
+        // Return the CallbackHandle NFR
+        callback_handle
+    }
+
+    /// Verify that a CallbackHandle that is given back by the CallbackScheduler after an
+    /// execution of a recurring callback is authentic. This method must be called from the
+    /// `return_callback_handle` method of the client component, before the CallbackHandle is put
+    /// back into the vault it was taken from by the `authorize_callback` method.
+    ///
+    /// Putting the CallbackHandle into that vault also ensures that it is of the right resource.
+    ///
+    /// # Arguments:
+    /// * `callback` - The callback the CallbackHandle belongs to
+    /// * `callback_handle` - The CallbackHandle that is given back
+    pub fn verify_returned_handle(callback: &Proof, callback_handle: &Bucket) {
+        let actual_callback_resource = callback.resource_address();
+        let callback = callback.non_fungible::<Callback>().data();
+        assert_eq!(
+            callback.id,
+            callback_handle.non_fungible::<CallbackHandle>().id(),
+            "Invalid CallbackHandle returned: id does not match that of the Callback"
+        );
+
+        Self::assert_callback_resource(actual_callback_resource, callback_handle);
+    }
+
+    /// Asserts that a supposed Callback NFR has the resource address that is stored in the
+    /// metadata of the CallbackHandle resource
+    fn assert_callback_resource(actual_callback_resource: ResourceAddress, callback_handle: &Bucket) {
         // Load the metadata of the CallbackHandle resource and retrieve
         // the expected resource address that the Callback NFR must have.
         let metadata = borrow_resource_manager!(callback_handle.resource_address()).metadata();
@@ -564,9 +946,6 @@ impl Callback {
             actual_callback_resource, expected_callback_resource,
             "Invalid Callback resource"
         );
-
-        // Return the CallbackHandle NFR
-        callback_handle
     }
 }
 
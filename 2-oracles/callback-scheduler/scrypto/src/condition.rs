@@ -0,0 +1,362 @@
+use scrypto::prelude::*;
+use std::fmt::Display;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A parsed `OnCondition` expression
+///
+/// Conditions compare operands and can be combined with `&&` and `||`, where `&&` binds
+/// stronger than `||`. Parentheses may be used for grouping. An operand is one of:
+/// * a decimal literal, e.g. `100` or `0.25`
+/// * the keyword `epoch`, which evaluates to the current epoch
+/// * a read-only method call on a component that takes no arguments and returns a `Decimal`,
+/// e.g. `02ab11a23a3f62486e5a99a4fbc6e8b640b17f531a0d90d54565f3.get_price()`
+///
+/// Supported comparisons are `<`, `<=`, `>`, `>=`, `==` and `!=`.
+///
+/// Example: `02ab11a23a3f62486e5a99a4fbc6e8b640b17f531a0d90d54565f3.get_price() < 100 && epoch >= 10`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// Both conditions must hold
+    And(Box<Condition>, Box<Condition>),
+
+    /// At least one of the conditions must hold
+    Or(Box<Condition>, Box<Condition>),
+
+    /// Compares two operands
+    Compare(Operand, Comparison, Operand),
+}
+
+/// An operand of a comparison
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    /// A decimal literal
+    Literal(Decimal),
+
+    /// The current epoch
+    Epoch,
+
+    /// A method without arguments that returns a `Decimal`
+    MethodCall {
+        component: ComponentAddress,
+        method: String,
+    },
+}
+
+/// A comparison operator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+/// Errors that can occur when parsing a condition
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConditionError {
+    /// The condition ended while more input was expected
+    UnexpectedEnd,
+
+    /// An unexpected character was found at the given position
+    UnexpectedCharacter(char, usize),
+
+    /// A word that is neither a number, `epoch` nor a method call
+    InvalidOperand(String),
+
+    /// The component part of a method call is not a valid component address
+    InvalidComponentAddress(String),
+
+    /// The condition was parsed completely but there is leftover input
+    TrailingInput(String),
+}
+
+impl Display for ConditionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of condition"),
+            Self::UnexpectedCharacter(c, position) => {
+                write!(f, "unexpected character '{c}' at position {position}")
+            }
+            Self::InvalidOperand(operand) => write!(
+                f,
+                "invalid operand '{operand}', expected a number, 'epoch' or \
+                '<component address>.<method>()'"
+            ),
+            Self::InvalidComponentAddress(address) => {
+                write!(f, "invalid component address '{address}'")
+            }
+            Self::TrailingInput(input) => write!(f, "unexpected input '{input}'"),
+        }
+    }
+}
+
+impl Condition {
+    /// Parses a condition from the given expression
+    ///
+    /// # Arguments:
+    /// * `expression` - The expression, see [Condition] for the syntax
+    ///
+    /// # Returns:
+    /// The parsed condition or an error describing why the expression is invalid
+    pub fn parse(expression: &str) -> Result<Self, ConditionError> {
+        let mut parser = Parser {
+            chars: expression.chars().peekable(),
+            position: 0,
+        };
+        let condition = parser.parse_or()?;
+        parser.skip_whitespace();
+        let rest: String = parser.chars.collect();
+        if !rest.is_empty() {
+            return Err(ConditionError::TrailingInput(rest));
+        }
+        Ok(condition)
+    }
+
+    /// Evaluates the condition, calling the referenced component methods
+    ///
+    /// Note that the methods are called by the CallbackScheduler component itself,
+    /// i.e. without any of the proofs that may later be used for the callback.
+    /// Only methods that are accessible to everyone can therefore be used in conditions.
+    pub fn evaluate(&self) -> bool {
+        match self {
+            Self::And(left, right) => left.evaluate() && right.evaluate(),
+            Self::Or(left, right) => left.evaluate() || right.evaluate(),
+            Self::Compare(left, comparison, right) => {
+                comparison.compare(left.evaluate(), right.evaluate())
+            }
+        }
+    }
+}
+
+impl Operand {
+    /// Returns the value of this operand
+    pub fn evaluate(&self) -> Decimal {
+        match self {
+            Self::Literal(value) => *value,
+            Self::Epoch => Decimal::from(Runtime::current_epoch()),
+            Self::MethodCall { component, method } => {
+                borrow_component!(*component).call::<Decimal>(method, args!())
+            }
+        }
+    }
+}
+
+impl Comparison {
+    fn compare(&self, left: Decimal, right: Decimal) -> bool {
+        match self {
+            Self::Less => left < right,
+            Self::LessOrEqual => left <= right,
+            Self::Greater => left > right,
+            Self::GreaterOrEqual => left >= right,
+            Self::Equal => left == right,
+            Self::NotEqual => left != right,
+        }
+    }
+}
+
+/// A simple recursive descent parser for conditions
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Option<char> {
+        self.position += 1;
+        self.chars.next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().map_or(false, |c| c.is_whitespace()) {
+            self.next();
+        }
+    }
+
+    /// Consumes the given token if it comes next
+    fn consume(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let mut lookahead = self.chars.clone();
+        if token.chars().all(|c| lookahead.next() == Some(c)) {
+            token.chars().for_each(|_| {
+                self.next();
+            });
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), ConditionError> {
+        if self.consume(token) {
+            return Ok(());
+        }
+        match self.chars.peek() {
+            Some(c) => Err(ConditionError::UnexpectedCharacter(*c, self.position)),
+            None => Err(ConditionError::UnexpectedEnd),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Condition, ConditionError> {
+        let mut condition = self.parse_and()?;
+        while self.consume("||") {
+            condition = Condition::Or(Box::new(condition), Box::new(self.parse_and()?));
+        }
+        Ok(condition)
+    }
+
+    fn parse_and(&mut self) -> Result<Condition, ConditionError> {
+        let mut condition = self.parse_primary()?;
+        while self.consume("&&") {
+            condition = Condition::And(Box::new(condition), Box::new(self.parse_primary()?));
+        }
+        Ok(condition)
+    }
+
+    fn parse_primary(&mut self) -> Result<Condition, ConditionError> {
+        if self.consume("(") {
+            let condition = self.parse_or()?;
+            self.expect(")")?;
+            return Ok(condition);
+        }
+        let left = self.parse_operand()?;
+        let comparison = self.parse_comparison()?;
+        let right = self.parse_operand()?;
+        Ok(Condition::Compare(left, comparison, right))
+    }
+
+    fn parse_comparison(&mut self) -> Result<Comparison, ConditionError> {
+        // Two character operators must be tried first
+        let comparisons = [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ];
+        for (token, comparison) in comparisons {
+            if self.consume(token) {
+                return Ok(comparison);
+            }
+        }
+        match self.chars.peek() {
+            Some(c) => Err(ConditionError::UnexpectedCharacter(*c, self.position)),
+            None => Err(ConditionError::UnexpectedEnd),
+        }
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, ConditionError> {
+        self.skip_whitespace();
+        let mut word = String::new();
+        while let Some(c) = self.chars.peek() {
+            if c.is_ascii_alphanumeric() || *c == '_' || *c == '.' || *c == '-' {
+                word.push(*c);
+                self.next();
+            } else {
+                break;
+            }
+        }
+
+        if word.is_empty() {
+            return match self.chars.peek() {
+                Some(c) => Err(ConditionError::UnexpectedCharacter(*c, self.position)),
+                None => Err(ConditionError::UnexpectedEnd),
+            };
+        }
+        if word == "epoch" {
+            return Ok(Operand::Epoch);
+        }
+        if let Ok(value) = Decimal::from_str(&word) {
+            return Ok(Operand::Literal(value));
+        }
+
+        // Anything else must be a method call: <component address>.<method>()
+        match word.split_once('.') {
+            Some((component, method)) if !method.is_empty() && self.consume("(") => {
+                self.expect(")")?;
+                let component = ComponentAddress::from_str(component)
+                    .map_err(|_| ConditionError::InvalidComponentAddress(component.to_owned()))?;
+                Ok(Operand::MethodCall {
+                    component,
+                    method: method.to_owned(),
+                })
+            }
+            _ => Err(ConditionError::InvalidOperand(word)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const COMPONENT: &str = "02ab11a23a3f62486e5a99a4fbc6e8b640b17f531a0d90d54565f3";
+
+    #[test]
+    fn test_parse_method_call_comparison() {
+        let condition = Condition::parse(&format!("{COMPONENT}.get_price() < 100")).unwrap();
+        assert_eq!(
+            condition,
+            Condition::Compare(
+                Operand::MethodCall {
+                    component: ComponentAddress::from_str(COMPONENT).unwrap(),
+                    method: "get_price".to_owned()
+                },
+                Comparison::Less,
+                Operand::Literal(dec!("100"))
+            )
+        );
+    }
+
+    #[test]
+    fn test_and_binds_stronger_than_or() {
+        let condition = Condition::parse("1 < 2 || epoch >= 10 && 0.5 != 0.25").unwrap();
+        match condition {
+            Condition::Or(left, right) => {
+                assert_eq!(
+                    *left,
+                    Condition::Compare(
+                        Operand::Literal(dec!("1")),
+                        Comparison::Less,
+                        Operand::Literal(dec!("2"))
+                    )
+                );
+                assert!(matches!(*right, Condition::And(_, _)));
+            }
+            _ => panic!("expected an Or condition"),
+        }
+
+        let grouped = Condition::parse("(1 < 2 || epoch >= 10) && 0.5 != 0.25").unwrap();
+        assert!(matches!(grouped, Condition::And(_, _)));
+    }
+
+    #[test]
+    fn test_literal_conditions_evaluate() {
+        assert!(Condition::parse("1 <= 1 && 2 > 1").unwrap().evaluate());
+        assert!(!Condition::parse("1 == 2 || 3 < -1").unwrap().evaluate());
+    }
+
+    #[test]
+    fn test_invalid_conditions_are_rejected() {
+        assert_eq!(Condition::parse("1 <"), Err(ConditionError::UnexpectedEnd));
+        assert_eq!(
+            Condition::parse("price < 100"),
+            Err(ConditionError::InvalidOperand("price".to_owned()))
+        );
+        assert_eq!(
+            Condition::parse("abc.get_price() < 100"),
+            Err(ConditionError::InvalidComponentAddress("abc".to_owned()))
+        );
+        assert_eq!(
+            Condition::parse("1 < 2 3"),
+            Err(ConditionError::TrailingInput("3".to_owned()))
+        );
+        assert_eq!(
+            Condition::parse("1 ~ 2"),
+            Err(ConditionError::UnexpectedCharacter('~', 2))
+        );
+    }
+}
@@ -1,4 +1,6 @@
 mod utils;
+mod condition;
 mod callback_scheduler;
 mod dummy_component;
 mod test_component;
+mod test_time_oracle;
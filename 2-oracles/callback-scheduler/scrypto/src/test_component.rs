@@ -30,6 +30,9 @@ blueprint! {
         /// An attack must not be allowed to deposit CallbackHandles here as they would then
         /// be able to schedule arbitrary callbacks that this component would authorize!
         callback_handles: Vault,
+
+        /// A dummy price that is read by an OnCondition callback
+        price: Decimal,
     }
 
     impl TestComponent {
@@ -69,7 +72,10 @@ blueprint! {
                     && require(protector_badge.resource_address())))
                 .method("schedule_example_callbacks", rule!(require(admin_badge.resource_address())))
                 .method("cancel_callback", rule!(require(admin_badge.resource_address())))
-                .method("authorize_callback", rule!(allow_all));
+                .method("get_price", rule!(allow_all))
+                .method("set_price", rule!(require(admin_badge.resource_address())))
+                .method("authorize_callback", rule!(allow_all))
+                .method("return_callback_handle", rule!(allow_all));
 
             let component =  Self {
                 admin_badge: admin_badge.resource_address(),
@@ -79,6 +85,7 @@ blueprint! {
                 // Here we retrieve the CallbackHandle resource address from the scheduler component
                 callback_handles: Vault::new(
                     callback_scheduler::get_callback_handle_resource(scheduler_component)),
+                price: dec!("120"),
             }
             .instantiate()
             .add_access_check(rules)
@@ -124,6 +131,21 @@ blueprint! {
             (callback_handle, proofs)
         }
 
+        /// This method is part of the SchedulerComponent's API and must be implemented if recurring
+        /// callbacks are scheduled. It will be called after each execution of a recurring callback,
+        /// except for the last one, to give back the CallbackHandle that was handed out by
+        /// `authorize_callback`.
+        ///
+        /// # Arguments:
+        /// * `callback`: A proof containing a single Callback NFR
+        /// * `callback_handle`: The CallbackHandle NFR that is associated with the callback
+        pub fn return_callback_handle(&mut self, callback: Proof, callback_handle: Bucket) {
+            // Verify the returned CallbackHandle is authentic. It is VERY IMPORTANT that this
+            // method is called here!
+            Callback::verify_returned_handle(&callback, &callback_handle);
+            self.callback_handles.put(callback_handle);
+        }
+
 
         /// A dummy method for demo purposes
         ///
@@ -163,29 +185,29 @@ blueprint! {
         ///
         /// Scheduling the callbacks this way from inside the component is mainly done because it is
         /// easier than doing it through the transaction manifest
-        pub fn schedule_example_callbacks(&mut self, fee: Bucket) -> Bucket {
+        pub fn schedule_example_callbacks(&mut self, deposit: Bucket) -> Bucket {
             let current_component = Runtime::actor().component_address().unwrap();
 
             // Schedule a 1st example callback
-            let(callback_handle, fee) = CallbackRequest::new(
+            let(callback_handle, deposit) = CallbackRequest::new(
                 Trigger::AtEpoch(1), current_component, "public_method", args!("Satoshi"), None)
-                .schedule_callback(self.scheduler_component, fee);
+                .schedule_callback(self.scheduler_component, deposit);
             self.callback_handles.put(callback_handle);
             debug!("");
 
             // Schedule a 2nd example callback
-            let(callback_handle, fee) = CallbackRequest::new(
+            let(callback_handle, deposit) = CallbackRequest::new(
                 Trigger::AtEpoch(1),
                 current_component,
                 "guarded_method",
                 args!(Runtime::actor().component_address().unwrap(), dec!("1000")),
                 None)
-                .schedule_callback(self.scheduler_component, fee);
+                .schedule_callback(self.scheduler_component, deposit);
             self.callback_handles.put(callback_handle);
             debug!("");
 
             // Schedule a 3rd example callback
-            let(callback_handle, fee) = CallbackRequest::new(
+            let(callback_handle, deposit) = CallbackRequest::new(
                 Trigger::AtDateTime {
                     date_time: "2022-10-1T12:42+00:00".to_owned(),
                     tolerance_seconds: 60
@@ -194,28 +216,61 @@ blueprint! {
                 "guarded_protected_method",
                 args!(Question("What do you get if you multiply six by nine?".to_owned())),
                 None)
-                .schedule_callback(self.scheduler_component, fee);
+                .schedule_callback(self.scheduler_component, deposit);
             self.callback_handles.put(callback_handle);
             debug!("");
 
             // Schedule a 4th example callback
-            let(callback_handle, fee) = CallbackRequest::new(
+            let(callback_handle, deposit) = CallbackRequest::new(
                 Trigger::AtEpoch(10), current_component, "public_method", args!("Vitalik"), None)
-                .schedule_callback(self.scheduler_component, fee);
+                .schedule_callback(self.scheduler_component, deposit);
             self.callback_handles.put(callback_handle);
             debug!("");
 
             // Schedule a 5th example callback
-            let(callback_handle, fee) = CallbackRequest::new(
+            let(callback_handle, deposit) = CallbackRequest::new(
                 Trigger::AtEpoch(10), current_component, "public_method", args!("Sam"), None)
-                .schedule_callback(self.scheduler_component, fee);
+                .schedule_callback(self.scheduler_component, deposit);
+            self.callback_handles.put(callback_handle);
+            debug!("");
+
+            // Schedule a 6th example callback that can only be executed once the price
+            // of this component has dropped below 100
+            let(callback_handle, deposit) = CallbackRequest::new(
+                Trigger::OnCondition(format!("{}.get_price() < 100", current_component)),
+                current_component,
+                "public_method",
+                args!("Ada"),
+                None)
+                .schedule_callback(self.scheduler_component, deposit);
+            self.callback_handles.put(callback_handle);
+            debug!("");
+
+            // Schedule a 7th example callback that is executed every 2 epochs, 3 times in total
+            let(callback_handle, deposit) = CallbackRequest::new(
+                Trigger::Recurring { start_epoch: 1, interval_epochs: 2, max_executions: Some(3) },
+                current_component,
+                "public_method",
+                args!("Grace"),
+                None)
+                .schedule_callback(self.scheduler_component, deposit);
             self.callback_handles.put(callback_handle);
 
-            fee
+            deposit
+        }
+
+        /// Returns the dummy price, this is read by the 6th example callback
+        pub fn get_price(&self) -> Decimal {
+            self.price
+        }
+
+        /// Sets the dummy price
+        pub fn set_price(&mut self, price: Decimal) {
+            self.price = price;
         }
 
-        /// Cancels a callback with the given ID
-        pub fn cancel_callback(&mut self, callback_id: NonFungibleId) {
+        /// Cancels a callback with the given ID and returns the deposit that was left for it
+        pub fn cancel_callback(&mut self, callback_id: NonFungibleId) -> Bucket {
             let callback_handle = self.callback_handles.take_non_fungible(&callback_id);
             borrow_component!(self.scheduler_component)
                 .call("cancel_callbacks", args!(callback_handle))
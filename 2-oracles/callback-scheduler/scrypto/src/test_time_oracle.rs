@@ -0,0 +1,34 @@
+use scrypto::prelude::*;
+
+blueprint! {
+
+    /// This is a time oracle component for the automated test.
+    /// It has the same `get_time` interface as a real time oracle, but
+    /// the time is simply set by whoever calls `set_time`.
+    struct TestTimeOracle {
+        /// The current UNIX time in seconds
+        time: String,
+    }
+
+    impl TestTimeOracle {
+
+        /// Instantiates a new TestTimeOracle
+        pub fn instantiate_test_time_oracle() -> ComponentAddress {
+            Self {
+                time: "0".to_owned(),
+            }
+            .instantiate()
+            .globalize()
+        }
+
+        /// Returns the current UNIX time in seconds
+        pub fn get_time(&self) -> String {
+            self.time.clone()
+        }
+
+        /// Sets the current UNIX time in seconds
+        pub fn set_time(&mut self, time: String) {
+            self.time = time;
+        }
+    }
+}
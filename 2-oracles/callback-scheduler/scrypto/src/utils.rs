@@ -63,4 +63,109 @@ pub(crate)  fn debug_log_non_fungible(message: &str, non_fungible: &Bucket) {
         non_fungible.non_fungible_ids().into_iter().next().unwrap(),
         non_fungible.resource_address()
     );
-}
\ No newline at end of file
+}
+
+/// Parses an ISO 8601 date time like "2022-10-01T12:30:55+00:00" into seconds since the UNIX epoch.
+/// Seconds and the UTC offset are optional (no offset means UTC) and single digit fields are
+/// accepted, e.g. "2022-10-1T12:42+00".
+pub(crate) fn parse_date_time(date_time: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid date time '{}', expected e.g. 2022-10-01T12:30:55+00:00", date_time);
+
+    let (date, time) = date_time.split_once('T').ok_or_else(invalid)?;
+
+    // Split off the UTC offset, if there is one
+    let (time, offset_seconds) = match time.find(|c: char| c == 'Z' || c == '+' || c == '-') {
+        Some(index) => {
+            let (time, offset) = time.split_at(index);
+            (time, parse_utc_offset(offset).ok_or_else(invalid)?)
+        }
+        None => (time, 0),
+    };
+
+    let date: Vec<i64> = parse_fields(date, '-').ok_or_else(invalid)?;
+    let time: Vec<i64> = parse_fields(time, ':').ok_or_else(invalid)?;
+    if date.len() != 3 || time.len() < 2 || time.len() > 3 {
+        return Err(invalid());
+    }
+    let (year, month, day) = (date[0], date[1], date[2]);
+    let (hour, minute, second) = (time[0], time[1], *time.get(2).unwrap_or(&0));
+    if month < 1 || month > 12 || day < 1 || day > days_in_month(year, month)
+        || hour > 23 || minute > 59 || second > 59 {
+        return Err(invalid());
+    }
+
+    let seconds = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second
+        - offset_seconds;
+    if seconds < 0 {
+        return Err(format!("Date time '{}' is before 1970-01-01", date_time));
+    }
+    Ok(seconds as u64)
+}
+
+fn parse_fields(fields: &str, separator: char) -> Option<Vec<i64>> {
+    fields.split(separator)
+        .map(|field| if field.is_empty() || !field.chars().all(|c| c.is_ascii_digit()) {
+            None
+        } else {
+            field.parse().ok()
+        })
+        .collect()
+}
+
+/// Parses "Z", "+HH", "+HH:MM" or "+HHMM" (or the same with "-") into seconds
+fn parse_utc_offset(offset: &str) -> Option<i64> {
+    if offset == "Z" {
+        return Some(0);
+    }
+    let sign = if offset.starts_with('-') { -1 } else { 1 };
+    let offset = offset[1..].replace(':', "");
+    if !(offset.len() == 2 || offset.len() == 4) || !offset.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i64 = offset[..2].parse().ok()?;
+    let minutes: i64 = if offset.len() == 4 { offset[2..].parse().ok()? } else { 0 };
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Number of days since 1970-01-01 of the given date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_date_time() {
+        assert_eq!(parse_date_time("1970-01-01T00:00Z"), Ok(0));
+        assert_eq!(parse_date_time("2022-10-01T12:30:55+00"), Ok(1664627455));
+        assert_eq!(parse_date_time("2022-10-1T12:42+00:00"), Ok(1664628120));
+        assert_eq!(parse_date_time("2022-10-01T14:42+02:00"), Ok(1664628120));
+        assert_eq!(parse_date_time("2024-02-29T00:00:00"), Ok(1709164800));
+    }
+
+    #[test]
+    fn test_parse_invalid_date_time() {
+        assert!(parse_date_time("2022-10-01").is_err());
+        assert!(parse_date_time("2022-13-01T00:00").is_err());
+        assert!(parse_date_time("2023-02-29T00:00").is_err());
+        assert!(parse_date_time("2022-10-01T24:00").is_err());
+        assert!(parse_date_time("2022-10-01T12:00+1").is_err());
+        assert!(parse_date_time("1969-12-31T23:59:59Z").is_err());
+    }
+}
@@ -2,6 +2,11 @@
 #set -x
 set -e
 
+# The XRD balance of the given account
+xrd_balance () {
+    resim show $1 | sed -nr "s/.*amount: ([0-9.]+), resource address: 030000000000000000000000000000000000000000000000000004.*/\1/p"
+}
+
 # Reset the simulator
 resim reset ; echo
 
@@ -16,20 +21,33 @@ result=$(resim publish .)
 printf "$result\n\n"
 export package=$(echo "$result" | sed -nr "s/Success! New Package: ([[:alnum:]_]+)/\1/p")
 
-# Instantiate a CallbackScheduler component with a fee of 10 XRD per callback scheduling
-result=$(resim call-function $package CallbackScheduler instantiate_callback_scheduler 10)
+# Instantiate a CallbackScheduler component with a fee of 10 XRD and a keeper bounty of 2 XRD per callback execution
+result=$(resim call-function $package CallbackScheduler instantiate_callback_scheduler 10 2)
 export scheduler_component=$(echo "$result" | sed -nr "s/.*Component: ([[:alnum:]_]+)/\1/p")
 export scheduler_component_admin_badge=$(echo "$result" | sed -nr "s/.*admin_badge=([[:alnum:]_]+)/\1/p")
 export callback_admin_handle_resource=$(echo "$result" | sed -nr "s/.*callback_admin_handle_resource=([[:alnum:]_]+)/\1/p")
 export callback_handle_resource=$(echo "$result" | sed -nr "s/.*callback_handle_resource=([[:alnum:]_]+)/\1/p")
 printf "$result\n\n"
 
+# Instantiate a TestTimeOracle component and configure it as the time oracle of the CallbackScheduler
+# component. The time oracle is needed to schedule and execute callbacks with an AtDateTime trigger
+result=$(resim call-function $package TestTimeOracle instantiate_test_time_oracle)
+printf "$result\n\n"
+export time_oracle=$(echo "$result" | sed -nr "s/.*Component: ([[:alnum:]_]+)/\1/p")
+resim run transactions/test/admin/set_time_oracle.rtm ; echo
+
 # Create another account for a user of the CallbackScheduler component
 result=$(resim new-account)
 printf "$result\n\n"
 export user_private_key=$(echo "$result" | sed -nr "s/Private key: ([[:alnum:]_]+)/\1/p")
 export user_account=$(echo "$result" | sed -nr "s/Account component address: ([[:alnum:]_]+)/\1/p")
 
+# Create an account for a keeper that executes callbacks in return for a bounty
+result=$(resim new-account)
+printf "$result\n\n"
+export keeper_private_key=$(echo "$result" | sed -nr "s/Private key: ([[:alnum:]_]+)/\1/p")
+export keeper_account=$(echo "$result" | sed -nr "s/Account component address: ([[:alnum:]_]+)/\1/p")
+
 # Take on the role of the user
 resim set-default-account $user_account $user_private_key ; echo
 
@@ -40,7 +58,7 @@ printf "$result\n\n"
 export test_component=$(echo "$result" | sed -nr "s/.*Component: ([[:alnum:]_]+)/\1/p")
 export test_component_admin_badge=$(echo "$result" | sed -nr "s/.*admin_badge=([[:alnum:]_]+)/\1/p")
 
-# Schedule 5 example callbacks
+# Schedule 7 example callbacks, each callback's deposit covers all of its executions (12 XRD per execution)
 result=$(resim run transactions/test/user/schedule_example_callbacks.rtm)
 printf "$result\n\n"
 export callback_id_1=$(echo "$result" | sed -nr "s/.*CallbackAdminHandle: #([[:alnum:]_]+).*/\1/p" | sed '1!d')
@@ -48,9 +66,11 @@ export callback_id_2=$(echo "$result" | sed -nr "s/.*CallbackAdminHandle: #([[:a
 export callback_id_3=$(echo "$result" | sed -nr "s/.*CallbackAdminHandle: #([[:alnum:]_]+).*/\1/p" | sed '3!d')
 export callback_id_4=$(echo "$result" | sed -nr "s/.*CallbackAdminHandle: #([[:alnum:]_]+).*/\1/p" | sed '4!d')
 export callback_id_5=$(echo "$result" | sed -nr "s/.*CallbackAdminHandle: #([[:alnum:]_]+).*/\1/p" | sed '5!d')
+export callback_id_6=$(echo "$result" | sed -nr "s/.*CallbackAdminHandle: #([[:alnum:]_]+).*/\1/p" | sed '6!d')
+export callback_id_7=$(echo "$result" | sed -nr "s/.*CallbackAdminHandle: #([[:alnum:]_]+).*/\1/p" | sed '7!d')
 
 # Immediately cancel callback #5
-# At the moment, fees are not reimbursed.
+# Its deposit of 12 XRD is reimbursed to the user.
 resim run transactions/test/user/cancel_callback_#5.rtm ; echo
 
 # Take on the role of the CallbackScheduler admin/operator
@@ -59,39 +79,90 @@ resim set-default-account $admin_account $admin_private_key ; echo
 # Retrieve the admin handles of all newly scheduled callbacks
 resim run transactions/test/admin/get_new_callback_admin_handles.rtm ; echo
 
-# Take a look at the admin account and observe that it contains the CallbackAdminHandles for the first 4 callbacks.
+# Take a look at the admin account and observe that it contains the CallbackAdminHandles for callbacks 1-4, 6 and 7.
 # The 5th callback was canceled by the user before we could retrieve it from the scheduler component.
 resim show $admin_account ; echo
 
+# Take on the role of the keeper. Anyone may execute callbacks, each execution pays a bounty of 2 XRD
+resim set-default-account $keeper_account $keeper_private_key ; echo
+
 # Try to execute the first callback
 # Observe that it fails as the SchedulerComponent prevents us from executing the callback in the wrong epoch
-resim run transactions/test/admin/execute_callback_#1.rtm || : ; echo
+resim run transactions/test/keeper/execute_callback_#1.rtm || : ; echo
 
 # Advance the epoch and try again
 resim set-current-epoch 1
-resim run transactions/test/admin/execute_callback_#1.rtm ; echo
+resim run transactions/test/keeper/execute_callback_#1.rtm ; echo
+
+# Also execute callback #2
+resim run transactions/test/keeper/execute_callback_#2.rtm ; echo
+
+# Try to execute callback #3, which is scheduled for 2022-10-01T12:42+00:00 with a tolerance of 60 seconds
+# Observe that it fails as the time oracle still reports the UNIX time 0
+resim run transactions/test/keeper/execute_callback_#3.rtm || : ; echo
+
+# Set the time oracle to 2022-10-01T12:42:30+00:00 and try again
+resim call-method $time_oracle set_time 1664628150 ; echo
+resim run transactions/test/keeper/execute_callback_#3.rtm ; echo
+
+# Switch to the admin/operator account and cancel the 4th callback
+# The user will still be in posession of their CallbackHandle
+# but no one will be able to execute the callback anymore.
+# Observe that the admin/operator does not receive the 12 XRD deposit of the callback,
+# it is kept in the scheduler component for the user.
+resim set-default-account $admin_account $admin_private_key ; echo
+balance_before=$(xrd_balance $admin_account)
+resim run transactions/test/admin/cancel_callback_#4.rtm ; echo
+balance_after=$(xrd_balance $admin_account)
+[ "$balance_before" = "$balance_after" ] || { echo "The admin/operator must not receive the deposit of a callback"; exit 1; }
 
-# Also execute callback #2 and #3
-resim run transactions/test/admin/execute_callback_#2.rtm ; echo
-resim run transactions/test/admin/execute_callback_#3.rtm ; echo
+# Switch to the keeper account and try to execute callback #4
+# This will fail as the callback has just been canceled by the admin/operator
+resim set-default-account $keeper_account $keeper_private_key ; echo
+resim run transactions/test/keeper/execute_callback_#4.rtm || : ; echo
 
-# Switch to the user account and cancel the 4th callback
-# The admin/operator will still be in posession of their CallbackAdminHandle
-# but will no longer be able to execute the callback.
+# Also cancel the callback from the user side with the orphan CallbackHandle
+# Observe that the user now receives the 12 XRD deposit of the callback
 resim set-default-account $user_account $user_private_key ; echo
+balance_before=$(xrd_balance $user_account)
 resim run transactions/test/user/cancel_callback_#4.rtm ; echo
+balance_after=$(xrd_balance $user_account)
+awk "BEGIN { exit !($balance_after - $balance_before == 12) }" || { echo "The user must receive the deposit of the callback canceled by the admin/operator"; exit 1; }
 
-# Switch back to the admin/operator account and try to execute callback #4
-# This will fail as the callback has just been canceled by the user
-resim set-default-account $admin_account $admin_private_key ; echo
-resim run transactions/test/admin/execute_callback_#4.rtm || : ; echo
+# Try to execute callback #6, which may only be executed once the price of the test component is below 100
+# Observe that it fails as the price is still 120
+resim set-default-account $keeper_account $keeper_private_key ; echo
+resim run transactions/test/keeper/execute_callback_#6.rtm || : ; echo
 
-# Also cancel the callback from the admin/operator side
-resim run transactions/test/admin/cancel_callback_#4.rtm ; echo
+# Switch to the user account and lower the price, then execute callback #6 again
+resim set-default-account $user_account $user_private_key ; echo
+export price=80
+resim run transactions/test/user/set_price.rtm ; echo
+resim set-default-account $keeper_account $keeper_private_key ; echo
+resim run transactions/test/keeper/execute_callback_#6.rtm ; echo
+
+# Execute the recurring callback #7, which is due every 2 epochs starting at epoch 1
+resim run transactions/test/keeper/execute_callback_#7.rtm ; echo
+
+# Observe that executing it again fails as the next execution is only due at epoch 3
+resim run transactions/test/keeper/execute_callback_#7.rtm || : ; echo
+
+# Advance the epoch and execute the 2nd and 3rd (last) execution
+resim set-current-epoch 3
+resim run transactions/test/keeper/execute_callback_#7.rtm ; echo
+resim set-current-epoch 5
+resim run transactions/test/keeper/execute_callback_#7.rtm ; echo
+
+# Take a look at the keeper account and observe that it has earned 7 bounties, i.e. 14 XRD
+resim show $keeper_account ; echo
+
+# Switch to the admin/operator account and burn the CallbackAdminHandles of all executed callbacks
+resim set-default-account $admin_account $admin_private_key ; echo
+resim run transactions/test/admin/burn_executed_callback_admin_handles.rtm ; echo
 
 # Finally withdraw the earned fees and show the admin account.
 # Observe that all callback handles are now gone and the XRD
-# balance has increased by 50.
+# balance has increased by 70, i.e. 7 executions with a fee of 10 XRD.
 resim run transactions/test/admin/withdraw_fees.rtm ; echo
 resim show $admin_account
 
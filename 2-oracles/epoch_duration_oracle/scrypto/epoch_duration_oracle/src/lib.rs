@@ -1,11 +1,29 @@
 use scrypto::prelude::*;
 
+#[derive(NonFungibleData)]
+pub struct Reporter {}
+
+// Reporters needed in an epoch before their median replaces the owner ticks
+const DEFAULT_REPORTERS_QUORUM: u64 = 3;
+
 blueprint! {
     struct EpochDurationOracle {
-        epochs_duration_millis: HashMap<u64, u64>,
+        epochs_duration_millis: BTreeMap<u64, u64>,
+        epochs_drift_millis: BTreeMap<u64, u64>,
         current_epoch: u64,
         millis_in_current_epoch: u64,
 
+        // Millis of the current epoch that do not come from reporters (bootstrap and owner ticks)
+        base_millis_in_current_epoch: u64,
+
+        // Reporters
+        reporter_badge_ref: ResourceAddress,
+        reporter_minter: Vault,
+        reporters_count: u64,
+        reporters_quorum: u64,
+        removed_reporters: HashSet<NonFungibleId>,
+        reporters_millis_in_current_epoch: HashMap<NonFungibleId, u64>,
+
         // Owner
         owner_badge_ref: ResourceAddress
     }
@@ -23,16 +41,38 @@ blueprint! {
                 .metadata("name", format!("Owner of epoch duration oracle."))
                 .initial_supply(1);
 
+            // Reporter relative
+            let reporter_minter: Bucket = ResourceBuilder::new_fungible()
+                .divisibility(DIVISIBILITY_NONE)
+                .metadata("name", format!("Reporter minter of epoch duration oracle."))
+                .initial_supply(1);
+
+            let reporter_badge_ref: ResourceAddress = ResourceBuilder::new_non_fungible()
+                .metadata("name", format!("Reporter of epoch duration oracle."))
+                .mintable(rule!(require(reporter_minter.resource_address())), LOCKED)
+                .no_initial_supply();
+
             let component = Self {
-                epochs_duration_millis: HashMap::new(),
+                epochs_duration_millis: BTreeMap::new(),
+                epochs_drift_millis: BTreeMap::new(),
                 current_epoch,
                 millis_in_current_epoch,
+                base_millis_in_current_epoch: millis_in_current_epoch,
+                reporter_badge_ref,
+                reporter_minter: Vault::with_bucket(reporter_minter),
+                reporters_count: 0,
+                reporters_quorum: DEFAULT_REPORTERS_QUORUM,
+                removed_reporters: HashSet::new(),
+                reporters_millis_in_current_epoch: HashMap::new(),
                 owner_badge_ref: owner_badge.resource_address()
             }.instantiate();
 
             // Access control
             let access_rules = AccessRules::new()
                 .method("tick", rule!(require(owner_badge.resource_address())))
+                .method("register_reporter", rule!(require(owner_badge.resource_address())))
+                .method("remove_reporter", rule!(require(owner_badge.resource_address())))
+                .method("set_reporters_quorum", rule!(require(owner_badge.resource_address())))
                 .default(AccessRule::AllowAll);
 
             // Component with owner badge
@@ -41,17 +81,61 @@ blueprint! {
 
         pub fn tick(&mut self, millis_since_last_tick: u64) -> u64 {
             if self.current_epoch >= Runtime::current_epoch() {
+                self.base_millis_in_current_epoch += millis_since_last_tick;
                 self.millis_in_current_epoch += millis_since_last_tick;
             }
             else {
-                self.epochs_duration_millis.insert(self.current_epoch, self.millis_in_current_epoch + millis_since_last_tick);
-                self.current_epoch = Runtime::current_epoch();
-                self.millis_in_current_epoch = 0;
+                self.close_current_epoch(self.millis_in_current_epoch + millis_since_last_tick);
             }
 
             return self.current_epoch
         }
 
+        pub fn register_reporter(&mut self) -> Bucket {
+            let reporter_id = NonFungibleId::from_u64(self.reporters_count);
+            self.reporters_count += 1;
+
+            let reporter_badge_ref = self.reporter_badge_ref;
+            self.reporter_minter.authorize(|| {
+                borrow_resource_manager!(reporter_badge_ref).mint_non_fungible(&reporter_id, Reporter {})
+            })
+        }
+
+        pub fn remove_reporter(&mut self, reporter_id: NonFungibleId) {
+            assert!(!self.removed_reporters.contains(&reporter_id), "This reporter was already removed.");
+
+            // Its ticks no longer count, the clock falls back to the others or the owner ticks
+            self.reporters_millis_in_current_epoch.remove(&reporter_id);
+            self.removed_reporters.insert(reporter_id);
+            self.millis_in_current_epoch = self.base_millis_in_current_epoch + self.reporters_millis_in_current_epoch();
+        }
+
+        pub fn set_reporters_quorum(&mut self, reporters_quorum: u64) {
+            assert!(reporters_quorum > 0, "At least one reporter is needed to reach a quorum.");
+
+            self.reporters_quorum = reporters_quorum;
+            self.millis_in_current_epoch = self.base_millis_in_current_epoch + self.reporters_millis_in_current_epoch();
+        }
+
+        pub fn report_tick(&mut self, reporter: Proof, millis_since_last_tick: u64) -> u64 {
+            assert!(reporter.resource_address() == self.reporter_badge_ref, "Only registered reporters can report ticks.");
+            assert!(reporter.amount() == Decimal::one(), "Ticks must be reported with exactly one reporter badge.");
+            let reporter_id = reporter.non_fungible::<Reporter>().id();
+            assert!(!self.removed_reporters.contains(&reporter_id), "This reporter was removed.");
+
+            *self.reporters_millis_in_current_epoch.entry(reporter_id).or_insert(0) += millis_since_last_tick;
+            let millis_in_current_epoch = self.base_millis_in_current_epoch + self.reporters_millis_in_current_epoch();
+
+            if self.current_epoch >= Runtime::current_epoch() {
+                self.millis_in_current_epoch = millis_in_current_epoch;
+            }
+            else {
+                self.close_current_epoch(millis_in_current_epoch);
+            }
+
+            self.current_epoch
+        }
+
         pub fn millis_since_epoch(&self, epoch: u64) -> u64 {
 
             assert!(epoch <= self.current_epoch, "The requested epoch has not yet happened or was not yet registered on ledger.");
@@ -92,5 +176,107 @@ blueprint! {
 
             *elapsed
         }
+
+        pub fn current_timestamp(&self) -> u64 {
+            self.epochs_duration_millis.values().sum::<u64>() + self.millis_in_current_epoch
+        }
+
+        pub fn timestamp_at_epoch(&self, epoch: u64) -> u64 {
+
+            assert!(epoch <= self.current_epoch, "The requested epoch has not yet happened or was not yet registered on ledger.");
+
+            self.current_timestamp() - self.millis_since_epoch(epoch)
+        }
+
+        pub fn epoch_at_timestamp(&self, timestamp: u64) -> u64 {
+
+            let mut epoch_end = self.current_timestamp();
+            assert!(timestamp <= epoch_end, "The requested timestamp has not yet happened or was not yet registered on ledger.");
+
+            let mut epoch_start = epoch_end - self.millis_in_current_epoch;
+            if timestamp >= epoch_start {
+                trace!("Requested timestamp is in the current epoch");
+                return self.current_epoch
+            }
+
+            trace!("Requested timestamp is in a passed epoch");
+            for (epoch, duration) in self.epochs_duration_millis.iter().rev() {
+                epoch_end = epoch_start;
+                epoch_start = epoch_end - duration;
+                if timestamp >= epoch_start {
+                    return *epoch
+                }
+            }
+
+            // The oracle clock starts at 0, the first registered epoch holds every timestamp before it
+            *self.epochs_duration_millis.keys().next().unwrap()
+        }
+
+        pub fn drift_since_epoch(&self, epoch: u64) -> u64 {
+
+            assert!(epoch <= self.current_epoch, "The requested epoch has not yet happened or was not yet registered on ledger.");
+
+            let drift: u64 = self.epochs_drift_millis.range(epoch..)
+            .map(|(_k, v)| v)
+            .sum();
+
+            drift + self.drift_in_current_epoch()
+        }
+
+        pub fn get_reporter_badge(&self) -> ResourceAddress {
+            self.reporter_badge_ref
+        }
+
+        fn reporters_millis_in_current_epoch(&self) -> u64 {
+            if !self.has_reporters_quorum() {
+                return 0
+            }
+
+            median(self.reporters_millis_in_current_epoch.values().cloned().collect())
+        }
+
+        fn drift_in_current_epoch(&self) -> u64 {
+            if !self.has_reporters_quorum() {
+                return 0
+            }
+
+            median_absolute_deviation(self.reporters_millis_in_current_epoch.values().cloned().collect())
+        }
+
+        fn has_reporters_quorum(&self) -> bool {
+            self.reporters_millis_in_current_epoch.len() as u64 >= self.reporters_quorum
+        }
+
+        fn close_current_epoch(&mut self, duration: u64) {
+            let drift = self.drift_in_current_epoch();
+            info!("Closing epoch {} after {} millis (drift {} millis)", self.current_epoch, duration, drift);
+
+            self.epochs_duration_millis.insert(self.current_epoch, duration);
+            self.epochs_drift_millis.insert(self.current_epoch, drift);
+            self.current_epoch = Runtime::current_epoch();
+            self.millis_in_current_epoch = 0;
+            self.base_millis_in_current_epoch = 0;
+            self.reporters_millis_in_current_epoch.clear();
+        }
     }
 }
+
+fn median(mut values: Vec<u64>) -> u64 {
+    if values.is_empty() {
+        return 0
+    }
+
+    values.sort();
+    let middle = values.len() / 2;
+    if values.len() % 2 == 0 {
+        // Does not overflow, however large a dishonest reporter ticks
+        return values[middle - 1] + (values[middle] - values[middle - 1]) / 2
+    }
+
+    values[middle]
+}
+
+fn median_absolute_deviation(values: Vec<u64>) -> u64 {
+    let median_value = median(values.clone());
+    median(values.iter().map(|value| if *value > median_value { value - median_value } else { median_value - value }).collect())
+}
@@ -612,3 +612,386 @@ fn can_tick_with_bootstrap() {
     let millis_in_epoch = millis_in_epoch_receipt.outputs.get(0).unwrap().to_string();
     assert!(millis_in_epoch == "1653431603254u64");
 }
+
+#[test]
+fn can_reconcile_reporter_ticks() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (pk, sk, oracle_owner) = executor.new_account();
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let oracle_creation = TransactionBuilder::new()
+        .call_function(package, "EpochDurationOracle", "new", args!())
+        .call_method_with_all_resources(oracle_owner, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let oracle_receipt = executor.validate_and_execute(&oracle_creation).unwrap();
+    assert!(oracle_receipt.result.is_ok());
+    let oracle = oracle_receipt.new_component_addresses.get(0).unwrap();
+    let oracle_badge_address = oracle_receipt.new_resource_addresses.get(0).unwrap();
+    let reporter_badge_address = oracle_receipt.new_resource_addresses.get(2).unwrap();
+
+    // I should be able to register 3 reporters, with ids 0, 1 and 2
+    let register_reporters = TransactionBuilder::new()
+        .create_proof_from_account_by_amount(dec!(1), *oracle_badge_address, oracle_owner)
+        .call_method(*oracle, "register_reporter", args!())
+        .call_method(*oracle, "register_reporter", args!())
+        .call_method(*oracle, "register_reporter", args!())
+        .call_method_with_all_resources(oracle_owner, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let reporters_registered = executor.validate_and_execute(&register_reporters).unwrap();
+    // println!("{:?}\n", reporters_registered);
+    assert!(reporters_registered.result.is_ok());
+
+    // Each reporter ticks in epoch 0, one of them being way off
+    for (reporter_id, millis) in [(0u64, 1000u64), (1, 1100), (2, 5000)] {
+        let mut ids = BTreeSet::new();
+        ids.insert(NonFungibleId::from_u64(reporter_id));
+
+        let reporter_tick = TransactionBuilder::new()
+            .create_proof_from_account_by_ids(&ids, *reporter_badge_address, oracle_owner)
+            .create_proof_from_auth_zone_by_ids(&ids, *reporter_badge_address, |builder, proof_id| {
+                builder.call_method(*oracle, "report_tick", args![scrypto::resource::Proof(proof_id), millis])
+            })
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+
+        let reporter_ticked = executor.validate_and_execute(&reporter_tick).unwrap();
+        // println!("{:?}\n", reporter_ticked);
+        assert!(reporter_ticked.result.is_ok());
+    }
+
+    // The duration of epoch 0 is the median of the reporters ticks
+    let get_millis_in_epoch = TransactionBuilder::new()
+        .call_method(*oracle, "millis_in_epoch", args!(0u64))
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let millis_in_epoch_receipt = executor.validate_and_execute(&get_millis_in_epoch).unwrap();
+    assert!(millis_in_epoch_receipt.result.is_ok());
+    let millis_in_epoch = millis_in_epoch_receipt.outputs.get(0).unwrap().to_string();
+    assert!(millis_in_epoch == "1100u64");
+
+    // And the drift is the median deviation from it, the outlier does not weigh on it
+    let get_drift_since_epoch = TransactionBuilder::new()
+        .call_method(*oracle, "drift_since_epoch", args!(0u64))
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let drift_since_epoch_receipt = executor.validate_and_execute(&get_drift_since_epoch).unwrap();
+    assert!(drift_since_epoch_receipt.result.is_ok());
+    let drift_since_epoch = drift_since_epoch_receipt.outputs.get(0).unwrap().to_string();
+    assert!(drift_since_epoch == "100u64");
+
+    // The owner badge is not a reporter badge
+    let owner_tick = TransactionBuilder::new()
+        .create_proof_from_account_by_amount(dec!(1), *oracle_badge_address, oracle_owner)
+        .create_proof_from_auth_zone(*oracle_badge_address, |builder, proof_id| {
+            builder.call_method(*oracle, "report_tick", args![scrypto::resource::Proof(proof_id), 1000u64])
+        })
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let owner_ticked = executor.validate_and_execute(&owner_tick).unwrap();
+    assert!(owner_ticked.result.is_err());
+
+    // An epoch happens on ledger
+    executor.substate_store_mut().set_epoch(1);
+
+    // The first reporter tick of epoch 1 closes epoch 0
+    let mut ids = BTreeSet::new();
+    ids.insert(NonFungibleId::from_u64(0));
+    let reporter_tick = TransactionBuilder::new()
+        .create_proof_from_account_by_ids(&ids, *reporter_badge_address, oracle_owner)
+        .create_proof_from_auth_zone_by_ids(&ids, *reporter_badge_address, |builder, proof_id| {
+            builder.call_method(*oracle, "report_tick", args![scrypto::resource::Proof(proof_id), 200u64])
+        })
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let reporter_ticked = executor.validate_and_execute(&reporter_tick).unwrap();
+    assert!(reporter_ticked.result.is_ok());
+    let ticked_epoch = reporter_ticked.outputs.get(2).unwrap().to_string();
+    assert!(ticked_epoch == "1u64");
+
+    // Epoch 0 lasted the median of 1200, 1100 and 5000 millis
+    let get_millis_in_epoch = TransactionBuilder::new()
+        .call_method(*oracle, "millis_in_epoch", args!(0u64))
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let millis_in_epoch_receipt = executor.validate_and_execute(&get_millis_in_epoch).unwrap();
+    assert!(millis_in_epoch_receipt.result.is_ok());
+    let millis_in_epoch = millis_in_epoch_receipt.outputs.get(0).unwrap().to_string();
+    assert!(millis_in_epoch == "1200u64");
+
+    // With the same drift, nothing was reported in epoch 1 yet
+    let drift_since_epoch_receipt = executor.validate_and_execute(&get_drift_since_epoch).unwrap();
+    assert!(drift_since_epoch_receipt.result.is_ok());
+    let drift_since_epoch = drift_since_epoch_receipt.outputs.get(0).unwrap().to_string();
+    assert!(drift_since_epoch == "100u64");
+}
+
+#[test]
+fn can_convert_timestamps_and_epochs() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (pk, sk, oracle_owner) = executor.new_account();
+    let package = executor.publish_package(compile_package!()).unwrap();
+    executor.substate_store_mut().set_epoch(631);
+
+    // Bootstrap with the unix timestamp in millis, so that the oracle clock is a wall clock
+    let oracle_creation = TransactionBuilder::new()
+        .call_function(
+            package,
+            "EpochDurationOracle",
+            "new_with_bootstrap",
+            args!(631u64, 1653431602254u64),
+        )
+        .call_method_with_all_resources(oracle_owner, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let oracle_receipt = executor.validate_and_execute(&oracle_creation).unwrap();
+    assert!(oracle_receipt.result.is_ok());
+    let oracle = oracle_receipt.new_component_addresses.get(0).unwrap();
+    let oracle_badge_address = oracle_receipt.new_resource_addresses.get(0).unwrap();
+
+    // Epoch 631 lasts 1500 more millis, then 2000 millis are spent in epoch 632
+    for (epoch, millis) in [(631u64, 1000u64), (632, 500), (632, 2000)] {
+        executor.substate_store_mut().set_epoch(epoch);
+        let oracle_tick = TransactionBuilder::new()
+            .create_proof_from_account_by_amount(dec!(1), *oracle_badge_address, oracle_owner)
+            .call_method(*oracle, "tick", args!(millis))
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+
+        let oracle_ticked = executor.validate_and_execute(&oracle_tick).unwrap();
+        assert!(oracle_ticked.result.is_ok());
+    }
+
+    // The oracle clock is now at 1653431605754
+    let get_current_timestamp = TransactionBuilder::new()
+        .call_method(*oracle, "current_timestamp", args!())
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let current_timestamp_receipt = executor.validate_and_execute(&get_current_timestamp).unwrap();
+    assert!(current_timestamp_receipt.result.is_ok());
+    let current_timestamp = current_timestamp_receipt.outputs.get(0).unwrap().to_string();
+    assert!(current_timestamp == "1653431605754u64");
+
+    // Epoch 632 started when epoch 631 ended
+    let get_timestamp_at_epoch = TransactionBuilder::new()
+        .call_method(*oracle, "timestamp_at_epoch", args!(632u64))
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let timestamp_at_epoch_receipt = executor.validate_and_execute(&get_timestamp_at_epoch).unwrap();
+    assert!(timestamp_at_epoch_receipt.result.is_ok());
+    let timestamp_at_epoch = timestamp_at_epoch_receipt.outputs.get(0).unwrap().to_string();
+    assert!(timestamp_at_epoch == "1653431603754u64");
+
+    // Timestamps are converted back to the epoch that contained them
+    for (timestamp, epoch) in [
+        (0u64, "631u64"),
+        (1653431603753, "631u64"),
+        (1653431603754, "632u64"),
+        (1653431605754, "632u64"),
+    ] {
+        let get_epoch_at_timestamp = TransactionBuilder::new()
+            .call_method(*oracle, "epoch_at_timestamp", args!(timestamp))
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+
+        let epoch_at_timestamp_receipt = executor.validate_and_execute(&get_epoch_at_timestamp).unwrap();
+        assert!(epoch_at_timestamp_receipt.result.is_ok());
+        let epoch_at_timestamp = epoch_at_timestamp_receipt.outputs.get(0).unwrap().to_string();
+        assert!(epoch_at_timestamp == epoch);
+    }
+
+    // But timestamps after the last tick are not known yet
+    let get_epoch_at_timestamp = TransactionBuilder::new()
+        .call_method(*oracle, "epoch_at_timestamp", args!(1653431605755u64))
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let epoch_at_timestamp_receipt = executor.validate_and_execute(&get_epoch_at_timestamp).unwrap();
+    assert!(epoch_at_timestamp_receipt.result.is_err());
+    let (_, error_message) = epoch_at_timestamp_receipt.logs.get(0).unwrap();
+    assert!(error_message
+        .contains("The requested timestamp has not yet happened or was not yet registered on ledger."));
+
+    // A single trusted ticker has no drift
+    let get_drift_since_epoch = TransactionBuilder::new()
+        .call_method(*oracle, "drift_since_epoch", args!(631u64))
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let drift_since_epoch_receipt = executor.validate_and_execute(&get_drift_since_epoch).unwrap();
+    assert!(drift_since_epoch_receipt.result.is_ok());
+    let drift_since_epoch = drift_since_epoch_receipt.outputs.get(0).unwrap().to_string();
+    assert!(drift_since_epoch == "0u64");
+}
+
+#[test]
+fn can_require_reporters_quorum_and_remove_reporters() {
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (pk, sk, oracle_owner) = executor.new_account();
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let oracle_creation = TransactionBuilder::new()
+        .call_function(package, "EpochDurationOracle", "new", args!())
+        .call_method_with_all_resources(oracle_owner, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let oracle_receipt = executor.validate_and_execute(&oracle_creation).unwrap();
+    assert!(oracle_receipt.result.is_ok());
+    let oracle = oracle_receipt.new_component_addresses.get(0).unwrap();
+    let oracle_badge_address = oracle_receipt.new_resource_addresses.get(0).unwrap();
+    let reporter_badge_address = oracle_receipt.new_resource_addresses.get(2).unwrap();
+
+    // I should be able to register 4 reporters, with ids 0 to 3
+    let register_reporters = TransactionBuilder::new()
+        .create_proof_from_account_by_amount(dec!(1), *oracle_badge_address, oracle_owner)
+        .call_method(*oracle, "register_reporter", args!())
+        .call_method(*oracle, "register_reporter", args!())
+        .call_method(*oracle, "register_reporter", args!())
+        .call_method(*oracle, "register_reporter", args!())
+        .call_method_with_all_resources(oracle_owner, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let reporters_registered = executor.validate_and_execute(&register_reporters).unwrap();
+    assert!(reporters_registered.result.is_ok());
+
+    // The owner ticks 500 millis in epoch 0
+    let oracle_tick = TransactionBuilder::new()
+        .create_proof_from_account_by_amount(dec!(1), *oracle_badge_address, oracle_owner)
+        .call_method(*oracle, "tick", args!(500u64))
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let oracle_ticked = executor.validate_and_execute(&oracle_tick).unwrap();
+    assert!(oracle_ticked.result.is_ok());
+
+    let get_millis_in_epoch = TransactionBuilder::new()
+        .call_method(*oracle, "millis_in_epoch", args!(0u64))
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let get_drift_since_epoch = TransactionBuilder::new()
+        .call_method(*oracle, "drift_since_epoch", args!(0u64))
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    // Reporters tick in epoch 0, the clock follows them once 3 of them reported
+    for (reporter_id, millis, expected_millis, expected_drift) in [
+        (0u64, 1000u64, "500u64", "0u64"),
+        (1, 1100, "500u64", "0u64"),
+        (2, 5000, "1600u64", "100u64"),
+    ] {
+        let mut ids = BTreeSet::new();
+        ids.insert(NonFungibleId::from_u64(reporter_id));
+
+        let reporter_tick = TransactionBuilder::new()
+            .create_proof_from_account_by_ids(&ids, *reporter_badge_address, oracle_owner)
+            .create_proof_from_auth_zone_by_ids(&ids, *reporter_badge_address, |builder, proof_id| {
+                builder.call_method(*oracle, "report_tick", args![scrypto::resource::Proof(proof_id), millis])
+            })
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+
+        let reporter_ticked = executor.validate_and_execute(&reporter_tick).unwrap();
+        assert!(reporter_ticked.result.is_ok());
+
+        let millis_in_epoch_receipt = executor.validate_and_execute(&get_millis_in_epoch).unwrap();
+        assert!(millis_in_epoch_receipt.result.is_ok());
+        let millis_in_epoch = millis_in_epoch_receipt.outputs.get(0).unwrap().to_string();
+        assert!(millis_in_epoch == expected_millis);
+
+        let drift_since_epoch_receipt = executor.validate_and_execute(&get_drift_since_epoch).unwrap();
+        assert!(drift_since_epoch_receipt.result.is_ok());
+        let drift_since_epoch = drift_since_epoch_receipt.outputs.get(0).unwrap().to_string();
+        assert!(drift_since_epoch == expected_drift);
+    }
+
+    // Only the owner can remove a reporter
+    let remove_reporter = TransactionBuilder::new()
+        .call_method(*oracle, "remove_reporter", args!(NonFungibleId::from_u64(2)))
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let reporter_removed = executor.validate_and_execute(&remove_reporter).unwrap();
+    assert!(reporter_removed.result.is_err());
+
+    // Once the dishonest reporter is removed, the quorum is lost and the clock falls back to the owner ticks
+    let remove_reporter = TransactionBuilder::new()
+        .create_proof_from_account_by_amount(dec!(1), *oracle_badge_address, oracle_owner)
+        .call_method(*oracle, "remove_reporter", args!(NonFungibleId::from_u64(2)))
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let reporter_removed = executor.validate_and_execute(&remove_reporter).unwrap();
+    assert!(reporter_removed.result.is_ok());
+
+    let millis_in_epoch_receipt = executor.validate_and_execute(&get_millis_in_epoch).unwrap();
+    assert!(millis_in_epoch_receipt.result.is_ok());
+    let millis_in_epoch = millis_in_epoch_receipt.outputs.get(0).unwrap().to_string();
+    assert!(millis_in_epoch == "500u64");
+
+    // And its badge can no longer report ticks
+    let mut ids = BTreeSet::new();
+    ids.insert(NonFungibleId::from_u64(2));
+    let removed_reporter_tick = TransactionBuilder::new()
+        .create_proof_from_account_by_ids(&ids, *reporter_badge_address, oracle_owner)
+        .create_proof_from_auth_zone_by_ids(&ids, *reporter_badge_address, |builder, proof_id| {
+            builder.call_method(*oracle, "report_tick", args![scrypto::resource::Proof(proof_id), 5000u64])
+        })
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let removed_reporter_ticked = executor.validate_and_execute(&removed_reporter_tick).unwrap();
+    assert!(removed_reporter_ticked.result.is_err());
+    let (_, error_message) = removed_reporter_ticked.logs.get(0).unwrap();
+    assert!(error_message.contains("This reporter was removed."));
+
+    // Another reporter restores the quorum, epoch 0 lasted 500 millis plus the median of 1000, 1100 and 1050 millis
+    let mut ids = BTreeSet::new();
+    ids.insert(NonFungibleId::from_u64(3));
+    let reporter_tick = TransactionBuilder::new()
+        .create_proof_from_account_by_ids(&ids, *reporter_badge_address, oracle_owner)
+        .create_proof_from_auth_zone_by_ids(&ids, *reporter_badge_address, |builder, proof_id| {
+            builder.call_method(*oracle, "report_tick", args![scrypto::resource::Proof(proof_id), 1050u64])
+        })
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let reporter_ticked = executor.validate_and_execute(&reporter_tick).unwrap();
+    assert!(reporter_ticked.result.is_ok());
+
+    let millis_in_epoch_receipt = executor.validate_and_execute(&get_millis_in_epoch).unwrap();
+    assert!(millis_in_epoch_receipt.result.is_ok());
+    let millis_in_epoch = millis_in_epoch_receipt.outputs.get(0).unwrap().to_string();
+    assert!(millis_in_epoch == "1550u64");
+
+    // The owner can raise the quorum above the reporters count, the clock then falls back to the owner ticks
+    let set_reporters_quorum = TransactionBuilder::new()
+        .create_proof_from_account_by_amount(dec!(1), *oracle_badge_address, oracle_owner)
+        .call_method(*oracle, "set_reporters_quorum", args!(4u64))
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+
+    let reporters_quorum_set = executor.validate_and_execute(&set_reporters_quorum).unwrap();
+    assert!(reporters_quorum_set.result.is_ok());
+
+    let millis_in_epoch_receipt = executor.validate_and_execute(&get_millis_in_epoch).unwrap();
+    assert!(millis_in_epoch_receipt.result.is_ok());
+    let millis_in_epoch = millis_in_epoch_receipt.outputs.get(0).unwrap().to_string();
+    assert!(millis_in_epoch == "500u64");
+}
@@ -0,0 +1,88 @@
+// Off-chain VRF prover for the VrfOracleContract.
+//
+// Usage:
+//   vrf-prover keygen
+//   vrf-prover public-key <secret key hex>
+//   vrf-prover prove <secret key hex> <input seed hex> [vrf component address]
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::process;
+
+use vrf_oracle_dekentz::prover;
+
+fn usage() -> ! {
+    eprintln!("Usage:");
+    eprintln!("  vrf-prover keygen");
+    eprintln!("  vrf-prover public-key <secret key hex>");
+    eprintln!("  vrf-prover prove <secret key hex> <input seed hex> [vrf component address]");
+    process::exit(1);
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("Error: {}", message);
+    process::exit(1);
+}
+
+fn decode_hex(name: &str, hex_string: &str) -> Vec<u8> {
+    hex::decode(hex_string).unwrap_or_else(|_| fail(&format!("{} is not a hex string", name)))
+}
+
+fn keygen() {
+    let mut urandom = File::open("/dev/urandom").unwrap_or_else(|_| fail("can't open /dev/urandom"));
+    let sk_bytes = loop {
+        let mut random_bytes = [0u8; 32];
+        urandom
+            .read_exact(&mut random_bytes)
+            .unwrap_or_else(|_| fail("can't read /dev/urandom"));
+        if let Some(sk_bytes) = prover::secret_key_from_random_bytes(random_bytes) {
+            break sk_bytes;
+        }
+    };
+    let pk_bytes = prover::public_key_from_secret_key(&sk_bytes).unwrap();
+    println!("secret key: {}", hex::encode(sk_bytes));
+    println!("public key: {}", hex::encode(pk_bytes));
+}
+
+fn public_key(sk_hex_string: &str) {
+    let sk_bytes = decode_hex("secret key", sk_hex_string);
+    let pk_bytes = prover::public_key_from_secret_key(&sk_bytes).unwrap_or_else(|err| fail(&err.to_string()));
+    println!("{}", hex::encode(pk_bytes));
+}
+
+// The input seed is the receipt id that the VrfOracleContract logs for the request.
+fn prove(sk_hex_string: &str, alpha_hex_string: &str, component: Option<&String>) {
+    let sk_bytes = decode_hex("secret key", sk_hex_string);
+    let alpha_bytes = decode_hex("input seed", alpha_hex_string);
+
+    let p256_vrf = prover::p256_sha256_sswu();
+    let pi_bytes = p256_vrf
+        .ecvrf_prove(&sk_bytes, &alpha_bytes)
+        .unwrap_or_else(|err| fail(&err.to_string()));
+    let beta_bytes = p256_vrf.ecvrf_proof_to_beta(&pi_bytes).unwrap();
+
+    let alpha_hex_string = hex::encode(alpha_bytes);
+    let pi_hex_string = hex::encode(pi_bytes);
+    println!("proof: {}", pi_hex_string);
+    println!("random bytes: {}", hex::encode(beta_bytes));
+    println!("fullfill_randomness_request arguments:");
+    println!("\"{}\" \"{}\"", alpha_hex_string, pi_hex_string);
+    if let Some(component) = component {
+        println!("manifest:");
+        println!(
+            "CALL_METHOD ComponentAddress(\"{}\") \"fullfill_randomness_request\" \"{}\" \"{}\";",
+            component, alpha_hex_string, pi_hex_string
+        );
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+        ["keygen"] => keygen(),
+        ["public-key", sk] => public_key(sk),
+        ["prove", sk, alpha] => prove(sk, alpha, None),
+        ["prove", sk, alpha, _] => prove(sk, alpha, args.get(3)),
+        _ => usage(),
+    }
+}
@@ -0,0 +1,66 @@
+use scrypto::prelude::*;
+
+// Example consumer that has random bytes delivered by a VrfOracleContract callback instead of fetching them.
+blueprint! {
+    struct VrfConsumer {
+        vrf_oracle: ComponentAddress,
+        // Receipt resource of the oracle, fetched on instantiation as the oracle can't be called back during a callback.
+        receipt_resource: ResourceAddress,
+        randomness: HashMap<NonFungibleId, Vec<u8>>,
+    }
+
+    impl VrfConsumer {
+        pub fn new(vrf_oracle: ComponentAddress) -> ComponentAddress {
+            let receipt_resource: ResourceAddress =
+                borrow_component!(vrf_oracle).call("get_receipt_resource", args![]);
+
+            Self {
+                vrf_oracle,
+                receipt_resource,
+                randomness: HashMap::new(),
+            }
+            .instantiate()
+            .globalize()
+        }
+
+        // Request random bytes and register receive_randomness as callback, returns the request id.
+        pub fn request_randomness(&mut self, payment: Bucket) -> NonFungibleId {
+            let receipt: Bucket =
+                borrow_component!(self.vrf_oracle).call("request_randomness", args![payment]);
+            self.register_callback(receipt)
+        }
+
+        // Same as request_randomness, but with a known seed. DO NOT USE IN PRODUCTION.
+        pub fn request_randomness_with_seed(&mut self, payment: Bucket, input_bytes: String) -> NonFungibleId {
+            let receipt: Bucket = borrow_component!(self.vrf_oracle)
+                .call("request_randomness_with_seed", args![payment, input_bytes]);
+            self.register_callback(receipt)
+        }
+
+        // Callback of the oracle, the proof of the receipt shows that the caller is the oracle.
+        pub fn receive_randomness(&mut self, receipt: Proof, random_bytes: Vec<u8>) {
+            assert!(
+                receipt.resource_address() == self.receipt_resource && receipt.amount() == Decimal::ONE,
+                "Invalid receipt"
+            );
+            let request_id = receipt.non_fungible_ids().into_iter().next().unwrap();
+            info!("Received random bytes {:x?} for request {}", random_bytes, request_id);
+            self.randomness.insert(request_id, random_bytes);
+        }
+
+        pub fn get_randomness(&self, request_id: NonFungibleId) -> Vec<u8> {
+            assert!(self.randomness.contains_key(&request_id), "Randomness not yet received");
+            self.randomness[&request_id].clone()
+        }
+
+        fn register_callback(&self, receipt: Bucket) -> NonFungibleId {
+            let request_id = receipt.non_fungible_ids().into_iter().next().unwrap();
+            let component = Runtime::actor().component_address().unwrap();
+            borrow_component!(self.vrf_oracle).call::<()>(
+                "register_callback",
+                args![receipt, component, "receive_randomness".to_string()],
+            );
+            request_id
+        }
+    }
+}
@@ -7,6 +7,10 @@ use sha2::{Digest, Sha256};
 
 use thiserror::Error;
 
+mod consumer;
+#[cfg(feature = "cli")]
+pub mod prover;
+
 #[derive(NonFungibleData)]
 struct MemberData {}
 
@@ -21,6 +25,8 @@ pub enum VrfError {
     VerifyError,
     #[error("Elliptic curve error")]
     EncodeToCurveError,
+    #[error("Secret Key")]
+    SecretKeyError,
 }
 
 pub struct EcvrfCiphersuite {
@@ -28,6 +34,11 @@ pub struct EcvrfCiphersuite {
     h2c_suite_id_string: Vec<u8>,
 }
 
+// The ECVRF-P256-SHA256-SSWU ciphersuite that the VrfOracleContract verifies proofs with.
+pub fn p256_sha256_sswu() -> EcvrfCiphersuite {
+    EcvrfCiphersuite::new(0x02, b"P256_XMD:SHA-256_SSWU_NU_")
+}
+
 impl EcvrfCiphersuite {
     pub fn new(suite_string: u8, h2c_suite_id_string: &[u8]) -> Self {
         Self {
@@ -231,6 +242,29 @@ impl EcvrfCiphersuite {
     }
 }
 
+// The number of epochs after which any registered provider may fulfill a request,
+// if the provider it was assigned to has not done so.
+const DEFAULT_REASSIGN_AFTER_EPOCHS: u64 = 1;
+
+// Decode a hex string into the bytes of a NistP256 public key, panics if it is not a valid key.
+fn decode_public_key(pk_hex_string: &str) -> Vec<u8> {
+    let pk_bytes = hex::decode(pk_hex_string);
+    assert!(pk_bytes.is_ok(), "Public key hex string decode error");
+    let pk_bytes = pk_bytes.unwrap();
+    assert!(
+        PublicKey::from_sec1_bytes(&pk_bytes).is_ok(),
+        "Public key is not a valid P256 point"
+    );
+    pk_bytes
+}
+
+#[derive(scrypto::Encode, scrypto::Decode, scrypto::TypeId, scrypto::Describe)]
+pub struct PendingRequest {
+    // Public key of the provider the request was assigned to
+    provider: Vec<u8>,
+    requested_at: u64,
+}
+
 blueprint! {
     struct VrfOracleContract {
         fee_vault: Vault,
@@ -238,16 +272,30 @@ blueprint! {
         owner_badge: ResourceAddress,
         receipt_minter: Vault,
         receipt_nft_address: ResourceAddress,
-        pk_bytes: Vec<u8>,
+        // Public keys of the off-chain oracle nodes, requests are assigned round-robin.
+        providers: Vec<Vec<u8>>,
+        next_provider: u64,
+        reassign_after: u64,
+        pending_requests: HashMap<NonFungibleId, PendingRequest>,
+        // Registered callbacks (component, method) and the receipts that were handed over for them.
+        callbacks: HashMap<NonFungibleId, (ComponentAddress, String)>,
+        callback_receipts: Vault,
         counter: u64,
     }
 
     impl VrfOracleContract {
         // When creating new oracle contract, provide the public key of the off-chain oracle node to store for VRF verification.
         pub fn new(pk_hex_string: String) -> (ComponentAddress, Bucket) {
-            let pk_bytes = hex::decode(pk_hex_string);
-            assert!(pk_bytes.is_ok(), "Public key hex string decode error");
-            let pk_bytes = pk_bytes.unwrap();
+            Self::new_with_providers(vec![pk_hex_string])
+        }
+
+        // Same as new, but with the public keys of several off-chain oracle nodes.
+        pub fn new_with_providers(pk_hex_strings: Vec<String>) -> (ComponentAddress, Bucket) {
+            assert!(!pk_hex_strings.is_empty(), "At least one provider public key is required");
+            let providers: Vec<Vec<u8>> = pk_hex_strings
+                .iter()
+                .map(|pk_hex_string| decode_public_key(pk_hex_string))
+                .collect();
 
             let owner_badges = ResourceBuilder::new_fungible()
                 .divisibility(DIVISIBILITY_NONE)
@@ -276,7 +324,12 @@ blueprint! {
                 owner_badge: owner_badges.resource_address(),
                 receipt_minter: Vault::with_bucket(receipt_minter_badge),
                 receipt_nft_address: receipt_nft_address,
-                pk_bytes,
+                providers,
+                next_provider: 0,
+                reassign_after: DEFAULT_REASSIGN_AFTER_EPOCHS,
+                pending_requests: HashMap::new(),
+                callbacks: HashMap::new(),
+                callback_receipts: Vault::new(receipt_nft_address),
                 counter: 0,
             }
             .instantiate();
@@ -286,6 +339,18 @@ blueprint! {
                     "withdraw_all",
                     rule!(require(owner_badges.resource_address())),
                 )
+                .method(
+                    "register_provider",
+                    rule!(require(owner_badges.resource_address())),
+                )
+                .method(
+                    "remove_provider",
+                    rule!(require(owner_badges.resource_address())),
+                )
+                .method(
+                    "set_reassign_after",
+                    rule!(require(owner_badges.resource_address())),
+                )
                 .default(rule!(allow_all));
 
             (
@@ -298,6 +363,35 @@ blueprint! {
             self.fee_vault.take_all()
         }
 
+        // Add the public key of another off-chain oracle node, it takes part in the round-robin assignment of new requests.
+        pub fn register_provider(&mut self, pk_hex_string: String) {
+            let pk_bytes = decode_public_key(&pk_hex_string);
+            assert!(!self.providers.contains(&pk_bytes), "Provider already registered");
+            self.providers.push(pk_bytes);
+        }
+
+        // Remove the public key of an off-chain oracle node. Its pending requests may be fulfilled by any other provider.
+        pub fn remove_provider(&mut self, pk_hex_string: String) {
+            let pk_bytes = decode_public_key(&pk_hex_string);
+            assert!(self.providers.contains(&pk_bytes), "Provider not registered");
+            assert!(self.providers.len() > 1, "Can't remove the last provider");
+            self.providers.retain(|provider| *provider != pk_bytes);
+        }
+
+        // Set the number of epochs after which any provider may fulfill a request that the assigned provider has not fulfilled.
+        pub fn set_reassign_after(&mut self, epochs: u64) {
+            self.reassign_after = epochs;
+        }
+
+        pub fn get_providers(&self) -> Vec<String> {
+            self.providers.iter().map(hex::encode).collect()
+        }
+
+        // Consumers receiving callbacks can check that the proof they are called with is of this resource.
+        pub fn get_receipt_resource(&self) -> ResourceAddress {
+            self.receipt_nft_address
+        }
+
         // This function can only be tested manually be copy and pasting the input seed to the off-chain vrf prover.
         // See request_randomness_by_counter for deterministic seed generation for automated tests.
         pub fn request_randomness(&mut self, payment: Bucket) -> Bucket {
@@ -305,25 +399,8 @@ blueprint! {
             self.fee_vault.put(payment);
 
             // mint new non-fungible receipt as job id to return to requestor
-            let receipt = self.receipt_minter.authorize(|| {
-                let receipt_nft_manager: &ResourceManager =
-                    borrow_resource_manager!(self.receipt_nft_address);
-
-                // Would be nice to have a description of the NFT receipt
-                receipt_nft_manager.mint_non_fungible(&NonFungibleId::random(), MemberData {})
-            });
-
-            let receipt_id = receipt.non_fungible::<MemberData>().id();
-
             // use random receipt id as alpha input seed
-            let alpha = receipt_id.clone();
-            // make off-chain oracle node request for random number
-            info!(
-                "Requesting VRF with input {} for receipt {}",
-                alpha, receipt_id
-            );
-
-            receipt
+            self.mint_receipt(NonFungibleId::random())
         }
 
         // Deterministic seed generation. Provided to make unit testing easier for scrypto oracles challenge.
@@ -331,33 +408,10 @@ blueprint! {
         pub fn request_randomness_by_counter(&mut self, payment: Bucket) -> Bucket {
             // take payment and add to fee_vault
             self.fee_vault.put(payment);
-            // generate UUID as random input seed and use as jobId
-            // let alpha = Runtime::generate_uuid();
-            // self.jobs.insert(alpha, caller);
-            // make off-chain oracle node request for random number
-            // info!("Requesting VRF with input {} for caller component {}", alpha, caller);
 
             // mint new non-fungible receipt
-            let receipt = self.receipt_minter.authorize(|| {
-                let receipt_nft_manager: &ResourceManager =
-                    borrow_resource_manager!(self.receipt_nft_address);
-
-                // Would be nice to have a description of the NFT receipt
-                receipt_nft_manager
-                    .mint_non_fungible(&NonFungibleId::from_u64(self.counter), MemberData {})
-            });
+            let receipt = self.mint_receipt(NonFungibleId::from_u64(self.counter));
             self.counter += 1;
-
-            let receipt_id = receipt.non_fungible::<MemberData>().id();
-
-            // use random receipt id as alpha input seed
-            let alpha = receipt_id.clone();
-            // make off-chain oracle node request for random number
-            info!(
-                "Requesting VRF with input {} for receipt {}",
-                alpha, receipt_id
-            );
-
             receipt
         }
 
@@ -366,39 +420,35 @@ blueprint! {
         pub fn request_randomness_with_seed(&mut self, payment: Bucket, input_bytes: String) -> Bucket {
             // take payment and add to fee_vault
             self.fee_vault.put(payment);
-            // generate UUID as random input seed and use as jobId
-            // let alpha = Runtime::generate_uuid();
-            // self.jobs.insert(alpha, caller);
-            // make off-chain oracle node request for random number
-            // info!("Requesting VRF with input {} for caller component {}", alpha, caller);
 
             // mint new non-fungible receipt
-            let receipt = self.receipt_minter.authorize(|| {
-                let receipt_nft_manager: &ResourceManager =
-                    borrow_resource_manager!(self.receipt_nft_address);
-
-                // Would be nice to have a description of the NFT receipt
-                receipt_nft_manager
-                    // .mint_non_fungible(&NonFungibleId::from_u64(self.counter), MemberData {})
-                    .mint_non_fungible(&NonFungibleId::from_bytes(hex::decode(input_bytes).unwrap()), MemberData {})
-            });
+            let receipt = self.mint_receipt(NonFungibleId::from_bytes(hex::decode(input_bytes).unwrap()));
             self.counter += 1;
+            receipt
+        }
 
-            let receipt_id = receipt.non_fungible::<MemberData>().id();
-
-            // use random receipt id as alpha input seed
-            let alpha = receipt_id.clone();
-            // make off-chain oracle node request for random number
-            info!(
-                "Requesting VRF with input {} for receipt {}",
-                alpha, receipt_id
+        // Hand over the receipt to have the random bytes delivered to the given component method instead of fetching them.
+        // The method is called with a proof of the receipt and the random bytes, (Proof, Vec<u8>), as soon as the request is fulfilled.
+        // If the request was already fulfilled, the method is called right away.
+        pub fn register_callback(&mut self, receipt_badge: Bucket, component: ComponentAddress, method: String) {
+            assert!(
+                receipt_badge.amount() == Decimal::ONE && receipt_badge.resource_address() == self.receipt_nft_address,
+                "Only one receipt allowed per callback"
             );
+            let receipt_id = receipt_badge.non_fungible::<MemberData>().id();
 
-            receipt
+            match self.jobs.remove(&receipt_id) {
+                Some(random_bytes) => self.deliver_randomness(receipt_badge, component, method, random_bytes),
+                None => {
+                    self.callbacks.insert(receipt_id, (component, method));
+                    self.callback_receipts.put(receipt_badge);
+                }
+            }
         }
 
-
         // Function for the off-chain oracle to call to provide vrf proof for given input seed alpha.
+        // The proof must be made by the provider the request was assigned to, unless that provider was removed or
+        // has not fulfilled the request within reassign_after epochs. Then a proof of any registered provider is accepted.
         pub fn fullfill_randomness_request(
             &mut self,
             alpha_hex_string: String,
@@ -412,16 +462,39 @@ blueprint! {
             assert!(proof_bytes.is_ok(), "proof hex string decode error");
             let proof_bytes = proof_bytes.unwrap();
 
-            let p256_vrf = EcvrfCiphersuite::new(0x02, b"P256_XMD:SHA-256_SSWU_NU_");
-            let randomness = p256_vrf.ecvrf_verify(&self.pk_bytes, &alpha_bytes, &proof_bytes);
-            assert!(randomness.is_ok(), "Verify proof failed");
+            let job_id = NonFungibleId::from_bytes(alpha_bytes.clone());
+            let request = self.pending_requests.remove(&job_id);
+            assert!(request.is_some(), "No pending request for input seed");
+            let request = request.unwrap();
+
+            let pk_candidates: Vec<&Vec<u8>> = if self.providers.contains(&request.provider)
+                && Runtime::current_epoch() < request.requested_at + self.reassign_after
+            {
+                vec![&request.provider]
+            } else {
+                self.providers.iter().collect()
+            };
+
+            let p256_vrf = p256_sha256_sswu();
+            let randomness = pk_candidates
+                .iter()
+                .find_map(|pk_bytes| p256_vrf.ecvrf_verify(pk_bytes, &alpha_bytes, &proof_bytes).ok());
+            assert!(randomness.is_some(), "Verify proof failed");
             let random_bytes = randomness.unwrap();
-            let job_id = NonFungibleId::from_bytes(alpha_bytes);
-            info!(
-                "VRF proof accepted, storing random bytes {:x?} for job_id {:?}",
-                random_bytes, job_id
-            );
-            self.jobs.insert(job_id, random_bytes.to_vec());
+
+            match self.callbacks.remove(&job_id) {
+                Some((component, method)) => {
+                    let receipt = self.callback_receipts.take_non_fungible(&job_id);
+                    self.deliver_randomness(receipt, component, method, random_bytes.to_vec());
+                }
+                None => {
+                    info!(
+                        "VRF proof accepted, storing random bytes {:x?} for job_id {:?}",
+                        random_bytes, job_id
+                    );
+                    self.jobs.insert(job_id, random_bytes.to_vec());
+                }
+            }
         }
 
         // After the consumer provides the NFT receipt badge, provide stored jobs results and also burn receipt so the VRF Proof for the given seed is one time use only. This also reduces the amount of stored jobs in the VrfOracleContract jobs hashmap.
@@ -449,5 +522,51 @@ blueprint! {
             );
             random_bytes
         }
+
+        // Mint a receipt for a new request and assign the request to the next provider in round-robin order.
+        fn mint_receipt(&mut self, receipt_id: NonFungibleId) -> Bucket {
+            let receipt = self.receipt_minter.authorize(|| {
+                let receipt_nft_manager: &ResourceManager =
+                    borrow_resource_manager!(self.receipt_nft_address);
+
+                // Would be nice to have a description of the NFT receipt
+                receipt_nft_manager.mint_non_fungible(&receipt_id, MemberData {})
+            });
+
+            let provider = self.providers[(self.next_provider % self.providers.len() as u64) as usize].clone();
+            self.next_provider += 1;
+
+            // use receipt id as alpha input seed
+            // make off-chain oracle node request for random number
+            info!(
+                "Requesting VRF with input {} for receipt {} from provider {}",
+                receipt_id, receipt_id, hex::encode(&provider)
+            );
+            self.pending_requests.insert(
+                receipt_id,
+                PendingRequest {
+                    provider,
+                    requested_at: Runtime::current_epoch(),
+                },
+            );
+
+            receipt
+        }
+
+        // Call the consumer method with a proof of the receipt and the random bytes, then burn the receipt.
+        fn deliver_randomness(&mut self, receipt: Bucket, component: ComponentAddress, method: String, random_bytes: Vec<u8>) {
+            info!(
+                "VRF proof accepted, delivering random bytes {:x?} to {}::{}",
+                random_bytes, component, method
+            );
+            Runtime::call_method(component, &method, args![receipt.create_proof(), random_bytes]);
+
+            self.receipt_minter.authorize(|| {
+                let receipt_nft_manager: &ResourceManager =
+                    borrow_resource_manager!(self.receipt_nft_address);
+
+                receipt_nft_manager.burn(receipt);
+            });
+        }
     }
 }
@@ -0,0 +1,157 @@
+// Off-chain counterpart of EcvrfCiphersuite::ecvrf_verify, used by the oracle node to generate VRF proofs.
+// Follows the same draft-irtf-cfrg-vrf-11 publication and ECVRF-P256-SHA256-SSWU ciphersuite as the verifier.
+use p256::elliptic_curve::ff::PrimeField;
+use p256::elliptic_curve::group::GroupEncoding;
+use p256::elliptic_curve::ScalarCore;
+use p256::{ProjectivePoint, Scalar};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+pub use crate::p256_sha256_sswu;
+use crate::{EcvrfCiphersuite, VrfError};
+
+// Order q of the NistP256 group
+const Q: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xbc, 0xe6, 0xfa, 0xad, 0xa7, 0x17, 0x9e, 0x84, 0xf3, 0xb9, 0xca, 0xc2, 0xfc, 0x63, 0x25, 0x51,
+];
+
+// Convert 32 big endian bytes into a secret key scalar x, with 0 < x < q.
+fn secret_scalar(sk_bytes: &[u8]) -> Result<Scalar, VrfError> {
+    let x: Scalar = match ScalarCore::from_be_slice(sk_bytes) {
+        Ok(sc) => sc.into(),
+        Err(_) => return Err(VrfError::SecretKeyError),
+    };
+    if x == Scalar::ZERO {
+        return Err(VrfError::SecretKeyError);
+    }
+    Ok(x)
+}
+
+// Derive the compressed SEC1 encoded public key Y = x*B from the secret key.
+pub fn public_key_from_secret_key(sk_bytes: &[u8]) -> Result<Vec<u8>, VrfError> {
+    let x = secret_scalar(sk_bytes)?;
+    Ok((ProjectivePoint::GENERATOR * x).to_bytes().to_vec())
+}
+
+// Turn 32 random bytes into a secret key, None if they are out of range and new random bytes are needed.
+pub fn secret_key_from_random_bytes(random_bytes: [u8; 32]) -> Option<Vec<u8>> {
+    secret_scalar(&random_bytes).ok().map(|_| random_bytes.to_vec())
+}
+
+impl EcvrfCiphersuite {
+    // 5.1.  ECVRF Proving
+    // https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-vrf-11#section-5.1
+    #[allow(non_snake_case)]
+    pub fn ecvrf_prove(&self, sk_bytes: &[u8], alpha_bytes: &[u8]) -> Result<Vec<u8>, VrfError> {
+        // 1.  Use SK to derive the VRF secret scalar x and the VRF public key Y = x*B
+        let x = secret_scalar(sk_bytes)?;
+        let B: ProjectivePoint = ProjectivePoint::GENERATOR;
+        let Y: ProjectivePoint = &B * &x;
+        let pk_bytes = Y.to_bytes();
+
+        // 2.  H = ECVRF_encode_to_curve(encode_to_curve_salt, alpha_string) (see Section 5.4.1)
+        let H = self.ecvrf_encode_to_curve(&pk_bytes, alpha_bytes)?;
+
+        // 3.  h_string = point_to_string(H)
+        let h_string = H.to_bytes();
+
+        // 4.  Gamma = x*H
+        let gamma = &H * &x;
+
+        // 5.  k = ECVRF_nonce_generation(SK, h_string) (see Section 5.4.2)
+        let k = ecvrf_nonce_generation_rfc6979(sk_bytes, &h_string)?;
+
+        // 6.  c = ECVRF_challenge_generation(Y, H, Gamma, k*B, k*H) (see Section 5.4.3)
+        let c_bytes = self.ecvrf_challenge_generation(&Y, &H, &gamma, &(&B * &k), &(&H * &k));
+        let zero_padded_c_bytes: [u8; 32] = match [[0u8; 16], c_bytes].concat().as_slice().try_into() {
+            Ok(bytes) => bytes,
+            Err(_) => return Err(VrfError::VerifyError),
+        };
+        let c: Scalar = match ScalarCore::from_be_slice(&zero_padded_c_bytes) {
+            Ok(scalarCore) => scalarCore.into(),
+            Err(_) => return Err(VrfError::VerifyError),
+        };
+
+        // 7.  s = (k + c*x) mod q
+        let s = k + c * x;
+
+        // 8.  pi_string = point_to_string(Gamma) || int_to_string(c, cLen) || int_to_string(s, qLen)
+        let mut pi_string: Vec<u8> = vec![];
+        pi_string.extend(gamma.to_bytes());
+        pi_string.extend(c_bytes);
+        pi_string.extend(s.to_repr());
+
+        // 9.  Output pi_string
+        Ok(pi_string)
+    }
+
+    // 5.2.  ECVRF Proof to Hash, for the prover to know beta_string without verifying its own proof.
+    pub fn ecvrf_proof_to_beta(&self, pi_bytes: &[u8]) -> Result<[u8; 32], VrfError> {
+        let (gamma, _, _) = self.ecvrf_decode_proof(pi_bytes)?;
+        Ok(self.ecvrf_proof_to_hash(gamma))
+    }
+}
+
+// 5.4.2.1.  ECVRF Nonce Generation from RFC 6979
+// https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-vrf-11#section-5.4.2.1
+// Deterministic nonce k as in https://datatracker.ietf.org/doc/html/rfc6979#section-3.2 with m = h_string.
+fn ecvrf_nonce_generation_rfc6979(sk_bytes: &[u8], h_string: &[u8]) -> Result<Scalar, VrfError> {
+    // int2octets(x) is the secret key itself, as qlen = 256
+    let x_octets = secret_scalar(sk_bytes)?.to_repr();
+
+    // h1 = H(m), bits2octets(h1) = int2octets(h1 mod q)
+    let h1: [u8; 32] = Sha256::digest(h_string).into();
+    let h1_octets = reduce_mod_q(h1);
+
+    // b. V = 0x01 0x01 ... 0x01, c. K = 0x00 0x00 ... 0x00
+    let mut v = [0x01u8; 32];
+    let mut k = [0x00u8; 32];
+
+    // d. K = HMAC_K(V || 0x00 || int2octets(x) || bits2octets(h1)), e. V = HMAC_K(V)
+    k = hmac_sha256(&k, &[&v, &[0x00], &x_octets, &h1_octets]);
+    v = hmac_sha256(&k, &[&v]);
+    // f. K = HMAC_K(V || 0x01 || int2octets(x) || bits2octets(h1)), g. V = HMAC_K(V)
+    k = hmac_sha256(&k, &[&v, &[0x01], &x_octets, &h1_octets]);
+    v = hmac_sha256(&k, &[&v]);
+
+    // h. Generate T = V until k = bits2int(T) is in [1, q-1]
+    loop {
+        v = hmac_sha256(&k, &[&v]);
+        if let Ok(sc) = ScalarCore::from_be_slice(&v) {
+            let nonce: Scalar = sc.into();
+            if nonce != Scalar::ZERO {
+                return Ok(nonce);
+            }
+        }
+        k = hmac_sha256(&k, &[&v, &[0x00]]);
+        v = hmac_sha256(&k, &[&v]);
+    }
+}
+
+// Reduce 32 big endian bytes modulo q. Any 256 bit value is smaller than 2q, so one subtraction is enough.
+fn reduce_mod_q(bytes: [u8; 32]) -> [u8; 32] {
+    if bytes < Q {
+        return bytes;
+    }
+    let mut reduced = [0u8; 32];
+    let mut borrow = 0i16;
+    for i in (0..32).rev() {
+        let mut diff = bytes[i] as i16 - Q[i] as i16 - borrow;
+        borrow = if diff < 0 { 1 } else { 0 };
+        if diff < 0 {
+            diff += 256;
+        }
+        reduced[i] = diff as u8;
+    }
+    reduced
+}
+
+// HMAC-SHA256 of the concatenated data
+fn hmac_sha256(key: &[u8; 32], data: &[&[u8]]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in data {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
@@ -414,3 +414,276 @@ fn vrf_verify_fail() {
     println!("{:?}\n", receipt3);
     assert!(receipt3.result.is_err());
 }
+
+// Requests are assigned round-robin, each request must be fulfilled with a proof of the assigned provider.
+#[test]
+fn vrf_round_robin_providers() {
+    let pk_1 = "0360fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6";
+    let pk_2 = "03596375e6ce57e0f20294fc46bdfcfd19a39f8161b58695b3ec5b3d16427c274d";
+    // Example 4 is proven with pk_1, example 6 with pk_2
+    let alpha_4 = "73616d706c65";
+    let pi_4 = "0331d984ca8fece9cbb9a144c0d53df3c4c7a33080c1e02ddb1a96a365394c7888782fffde7b842c38c20c08de6ec6c2e7027a97000f2c9fa4425d5c03e639fb48fde58114d755985498d7eb234cf4aed9";
+    let alpha_6 = "4578616d706c65207573696e67204543445341206b65792066726f6d20417070656e646978204c2e342e32206f6620414e53492e58392d36322d32303035";
+    let pi_6 = "039f8d9cdc162c89be2871cbcb1435144739431db7fab437ab7bc4e2651a9e99d5488405a11a6c7fc8defddd9e1573a563b7333aab4effe73ae9803274174c659269fd39b53e133dcd9e0d24f01288de9a";
+
+    // // Set up environment.
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (pk, sk, account) = executor.new_account();
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let transaction1 = TransactionBuilder::new()
+        .call_function(
+            package,
+            "VrfOracleContract",
+            "new_with_providers",
+            args![vec![pk_1.to_string(), pk_2.to_string()]],
+        )
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt1 = executor.validate_and_execute(&transaction1).unwrap();
+    println!("{:?}\n", receipt1);
+    assert!(receipt1.result.is_ok());
+    let vrf_component = receipt1.new_component_addresses[0];
+
+    // The 1st request is assigned to pk_1, the 2nd to pk_2.
+    for alpha in [alpha_4, alpha_6] {
+        let transaction = TransactionBuilder::new()
+            .withdraw_from_account_by_amount(Decimal(5), RADIX_TOKEN, account)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_method(
+                    vrf_component,
+                    "request_randomness_with_seed",
+                    args![scrypto::resource::Bucket(bucket_id), alpha],
+                )
+            })
+            .call_method_with_all_resources(account, "deposit_batch")
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+        let receipt = executor.validate_and_execute(&transaction).unwrap();
+        println!("{:?}\n", receipt);
+        assert!(receipt.result.is_ok());
+    }
+
+    for (alpha, pi) in [(alpha_4, pi_4), (alpha_6, pi_6)] {
+        let transaction = TransactionBuilder::new()
+            .call_method(vrf_component, "fullfill_randomness_request", args![alpha, pi])
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+        let receipt = executor.validate_and_execute(&transaction).unwrap();
+        println!("{:?}\n", receipt);
+        assert!(receipt.result.is_ok());
+    }
+}
+
+#[test]
+fn vrf_verify_fail_wrong_provider() {
+    let pk_1 = "0360fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6";
+    let pk_2 = "03596375e6ce57e0f20294fc46bdfcfd19a39f8161b58695b3ec5b3d16427c274d";
+    // Example 6 is proven with pk_2
+    let alpha = "4578616d706c65207573696e67204543445341206b65792066726f6d20417070656e646978204c2e342e32206f6620414e53492e58392d36322d32303035";
+    let pi = "039f8d9cdc162c89be2871cbcb1435144739431db7fab437ab7bc4e2651a9e99d5488405a11a6c7fc8defddd9e1573a563b7333aab4effe73ae9803274174c659269fd39b53e133dcd9e0d24f01288de9a";
+
+    // // Set up environment.
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (pk, sk, account) = executor.new_account();
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let transaction1 = TransactionBuilder::new()
+        .call_function(
+            package,
+            "VrfOracleContract",
+            "new_with_providers",
+            args![vec![pk_1.to_string(), pk_2.to_string()]],
+        )
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt1 = executor.validate_and_execute(&transaction1).unwrap();
+    println!("{:?}\n", receipt1);
+    assert!(receipt1.result.is_ok());
+
+    // The request is assigned to pk_1.
+    let vrf_component = receipt1.new_component_addresses[0];
+    let transaction2 = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(Decimal(5), RADIX_TOKEN, account)
+        .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+            builder.call_method(
+                vrf_component,
+                "request_randomness_with_seed",
+                args![scrypto::resource::Bucket(bucket_id), alpha],
+            )
+        })
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt2 = executor.validate_and_execute(&transaction2).unwrap();
+    println!("{:?}\n", receipt2);
+    assert!(receipt2.result.is_ok());
+
+    // A valid proof of pk_2 is rejected as long as the request is not reassigned.
+    let transaction3 = TransactionBuilder::new()
+        .call_method(vrf_component, "fullfill_randomness_request", args![alpha, pi])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt3 = executor.validate_and_execute(&transaction3).unwrap();
+    println!("{:?}\n", receipt3);
+    assert!(receipt3.result.is_err());
+}
+
+// A request that the assigned provider has not fulfilled within reassign_after epochs is accepted from any provider.
+#[test]
+fn vrf_reassign_after_epochs() {
+    let pk_1 = "0360fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6";
+    let pk_2 = "03596375e6ce57e0f20294fc46bdfcfd19a39f8161b58695b3ec5b3d16427c274d";
+    // Example 6 is proven with pk_2
+    let alpha = "4578616d706c65207573696e67204543445341206b65792066726f6d20417070656e646978204c2e342e32206f6620414e53492e58392d36322d32303035";
+    let pi = "039f8d9cdc162c89be2871cbcb1435144739431db7fab437ab7bc4e2651a9e99d5488405a11a6c7fc8defddd9e1573a563b7333aab4effe73ae9803274174c659269fd39b53e133dcd9e0d24f01288de9a";
+
+    // // Set up environment.
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (pk, sk, account) = executor.new_account();
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let transaction1 = TransactionBuilder::new()
+        .call_function(
+            package,
+            "VrfOracleContract",
+            "new_with_providers",
+            args![vec![pk_1.to_string(), pk_2.to_string()]],
+        )
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt1 = executor.validate_and_execute(&transaction1).unwrap();
+    println!("{:?}\n", receipt1);
+    assert!(receipt1.result.is_ok());
+    let vrf_component = receipt1.new_component_addresses[0];
+    let owner_badge = receipt1.new_resource_addresses[0];
+
+    // Only the owner can set reassign_after, here to 2 epochs.
+    let transaction2 = TransactionBuilder::new()
+        .call_method(vrf_component, "set_reassign_after", args![2u64])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt2 = executor.validate_and_execute(&transaction2).unwrap();
+    println!("{:?}\n", receipt2);
+    assert!(receipt2.result.is_err());
+
+    let transaction3 = TransactionBuilder::new()
+        .create_proof_from_account_by_amount(Decimal::ONE, owner_badge, account)
+        .call_method(vrf_component, "set_reassign_after", args![2u64])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt3 = executor.validate_and_execute(&transaction3).unwrap();
+    println!("{:?}\n", receipt3);
+    assert!(receipt3.result.is_ok());
+
+    // The request is assigned to pk_1 in epoch 0.
+    let transaction4 = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(Decimal(5), RADIX_TOKEN, account)
+        .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+            builder.call_method(
+                vrf_component,
+                "request_randomness_with_seed",
+                args![scrypto::resource::Bucket(bucket_id), alpha],
+            )
+        })
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt4 = executor.validate_and_execute(&transaction4).unwrap();
+    println!("{:?}\n", receipt4);
+    assert!(receipt4.result.is_ok());
+
+    // A valid proof of pk_2 is rejected until 2 epochs have passed, then the request is reassigned.
+    for (epoch, reassigned) in [(0u64, false), (1, false), (2, true)] {
+        executor.substate_store_mut().set_epoch(epoch);
+        let transaction = TransactionBuilder::new()
+            .call_method(vrf_component, "fullfill_randomness_request", args![alpha, pi])
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+        let receipt = executor.validate_and_execute(&transaction).unwrap();
+        println!("{:?}\n", receipt);
+        assert_eq!(receipt.result.is_ok(), reassigned);
+    }
+}
+
+// The random bytes are delivered to the consumer as soon as the request is fulfilled.
+#[test]
+fn vrf_callback_delivery() {
+    let PK = "0360fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6";
+    let alpha = "73616d706c65"; // (ASCII "sample")
+    let pi = "0331d984ca8fece9cbb9a144c0d53df3c4c7a33080c1e02ddb1a96a365394c7888782fffde7b842c38c20c08de6ec6c2e7027a97000f2c9fa4425d5c03e639fb48fde58114d755985498d7eb234cf4aed9";
+
+    // // Set up environment.
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (pk, sk, account) = executor.new_account();
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let transaction1 = TransactionBuilder::new()
+        .call_function(package, "VrfOracleContract", "new", args![PK])
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt1 = executor.validate_and_execute(&transaction1).unwrap();
+    println!("{:?}\n", receipt1);
+    assert!(receipt1.result.is_ok());
+    let vrf_component = receipt1.new_component_addresses[0];
+
+    let transaction2 = TransactionBuilder::new()
+        .call_function(package, "VrfConsumer", "new", args![vrf_component])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt2 = executor.validate_and_execute(&transaction2).unwrap();
+    println!("{:?}\n", receipt2);
+    assert!(receipt2.result.is_ok());
+    let consumer_component = receipt2.new_component_addresses[0];
+
+    // The consumer requests randomness and registers its callback.
+    let transaction3 = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(Decimal(5), RADIX_TOKEN, account)
+        .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+            builder.call_method(
+                consumer_component,
+                "request_randomness_with_seed",
+                args![scrypto::resource::Bucket(bucket_id), alpha],
+            )
+        })
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt3 = executor.validate_and_execute(&transaction3).unwrap();
+    println!("{:?}\n", receipt3);
+    assert!(receipt3.result.is_ok());
+
+    let request_id = NonFungibleId::from_bytes(hex::decode(alpha).unwrap());
+
+    // Not delivered before the request is fulfilled.
+    let transaction4 = TransactionBuilder::new()
+        .call_method(consumer_component, "get_randomness", args![request_id.clone()])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt4 = executor.validate_and_execute(&transaction4).unwrap();
+    assert!(receipt4.result.is_err());
+
+    let transaction5 = TransactionBuilder::new()
+        .call_method(vrf_component, "fullfill_randomness_request", args![alpha, pi])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt5 = executor.validate_and_execute(&transaction5).unwrap();
+    println!("{:?}\n", receipt5);
+    assert!(receipt5.result.is_ok());
+
+    let transaction6 = TransactionBuilder::new()
+        .call_method(consumer_component, "get_randomness", args![request_id])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt6 = executor.validate_and_execute(&transaction6).unwrap();
+    println!("{:?}\n", receipt6);
+    assert!(receipt6.result.is_ok());
+}
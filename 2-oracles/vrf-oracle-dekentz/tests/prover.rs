@@ -0,0 +1,267 @@
+// Tests of the off-chain prover, only built with the cli feature: cargo test --features cli
+use radix_engine::ledger::*;
+use radix_engine::transaction::*;
+use scrypto::prelude::*;
+use vrf_oracle_dekentz::prover;
+
+// Examples 4, 5 and 6 from https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-vrf-11#appendix-A.2
+// (secret key, public key, alpha, pi)
+const PROVER_EXAMPLES: [(&str, &str, &str, &str); 3] = [
+    (
+        "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721",
+        "0360fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6",
+        "73616d706c65",
+        "0331d984ca8fece9cbb9a144c0d53df3c4c7a33080c1e02ddb1a96a365394c7888782fffde7b842c38c20c08de6ec6c2e7027a97000f2c9fa4425d5c03e639fb48fde58114d755985498d7eb234cf4aed9",
+    ),
+    (
+        "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721",
+        "0360fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6",
+        "74657374",
+        "03f814c0455d32dbc75ad3aea08c7e2db31748e12802db23640203aebf1fa8db2743aad348a3006dc1caad7da28687320740bf7dd78fe13c298867321ce3b36b79ec3093b7083ac5e4daf3465f9f43c627",
+    ),
+    (
+        "2ca1411a41b17b24cc8c3b089cfd033f1920202a6c0de8abb97df1498d50d2c8",
+        "03596375e6ce57e0f20294fc46bdfcfd19a39f8161b58695b3ec5b3d16427c274d",
+        "4578616d706c65207573696e67204543445341206b65792066726f6d20417070656e646978204c2e342e32206f6620414e53492e58392d36322d32303035",
+        "039f8d9cdc162c89be2871cbcb1435144739431db7fab437ab7bc4e2651a9e99d5488405a11a6c7fc8defddd9e1573a563b7333aab4effe73ae9803274174c659269fd39b53e133dcd9e0d24f01288de9a",
+    ),
+];
+
+#[test]
+fn prover_matches_spec_examples() {
+    let p256_vrf = prover::p256_sha256_sswu();
+    for (sk, pk, alpha, pi) in PROVER_EXAMPLES {
+        let sk_bytes = hex::decode(sk).unwrap();
+        let alpha_bytes = hex::decode(alpha).unwrap();
+        assert_eq!(hex::encode(prover::public_key_from_secret_key(&sk_bytes).unwrap()), pk);
+
+        let pi_bytes = p256_vrf.ecvrf_prove(&sk_bytes, &alpha_bytes).unwrap();
+        assert_eq!(hex::encode(&pi_bytes), pi);
+
+        // Round trip through the verifier
+        let beta_bytes = p256_vrf
+            .ecvrf_verify(&hex::decode(pk).unwrap(), &alpha_bytes, &pi_bytes)
+            .unwrap();
+        assert_eq!(beta_bytes, p256_vrf.ecvrf_proof_to_beta(&pi_bytes).unwrap());
+    }
+}
+
+#[test]
+fn prover_rejects_invalid_secret_keys() {
+    assert!(prover::public_key_from_secret_key(&[0u8; 32]).is_err());
+    assert!(prover::public_key_from_secret_key(&[0xffu8; 32]).is_err());
+    assert!(prover::secret_key_from_random_bytes([0xffu8; 32]).is_none());
+}
+
+#[test]
+fn prover_tampered_proof_fails_verify() {
+    let p256_vrf = prover::p256_sha256_sswu();
+    let sk_bytes = prover::secret_key_from_random_bytes([7u8; 32]).unwrap();
+    let pk_bytes = prover::public_key_from_secret_key(&sk_bytes).unwrap();
+    let alpha_bytes = hex::decode("0000000000000000").unwrap();
+
+    let mut pi_bytes = p256_vrf.ecvrf_prove(&sk_bytes, &alpha_bytes).unwrap();
+    assert!(p256_vrf.ecvrf_verify(&pk_bytes, &alpha_bytes, &pi_bytes).is_ok());
+
+    // Flip a bit of s
+    let last = pi_bytes.len() - 1;
+    pi_bytes[last] ^= 0x01;
+    assert!(p256_vrf.ecvrf_verify(&pk_bytes, &alpha_bytes, &pi_bytes).is_err());
+}
+
+// A proof generated by the prover for a fresh key pair is accepted by the VrfOracleContract.
+#[test]
+fn prover_round_trip_on_ledger() {
+    let sk_bytes = prover::secret_key_from_random_bytes([42u8; 32]).unwrap();
+    let pk_hex_string = hex::encode(prover::public_key_from_secret_key(&sk_bytes).unwrap());
+    let alpha = "0000000000000000";
+    let pi = hex::encode(
+        prover::p256_sha256_sswu()
+            .ecvrf_prove(&sk_bytes, &hex::decode(alpha).unwrap())
+            .unwrap(),
+    );
+
+    // // Set up environment.
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (pk, sk, account) = executor.new_account();
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let transaction1 = TransactionBuilder::new()
+        .call_function(package, "VrfOracleContract", "new", args![pk_hex_string])
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt1 = executor.validate_and_execute(&transaction1).unwrap();
+    println!("{:?}\n", receipt1);
+    assert!(receipt1.result.is_ok());
+    let nft_receipt_addr = receipt1.new_resource_addresses.get(2).unwrap();
+
+    // Start a request_randomess with 5 XRD payment.
+    let vrf_component = receipt1.new_component_addresses[0];
+    let transaction2 = TransactionBuilder::new()
+        .withdraw_from_account_by_amount(Decimal(5), RADIX_TOKEN, account)
+        .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+            builder.call_method(
+                vrf_component,
+                "request_randomness_by_counter",
+                args![scrypto::resource::Bucket(bucket_id)],
+            )
+        })
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt2 = executor.validate_and_execute(&transaction2).unwrap();
+    println!("{:?}\n", receipt2);
+    assert!(receipt2.result.is_ok());
+
+    let transaction3 = TransactionBuilder::new()
+        .call_method(vrf_component, "fullfill_randomness_request", args![alpha, pi])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt3 = executor.validate_and_execute(&transaction3).unwrap();
+    println!("{:?}\n", receipt3);
+    assert!(receipt3.result.is_ok());
+
+    let mut ids = BTreeSet::new();
+    ids.insert(NonFungibleId::from_u64(0));
+    let transaction4 = TransactionBuilder::new()
+        .withdraw_from_account_by_ids(&ids, *nft_receipt_addr, account)
+        .take_from_worktop_by_ids(&ids, *nft_receipt_addr, |builder, bucket_id| {
+            builder.call_method(
+                vrf_component,
+                "fetch_randomness",
+                args![scrypto::resource::Bucket(bucket_id)],
+            )
+        })
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt4 = executor.validate_and_execute(&transaction4).unwrap();
+    println!("{:?}\n", receipt4);
+    assert!(receipt4.result.is_ok());
+}
+
+// Registered providers join the round-robin order, removed providers leave it and their requests are accepted from any provider.
+#[test]
+fn vrf_register_and_remove_provider_round_robin() {
+    let (sk_1, pk_1) = (PROVER_EXAMPLES[0].0, PROVER_EXAMPLES[0].1);
+    let (sk_2, pk_2) = (PROVER_EXAMPLES[2].0, PROVER_EXAMPLES[2].1);
+    let alphas = ["0000000000000000", "0000000000000001", "0000000000000002"];
+    let prove = |sk: &str, alpha: &str| {
+        hex::encode(
+            prover::p256_sha256_sswu()
+                .ecvrf_prove(&hex::decode(sk).unwrap(), &hex::decode(alpha).unwrap())
+                .unwrap(),
+        )
+    };
+
+    // // Set up environment.
+    let mut ledger = InMemorySubstateStore::with_bootstrap();
+    let mut executor = TransactionExecutor::new(&mut ledger, false);
+    let (pk, sk, account) = executor.new_account();
+    let package = executor.publish_package(compile_package!()).unwrap();
+
+    let transaction1 = TransactionBuilder::new()
+        .call_function(package, "VrfOracleContract", "new", args![pk_1])
+        .call_method_with_all_resources(account, "deposit_batch")
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt1 = executor.validate_and_execute(&transaction1).unwrap();
+    println!("{:?}\n", receipt1);
+    assert!(receipt1.result.is_ok());
+    let vrf_component = receipt1.new_component_addresses[0];
+    let owner_badge = receipt1.new_resource_addresses[0];
+
+    // Only the owner can register a provider.
+    let transaction2 = TransactionBuilder::new()
+        .call_method(vrf_component, "register_provider", args![pk_2])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt2 = executor.validate_and_execute(&transaction2).unwrap();
+    println!("{:?}\n", receipt2);
+    assert!(receipt2.result.is_err());
+
+    let transaction3 = TransactionBuilder::new()
+        .create_proof_from_account_by_amount(Decimal::ONE, owner_badge, account)
+        .call_method(vrf_component, "register_provider", args![pk_2])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt3 = executor.validate_and_execute(&transaction3).unwrap();
+    println!("{:?}\n", receipt3);
+    assert!(receipt3.result.is_ok());
+
+    let request = |alpha: &str, nonce| {
+        TransactionBuilder::new()
+            .withdraw_from_account_by_amount(Decimal(5), RADIX_TOKEN, account)
+            .take_from_worktop(RADIX_TOKEN, |builder, bucket_id| {
+                builder.call_method(
+                    vrf_component,
+                    "request_randomness_with_seed",
+                    args![scrypto::resource::Bucket(bucket_id), alpha],
+                )
+            })
+            .call_method_with_all_resources(account, "deposit_batch")
+            .build(nonce)
+            .sign([&sk])
+    };
+
+    // The 1st request is assigned to pk_1, the 2nd to the registered pk_2.
+    for alpha in [alphas[0], alphas[1]] {
+        let transaction = request(alpha, executor.get_nonce([pk]));
+        let receipt = executor.validate_and_execute(&transaction).unwrap();
+        println!("{:?}\n", receipt);
+        assert!(receipt.result.is_ok());
+    }
+
+    // pk_1 can't fulfill the 2nd request, pk_2 can.
+    for (sk_hex_string, fulfilled) in [(sk_1, false), (sk_2, true)] {
+        let transaction = TransactionBuilder::new()
+            .call_method(vrf_component, "fullfill_randomness_request", args![alphas[1], prove(sk_hex_string, alphas[1])])
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+        let receipt = executor.validate_and_execute(&transaction).unwrap();
+        println!("{:?}\n", receipt);
+        assert_eq!(receipt.result.is_ok(), fulfilled);
+    }
+
+    // Once pk_1 is removed, the 3rd request is assigned to pk_2 as well.
+    let transaction4 = TransactionBuilder::new()
+        .create_proof_from_account_by_amount(Decimal::ONE, owner_badge, account)
+        .call_method(vrf_component, "remove_provider", args![pk_1])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt4 = executor.validate_and_execute(&transaction4).unwrap();
+    println!("{:?}\n", receipt4);
+    assert!(receipt4.result.is_ok());
+
+    let transaction5 = request(alphas[2], executor.get_nonce([pk]));
+    let receipt5 = executor.validate_and_execute(&transaction5).unwrap();
+    println!("{:?}\n", receipt5);
+    assert!(receipt5.result.is_ok());
+
+    // pk_1 can't fulfill any request anymore, pk_2 fulfills its own and the 1st one, assigned to the removed pk_1.
+    for (sk_hex_string, alpha, fulfilled) in [
+        (sk_1, alphas[2], false),
+        (sk_1, alphas[0], false),
+        (sk_2, alphas[2], true),
+        (sk_2, alphas[0], true),
+    ] {
+        let transaction = TransactionBuilder::new()
+            .call_method(vrf_component, "fullfill_randomness_request", args![alpha, prove(sk_hex_string, alpha)])
+            .build(executor.get_nonce([pk]))
+            .sign([&sk]);
+        let receipt = executor.validate_and_execute(&transaction).unwrap();
+        println!("{:?}\n", receipt);
+        assert_eq!(receipt.result.is_ok(), fulfilled);
+    }
+
+    // The last provider can't be removed.
+    let transaction6 = TransactionBuilder::new()
+        .create_proof_from_account_by_amount(Decimal::ONE, owner_badge, account)
+        .call_method(vrf_component, "remove_provider", args![pk_2])
+        .build(executor.get_nonce([pk]))
+        .sign([&sk]);
+    let receipt6 = executor.validate_and_execute(&transaction6).unwrap();
+    println!("{:?}\n", receipt6);
+    assert!(receipt6.result.is_err());
+}